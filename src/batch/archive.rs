@@ -0,0 +1,435 @@
+//! The `.kbatch` archive format
+//!
+//! ```text
+//! KOPYBATCH1\n                 magic
+//! payload bytes                file contents, back to back
+//! manifest JSON                BatchManifest
+//! manifest length              u64, little endian
+//! ```
+//!
+//! The manifest goes last so that the archive is written in one pass: each
+//! payload is hashed while it is copied in, and its offset, size and Blake3
+//! hash are recorded in the manifest.
+
+use crate::diff::{DeleteLimit, DiffPlan, TreeTotals};
+use crate::types::{DeleteMode, FileEntry, KopyError, SyncAction};
+use crate::{Config, ConflictMode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Leading bytes of every batch archive.
+const MAGIC: &[u8] = b"KOPYBATCH1\n";
+
+/// Current manifest format.
+const BATCH_FORMAT: u32 = 1;
+
+/// Size of the trailing manifest length.
+const TRAILER_LEN: u64 = 8;
+
+/// Everything a batch replays, apart from the payload bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchManifest {
+    /// Manifest format version
+    pub format: u32,
+
+    /// kopy version that wrote the batch
+    pub kopy_version: String,
+
+    /// How deletes are carried out on replay
+    pub delete_mode: DeleteMode,
+
+    /// Conflict policy the batch was created with; `fail` refuses replay
+    /// when the plan recorded conflicts
+    #[serde(default)]
+    pub conflict_mode: ConflictMode,
+
+    /// Most the batch may delete before `kopy batch apply` refuses it
+    #[serde(default)]
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Whether deleting everything from an empty source is intended
+    #[serde(default)]
+    pub allow_empty_source: bool,
+
+    /// File counts of the source and the indexed destination
+    #[serde(default)]
+    pub totals: TreeTotals,
+
+    /// Destination entries the actions expect, by relative path; a touched
+    /// path missing here is expected to be absent
+    pub destination_entries: BTreeMap<PathBuf, FileEntry>,
+
+    /// The actions to replay
+    pub plan: DiffPlan,
+
+    /// File contents carried by the archive, one per transferred file
+    pub payloads: Vec<Payload>,
+}
+
+/// One file's contents inside the archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Payload {
+    /// Relative path of the file
+    pub path: PathBuf,
+
+    /// Offset from the end of the magic
+    pub offset: u64,
+
+    /// Length in bytes
+    pub size: u64,
+
+    /// Blake3 hash of the contents, hex encoded
+    pub hash: String,
+}
+
+/// Write a batch archive replaying `plan`.
+///
+/// Regular files copied or updated by the plan are read from
+/// `config.source` (or their overlay root) into the archive. The manifest's plan refers to the
+/// payloads only, so overlay roots are dropped from its entries.
+///
+/// # Errors
+/// Returns `KopyError::Validation` when a source file changed size while it
+/// was packed, and `KopyError::Io` for read or write failures.
+pub fn write_batch(
+    out: &Path,
+    plan: &DiffPlan,
+    config: &Config,
+    totals: TreeTotals,
+    destination_entries: BTreeMap<PathBuf, FileEntry>,
+) -> Result<BatchManifest, KopyError> {
+    let source = config.source.as_path();
+    let mut part = out.as_os_str().to_owned();
+    part.push(".part");
+    let mut writer = BufWriter::new(File::create(&part).map_err(KopyError::Io)?);
+    writer.write_all(MAGIC).map_err(KopyError::Io)?;
+
+    let mut payloads = Vec::new();
+    let mut replay = DiffPlan::new();
+    let mut offset = 0u64;
+    for action in &plan.actions {
+        let action = match action {
+            SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry, _) => {
+                if !entry.is_symlink {
+                    let payload = append_payload(&mut writer, source, entry, offset)?;
+                    offset += payload.size;
+                    payloads.push(payload);
+                }
+                with_entry(
+                    action,
+                    FileEntry {
+                        source_root: None,
+                        ..entry.clone()
+                    },
+                )
+            }
+            other => other.clone(),
+        };
+        replay.add_action(action);
+    }
+    for conflict in &plan.conflicts {
+        replay.add_conflict(conflict.clone());
+    }
+    replay.stats.delete_bytes = plan.stats.delete_bytes;
+
+    let manifest = BatchManifest {
+        format: BATCH_FORMAT,
+        kopy_version: crate::VERSION.to_string(),
+        delete_mode: config.delete_mode,
+        conflict_mode: config.conflict_mode,
+        max_delete: config.max_delete,
+        allow_empty_source: config.allow_empty_source,
+        totals,
+        destination_entries,
+        plan: replay,
+        payloads,
+    };
+    let json = serde_json::to_vec(&manifest)
+        .map_err(|err| KopyError::Config(format!("Failed to encode batch manifest: {}", err)))?;
+    writer.write_all(&json).map_err(KopyError::Io)?;
+    writer
+        .write_all(&(json.len() as u64).to_le_bytes())
+        .map_err(KopyError::Io)?;
+    writer.flush().map_err(KopyError::Io)?;
+    drop(writer);
+
+    fs::rename(&part, out).map_err(KopyError::Io)?;
+    Ok(manifest)
+}
+
+/// Read the manifest of a batch archive.
+///
+/// # Errors
+/// Returns `KopyError::Config` when the file is not a batch archive or its
+/// manifest is malformed or of a newer format.
+pub fn read_manifest(path: &Path) -> Result<BatchManifest, KopyError> {
+    let invalid =
+        |detail: &str| KopyError::Config(format!("Invalid batch {}: {}", path.display(), detail));
+    let mut file = File::open(path).map_err(KopyError::Io)?;
+    let mut magic = [0u8; MAGIC.len()];
+    file.read_exact(&mut magic)
+        .map_err(|_| invalid("not a kopy batch"))?;
+    if magic != MAGIC {
+        return Err(invalid("not a kopy batch"));
+    }
+
+    let length = file.metadata().map_err(KopyError::Io)?.len();
+    if length < MAGIC.len() as u64 + TRAILER_LEN {
+        return Err(invalid("truncated"));
+    }
+    file.seek(SeekFrom::Start(length - TRAILER_LEN))
+        .map_err(KopyError::Io)?;
+    let mut trailer = [0u8; TRAILER_LEN as usize];
+    file.read_exact(&mut trailer).map_err(KopyError::Io)?;
+    let manifest_len = u64::from_le_bytes(trailer);
+    let Some(manifest_start) = (length - TRAILER_LEN).checked_sub(manifest_len) else {
+        return Err(invalid("truncated"));
+    };
+    if manifest_start < MAGIC.len() as u64 {
+        return Err(invalid("truncated"));
+    }
+
+    file.seek(SeekFrom::Start(manifest_start))
+        .map_err(KopyError::Io)?;
+    let mut json = Vec::new();
+    file.take(manifest_len)
+        .read_to_end(&mut json)
+        .map_err(KopyError::Io)?;
+    let manifest: BatchManifest =
+        serde_json::from_slice(&json).map_err(|err| invalid(&err.to_string()))?;
+    if manifest.format != BATCH_FORMAT {
+        return Err(KopyError::Config(format!(
+            "Unsupported batch format {} in {}",
+            manifest.format,
+            path.display()
+        )));
+    }
+
+    let mut paths = manifest.payloads.iter().map(|payload| &payload.path).chain(
+        manifest
+            .plan
+            .actions
+            .iter()
+            .flat_map(|action| match action {
+                SyncAction::Move { from, to } => vec![from, to],
+                other => other.path().into_iter().collect(),
+            }),
+    );
+    if let Some(escaping) = paths.find(|path| !is_contained(path)) {
+        return Err(invalid(&format!(
+            "path {} leaves the destination",
+            escaping.display()
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Whether `path` is relative and stays below the directory it is joined to.
+fn is_contained(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// Unpack every payload of `manifest` into `staging`, checking its hash.
+///
+/// Each file is given the mtime and permissions of its planned entry, so the
+/// executor copies them over exactly as a direct sync would.
+///
+/// # Errors
+/// Returns `KopyError::ChecksumMismatch` for a payload whose contents do not
+/// match its recorded hash.
+pub fn extract_payloads(
+    archive: &Path,
+    manifest: &BatchManifest,
+    staging: &Path,
+) -> Result<(), KopyError> {
+    let entries: BTreeMap<&PathBuf, &FileEntry> = manifest
+        .plan
+        .actions
+        .iter()
+        .filter_map(|action| action.file_entry())
+        .map(|entry| (&entry.path, entry))
+        .collect();
+
+    let mut reader = BufReader::new(File::open(archive).map_err(KopyError::Io)?);
+    for payload in &manifest.payloads {
+        let path = staging.join(&payload.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(KopyError::Io)?;
+        }
+        reader
+            .seek(SeekFrom::Start(MAGIC.len() as u64 + payload.offset))
+            .map_err(KopyError::Io)?;
+        let mut writer = HashingWriter::new(File::create(&path).map_err(KopyError::Io)?);
+        let copied = std::io::copy(&mut (&mut reader).take(payload.size), &mut writer)
+            .map_err(KopyError::Io)?;
+        if copied != payload.size || writer.finish() != payload.hash {
+            return Err(KopyError::ChecksumMismatch {
+                path: payload.path.clone(),
+            });
+        }
+
+        if let Some(entry) = entries.get(&payload.path) {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(entry.permissions))
+                    .map_err(KopyError::Io)?;
+            }
+            filetime::set_file_mtime(&path, filetime::FileTime::from_system_time(entry.mtime))
+                .map_err(KopyError::Io)?;
+        }
+    }
+    Ok(())
+}
+
+fn append_payload(
+    writer: &mut impl Write,
+    source: &Path,
+    entry: &FileEntry,
+    offset: u64,
+) -> Result<Payload, KopyError> {
+    let mut file = File::open(entry.source_path(source)).map_err(KopyError::Io)?;
+    let mut hashing = HashingWriter::new(writer);
+    let size = std::io::copy(&mut file, &mut hashing).map_err(KopyError::Io)?;
+    if size != entry.size {
+        return Err(KopyError::Validation(format!(
+            "{} changed while it was packed ({} bytes planned, {} read)",
+            entry.path.display(),
+            entry.size,
+            size
+        )));
+    }
+    Ok(Payload {
+        path: entry.path.clone(),
+        offset,
+        size,
+        hash: hashing.finish(),
+    })
+}
+
+fn with_entry(action: &SyncAction, entry: FileEntry) -> SyncAction {
+    match action {
+        SyncAction::Overwrite(_, reason) => SyncAction::Overwrite(entry, *reason),
+        _ => SyncAction::CopyNew(entry),
+    }
+}
+
+/// Writer that hashes everything passed through it.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: blake3::Hasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    fn finish(&self) -> String {
+        self.hasher.finalize().to_hex().to_string()
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_read_and_extract_round_trip() {
+        let src = TempDir::new().expect("create src");
+        let work = TempDir::new().expect("create work dir");
+        fs::write(src.path().join("a.txt"), b"alpha").expect("write a");
+        fs::write(src.path().join("b.txt"), b"bravo!").expect("write b");
+
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut plan = DiffPlan::new();
+        for (name, size) in [("a.txt", 5), ("b.txt", 6)] {
+            plan.add_action(SyncAction::CopyNew(FileEntry::new(
+                PathBuf::from(name),
+                size,
+                mtime,
+                0o600,
+            )));
+        }
+        plan.add_action(SyncAction::Delete(PathBuf::from("old.txt")));
+
+        let out = work.path().join("out.kbatch");
+        let config = Config {
+            source: src.path().to_path_buf(),
+            delete_mode: DeleteMode::Trash,
+            ..Config::default()
+        };
+        write_batch(&out, &plan, &config, TreeTotals::default(), BTreeMap::new())
+            .expect("write batch");
+        let manifest = read_manifest(&out).expect("read manifest");
+        assert_eq!(manifest.plan, plan);
+        assert_eq!(manifest.payloads.len(), 2);
+        assert_eq!(manifest.payloads[1].offset, 5);
+
+        let staging = work.path().join("staging");
+        extract_payloads(&out, &manifest, &staging).expect("extract payloads");
+        assert_eq!(fs::read(staging.join("b.txt")).unwrap(), b"bravo!");
+        let metadata = fs::metadata(staging.join("a.txt")).unwrap();
+        assert_eq!(metadata.modified().unwrap(), mtime);
+    }
+
+    #[test]
+    fn test_extract_rejects_corrupted_payload() {
+        let src = TempDir::new().expect("create src");
+        let work = TempDir::new().expect("create work dir");
+        fs::write(src.path().join("a.txt"), b"alpha").expect("write a");
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::CopyNew(FileEntry::new(
+            PathBuf::from("a.txt"),
+            5,
+            UNIX_EPOCH + Duration::from_secs(1),
+            0o644,
+        )));
+
+        let out = work.path().join("out.kbatch");
+        let config = Config {
+            source: src.path().to_path_buf(),
+            delete_mode: DeleteMode::None,
+            ..Config::default()
+        };
+        write_batch(&out, &plan, &config, TreeTotals::default(), BTreeMap::new())
+            .expect("write batch");
+        let mut bytes = fs::read(&out).unwrap();
+        bytes[MAGIC.len()] ^= 0xff;
+        fs::write(&out, bytes).unwrap();
+
+        let manifest = read_manifest(&out).expect("manifest is intact");
+        let err = extract_payloads(&out, &manifest, &work.path().join("staging"))
+            .expect_err("corrupted payload must be rejected");
+        assert!(matches!(err, KopyError::ChecksumMismatch { .. }));
+        assert!(read_manifest(&src.path().join("a.txt")).is_err());
+    }
+
+    #[test]
+    fn test_is_contained_rejects_escaping_paths() {
+        assert!(is_contained(Path::new("a/b.txt")));
+        assert!(!is_contained(Path::new("../b.txt")));
+        assert!(!is_contained(Path::new("/etc/passwd")));
+    }
+}
@@ -0,0 +1,83 @@
+//! Destination snapshot index for offline batches
+
+use crate::types::{FileEntry, FileTree, KopyError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Current index file format.
+const INDEX_FORMAT: u32 = 1;
+
+/// A destination tree as scanned by `kopy batch index`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DestinationIndex {
+    /// Index file format version
+    pub format: u32,
+
+    /// Destination root the index was taken from (informational)
+    pub root: PathBuf,
+
+    /// Mtime resolution probed on the destination filesystem
+    pub mtime_resolution: Option<Duration>,
+
+    /// Destination entries by relative path
+    pub entries: BTreeMap<PathBuf, FileEntry>,
+}
+
+impl DestinationIndex {
+    /// Snapshot a freshly scanned destination tree.
+    pub fn from_tree(tree: &FileTree, mtime_resolution: Option<Duration>) -> Self {
+        Self {
+            format: INDEX_FORMAT,
+            root: tree.root_path.clone(),
+            mtime_resolution,
+            entries: tree
+                .iter()
+                .map(|(path, entry)| (path.clone(), entry.clone()))
+                .collect(),
+        }
+    }
+
+    /// Rebuild the destination tree the index was taken from.
+    pub fn to_tree(&self) -> FileTree {
+        let mut tree = FileTree::new(self.root.clone());
+        for (path, entry) in &self.entries {
+            tree.insert(path.clone(), entry.clone());
+        }
+        tree
+    }
+
+    /// Load an index file.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for unreadable, malformed or newer-format
+    /// index files.
+    pub fn load(path: &Path) -> Result<Self, KopyError> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            KopyError::Config(format!("Failed to read index {}: {}", path.display(), err))
+        })?;
+        let index: Self = serde_json::from_str(&text).map_err(|err| {
+            KopyError::Config(format!("Invalid index {}: {}", path.display(), err))
+        })?;
+        if index.format != INDEX_FORMAT {
+            return Err(KopyError::Config(format!(
+                "Unsupported index format {} in {}",
+                index.format,
+                path.display()
+            )));
+        }
+        Ok(index)
+    }
+
+    /// Write the index file atomically.
+    pub fn save(&self, path: &Path) -> Result<(), KopyError> {
+        let json = serde_json::to_string(self)
+            .map_err(|err| KopyError::Config(format!("Failed to encode index: {}", err)))?;
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        fs::write(&part, json).map_err(KopyError::Io)?;
+        fs::rename(&part, path).map_err(KopyError::Io)
+    }
+}
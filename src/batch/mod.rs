@@ -0,0 +1,16 @@
+//! Offline batches for air-gapped destinations
+//!
+//! The destination side records its tree with `kopy batch index DST
+//! dst.index` ([`DestinationIndex`]). The source side plans against that
+//! index and packs the actions plus the file contents they need into one
+//! `.kbatch` archive (`kopy batch create SRC dst.index out.kbatch`). Back at
+//! the destination, `kopy batch apply out.kbatch DST` checks that every path
+//! the batch touches still matches the index, unpacks the payloads after
+//! checking their Blake3 hashes and replays the actions through the
+//! executor.
+
+mod archive;
+mod index;
+
+pub use archive::{extract_payloads, read_manifest, write_batch, BatchManifest, Payload};
+pub use index::DestinationIndex;
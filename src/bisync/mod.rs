@@ -0,0 +1,18 @@
+//! Two-way sync between two directories
+//!
+//! `kopy bisync LEFT RIGHT` remembers both trees as they were after the last
+//! successful run ([`BisyncState`]). Comparing each side with its snapshot
+//! tells creates, edits and deletes apart, so changes made on either side are
+//! propagated to the other. Paths changed on both sides become conflicts,
+//! resolved by a [`ConflictPolicy`]; a version that loses a conflict goes to
+//! the `.kopy_trash` of its side or is kept under a conflict name, never
+//! overwritten in place.
+
+mod reconcile;
+mod state;
+
+pub use reconcile::{
+    classify, reconcile, resolve, BisyncStep, Change, Conflict, ConflictKind, ConflictPolicy,
+    Resolution, Side,
+};
+pub use state::{default_state_dir, state_file_path, BisyncState};
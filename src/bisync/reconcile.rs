@@ -0,0 +1,427 @@
+//! Change classification and conflict resolution
+
+use super::BisyncState;
+use crate::hash::compute_hash;
+use crate::types::{FileEntry, FileTree};
+use clap::ValueEnum;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// One side of a bisync pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    /// The opposite side.
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    /// Lowercase name for output and conflict file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+}
+
+/// How a path changed on one side since the last run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Unchanged,
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    fn is_write(self) -> bool {
+        matches!(self, Change::Created | Change::Modified)
+    }
+}
+
+/// Classify one side of a path against its snapshot entry.
+///
+/// Regular files compare by size and mtime, symlinks by target.
+pub fn classify(previous: Option<&FileEntry>, current: Option<&FileEntry>) -> Change {
+    match (previous, current) {
+        (None, None) => Change::Unchanged,
+        (None, Some(_)) => Change::Created,
+        (Some(_), None) => Change::Deleted,
+        (Some(previous), Some(current)) if same_version(previous, current) => Change::Unchanged,
+        (Some(_), Some(_)) => Change::Modified,
+    }
+}
+
+fn same_version(a: &FileEntry, b: &FileEntry) -> bool {
+    if a.is_symlink || b.is_symlink {
+        return a.is_symlink == b.is_symlink && a.symlink_target == b.symlink_target;
+    }
+    a.size == b.size && a.mtime == b.mtime
+}
+
+/// Why a path is in conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Created or modified on both sides with different content
+    BothChanged,
+    /// Deleted on the given side, created or modified on the other
+    DeletedOn(Side),
+}
+
+impl ConflictKind {
+    /// Short description for output.
+    pub fn describe(self) -> String {
+        match self {
+            ConflictKind::BothChanged => "changed on both sides".to_string(),
+            ConflictKind::DeletedOn(side) => format!(
+                "deleted on {}, changed on {}",
+                side.name(),
+                side.other().name()
+            ),
+        }
+    }
+}
+
+/// A path changed on both sides since the last run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub path: PathBuf,
+    pub kind: ConflictKind,
+    pub left: Option<FileEntry>,
+    pub right: Option<FileEntry>,
+}
+
+impl Conflict {
+    /// The current entry on `side`, if the path exists there.
+    pub fn entry(&self, side: Side) -> Option<&FileEntry> {
+        match side {
+            Side::Left => self.left.as_ref(),
+            Side::Right => self.right.as_ref(),
+        }
+    }
+}
+
+/// One reconciliation step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BisyncStep {
+    /// Copy `entry` from side `from` to the other side
+    Copy { from: Side, entry: FileEntry },
+    /// Delete `path` on side `on` (moved to that side's trash)
+    Delete { on: Side, path: PathBuf },
+    /// Changed on both sides; see [`resolve`]
+    Conflict(Conflict),
+}
+
+/// How to settle a path changed on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keep both versions; the right one is renamed to NAME.conflict-right-TIMESTAMP
+    #[default]
+    KeepBoth,
+    /// The more recently modified version wins (an edit beats a delete; ties stay unresolved)
+    Newer,
+    /// The left version wins
+    Left,
+    /// The right version wins
+    Right,
+    /// Report conflicts and leave both sides untouched
+    Skip,
+}
+
+/// Outcome of applying a [`ConflictPolicy`] to a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Leave both sides as they are
+    Unresolved,
+    /// Keep both versions under different names
+    KeepBoth,
+    /// Make the other side match this side (the losing version goes to trash)
+    Winner(Side),
+}
+
+/// Compare both trees with the previous snapshot and list the steps that
+/// bring them back in line.
+///
+/// Without a snapshot every path counts as created, so one-sided paths are
+/// copied and paths present on both sides with different content conflict.
+/// Paths created or modified on both sides with identical content (same
+/// Blake3 hash when mtimes differ) are already in sync.
+pub fn reconcile(
+    previous: Option<&BisyncState>,
+    left: &FileTree,
+    right: &FileTree,
+) -> Vec<BisyncStep> {
+    let mut paths: BTreeSet<&PathBuf> = left.paths().chain(right.paths()).collect();
+    if let Some(state) = previous {
+        paths.extend(state.left.keys().chain(state.right.keys()));
+    }
+
+    let mut steps = Vec::new();
+    for path in paths {
+        let current_left = left.get(path);
+        let current_right = right.get(path);
+        let left_change = classify(previous.and_then(|s| s.left.get(path)), current_left);
+        let right_change = classify(previous.and_then(|s| s.right.get(path)), current_right);
+
+        let conflict = |kind| {
+            BisyncStep::Conflict(Conflict {
+                path: path.clone(),
+                kind,
+                left: current_left.cloned(),
+                right: current_right.cloned(),
+            })
+        };
+        let step = match (left_change, right_change) {
+            (Change::Unchanged, Change::Unchanged) | (Change::Deleted, Change::Deleted) => None,
+            (Change::Unchanged, Change::Deleted) => current_left.map(|_| BisyncStep::Delete {
+                on: Side::Left,
+                path: path.clone(),
+            }),
+            (Change::Deleted, Change::Unchanged) => current_right.map(|_| BisyncStep::Delete {
+                on: Side::Right,
+                path: path.clone(),
+            }),
+            (change, Change::Unchanged) if change.is_write() => {
+                current_left.map(|entry| BisyncStep::Copy {
+                    from: Side::Left,
+                    entry: entry.clone(),
+                })
+            }
+            (Change::Unchanged, change) if change.is_write() => {
+                current_right.map(|entry| BisyncStep::Copy {
+                    from: Side::Right,
+                    entry: entry.clone(),
+                })
+            }
+            (Change::Deleted, _) => Some(conflict(ConflictKind::DeletedOn(Side::Left))),
+            (_, Change::Deleted) => Some(conflict(ConflictKind::DeletedOn(Side::Right))),
+            _ => match (current_left, current_right) {
+                (Some(l), Some(r)) if same_content(l, r, left, right) => None,
+                _ => Some(conflict(ConflictKind::BothChanged)),
+            },
+        };
+        steps.extend(step);
+    }
+    steps
+}
+
+fn same_content(
+    left: &FileEntry,
+    right: &FileEntry,
+    left_tree: &FileTree,
+    right_tree: &FileTree,
+) -> bool {
+    if same_version(left, right) {
+        return true;
+    }
+    if left.is_symlink || right.is_symlink || left.size != right.size {
+        return false;
+    }
+    match (
+        compute_hash(&left_tree.root_path.join(&left.path)),
+        compute_hash(&right_tree.root_path.join(&right.path)),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Apply `policy` to `conflict`.
+pub fn resolve(conflict: &Conflict, policy: ConflictPolicy) -> Resolution {
+    match (policy, conflict.kind) {
+        (ConflictPolicy::Skip, _) => Resolution::Unresolved,
+        (ConflictPolicy::Left, _) => Resolution::Winner(Side::Left),
+        (ConflictPolicy::Right, _) => Resolution::Winner(Side::Right),
+        // An edit is never dropped in favor of a delete by these two policies.
+        (ConflictPolicy::KeepBoth | ConflictPolicy::Newer, ConflictKind::DeletedOn(side)) => {
+            Resolution::Winner(side.other())
+        }
+        (ConflictPolicy::KeepBoth, ConflictKind::BothChanged) => Resolution::KeepBoth,
+        (ConflictPolicy::Newer, ConflictKind::BothChanged) => {
+            match (&conflict.left, &conflict.right) {
+                (Some(l), Some(r)) if l.mtime > r.mtime => Resolution::Winner(Side::Left),
+                (Some(l), Some(r)) if r.mtime > l.mtime => Resolution::Winner(Side::Right),
+                _ => Resolution::Unresolved,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn entry(path: &str, size: u64, secs: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            size,
+            UNIX_EPOCH + Duration::from_secs(secs),
+            0o644,
+        )
+    }
+
+    fn tree(root: &Path, entries: &[FileEntry]) -> FileTree {
+        let mut tree = FileTree::new(root.to_path_buf());
+        for entry in entries {
+            tree.insert(entry.path.clone(), entry.clone());
+        }
+        tree
+    }
+
+    #[test]
+    fn test_classify_tells_changes_apart() {
+        let old = entry("a", 1, 100);
+        assert_eq!(classify(None, Some(&old)), Change::Created);
+        assert_eq!(classify(Some(&old), None), Change::Deleted);
+        assert_eq!(classify(Some(&old), Some(&old)), Change::Unchanged);
+        assert_eq!(
+            classify(Some(&old), Some(&entry("a", 1, 200))),
+            Change::Modified
+        );
+    }
+
+    #[test]
+    fn test_reconcile_propagates_one_sided_changes() {
+        let root = Path::new("/unused");
+        let mut state = BisyncState::new(root, root);
+        for path in ["edited.txt", "removed.txt", "same.txt"] {
+            let e = entry(path, 1, 100);
+            state.record(Path::new(path), Some(&e), Some(&e));
+        }
+
+        let left = tree(
+            root,
+            &[
+                entry("edited.txt", 2, 200),
+                entry("removed.txt", 1, 100),
+                entry("same.txt", 1, 100),
+                entry("new-left.txt", 1, 300),
+            ],
+        );
+        let right = tree(
+            root,
+            &[
+                entry("edited.txt", 1, 100),
+                entry("same.txt", 1, 100),
+                entry("new-right.txt", 1, 300),
+            ],
+        );
+
+        let steps = reconcile(Some(&state), &left, &right);
+        assert_eq!(
+            steps,
+            vec![
+                BisyncStep::Copy {
+                    from: Side::Left,
+                    entry: entry("edited.txt", 2, 200)
+                },
+                BisyncStep::Copy {
+                    from: Side::Left,
+                    entry: entry("new-left.txt", 1, 300)
+                },
+                BisyncStep::Copy {
+                    from: Side::Right,
+                    entry: entry("new-right.txt", 1, 300)
+                },
+                BisyncStep::Delete {
+                    on: Side::Left,
+                    path: PathBuf::from("removed.txt")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reconcile_reports_conflicts_and_skips_converged_edits() {
+        let left_dir = TempDir::new().expect("create left");
+        let right_dir = TempDir::new().expect("create right");
+        fs::write(left_dir.path().join("same.txt"), b"same").expect("write left same");
+        fs::write(right_dir.path().join("same.txt"), b"same").expect("write right same");
+
+        let mut state = BisyncState::new(left_dir.path(), right_dir.path());
+        for path in ["both.txt", "gone.txt"] {
+            let e = entry(path, 1, 100);
+            state.record(Path::new(path), Some(&e), Some(&e));
+        }
+
+        let left = tree(
+            left_dir.path(),
+            &[
+                entry("both.txt", 2, 200),
+                entry("gone.txt", 5, 500),
+                entry("same.txt", 4, 100),
+            ],
+        );
+        let right = tree(
+            right_dir.path(),
+            &[entry("both.txt", 3, 300), entry("same.txt", 4, 900)],
+        );
+
+        let steps = reconcile(Some(&state), &left, &right);
+        let kinds: Vec<(PathBuf, ConflictKind)> = steps
+            .iter()
+            .map(|step| match step {
+                BisyncStep::Conflict(c) => (c.path.clone(), c.kind),
+                other => panic!("Expected only conflicts, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (PathBuf::from("both.txt"), ConflictKind::BothChanged),
+                (
+                    PathBuf::from("gone.txt"),
+                    ConflictKind::DeletedOn(Side::Right)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_policies() {
+        let conflict = Conflict {
+            path: PathBuf::from("a.txt"),
+            kind: ConflictKind::BothChanged,
+            left: Some(entry("a.txt", 1, 200)),
+            right: Some(entry("a.txt", 2, 100)),
+        };
+        assert_eq!(
+            resolve(&conflict, ConflictPolicy::Newer),
+            Resolution::Winner(Side::Left)
+        );
+        assert_eq!(
+            resolve(&conflict, ConflictPolicy::KeepBoth),
+            Resolution::KeepBoth
+        );
+        assert_eq!(
+            resolve(&conflict, ConflictPolicy::Skip),
+            Resolution::Unresolved
+        );
+
+        let deleted = Conflict {
+            kind: ConflictKind::DeletedOn(Side::Left),
+            left: None,
+            ..conflict
+        };
+        assert_eq!(
+            resolve(&deleted, ConflictPolicy::Newer),
+            Resolution::Winner(Side::Right)
+        );
+        assert_eq!(
+            resolve(&deleted, ConflictPolicy::Left),
+            Resolution::Winner(Side::Left)
+        );
+    }
+}
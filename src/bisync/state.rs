@@ -0,0 +1,180 @@
+//! Persisted snapshot of both sides after the last bisync run
+
+use crate::types::{FileEntry, FileTree, KopyError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Current state file format.
+const STATE_FORMAT: u32 = 1;
+
+/// Both trees as they were when the last bisync run finished.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BisyncState {
+    /// State file format version
+    pub format: u32,
+
+    /// Left root the snapshot was taken from (informational)
+    pub left_root: PathBuf,
+
+    /// Right root the snapshot was taken from (informational)
+    pub right_root: PathBuf,
+
+    /// Left entries by relative path
+    pub left: BTreeMap<PathBuf, FileEntry>,
+
+    /// Right entries by relative path
+    pub right: BTreeMap<PathBuf, FileEntry>,
+}
+
+impl BisyncState {
+    /// Create an empty snapshot for a pair of roots.
+    pub fn new(left_root: &Path, right_root: &Path) -> Self {
+        Self {
+            format: STATE_FORMAT,
+            left_root: left_root.to_path_buf(),
+            right_root: right_root.to_path_buf(),
+            ..Self::default()
+        }
+    }
+
+    /// Record `path` as present (or absent) on each side.
+    pub fn record(&mut self, path: &Path, left: Option<&FileEntry>, right: Option<&FileEntry>) {
+        for (entries, entry) in [(&mut self.left, left), (&mut self.right, right)] {
+            match entry {
+                Some(entry) => {
+                    entries.insert(path.to_path_buf(), entry.clone());
+                }
+                None => {
+                    entries.remove(path);
+                }
+            }
+        }
+    }
+
+    /// Snapshot two freshly scanned trees.
+    pub fn from_trees(left: &FileTree, right: &FileTree) -> Self {
+        let mut state = Self::new(&left.root_path, &right.root_path);
+        state.left = left
+            .iter()
+            .map(|(path, entry)| (path.clone(), entry.clone()))
+            .collect();
+        state.right = right
+            .iter()
+            .map(|(path, entry)| (path.clone(), entry.clone()))
+            .collect();
+        state
+    }
+
+    /// Load a state file; a missing file means no previous run.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for unreadable, malformed or newer-format
+    /// state files.
+    pub fn load(path: &Path) -> Result<Option<Self>, KopyError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(KopyError::Config(format!(
+                    "Failed to read bisync state {}: {}",
+                    path.display(),
+                    err
+                )))
+            }
+        };
+        let state: Self = serde_json::from_str(&text).map_err(|err| {
+            KopyError::Config(format!("Invalid bisync state {}: {}", path.display(), err))
+        })?;
+        if state.format != STATE_FORMAT {
+            return Err(KopyError::Config(format!(
+                "Unsupported bisync state format {} in {}",
+                state.format,
+                path.display()
+            )));
+        }
+        Ok(Some(state))
+    }
+
+    /// Write the state file atomically, creating its directory.
+    pub fn save(&self, path: &Path) -> Result<(), KopyError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(KopyError::Io)?;
+        }
+        let json = serde_json::to_string(self)
+            .map_err(|err| KopyError::Config(format!("Failed to encode bisync state: {}", err)))?;
+        let part = path.with_extension("json.part");
+        fs::write(&part, json).map_err(KopyError::Io)?;
+        fs::rename(&part, path).map_err(KopyError::Io)
+    }
+}
+
+/// Default directory for state files: `$XDG_STATE_HOME/kopy/bisync`, falling
+/// back to `~/.local/state/kopy/bisync`.
+pub fn default_state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|value| !value.is_empty())
+                .map(|home| PathBuf::from(home).join(".local").join("state"))
+        })
+        .map(|state_home| state_home.join("kopy").join("bisync"))
+}
+
+/// State file for a pair of roots inside `state_dir`.
+///
+/// The name is a Blake3 digest of both canonical roots, so the same pair
+/// finds its state again from any working directory.
+pub fn state_file_path(state_dir: &Path, left: &Path, right: &Path) -> PathBuf {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut key = canonical(left).into_os_string();
+    key.push("\0");
+    key.push(canonical(right).into_os_string());
+    let digest = blake3::hash(key.as_encoded_bytes()).to_hex();
+    state_dir.join(format!("{}.json", &digest[..32]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            3,
+            UNIX_EPOCH + Duration::from_secs(1_000),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_state_round_trips_and_missing_file_is_none() {
+        let dir = TempDir::new().expect("create temp dir");
+        let path = dir.path().join("nested/state.json");
+        assert_eq!(BisyncState::load(&path).expect("load missing"), None);
+
+        let mut state = BisyncState::new(Path::new("/a"), Path::new("/b"));
+        state.record(Path::new("x.txt"), Some(&entry("x.txt")), None);
+        state.save(&path).expect("save state");
+
+        let loaded = BisyncState::load(&path)
+            .expect("load state")
+            .expect("state exists");
+        assert_eq!(loaded, state);
+        assert!(loaded.right.is_empty());
+    }
+
+    #[test]
+    fn test_state_file_path_depends_on_order_of_roots() {
+        let dir = Path::new("/state");
+        let ab = state_file_path(dir, Path::new("/a"), Path::new("/b"));
+        assert_eq!(ab, state_file_path(dir, Path::new("/a"), Path::new("/b")));
+        assert_ne!(ab, state_file_path(dir, Path::new("/b"), Path::new("/a")));
+        assert!(ab.starts_with(dir));
+    }
+}
@@ -0,0 +1,83 @@
+//! `kopy apply`: execute a plan saved with `kopy plan --out`
+
+use crate::commands::sync::{
+    execute_with_progress, format_error_summary, has_executable_actions, print_plan_summary,
+    refuse_conflicts,
+};
+use crate::config::ApplyArgs;
+use crate::diff::{check_deletes, PlanDocument, StaleEntry};
+use crate::types::KopyError;
+use crate::ui::ProgressReporter;
+use std::sync::{Arc, Mutex};
+
+/// Re-validate a saved plan against the filesystem and execute it.
+///
+/// # Errors
+/// Returns `KopyError::Validation` when planned entries changed since the
+/// plan was saved (unless `--skip-stale`), or when the plan was built with
+/// `--conflict fail` and has conflicts, and `KopyError::DeleteRefused` when
+/// its deletes trip the guard of the settings it was built with.
+pub fn run(args: ApplyArgs) -> Result<(), KopyError> {
+    let document = PlanDocument::load(&args.plan)?;
+    let config = document.settings.to_config();
+    config.validate()?;
+
+    let stale = document.stale_entries();
+    let plan = if stale.is_empty() {
+        document.plan.clone()
+    } else {
+        println!("{}", format_stale_entries(&stale));
+        if !args.skip_stale {
+            return Err(KopyError::Validation(format!(
+                "{} planned entr{} changed since the plan was saved; \
+                 re-run `kopy plan` or pass --skip-stale",
+                stale.len(),
+                if stale.len() == 1 { "y" } else { "ies" }
+            )));
+        }
+        document.without_stale(&stale)
+    };
+
+    print_plan_summary(&plan);
+    refuse_conflicts(&plan.conflicts, &config)?;
+    check_deletes(&plan.stats, &document.totals, &config)?;
+    if !has_executable_actions(&plan) {
+        println!("Nothing to sync.");
+        return Ok(());
+    }
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let (result, records) = execute_with_progress(&plan, &config, reporter);
+    if !records.is_empty() {
+        println!("{}", format_error_summary(&records));
+    }
+    result
+}
+
+fn format_stale_entries(stale: &[StaleEntry]) -> String {
+    let mut lines = vec!["Stale entries:".to_string()];
+    lines.extend(
+        stale
+            .iter()
+            .map(|entry| format!("  STALE     {} ({})", entry.path.display(), entry.reason)),
+    );
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_stale_entries_lists_path_and_reason() {
+        let stale = [StaleEntry {
+            path: PathBuf::from("/dst/a.txt"),
+            reason: "size changed".to_string(),
+        }];
+        assert_eq!(
+            format_stale_entries(&stale),
+            "Stale entries:\n  STALE     /dst/a.txt (size changed)"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! `kopy batch`: index, create and apply offline batches
+
+use crate::batch::{extract_payloads, read_manifest, write_batch, DestinationIndex};
+use crate::commands::sync::{
+    destination_mtime_resolution, execute_with_progress, format_error_summary,
+    has_executable_actions, print_plan_summary, refuse_conflicts, scan_destination_tree,
+    scan_source_tree,
+};
+use crate::config::{BatchArgs, BatchCommand, FilterOptions, SyncOptions};
+use crate::diff::{
+    check_deletes, destination_paths, generate_sync_plan, stale_destination_entries, StaleEntry,
+    TreeTotals,
+};
+use crate::types::KopyError;
+use crate::ui::ProgressReporter;
+use crate::Config;
+use indicatif::HumanBytes;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Dispatch a `kopy batch` subcommand.
+pub fn run(args: BatchArgs) -> Result<(), KopyError> {
+    match args.command {
+        BatchCommand::Index {
+            destination,
+            index,
+            filters,
+        } => write_index(&destination, &index, &filters),
+        BatchCommand::Create {
+            source,
+            index,
+            out,
+            options,
+        } => create(&source, &index, &out, &options),
+        BatchCommand::Apply { batch, destination } => apply(&batch, &destination),
+    }
+}
+
+/// Scan `destination` and save it as an index.
+fn write_index(
+    destination: &Path,
+    index_path: &Path,
+    filters: &FilterOptions,
+) -> Result<(), KopyError> {
+    let mut config = Config {
+        destination: destination.to_path_buf(),
+        ..Config::default()
+    };
+    filters.apply_to(&mut config);
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let tree = scan_destination_tree(&config, &reporter, None, "destination")?;
+    let resolution = destination_mtime_resolution(destination, Some(&tree));
+    let index = DestinationIndex::from_tree(&tree, resolution);
+    index.save(index_path)?;
+    println!(
+        "Indexed {} file(s) of {} into {}.",
+        index.entries.len(),
+        destination.display(),
+        index_path.display()
+    );
+    Ok(())
+}
+
+/// Plan `source` against the index and pack the batch.
+fn create(
+    source: &Path,
+    index_path: &Path,
+    out: &Path,
+    options: &SyncOptions,
+) -> Result<(), KopyError> {
+    if options.checksum || options.files_from.is_some() || !options.extra_destinations.is_empty() {
+        return Err(KopyError::Config(
+            "--checksum, --files-from and --dest need the destination itself and cannot be used with batch create"
+                .to_string(),
+        ));
+    }
+
+    let index = DestinationIndex::load(index_path)?;
+    let mut config = Config {
+        source: source.to_path_buf(),
+        destination: index.root.clone(),
+        ..Config::default()
+    };
+    options.apply_to(&mut config);
+    if !source.is_dir() {
+        return Err(KopyError::Config(format!(
+            "Source must be a directory: {}",
+            source.display()
+        )));
+    }
+    if !config.size_only && !config.ignore_times && config.widen_modify_window {
+        if let Some(resolution) = index.mtime_resolution {
+            config.modify_window = config.modify_window.max(resolution);
+        }
+    }
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let src_tree = scan_source_tree(&config, &reporter, None)?;
+    let dest_tree = index.to_tree();
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    print_plan_summary(&plan);
+    let totals = TreeTotals::of(&src_tree, &dest_tree);
+    refuse_conflicts(&plan.conflicts, &config)?;
+    check_deletes(&plan.stats, &totals, &config)?;
+
+    let destination_entries = destination_paths(&plan)
+        .into_iter()
+        .filter_map(|path| index.entries.get(&path).map(|entry| (path, entry.clone())))
+        .collect();
+    let manifest = write_batch(out, &plan, &config, totals, destination_entries)?;
+    let payload_bytes: u64 = manifest.payloads.iter().map(|payload| payload.size).sum();
+    println!(
+        "Batch written to {}: {} action(s), {} file(s), {}.",
+        out.display(),
+        manifest.plan.actions.len(),
+        manifest.payloads.len(),
+        HumanBytes(payload_bytes)
+    );
+    Ok(())
+}
+
+/// Check `destination` against the batch, unpack it and replay it.
+fn apply(batch: &Path, destination: &Path) -> Result<(), KopyError> {
+    let manifest = read_manifest(batch)?;
+    let stale =
+        stale_destination_entries(&manifest.plan, destination, &manifest.destination_entries);
+    if !stale.is_empty() {
+        println!("{}", format_mismatches(&stale));
+        return Err(KopyError::Validation(format!(
+            "{} path(s) in {} no longer match the index the batch was created from",
+            stale.len(),
+            destination.display()
+        )));
+    }
+
+    let mut config = Config {
+        destination: destination.to_path_buf(),
+        delete_mode: manifest.delete_mode,
+        conflict_mode: manifest.conflict_mode,
+        max_delete: manifest.max_delete,
+        allow_empty_source: manifest.allow_empty_source,
+        ..Config::default()
+    };
+    print_plan_summary(&manifest.plan);
+    refuse_conflicts(&manifest.plan.conflicts, &config)?;
+    check_deletes(&manifest.plan.stats, &manifest.totals, &config)?;
+    if !has_executable_actions(&manifest.plan) {
+        println!("Nothing to sync.");
+        return Ok(());
+    }
+
+    let staging = Staging::create(destination)?;
+    extract_payloads(batch, &manifest, &staging.0)?;
+    config.source = staging.0.clone();
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let (result, records) = execute_with_progress(&manifest.plan, &config, reporter);
+    if !records.is_empty() {
+        println!("{}", format_error_summary(&records));
+    }
+    result
+}
+
+fn format_mismatches(stale: &[StaleEntry]) -> String {
+    let mut lines = vec!["Destination does not match the batch:".to_string()];
+    lines.extend(
+        stale
+            .iter()
+            .map(|entry| format!("  CHANGED   {} ({})", entry.path.display(), entry.reason)),
+    );
+    lines.join("\n")
+}
+
+/// Directory the payloads are unpacked into, removed when dropped.
+///
+/// It lives inside the destination, like `.kopy_trash`, so that it is on the
+/// same filesystem.
+struct Staging(PathBuf);
+
+impl Staging {
+    fn create(destination: &Path) -> Result<Self, KopyError> {
+        let path = destination.join(format!(".kopy_batch-{}", std::process::id()));
+        fs::create_dir_all(&path).map_err(KopyError::Io)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
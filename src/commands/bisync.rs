@@ -0,0 +1,407 @@
+//! `kopy bisync`: two-way sync with persisted state
+
+use super::sync::{execute_with_progress, format_error_summary, scan_destination_tree};
+use crate::bisync::{
+    default_state_dir, reconcile, resolve, state_file_path, BisyncState, BisyncStep, Conflict,
+    ConflictPolicy, Resolution, Side,
+};
+use crate::config::BisyncArgs;
+use crate::diff::{check_deletes, conflict_copy_path, DiffPlan, TreeTotals};
+use crate::types::{DeleteMode, FileEntry, FileTree, KopyError, SyncAction, UpdateReason};
+use crate::ui::ProgressReporter;
+use crate::Config;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Reconcile LEFT and RIGHT against the state of the last run and apply the
+/// resulting changes in both directions.
+///
+/// The state file is only written after a real run. Paths that could not be
+/// settled (unresolved conflicts, failed copies) keep their previous snapshot
+/// entries so they are looked at again next time.
+///
+/// A side that held files at the last run but is now missing or empty
+/// (an unmounted disk, say) is refused rather than read as "everything was
+/// deleted there", and the deletes of each direction go through the same
+/// guard as `kopy sync`.
+///
+/// # Errors
+/// Returns `KopyError::Validation` when a side vanished, conflicts were left
+/// unresolved or some actions failed, and `KopyError::DeleteRefused` when the
+/// deletes trip `--max-delete` or the empty-source guard; after execution
+/// the state file is still updated for every other path.
+pub fn run(args: BisyncArgs) -> Result<(), KopyError> {
+    let left_to_right = side_config(&args.left, &args.right, &args)?;
+    let right_to_left = side_config(&args.right, &args.left, &args)?;
+    if !args.left.is_dir() {
+        return Err(KopyError::Config(format!(
+            "Bisync roots must be directories: {:?}",
+            args.left
+        )));
+    }
+
+    let state_dir = match args.state_dir.clone().or_else(default_state_dir) {
+        Some(dir) => dir,
+        None => {
+            return Err(KopyError::Config(
+                "Cannot determine a state directory; pass --state-dir".to_string(),
+            ))
+        }
+    };
+    let state_path = state_file_path(&state_dir, &args.left, &args.right);
+    let previous = BisyncState::load(&state_path)?;
+
+    let (left_tree, right_tree) = scan_sides(&left_to_right, &right_to_left)?;
+    if let Some(previous) = &previous {
+        refuse_vanished_side(Side::Left, &previous.left, &left_tree, &left_to_right)?;
+        refuse_vanished_side(Side::Right, &previous.right, &right_tree, &left_to_right)?;
+    }
+    let steps = reconcile(previous.as_ref(), &left_tree, &right_tree);
+
+    if previous.is_none() {
+        println!("No previous bisync state: every path counts as new on its side.");
+    }
+    println!("{}", format_steps(&steps, args.conflict));
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut to_right = DiffPlan::new();
+    let mut to_left = DiffPlan::new();
+    // Conflict actions run after the sorted copies and deletes, in order:
+    // a keep-both rename must precede the copy into its path.
+    let mut settle_right = DiffPlan::new();
+    let mut settle_left = DiffPlan::new();
+    let mut unsettled: HashSet<PathBuf> = HashSet::new();
+    let mut unresolved = 0;
+
+    for step in &steps {
+        match step {
+            BisyncStep::Copy { from, entry } => {
+                let (plan, target_tree) = match from {
+                    Side::Left => (&mut to_right, &right_tree),
+                    Side::Right => (&mut to_left, &left_tree),
+                };
+                plan.add_action(copy_action(entry.clone(), target_tree));
+            }
+            BisyncStep::Delete { on, path } => match on {
+                Side::Left => to_left.add_action(SyncAction::Delete(path.clone())),
+                Side::Right => to_right.add_action(SyncAction::Delete(path.clone())),
+            },
+            BisyncStep::Conflict(conflict) => {
+                let settled = match resolve(conflict, args.conflict) {
+                    Resolution::Unresolved => {
+                        unresolved += 1;
+                        false
+                    }
+                    Resolution::KeepBoth => keep_both(
+                        conflict,
+                        &timestamp,
+                        &left_to_right,
+                        &mut settle_right,
+                        &mut settle_left,
+                    ),
+                    Resolution::Winner(winner) => {
+                        let plan = match winner {
+                            Side::Left => &mut settle_right,
+                            Side::Right => &mut settle_left,
+                        };
+                        take_winner(conflict, winner, plan);
+                        true
+                    }
+                };
+                if !settled {
+                    unsettled.insert(conflict.path.clone());
+                }
+            }
+        }
+    }
+
+    let mut plans = Vec::new();
+    for (mut plan, settle, config, target_tree, source_tree) in [
+        (
+            to_right,
+            settle_right,
+            &left_to_right,
+            &right_tree,
+            &left_tree,
+        ),
+        (
+            to_left,
+            settle_left,
+            &right_to_left,
+            &left_tree,
+            &right_tree,
+        ),
+    ] {
+        plan.sort_by_path();
+        for action in settle.actions {
+            plan.add_action(action);
+        }
+        plan.stats.delete_bytes = plan
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                SyncAction::Delete(path) => target_tree.get(path).map(|entry| entry.size),
+                _ => None,
+            })
+            .sum();
+        let totals = TreeTotals::of(source_tree, target_tree);
+        plans.push((plan, config, totals));
+    }
+
+    if args.dry_run {
+        println!("Dry-run mode: no changes were made.");
+    }
+    for (plan, config, totals) in &plans {
+        check_deletes(&plan.stats, totals, config)?;
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let mut failures = Vec::new();
+    for (plan, config, _) in &plans {
+        if plan.actions.is_empty() {
+            continue;
+        }
+        let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+        let (result, records) = execute_with_progress(plan, config, reporter);
+        if !records.is_empty() {
+            println!("{}", format_error_summary(&records));
+        }
+        unsettled.extend(records.iter().filter_map(|record| record.path.clone()));
+        if let Err(err) = result {
+            failures.push(err.to_string());
+        }
+    }
+
+    let (left_tree, right_tree) = scan_sides(&left_to_right, &right_to_left)?;
+    let mut state = BisyncState::from_trees(&left_tree, &right_tree);
+    for path in &unsettled {
+        state.record(
+            path,
+            previous.as_ref().and_then(|p| p.left.get(path)),
+            previous.as_ref().and_then(|p| p.right.get(path)),
+        );
+    }
+    state.save(&state_path)?;
+
+    if unresolved > 0 {
+        failures.push(format!(
+            "{} conflict(s) left unresolved (see --conflict)",
+            unresolved
+        ));
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(KopyError::Validation(failures.join("; ")))
+    }
+}
+
+/// Config for syncing `source` into `destination` with the bisync filters.
+///
+/// Deletes always go to the trash of the side they happen on.
+fn side_config(source: &Path, destination: &Path, args: &BisyncArgs) -> Result<Config, KopyError> {
+    let mut config = Config {
+        source: source.to_path_buf(),
+        destination: destination.to_path_buf(),
+        delete_mode: DeleteMode::Trash,
+        ..Config::default()
+    };
+    config.max_delete = args.max_delete;
+    config.allow_empty_source = args.allow_empty_source;
+    args.filters.apply_to(&mut config);
+    if source.exists() {
+        config.validate()?;
+    }
+    Ok(config)
+}
+
+/// Scan both roots, each as a destination so that its `.kopy_trash` is skipped.
+fn scan_sides(
+    left_to_right: &Config,
+    right_to_left: &Config,
+) -> Result<(FileTree, FileTree), KopyError> {
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let left = scan_destination_tree(right_to_left, &reporter, None, "left")?;
+    let right = scan_destination_tree(left_to_right, &reporter, None, "right")?;
+    Ok((left, right))
+}
+
+fn copy_action(entry: FileEntry, target_tree: &FileTree) -> SyncAction {
+    if target_tree.contains(&entry.path) {
+        SyncAction::Overwrite(entry, UpdateReason::ChangedOnOtherSide)
+    } else {
+        SyncAction::CopyNew(entry)
+    }
+}
+
+/// Refuse a side that held files at the last run but is now missing or
+/// empty, unless `--allow-empty-source` says emptying it was intended.
+fn refuse_vanished_side(
+    side: Side,
+    previous: &BTreeMap<PathBuf, FileEntry>,
+    tree: &FileTree,
+    config: &Config,
+) -> Result<(), KopyError> {
+    if previous.is_empty() || !tree.is_empty() || config.allow_empty_source {
+        return Ok(());
+    }
+    Err(KopyError::Validation(format!(
+        "the {} side {} is missing or empty but held {} file(s) at the last run; \
+         check that it is mounted and spelled correctly, or pass --allow-empty-source",
+        side.name(),
+        tree.root_path.display(),
+        previous.len()
+    )))
+}
+
+/// Rename the right version to a conflict name, then copy each version to
+/// the side that lacks it.
+///
+/// Returns `false` when the conflict name is already taken.
+fn keep_both(
+    conflict: &Conflict,
+    timestamp: &str,
+    left_to_right: &Config,
+    to_right: &mut DiffPlan,
+    to_left: &mut DiffPlan,
+) -> bool {
+    let (Some(left), Some(right)) = (&conflict.left, &conflict.right) else {
+        return false;
+    };
+    let renamed = conflict_copy_path(&conflict.path, Side::Right.name(), timestamp);
+    if left_to_right
+        .destination
+        .join(&renamed)
+        .symlink_metadata()
+        .is_ok()
+        || left_to_right
+            .source
+            .join(&renamed)
+            .symlink_metadata()
+            .is_ok()
+    {
+        return false;
+    }
+
+    to_right.add_action(SyncAction::Move {
+        from: conflict.path.clone(),
+        to: renamed.clone(),
+    });
+    to_right.add_action(SyncAction::CopyNew(left.clone()));
+    to_left.add_action(SyncAction::CopyNew(FileEntry {
+        path: renamed,
+        ..right.clone()
+    }));
+    true
+}
+
+/// Make the losing side match `winner`; the losing version is deleted (to
+/// the trash of its side) before the winner is copied over.
+fn take_winner(conflict: &Conflict, winner: Side, plan: &mut DiffPlan) {
+    if conflict.entry(winner.other()).is_some() {
+        plan.add_action(SyncAction::Delete(conflict.path.clone()));
+    }
+    if let Some(entry) = conflict.entry(winner) {
+        plan.add_action(SyncAction::CopyNew(entry.clone()));
+    }
+}
+
+/// One line per step: `>` goes from left to right, `<` from right to left.
+fn format_steps(steps: &[BisyncStep], policy: ConflictPolicy) -> String {
+    if steps.is_empty() {
+        return "Both sides are in sync.".to_string();
+    }
+
+    let (mut copies, mut deletes, mut conflicts) = (0, 0, 0);
+    let mut lines = Vec::new();
+    for step in steps {
+        lines.push(match step {
+            BisyncStep::Copy { from, entry } => {
+                copies += 1;
+                format!("  {}  COPY      {}", arrow(*from), entry.path.display())
+            }
+            BisyncStep::Delete { on, path } => {
+                deletes += 1;
+                format!("  {}  DELETE    {}", arrow(on.other()), path.display())
+            }
+            BisyncStep::Conflict(conflict) => {
+                conflicts += 1;
+                let outcome = match resolve(conflict, policy) {
+                    Resolution::Unresolved => "left as is".to_string(),
+                    Resolution::KeepBoth => "keep both".to_string(),
+                    Resolution::Winner(side) => format!("{} wins", side.name()),
+                };
+                format!(
+                    "  !  CONFLICT  {} ({}; {})",
+                    conflict.path.display(),
+                    conflict.kind.describe(),
+                    outcome
+                )
+            }
+        });
+    }
+    lines.insert(
+        0,
+        format!(
+            "Bisync plan:\n  Copy: {}  Delete: {}  Conflict: {}",
+            copies, deletes, conflicts
+        ),
+    );
+    lines.join("\n")
+}
+
+fn arrow(from: Side) -> &'static str {
+    match from {
+        Side::Left => ">",
+        Side::Right => "<",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bisync::ConflictKind;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(path: &str, secs: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            1,
+            UNIX_EPOCH + Duration::from_secs(secs),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_format_steps_shows_direction_and_conflict_outcome() {
+        let steps = vec![
+            BisyncStep::Copy {
+                from: Side::Left,
+                entry: entry("a.txt", 1),
+            },
+            BisyncStep::Delete {
+                on: Side::Left,
+                path: PathBuf::from("b.txt"),
+            },
+            BisyncStep::Conflict(Conflict {
+                path: PathBuf::from("c.txt"),
+                kind: ConflictKind::BothChanged,
+                left: Some(entry("c.txt", 1)),
+                right: Some(entry("c.txt", 2)),
+            }),
+        ];
+
+        let text = format_steps(&steps, ConflictPolicy::Newer);
+        assert!(text.contains("Copy: 1  Delete: 1  Conflict: 1"));
+        assert!(text.contains(">  COPY      a.txt"));
+        assert!(text.contains("<  DELETE    b.txt"));
+        assert!(text.contains("!  CONFLICT  c.txt (changed on both sides; right wins)"));
+        assert_eq!(
+            format_steps(&[], ConflictPolicy::Skip),
+            "Both sides are in sync."
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! `kopy diff`: list paths that differ between source and destination
+
+use crate::commands::sync::build_plans;
+use crate::diff::DiffPlan;
+use crate::types::{DeleteMode, KopyError, SyncAction};
+use crate::Config;
+
+/// Print one line per differing path.
+///
+/// `+` marks source-only paths, `~` paths whose content differs, `*` paths
+/// whose content matches but permissions or mtime differ, `-`
+/// destination-only paths and `!` destination files newer than their source. Comparison uses size/mtime unless checksum mode
+/// is enabled. With several destinations, each listing is headed by its
+/// destination path.
+pub fn run(mut config: Config) -> Result<(), KopyError> {
+    config.dry_run = true;
+    if config.delete_mode == DeleteMode::None {
+        config.delete_mode = DeleteMode::Trash;
+    }
+
+    let plans = build_plans(&config)?;
+    let several = plans.len() > 1;
+    for (plan, target) in &plans {
+        if several {
+            println!("Destination: {}", target.destination.display());
+        }
+        let output = format_diff_lines(plan);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+    }
+    Ok(())
+}
+
+fn format_diff_lines(plan: &DiffPlan) -> String {
+    plan.actions
+        .iter()
+        .filter_map(|action| match action {
+            SyncAction::CopyNew(entry) => Some(format!("+ {}", entry.path.display())),
+            SyncAction::Overwrite(entry, _) => Some(format!("~ {}", entry.path.display())),
+            SyncAction::UpdateMetadata(entry) => Some(format!("* {}", entry.path.display())),
+            SyncAction::Delete(path) => Some(format!("- {}", path.display())),
+            SyncAction::Move { from, to } => {
+                Some(format!("> {} -> {}", from.display(), to.display()))
+            }
+            SyncAction::Skip => None,
+        })
+        .chain(
+            plan.conflicts
+                .iter()
+                .map(|conflict| format!("! {}", conflict.path.display())),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileEntry, UpdateReason};
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format_diff_lines_marks_each_kind() {
+        let entry = |path: &str| {
+            FileEntry::new(
+                PathBuf::from(path),
+                1,
+                UNIX_EPOCH + Duration::from_secs(1_000),
+                0o644,
+            )
+        };
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::CopyNew(entry("new.txt")));
+        plan.add_action(SyncAction::Overwrite(
+            entry("changed.txt"),
+            UpdateReason::SourceNewer,
+        ));
+        plan.add_action(SyncAction::Delete(PathBuf::from("gone.txt")));
+        plan.add_action(SyncAction::Skip);
+
+        assert_eq!(
+            format_diff_lines(&plan),
+            "+ new.txt\n~ changed.txt\n- gone.txt"
+        );
+    }
+
+    #[test]
+    fn test_format_diff_lines_empty_for_identical_trees() {
+        assert!(format_diff_lines(&DiffPlan::new()).is_empty());
+    }
+}
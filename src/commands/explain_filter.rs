@@ -0,0 +1,116 @@
+//! `kopy explain-filter`: show why a path is included or excluded
+
+use crate::config::ExplainFilterArgs;
+use crate::filter::{explain_path, FilterExplanation, LayerVerdict};
+use crate::types::KopyError;
+use crate::Config;
+
+/// Explain the filter verdict for one path.
+pub fn run(args: ExplainFilterArgs) -> Result<(), KopyError> {
+    let mut config = Config {
+        source: args.root.clone(),
+        ..Config::default()
+    };
+    args.filters.apply_to(&mut config);
+
+    let explanation = explain_path(&args.root, &args.path, &config)?;
+    println!("{}", format_explanation(&explanation));
+    Ok(())
+}
+
+fn format_explanation(explanation: &FilterExplanation) -> String {
+    let kind = match (explanation.exists, explanation.is_dir) {
+        (false, _) => "missing, evaluated as a file",
+        (true, true) => "directory",
+        (true, false) => "file",
+    };
+    let mut lines = vec![format!(
+        "{} ({})",
+        explanation.relative_path.display(),
+        kind
+    )];
+
+    let width = explanation
+        .layers
+        .iter()
+        .map(|report| report.layer.label().len())
+        .max()
+        .unwrap_or(0);
+    for report in &explanation.layers {
+        let detail = match &report.verdict {
+            LayerVerdict::Inactive(reason) => format!("inactive ({})", reason),
+            LayerVerdict::NoMatch => "no match".to_string(),
+            LayerVerdict::Include(hit) | LayerVerdict::Exclude(hit) => {
+                let action = if matches!(report.verdict, LayerVerdict::Include(_)) {
+                    "include"
+                } else {
+                    "exclude"
+                };
+                let mut detail = format!("{}  '{}'  {}", action, hit.rule, hit.source);
+                if hit.matched_path != explanation.relative_path {
+                    detail.push_str(&format!(" (via {})", hit.matched_path.display()));
+                }
+                detail
+            }
+        };
+        lines.push(format!(
+            "  {:<width$}  {}",
+            report.layer.label(),
+            detail,
+            width = width
+        ));
+    }
+
+    lines.push(match explanation.excluded_by {
+        Some(layer) => format!("Result: excluded by {}", layer.label()),
+        None => "Result: included".to_string(),
+    });
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{FilterLayer, LayerReport, RuleHit, RuleSource};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_explanation_shows_rule_location_and_ancestor() {
+        let explanation = FilterExplanation {
+            relative_path: PathBuf::from("build/app"),
+            is_dir: false,
+            exists: true,
+            layers: vec![
+                LayerReport {
+                    layer: FilterLayer::CustomIgnore,
+                    verdict: LayerVerdict::NoMatch,
+                },
+                LayerReport {
+                    layer: FilterLayer::GitIgnore,
+                    verdict: LayerVerdict::Exclude(RuleHit {
+                        rule: "build/".to_string(),
+                        source: RuleSource::File {
+                            path: PathBuf::from("/repo/.gitignore"),
+                            line: 3,
+                        },
+                        matched_path: PathBuf::from("build"),
+                    }),
+                },
+                LayerReport {
+                    layer: FilterLayer::FilterRules,
+                    verdict: LayerVerdict::Inactive("no filter rules configured"),
+                },
+            ],
+            excluded_by: Some(FilterLayer::GitIgnore),
+        };
+
+        let text = format_explanation(&explanation);
+        assert!(text.starts_with("build/app (file)"));
+        assert!(text.contains("  .kopyignore/custom  no match"));
+        assert!(
+            text.contains(".gitignore          exclude  'build/'  /repo/.gitignore:3 (via build)")
+        );
+        assert!(text.contains("inactive (no filter rules configured)"));
+        assert!(text.ends_with("Result: excluded by .gitignore"));
+    }
+}
@@ -1,3 +1,13 @@
 //! Command implementations
 
+pub mod apply;
+pub mod batch;
+pub mod bisync;
+pub mod diff;
+pub mod explain_filter;
+pub mod plan;
+pub mod presets;
+pub mod run;
 pub mod sync;
+pub mod trash;
+pub mod verify;
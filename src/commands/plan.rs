@@ -0,0 +1,190 @@
+//! `kopy plan`: print the full sync plan without executing it
+
+use crate::commands::sync::{
+    build_plans, print_dry_run_actions, print_plan_summary, scan_trees, widen_modify_window,
+};
+use crate::diff::{generate_sync_plan, DiffPlan, PlanDocument};
+use crate::types::{KopyError, SyncAction};
+use crate::ui::ProgressReporter;
+use crate::Config;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Scan both sides and print every planned action, per destination.
+///
+/// With `explain`, only the actions touching that path are printed, each
+/// with the reason it was planned. With `out`, the plan is also saved as a
+/// [`PlanDocument`] for `kopy apply`. Nothing is written to the destination
+/// regardless of `config.dry_run`.
+///
+/// # Errors
+/// Returns `KopyError::Config` when `out` is given for a single-file source
+/// or several destinations.
+pub fn run(
+    mut config: Config,
+    explain: Option<&Path>,
+    out: Option<&Path>,
+) -> Result<(), KopyError> {
+    // Planning must not touch the destination, not even to probe it.
+    config.dry_run = true;
+    if let Some(out) = out {
+        return save_plan(config, explain, out);
+    }
+
+    let plans = build_plans(&config)?;
+    let several = plans.len() > 1;
+    for (plan, target) in &plans {
+        if several {
+            println!("Destination: {}", target.destination.display());
+        }
+        print_plan(plan, target, explain);
+    }
+    println!("Plan only: no changes were made.");
+    Ok(())
+}
+
+/// Build the plan for one destination directory and save it to `out`.
+fn save_plan(mut config: Config, explain: Option<&Path>, out: &Path) -> Result<(), KopyError> {
+    if config.source.is_file() || !config.extra_destinations.is_empty() {
+        return Err(KopyError::Config(
+            "--out needs a source directory and a single destination".to_string(),
+        ));
+    }
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let (src_tree, dest_tree) = scan_trees(&config, &reporter)?;
+    widen_modify_window(&mut config, Some(&dest_tree));
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    print_plan(&plan, &config, explain);
+
+    PlanDocument::new(plan, &config, &src_tree, &dest_tree).save(out)?;
+    println!(
+        "Plan saved to {}; run `kopy apply {}` to execute it.",
+        out.display(),
+        out.display()
+    );
+    Ok(())
+}
+
+fn print_plan(plan: &DiffPlan, target: &Config, explain: Option<&Path>) {
+    match explain {
+        Some(path) => {
+            let path = relative_to_roots(path, target);
+            println!("{}", format_explanation(plan, &path));
+        }
+        None => {
+            print_plan_summary(plan);
+            print_dry_run_actions(plan);
+        }
+    }
+}
+
+/// Strip the source or destination root from an absolute `path`.
+fn relative_to_roots(path: &Path, config: &Config) -> PathBuf {
+    config
+        .sources()
+        .chain(std::iter::once(&config.destination))
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Describe every planned action and conflict that touches `path`.
+fn format_explanation(plan: &DiffPlan, path: &Path) -> String {
+    let mut lines = vec![path.display().to_string()];
+    for action in &plan.actions {
+        let reason = match action {
+            SyncAction::CopyNew(entry) if entry.path == path => {
+                "COPY      missing in destination".to_string()
+            }
+            SyncAction::Overwrite(entry, reason) if entry.path == path => {
+                format!("UPDATE    {}", reason.describe())
+            }
+            SyncAction::UpdateMetadata(entry) if entry.path == path => {
+                "METADATA  content matches; permissions or mtime differ".to_string()
+            }
+            SyncAction::Delete(deleted) if deleted == path => "DELETE    not in source".to_string(),
+            SyncAction::Move { from, to } if path.starts_with(from) || path.starts_with(to) => {
+                format!(
+                    "MOVE      {} -> {} (renamed in source)",
+                    from.display(),
+                    to.display()
+                )
+            }
+            _ => continue,
+        };
+        lines.push(format!("  {}", reason));
+    }
+    for conflict in plan.conflicts.iter().filter(|c| c.path == path) {
+        lines.push(format!(
+            "  CONFLICT  destination is newer; {}",
+            conflict.outcome.describe()
+        ));
+    }
+
+    if lines.len() == 1 {
+        lines.push(
+            "  no action: unchanged, or filtered out / absent on both sides \
+             (see `kopy explain-filter`)"
+                .to_string(),
+        );
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileEntry, UpdateReason};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            12,
+            UNIX_EPOCH + Duration::from_secs(1_000),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_format_explanation_shows_reason_for_path() {
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::Overwrite(
+            entry("app.conf"),
+            UpdateReason::SizeChanged {
+                source: 12,
+                destination: 10,
+            },
+        ));
+        plan.add_action(SyncAction::CopyNew(entry("new.txt")));
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("old"),
+            to: PathBuf::from("new"),
+        });
+
+        assert_eq!(
+            format_explanation(&plan, Path::new("app.conf")),
+            "app.conf\n  UPDATE    size changed: 10 -> 12 bytes"
+        );
+        assert!(format_explanation(&plan, Path::new("new/a.txt")).contains("MOVE      old -> new"));
+        assert!(format_explanation(&plan, Path::new("same.txt")).contains("no action"));
+    }
+
+    #[test]
+    fn test_relative_to_roots_strips_source_or_destination() {
+        let config = Config {
+            source: PathBuf::from("/srv/src"),
+            destination: PathBuf::from("/srv/dst"),
+            ..Config::default()
+        };
+        assert_eq!(
+            relative_to_roots(Path::new("/srv/dst/a/b.txt"), &config),
+            PathBuf::from("a/b.txt")
+        );
+        assert_eq!(
+            relative_to_roots(Path::new("a/b.txt"), &config),
+            PathBuf::from("a/b.txt")
+        );
+    }
+}
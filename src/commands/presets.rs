@@ -0,0 +1,47 @@
+//! `kopy presets`: show the built-in exclude sets
+
+use crate::config::{Preset, PresetsArgs, PresetsCommand};
+use crate::types::KopyError;
+
+/// Dispatch a `kopy presets` subcommand.
+pub fn run(args: PresetsArgs) -> Result<(), KopyError> {
+    match args.command {
+        PresetsCommand::List => println!("{}", format_preset_list(&Preset::ALL)),
+    }
+    Ok(())
+}
+
+fn format_preset_list(presets: &[Preset]) -> String {
+    let width = presets
+        .iter()
+        .map(|preset| preset.name().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for preset in presets {
+        lines.push(format!(
+            "{:<width$}  {}",
+            preset.name(),
+            preset.description()
+        ));
+        lines.push(format!("{:<width$}  {}", "", preset.patterns().join("  ")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_preset_list_aligns_names_and_patterns() {
+        let output = format_preset_list(&[Preset::Rust, Preset::OsJunk]);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "rust     Cargo build output");
+        assert_eq!(lines[1], "         **/target/**  **/*.rs.bk");
+        assert!(lines[2].starts_with("os-junk  macOS"));
+        assert!(lines[3].contains("**/.DS_Store"));
+    }
+}
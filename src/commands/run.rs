@@ -0,0 +1,105 @@
+//! `kopy run <job>`: resolve a named job from `kopy.toml`
+
+use crate::config::{locate_profile_file, profile_search_paths, ProfileFile, RunArgs};
+use crate::types::KopyError;
+use crate::Config;
+
+/// Resolve the configuration for a named job.
+///
+/// Uses `--config` when given, otherwise the first `kopy.toml` found by
+/// [`profile_search_paths`]. CLI flags in `args` override the job's values.
+///
+/// # Errors
+/// Returns `KopyError::Config` when no profile file exists, the job is not
+/// declared, or the merged configuration fails validation.
+pub fn load_job_config(args: &RunArgs) -> Result<Config, KopyError> {
+    let path = match &args.config {
+        Some(path) => path.clone(),
+        None => locate_profile_file().ok_or_else(|| {
+            let searched = profile_search_paths()
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            KopyError::Config(format!("No kopy.toml found (searched: {})", searched))
+        })?,
+    };
+
+    let profiles = ProfileFile::load(&path)?;
+    let job = profiles.job(&args.job).ok_or_else(|| {
+        let available = profiles.job_names();
+        KopyError::Config(format!(
+            "Job '{}' not found in {} (available: {})",
+            args.job,
+            path.display(),
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        ))
+    })?;
+
+    Config::from_job(job, &args.options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SyncOptions;
+    use crate::DeleteMode;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_profile(dir: &TempDir) -> std::path::PathBuf {
+        fs::create_dir_all(dir.path().join("src")).expect("create source dir");
+        let path = dir.path().join("kopy.toml");
+        fs::write(
+            &path,
+            "[[job]]\nname = \"mirror\"\nsource = \"src\"\ndestination = \"dst\"\ndelete = \"trash\"\n",
+        )
+        .expect("write profile");
+        path
+    }
+
+    #[test]
+    fn test_load_job_config_resolves_named_job() {
+        let dir = TempDir::new().expect("create temp dir");
+        let path = write_profile(&dir);
+
+        let args = RunArgs {
+            job: "mirror".to_string(),
+            config: Some(path),
+            options: SyncOptions {
+                dry_run: true,
+                ..SyncOptions::default()
+            },
+        };
+
+        let config = load_job_config(&args).expect("load job");
+        assert_eq!(config.source, dir.path().join("src"));
+        assert_eq!(config.destination, dir.path().join("dst"));
+        assert_eq!(config.delete_mode, DeleteMode::Trash);
+        assert!(config.dry_run);
+    }
+
+    #[test]
+    fn test_load_job_config_unknown_job_lists_available() {
+        let dir = TempDir::new().expect("create temp dir");
+        let path = write_profile(&dir);
+
+        let args = RunArgs {
+            job: "missing".to_string(),
+            config: Some(path),
+            options: SyncOptions::default(),
+        };
+
+        match load_job_config(&args) {
+            Err(KopyError::Config(msg)) => {
+                assert!(msg.contains("'missing' not found"));
+                assert!(msg.contains("mirror"));
+            }
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+}
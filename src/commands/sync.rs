@@ -1,14 +1,20 @@
 //! Main sync command
 
-use crate::diff::{compare_files, generate_sync_plan, DiffPlan};
-use crate::executor::{execute_plan, execute_plan_parallel, ExecutionEvent};
+use crate::diff::{
+    check_deletes, compare_files, generate_sync_plan, merge_sync_plan, DiffPlan, PlanConflict,
+    PlanStats, StreamedPlan, TreeTotals,
+};
+use crate::executor::{execute_plan, execute_plan_parallel, ExecutionEvent, ExecutionStats};
+use crate::hash::compute_hash;
 use crate::scanner::{
-    resolve_scan_mode, scan_directory, scan_directory_parallel, ResolvedScanMode,
+    infer_mtime_resolution, read_file_list, resolve_scan_mode, scan_directory,
+    scan_directory_parallel, scan_file_list, scan_sorted, ResolvedScanMode,
 };
 use crate::types::{FileEntry, FileTree, KopyError, SyncAction};
 use crate::ui::ProgressReporter;
-use crate::Config;
-use indicatif::HumanBytes;
+use crate::{Config, ConflictMode};
+use indicatif::{HumanBytes, MultiProgress};
+use std::borrow::Borrow;
 use std::io::ErrorKind;
 use std::sync::{Arc, Mutex};
 use std::{collections::BTreeMap, path::PathBuf};
@@ -16,7 +22,11 @@ use std::{collections::BTreeMap, path::PathBuf};
 /// Run a full sync cycle for the provided configuration.
 ///
 /// This scans source and destination, builds a diff plan, prints a summary,
-/// and executes actions unless `dry_run` is enabled.
+/// and executes actions unless `dry_run` is enabled. A dry run still fails
+/// when `--conflict fail` or the delete guard would refuse the plan. With
+/// `extra_destinations`, the source is scanned once and every destination is
+/// synced from that scan. With `streaming`, the plan is merged from sorted
+/// scans (see [`crate::diff::merge_sync_plan`]) instead of complete trees.
 ///
 /// # Example
 /// ```no_run
@@ -32,71 +42,241 @@ use std::{collections::BTreeMap, path::PathBuf};
 /// run(config)?;
 /// # Ok::<(), kopy::types::KopyError>(())
 /// ```
-pub fn run(config: Config) -> Result<(), KopyError> {
+pub fn run(mut config: Config) -> Result<(), KopyError> {
+    if !config.extra_destinations.is_empty() {
+        return run_fan_out(config);
+    }
     if config.source.is_file() {
         return run_single_file_sync(config);
     }
+    if config.streaming {
+        return run_streaming(config);
+    }
 
     let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let (src_tree, dest_tree) = scan_trees(&config, &reporter)?;
+
+    widen_modify_window(&mut config, Some(&dest_tree));
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    print_plan_summary(&plan);
+
+    if config.dry_run {
+        print_dry_run_actions(&plan);
+        println!("Dry-run mode: no changes were made.");
+    }
+
+    refuse_conflicts(&plan.conflicts, &config)?;
+    check_deletes(&plan.stats, &TreeTotals::of(&src_tree, &dest_tree), &config)?;
+    if config.dry_run {
+        return Ok(());
+    }
+    if !has_executable_actions(&plan) {
+        println!("Nothing to sync.");
+        return Ok(());
+    }
+
+    let (result, records) = execute_with_progress(&plan, &config, reporter);
+    if !records.is_empty() {
+        println!("{}", format_error_summary(&records));
+    }
+
+    result
+}
+
+/// Actions executed per chunk of a streamed plan.
+const STREAMING_CHUNK_ACTIONS: usize = 10_000;
+
+/// Sync with a plan merged from sorted scans.
+///
+/// Neither tree is held in memory. Planned actions spill to disk beyond
+/// [`crate::diff::SPILL_THRESHOLD`] and are listed or executed chunk by
+/// chunk.
+fn run_streaming(mut config: Config) -> Result<(), KopyError> {
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    widen_modify_window(&mut config, None);
+    let (mut plan, totals) = stream_plan(&config, &reporter)?;
+    println!("{}", format_plan_preview(&plan.stats));
+
+    if config.dry_run {
+        println!("Dry-run actions:");
+        if planned_action_count(&plan.stats) == 0
+            && plan.conflicts.is_empty()
+            && plan.protected.is_empty()
+        {
+            println!("  (no planned actions)");
+        }
+        for chunk in plan.chunks(STREAMING_CHUNK_ACTIONS)? {
+            for line in chunk?.actions.iter().filter_map(format_action_line) {
+                println!("{}", line);
+            }
+        }
+        for conflict in &plan.conflicts {
+            println!("{}", format_conflict_line(conflict));
+        }
+        for path in &plan.protected {
+            println!("  PROTECT   {}", path.display());
+        }
+        println!("Dry-run mode: no changes were made.");
+    }
+
+    refuse_conflicts(&plan.conflicts, &config)?;
+    check_deletes(&plan.stats, &totals, &config)?;
+    if config.dry_run {
+        return Ok(());
+    }
+    let stats = plan.stats.clone();
+    if planned_action_count(&stats) == 0 {
+        println!("Nothing to sync.");
+        return Ok(());
+    }
+
+    let chunks = plan.chunks(STREAMING_CHUNK_ACTIONS)?;
+    let (result, records) = execute_chunks_with_progress(chunks, &stats, &config, reporter);
+    if !records.is_empty() {
+        println!("{}", format_error_summary(&records));
+    }
+    result
+}
 
+/// Actions other than skips counted in `stats`.
+fn planned_action_count(stats: &PlanStats) -> usize {
+    stats.total_files + stats.metadata_count + stats.delete_count + stats.move_count
+}
+
+/// Scan source and destination side by side in path order and merge them
+/// into a plan.
+fn stream_plan(
+    config: &Config,
+    reporter: &Arc<Mutex<ProgressReporter>>,
+) -> Result<(StreamedPlan, TreeTotals), KopyError> {
+    const LABEL: &str = "source and destination";
     if let Ok(progress) = reporter.lock() {
-        progress.start_scan("source");
+        progress.start_scan(LABEL);
     }
-    let src_progress: crate::scanner::ProgressCallback = {
-        let reporter = Arc::clone(&reporter);
+    // Both scans advance together, so the spinner shows their sum.
+    let counts = Arc::new(Mutex::new([(0u64, 0u64); 2]));
+    let side_progress = |side: usize| -> crate::scanner::ProgressCallback {
+        let reporter = Arc::clone(reporter);
+        let counts = Arc::clone(&counts);
         Box::new(move |files: u64, bytes: u64| {
-            if let Ok(progress) = reporter.lock() {
-                progress.update_scan("source", files, bytes);
+            if let Ok(mut counts) = counts.lock() {
+                counts[side] = (files, bytes);
+                if let Ok(progress) = reporter.lock() {
+                    progress.update_scan(
+                        LABEL,
+                        counts[0].0 + counts[1].0,
+                        counts[0].1 + counts[1].1,
+                    );
+                }
             }
         })
     };
-    let src_tree = scan_with_mode(&config.source, &config, Some(&src_progress))?;
-    if let Ok(progress) = reporter.lock() {
-        progress.finish_scan("source", src_tree.total_files, src_tree.total_size);
-        progress.start_scan("destination");
-    }
+    let src_progress = side_progress(0);
+    let dest_progress = side_progress(1);
 
-    let dest_tree = if config.destination.exists() {
-        let dest_progress: crate::scanner::ProgressCallback = {
-            let reporter = Arc::clone(&reporter);
-            Box::new(move |files: u64, bytes: u64| {
-                if let Ok(progress) = reporter.lock() {
-                    progress.update_scan("destination", files, bytes);
-                }
-            })
-        };
-        scan_with_mode(&config.destination, &config, Some(&dest_progress))?
+    let mut plan = StreamedPlan::new();
+    let source = scan_sorted(&config.source, config, Some(&src_progress))?;
+    if config.destination.exists() {
+        let destination = scan_sorted(&config.destination, config, Some(&dest_progress))?;
+        merge_sync_plan(source, destination, config, &mut plan)?;
     } else {
-        crate::types::FileTree::new(config.destination.clone())
-    };
-    if let Ok(progress) = reporter.lock() {
-        progress.finish_scan("destination", dest_tree.total_files, dest_tree.total_size);
+        merge_sync_plan(source, std::iter::empty(), config, &mut plan)?;
     }
 
-    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
-    print_plan_summary(&plan);
+    let counts = counts.lock().map(|counts| *counts).unwrap_or_default();
+    if let Ok(progress) = reporter.lock() {
+        progress.finish_scan(
+            LABEL,
+            (counts[0].0 + counts[1].0) as usize,
+            counts[0].1 + counts[1].1,
+        );
+    }
+    let totals = TreeTotals {
+        source_files: counts[0].0 as usize,
+        destination_files: counts[1].0 as usize,
+        destination_bytes: counts[1].1,
+    };
+    Ok((plan, totals))
+}
 
-    if config.dry_run {
-        print_dry_run_actions(&plan);
-        println!("Dry-run mode: no changes were made.");
-        return Ok(());
+/// Widen `config.modify_window` to the mtime resolution of the destination,
+/// so that mtimes truncated by a coarser filesystem still match.
+///
+/// The resolution is inferred from the mtimes in `dest_tree` and of the
+/// destination directory, without writing to the destination, so a dry run
+/// and the real run plan with the same window. An explicit `--modify-window`
+/// is kept as given.
+pub(crate) fn widen_modify_window(config: &mut Config, dest_tree: Option<&FileTree>) {
+    if config.size_only || config.ignore_times || !config.widen_modify_window {
+        return;
     }
+    if let Some(resolution) = destination_mtime_resolution(&config.destination, dest_tree) {
+        config.modify_window = config.modify_window.max(resolution);
+    }
+}
 
-    if !has_executable_actions(&plan) {
-        println!("Nothing to sync.");
-        return Ok(());
+/// Mtime resolution of `destination`, inferred from its own mtime and the
+/// mtimes in `dest_tree`.
+pub(crate) fn destination_mtime_resolution(
+    destination: &std::path::Path,
+    dest_tree: Option<&FileTree>,
+) -> Option<std::time::Duration> {
+    let root_mtime = std::fs::metadata(destination)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let tree_mtimes = dest_tree
+        .into_iter()
+        .flat_map(|tree| tree.iter().map(|(_, entry)| entry.mtime));
+    infer_mtime_resolution(root_mtime.into_iter().chain(tree_mtimes))
+}
+
+/// Refuse to execute a plan when `--conflict fail` found conflicts.
+pub(crate) fn refuse_conflicts(
+    conflicts: &[PlanConflict],
+    config: &Config,
+) -> Result<(), KopyError> {
+    match conflicts.first() {
+        Some(first) if config.conflict_mode == ConflictMode::Fail => {
+            Err(KopyError::Validation(format!(
+                "{} destination file(s) are newer than their source, e.g. {} (--conflict fail)",
+                conflicts.len(),
+                first.path.display()
+            )))
+        }
+        _ => Ok(()),
     }
+}
+
+/// Execute `plan`, driving `reporter` and collecting per-action errors.
+pub(crate) fn execute_with_progress(
+    plan: &DiffPlan,
+    config: &Config,
+    reporter: Arc<Mutex<ProgressReporter>>,
+) -> (Result<(), KopyError>, Vec<ErrorRecord>) {
+    execute_chunks_with_progress(std::iter::once(Ok(plan)), &plan.stats, config, reporter)
+}
 
+/// Execute plan chunks in order under one progress display.
+///
+/// `stats` covers every chunk. Failed actions do not stop later chunks; the
+/// first chunk error is returned once all have run. A chunk that cannot be
+/// read stops execution.
+fn execute_chunks_with_progress<P: Borrow<DiffPlan>>(
+    chunks: impl IntoIterator<Item = Result<P, KopyError>>,
+    stats: &PlanStats,
+    config: &Config,
+    reporter: Arc<Mutex<ProgressReporter>>,
+) -> (Result<(), KopyError>, Vec<ErrorRecord>) {
     if let Ok(mut progress) = reporter.lock() {
-        progress.start_transfer(plan.stats.total_files as u64);
+        progress.start_transfer(stats.total_files as u64);
     }
 
-    let transfer_total = plan.stats.total_files;
-    let delete_total = plan.stats.delete_count;
     let error_records: Arc<Mutex<Vec<ErrorRecord>>> = Arc::new(Mutex::new(Vec::new()));
+    let totals: Arc<Mutex<ExecutionStats>> = Arc::new(Mutex::new(ExecutionStats::default()));
     let progress_cb = {
         let reporter = Arc::clone(&reporter);
         let error_records = Arc::clone(&error_records);
+        let totals = Arc::clone(&totals);
         move |event: &ExecutionEvent| match event {
             ExecutionEvent::ActionStart { action, path, .. } => {
                 if let Ok(progress) = reporter.lock() {
@@ -130,65 +310,410 @@ pub fn run(config: Config) -> Result<(), KopyError> {
                 }
             }
             ExecutionEvent::Complete { stats } => {
-                if let Ok(mut progress) = reporter.lock() {
-                    progress.reconcile_transfer_completion(transfer_total, stats.bytes_copied);
-                    progress.finish_transfer(
-                        stats.completed_actions,
-                        stats.failed_actions,
-                        stats.bytes_copied,
-                        transfer_total,
-                        delete_total,
-                    );
+                if let Ok(mut totals) = totals.lock() {
+                    totals.completed_actions += stats.completed_actions;
+                    totals.failed_actions += stats.failed_actions;
+                    totals.bytes_copied += stats.bytes_copied;
                 }
             }
         }
     };
 
-    let result = if config.threads > 1 {
-        execute_plan_parallel(&plan, &config, Some(&progress_cb))
-    } else {
-        execute_plan(&plan, &config, Some(&progress_cb))
+    let mut result = Ok(());
+    for chunk in chunks {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        };
+        let chunk_result = if config.threads > 1 {
+            execute_plan_parallel(chunk.borrow(), config, Some(&progress_cb))
+        } else {
+            execute_plan(chunk.borrow(), config, Some(&progress_cb))
+        };
+        if let (Ok(()), Err(error)) = (&result, chunk_result) {
+            result = Err(error);
+        }
+    }
+
+    if let (Ok(mut progress), Ok(totals)) = (reporter.lock(), totals.lock()) {
+        progress.reconcile_transfer_completion(stats.total_files, totals.bytes_copied);
+        progress.finish_transfer(
+            totals.completed_actions,
+            totals.failed_actions,
+            totals.bytes_copied,
+            stats.total_files,
+            stats.delete_count,
+        );
+    }
+    let records = match error_records.lock() {
+        Ok(mut records) => std::mem::take(&mut *records),
+        Err(_) => Vec::new(),
     };
-    if let Ok(records) = error_records.lock() {
-        if !records.is_empty() {
-            println!("{}", format_error_summary(&records));
+    (result, records)
+}
+
+/// Outcome of syncing one destination of a fan-out run.
+struct ReplicaOutcome {
+    destination: PathBuf,
+    result: Result<(), KopyError>,
+    errors: Vec<ErrorRecord>,
+}
+
+/// Sync one source into every destination in `config`.
+///
+/// The source is scanned once. Destinations are scanned and planned one after
+/// another, then executed concurrently with a progress bar each. A destination
+/// that fails to scan or sync does not stop the others; failures are reported
+/// after every destination has finished.
+fn run_fan_out(config: Config) -> Result<(), KopyError> {
+    let targets = config.destination_configs();
+    if config.source.is_file() {
+        let outcomes = targets
+            .into_iter()
+            .map(|target| {
+                println!("Destination: {}", target.destination.display());
+                ReplicaOutcome {
+                    destination: target.destination.clone(),
+                    result: run_single_file_sync(target),
+                    errors: Vec::new(),
+                }
+            })
+            .collect();
+        return finish_fan_out(outcomes);
+    }
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    let file_list = listed_entries(&config)?;
+    let mut src_tree = scan_source_tree(&config, &reporter, file_list.as_deref())?;
+
+    let count = targets.len();
+    let mut scanned = Vec::with_capacity(count);
+    for (index, target) in targets.into_iter().enumerate() {
+        let label = format!("destination {}/{}", index + 1, count);
+        let dest_tree = scan_destination_tree(&target, &reporter, file_list.as_deref(), &label);
+        scanned.push((target, dest_tree));
+    }
+    if config.checksum_mode {
+        let dest_trees: Vec<&FileTree> = scanned
+            .iter()
+            .filter_map(|(_, tree)| tree.as_ref().ok())
+            .collect();
+        hash_shared_source_files(&mut src_tree, &config.source, &dest_trees);
+    }
+
+    let mut jobs = Vec::with_capacity(count);
+    for (mut target, dest_tree) in scanned {
+        println!("Destination: {}", target.destination.display());
+        let dest_tree = match dest_tree {
+            Ok(tree) => tree,
+            Err(error) => {
+                println!("  Scan failed: {}", error);
+                jobs.push((target, Err(error)));
+                continue;
+            }
+        };
+        widen_modify_window(&mut target, Some(&dest_tree));
+        let plan = generate_sync_plan(&src_tree, &dest_tree, &target);
+        print_plan_summary(&plan);
+        if config.dry_run {
+            print_dry_run_actions(&plan);
         }
+        let checked = refuse_conflicts(&plan.conflicts, &target)
+            .and_then(|_| {
+                check_deletes(&plan.stats, &TreeTotals::of(&src_tree, &dest_tree), &target)
+            })
+            .map(|_| plan);
+        jobs.push((target, checked));
+    }
+    if config.dry_run {
+        println!("Dry-run mode: no changes were made.");
     }
 
-    result?;
-    Ok(())
+    let multi = MultiProgress::new();
+    let outcomes = std::thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|(target, plan)| {
+                let multi = &multi;
+                let dry_run = config.dry_run;
+                scope.spawn(move || {
+                    let destination = target.destination.clone();
+                    let (result, errors) = match plan {
+                        Err(error) => (Err(error), Vec::new()),
+                        Ok(_) if dry_run => (Ok(()), Vec::new()),
+                        Ok(plan) if !has_executable_actions(&plan) => (Ok(()), Vec::new()),
+                        Ok(plan) => {
+                            let label = destination.display().to_string();
+                            let reporter =
+                                Arc::new(Mutex::new(ProgressReporter::labelled(multi, &label)));
+                            execute_with_progress(&plan, &target, reporter)
+                        }
+                    };
+                    ReplicaOutcome {
+                        destination,
+                        result,
+                        errors,
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    finish_fan_out(outcomes)
 }
 
-fn run_single_file_sync(config: Config) -> Result<(), KopyError> {
-    if config.delete_mode != crate::types::DeleteMode::None {
-        eprintln!("Warning: delete flags are ignored when source is a single file.");
+/// Print a per-destination result and error summary.
+///
+/// # Errors
+/// Returns `KopyError::Validation` naming the failed destinations when any
+/// destination failed.
+fn finish_fan_out(outcomes: Vec<ReplicaOutcome>) -> Result<(), KopyError> {
+    println!("{}", format_fan_out_results(&outcomes));
+    for outcome in &outcomes {
+        if !outcome.errors.is_empty() {
+            println!("{}:", outcome.destination.display());
+            println!("{}", format_error_summary(&outcome.errors));
+        }
     }
 
-    let source_entry = build_source_file_entry(&config.source)?;
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_err())
+        .map(|outcome| outcome.destination.display().to_string())
+        .collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err(KopyError::Validation(format!(
+        "{} of {} destination(s) failed: {}",
+        failed.len(),
+        outcomes.len(),
+        failed.join(", ")
+    )))
+}
+
+fn format_fan_out_results(outcomes: &[ReplicaOutcome]) -> String {
+    let mut lines = vec!["Destinations:".to_string()];
+    for outcome in outcomes {
+        let status = match (&outcome.result, outcome.errors.len()) {
+            (Ok(()), _) => "ok".to_string(),
+            (Err(_), 0) => "FAILED".to_string(),
+            (Err(_), errors) => format!("FAILED ({} error(s))", errors),
+        };
+        lines.push(format!("  {}: {}", outcome.destination.display(), status));
+    }
+    lines.join("\n")
+}
+
+/// Hash source files that more than one destination will compare by content,
+/// so checksum mode reads each of them once instead of once per destination.
+fn hash_shared_source_files(
+    src_tree: &mut FileTree,
+    source_root: &std::path::Path,
+    dest_trees: &[&FileTree],
+) {
+    for (path, entry) in src_tree.entries.iter_mut() {
+        if entry.is_symlink || entry.hash.is_some() {
+            continue;
+        }
+        let comparisons = dest_trees
+            .iter()
+            .filter_map(|tree| tree.get(path))
+            .filter(|dest| !dest.is_symlink && dest.size == entry.size)
+            .count();
+        if comparisons < 2 {
+            continue;
+        }
+        if let Ok(hash) = compute_hash(&entry.source_path(source_root)) {
+            entry.hash = Some(hash);
+        }
+    }
+}
+
+/// Scan source and destination with live progress output.
+///
+/// A missing destination yields an empty tree. Several sources are merged
+/// into one tree, later sources winning on collisions. With `--files-from`,
+/// both sides only contain the listed paths, so deletes stay within them.
+pub(crate) fn scan_trees(
+    config: &Config,
+    reporter: &Arc<Mutex<ProgressReporter>>,
+) -> Result<(FileTree, FileTree), KopyError> {
+    let file_list = listed_entries(config)?;
+    let src_tree = scan_source_tree(config, reporter, file_list.as_deref())?;
+    let dest_tree = scan_destination_tree(config, reporter, file_list.as_deref(), "destination")?;
+    Ok((src_tree, dest_tree))
+}
+
+/// The `--files-from` entries present in some source, if a list was given.
+fn listed_entries(config: &Config) -> Result<Option<Vec<PathBuf>>, KopyError> {
+    match &config.files_from {
+        Some(list) => Ok(Some(listed_source_entries(config, list)?)),
+        None => Ok(None),
+    }
+}
+
+/// Scan every source, merging overlays over the primary source.
+pub(crate) fn scan_source_tree(
+    config: &Config,
+    reporter: &Arc<Mutex<ProgressReporter>>,
+    file_list: Option<&[PathBuf]>,
+) -> Result<FileTree, KopyError> {
+    let source_count = 1 + config.overlay_sources.len();
     let mut src_tree = FileTree::new(config.source.clone());
-    src_tree.insert(PathBuf::new(), source_entry.clone());
+    for (index, root) in config.sources().enumerate() {
+        let label = if source_count == 1 {
+            "source".to_string()
+        } else {
+            format!("source {}/{}", index + 1, source_count)
+        };
+        if let Ok(progress) = reporter.lock() {
+            progress.start_scan(&label);
+        }
+        let src_progress: crate::scanner::ProgressCallback = {
+            let reporter = Arc::clone(reporter);
+            let label = label.clone();
+            Box::new(move |files: u64, bytes: u64| {
+                if let Ok(progress) = reporter.lock() {
+                    progress.update_scan(&label, files, bytes);
+                }
+            })
+        };
+        let tree = match file_list {
+            Some(entries) => scan_file_list(root, entries, config, Some(&src_progress))?,
+            None => scan_with_mode(root, config, Some(&src_progress))?,
+        };
+        if let Ok(progress) = reporter.lock() {
+            progress.finish_scan(&label, tree.total_files, tree.total_size);
+        }
 
-    let resolved_destination = resolve_single_file_destination_path(&config)?;
+        if source_count == 1 {
+            src_tree = tree;
+        } else {
+            src_tree.merge_overlay(tree, root);
+        }
+    }
+    Ok(src_tree)
+}
 
-    let mut dest_tree = FileTree::new(resolved_destination.clone());
-    if let Some(dest_entry) = build_destination_file_entry(&resolved_destination)? {
-        dest_tree.insert(PathBuf::new(), dest_entry);
+/// Scan `config.destination`; a missing destination yields an empty tree.
+pub(crate) fn scan_destination_tree(
+    config: &Config,
+    reporter: &Arc<Mutex<ProgressReporter>>,
+    file_list: Option<&[PathBuf]>,
+    label: &str,
+) -> Result<FileTree, KopyError> {
+    if let Ok(progress) = reporter.lock() {
+        progress.start_scan(label);
     }
 
-    let mut single_file_config = config.clone();
-    single_file_config.delete_mode = crate::types::DeleteMode::None;
-    single_file_config.destination = resolved_destination;
+    let dest_tree = if config.destination.exists() {
+        let dest_progress: crate::scanner::ProgressCallback = {
+            let reporter = Arc::clone(reporter);
+            let label = label.to_string();
+            Box::new(move |files: u64, bytes: u64| {
+                if let Ok(progress) = reporter.lock() {
+                    progress.update_scan(&label, files, bytes);
+                }
+            })
+        };
+        match file_list {
+            Some(entries) => {
+                scan_file_list(&config.destination, entries, config, Some(&dest_progress))?
+            }
+            None => scan_with_mode(&config.destination, config, Some(&dest_progress))?,
+        }
+    } else {
+        FileTree::new(config.destination.clone())
+    };
+    if let Ok(progress) = reporter.lock() {
+        progress.finish_scan(label, dest_tree.total_files, dest_tree.total_size);
+    }
 
-    let mut plan = DiffPlan::new();
-    match dest_tree.get(&PathBuf::new()) {
-        None => plan.add_action(SyncAction::CopyNew(source_entry)),
-        Some(dest_entry) => plan.add_action(compare_files(
-            &source_entry,
-            dest_entry,
-            &single_file_config,
-        )),
+    Ok(dest_tree)
+}
+
+/// Read the `--files-from` list, dropping entries missing from the source so
+/// that they are not deleted from the destination either.
+fn listed_source_entries(
+    config: &Config,
+    list: &std::path::Path,
+) -> Result<Vec<std::path::PathBuf>, KopyError> {
+    let source_root = config
+        .source
+        .canonicalize()
+        .unwrap_or_else(|_| config.source.clone());
+    let entries = read_file_list(list, &source_root)?;
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| {
+            let exists = config
+                .sources()
+                .any(|root| root.join(entry).symlink_metadata().is_ok());
+            if !exists {
+                eprintln!(
+                    "Warning: {} is listed in {} but missing from the source. It will be skipped.",
+                    entry.display(),
+                    list.display()
+                );
+            }
+            exists
+        })
+        .collect())
+}
+
+/// Scan both sides and build one sync plan per destination without executing
+/// anything.
+///
+/// The source is scanned once. Each plan comes with the configuration it was
+/// built for; for a single-file source the destination is resolved to the
+/// target file path. A streamed plan is loaded back into memory here, since
+/// callers list or save every action.
+pub(crate) fn build_plans(config: &Config) -> Result<Vec<(DiffPlan, Config)>, KopyError> {
+    let targets = config.destination_configs();
+    if config.source.is_file() {
+        return targets.iter().map(build_single_file_plan).collect();
     }
-    plan.sort_by_path();
+
+    let reporter = Arc::new(Mutex::new(ProgressReporter::new()));
+    if config.streaming {
+        let mut target = config.clone();
+        widen_modify_window(&mut target, None);
+        let plan = stream_plan(&target, &reporter)?.0.into_plan()?;
+        return Ok(vec![(plan, target)]);
+    }
+    let file_list = listed_entries(config)?;
+    let src_tree = scan_source_tree(config, &reporter, file_list.as_deref())?;
+    targets
+        .into_iter()
+        .map(|mut target| {
+            let dest_tree =
+                scan_destination_tree(&target, &reporter, file_list.as_deref(), "destination")?;
+            widen_modify_window(&mut target, Some(&dest_tree));
+            let plan = generate_sync_plan(&src_tree, &dest_tree, &target);
+            Ok((plan, target))
+        })
+        .collect()
+}
+
+fn run_single_file_sync(config: Config) -> Result<(), KopyError> {
+    if config.delete_mode != crate::types::DeleteMode::None {
+        eprintln!("Warning: delete flags are ignored when source is a single file.");
+    }
+
+    let (plan, single_file_config) = build_single_file_plan(&config)?;
 
     print_plan_summary(&plan);
     if config.dry_run {
@@ -260,6 +785,36 @@ fn run_single_file_sync(config: Config) -> Result<(), KopyError> {
     Ok(())
 }
 
+fn build_single_file_plan(config: &Config) -> Result<(DiffPlan, Config), KopyError> {
+    let source_entry = build_source_file_entry(&config.source)?;
+    let mut src_tree = FileTree::new(config.source.clone());
+    src_tree.insert(PathBuf::new(), source_entry.clone());
+
+    let resolved_destination = resolve_single_file_destination_path(config)?;
+
+    let mut dest_tree = FileTree::new(resolved_destination.clone());
+    if let Some(dest_entry) = build_destination_file_entry(&resolved_destination)? {
+        dest_tree.insert(PathBuf::new(), dest_entry);
+    }
+
+    let mut single_file_config = config.clone();
+    single_file_config.delete_mode = crate::types::DeleteMode::None;
+    single_file_config.destination = resolved_destination;
+
+    let mut plan = DiffPlan::new();
+    match dest_tree.get(&PathBuf::new()) {
+        None => plan.add_action(SyncAction::CopyNew(source_entry)),
+        Some(dest_entry) => plan.add_action(compare_files(
+            &source_entry,
+            dest_entry,
+            &single_file_config,
+        )),
+    }
+    plan.sort_by_path();
+
+    Ok((plan, single_file_config))
+}
+
 fn build_source_file_entry(source_path: &std::path::Path) -> Result<FileEntry, KopyError> {
     let metadata = std::fs::symlink_metadata(source_path).map_err(KopyError::Io)?;
     let mtime = metadata.modified().map_err(KopyError::Io)?;
@@ -351,31 +906,35 @@ fn is_transfer_action(action: &str) -> bool {
     matches!(action, "Copy" | "Update")
 }
 
-fn has_executable_actions(plan: &crate::diff::DiffPlan) -> bool {
+pub(crate) fn has_executable_actions(plan: &crate::diff::DiffPlan) -> bool {
     plan.actions.iter().any(|action| !action.is_skip())
 }
 
-fn print_plan_summary(plan: &crate::diff::DiffPlan) {
-    println!("{}", format_plan_preview(plan));
+pub(crate) fn print_plan_summary(plan: &crate::diff::DiffPlan) {
+    println!("{}", format_plan_preview(&plan.stats));
 }
 
-fn format_plan_preview(plan: &crate::diff::DiffPlan) -> String {
+pub(crate) fn format_plan_preview(stats: &PlanStats) -> String {
     format!(
-        "Plan:\n  Copy: {}  Update: {}  Delete: {}  Skip: {}\n  Total bytes to transfer: {}",
-        plan.stats.copy_count,
-        plan.stats.overwrite_count,
-        plan.stats.delete_count,
-        plan.stats.skip_count,
-        HumanBytes(plan.stats.total_bytes)
+        "Plan:\n  Copy: {}  Update: {}  Metadata: {}  Delete: {}  Move: {}  Skip: {}  Conflict: {}  Protected: {}\n  Total bytes to transfer: {}",
+        stats.copy_count,
+        stats.overwrite_count,
+        stats.metadata_count,
+        stats.delete_count,
+        stats.move_count,
+        stats.skip_count,
+        stats.conflict_count,
+        stats.protected_count,
+        HumanBytes(stats.total_bytes)
     )
 }
 
-fn print_dry_run_actions(plan: &crate::diff::DiffPlan) {
+pub(crate) fn print_dry_run_actions(plan: &crate::diff::DiffPlan) {
     println!("{}", format_dry_run_actions(plan));
 }
 
-fn format_dry_run_actions(plan: &crate::diff::DiffPlan) -> String {
-    if plan.actions.is_empty() {
+pub(crate) fn format_dry_run_actions(plan: &crate::diff::DiffPlan) -> String {
+    if plan.actions.is_empty() && plan.conflicts.is_empty() && plan.protected.is_empty() {
         return "Dry-run actions:\n  (no planned actions)".to_string();
     }
 
@@ -383,28 +942,17 @@ fn format_dry_run_actions(plan: &crate::diff::DiffPlan) -> String {
     lines.push("Dry-run actions:".to_string());
     let mut skipped = 0usize;
     for action in &plan.actions {
-        match action {
-            crate::types::SyncAction::CopyNew(entry) => {
-                lines.push(format!("  COPY      {}", entry.path.display()));
-            }
-            crate::types::SyncAction::Overwrite(entry) => {
-                lines.push(format!("  UPDATE    {}", entry.path.display()));
-            }
-            crate::types::SyncAction::Delete(path) => {
-                lines.push(format!("  DELETE    {}", path.display()));
-            }
-            crate::types::SyncAction::Skip => {
-                skipped += 1;
-            }
-            crate::types::SyncAction::Move { from, to } => {
-                lines.push(format!(
-                    "  MOVE      {} -> {}",
-                    from.display(),
-                    to.display()
-                ));
-            }
+        match format_action_line(action) {
+            Some(line) => lines.push(line),
+            None => skipped += 1,
         }
     }
+    lines.extend(plan.conflicts.iter().map(format_conflict_line));
+    lines.extend(
+        plan.protected
+            .iter()
+            .map(|path| format!("  PROTECT   {}", path.display())),
+    );
 
     if skipped > 0 {
         lines.push(format!("  ({skipped} unchanged file(s) omitted)"));
@@ -413,10 +961,38 @@ fn format_dry_run_actions(plan: &crate::diff::DiffPlan) -> String {
     lines.join("\n")
 }
 
+/// One dry-run line for `action`; `None` for skips.
+fn format_action_line(action: &SyncAction) -> Option<String> {
+    match action {
+        SyncAction::CopyNew(entry) => Some(format!("  COPY      {}", entry.path.display())),
+        SyncAction::Overwrite(entry, reason) => Some(format!(
+            "  UPDATE    {} ({})",
+            entry.path.display(),
+            reason.describe()
+        )),
+        SyncAction::UpdateMetadata(entry) => Some(format!("  METADATA  {}", entry.path.display())),
+        SyncAction::Delete(path) => Some(format!("  DELETE    {}", path.display())),
+        SyncAction::Skip => None,
+        SyncAction::Move { from, to } => Some(format!(
+            "  MOVE      {} -> {}",
+            from.display(),
+            to.display()
+        )),
+    }
+}
+
+fn format_conflict_line(conflict: &PlanConflict) -> String {
+    format!(
+        "  CONFLICT  {} (destination is newer; {})",
+        conflict.path.display(),
+        conflict.outcome.describe()
+    )
+}
+
 #[derive(Debug)]
-struct ErrorRecord {
+pub(crate) struct ErrorRecord {
     kind: &'static str,
-    path: Option<PathBuf>,
+    pub(crate) path: Option<PathBuf>,
     message: String,
     suggestion: Option<String>,
 }
@@ -483,6 +1059,13 @@ fn humanize_error(error: &KopyError) -> (String, Option<String>) {
             msg.clone(),
             Some("Check SSH connectivity and credentials.".to_string()),
         ),
+        KopyError::DeleteRefused { planned, reason } => (
+            format!("Refused to delete {} path(s): {}", planned, reason),
+            Some(
+                "Check the source path, or raise --max-delete if the deletes are intended."
+                    .to_string(),
+            ),
+        ),
         KopyError::DryRun => ("Dry-run mode: no changes were made".to_string(), None),
     }
 }
@@ -497,11 +1080,12 @@ fn error_kind_label(error: &KopyError) -> &'static str {
         KopyError::ChecksumMismatch { .. } => "Checksum mismatch",
         KopyError::TransferInterrupted { .. } => "Transfer interrupted",
         KopyError::SshError(_) => "SSH error",
+        KopyError::DeleteRefused { .. } => "Delete refused",
         KopyError::DryRun => "Dry run",
     }
 }
 
-fn format_error_summary(records: &[ErrorRecord]) -> String {
+pub(crate) fn format_error_summary(records: &[ErrorRecord]) -> String {
     let mut groups: BTreeMap<&'static str, Vec<&ErrorRecord>> = BTreeMap::new();
     for record in records {
         groups.entry(record.kind).or_default().push(record);
@@ -530,11 +1114,45 @@ fn format_error_summary(records: &[ErrorRecord]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::diff::DiffPlan;
-    use crate::types::{FileEntry, SyncAction};
+    use crate::diff::{ConflictOutcome, DiffPlan, PlanConflict};
+    use crate::types::{FileEntry, SyncAction, UpdateReason};
     use std::path::PathBuf;
     use std::time::{Duration, UNIX_EPOCH};
 
+    #[test]
+    fn test_widen_modify_window_same_for_dry_run_and_keeps_explicit_window() {
+        let dest = tempfile::TempDir::new().expect("create dest dir");
+        filetime::set_file_mtime(
+            dest.path(),
+            filetime::FileTime::from_unix_time(1_000_000_000, 0),
+        )
+        .expect("set destination mtime");
+        let config = Config {
+            destination: dest.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        for dry_run in [false, true] {
+            let mut widened = Config {
+                dry_run,
+                ..config.clone()
+            };
+            widen_modify_window(&mut widened, None);
+            assert_eq!(widened.modify_window, Duration::from_secs(2));
+        }
+        assert_eq!(
+            std::fs::read_dir(dest.path()).expect("read dest").count(),
+            0
+        );
+
+        let mut explicit = Config {
+            widen_modify_window: false,
+            ..config
+        };
+        widen_modify_window(&mut explicit, None);
+        assert_eq!(explicit.modify_window, Duration::ZERO);
+    }
+
     #[test]
     fn test_is_transfer_action() {
         assert!(is_transfer_action("Copy"));
@@ -574,16 +1192,19 @@ mod tests {
             UNIX_EPOCH + Duration::from_secs(1_000),
             0o644,
         )));
-        plan.add_action(SyncAction::Overwrite(FileEntry::new(
-            PathBuf::from("update.txt"),
-            2048,
-            UNIX_EPOCH + Duration::from_secs(2_000),
-            0o644,
-        )));
+        plan.add_action(SyncAction::Overwrite(
+            FileEntry::new(
+                PathBuf::from("update.txt"),
+                2048,
+                UNIX_EPOCH + Duration::from_secs(2_000),
+                0o644,
+            ),
+            UpdateReason::SourceNewer,
+        ));
         plan.add_action(SyncAction::Delete(PathBuf::from("delete.txt")));
         plan.add_action(SyncAction::Skip);
 
-        let preview = format_plan_preview(&plan);
+        let preview = format_plan_preview(&plan.stats);
         assert!(preview.contains("Copy: 1"));
         assert!(preview.contains("Update: 1"));
         assert!(preview.contains("Delete: 1"));
@@ -600,7 +1221,7 @@ mod tests {
             0o644,
         )));
 
-        let preview = format_plan_preview(&plan);
+        let preview = format_plan_preview(&plan.stats);
         assert!(
             preview.contains("Total bytes to transfer:") && preview.contains("MiB"),
             "expected human-readable size in preview, got: {preview}"
@@ -616,23 +1237,58 @@ mod tests {
             UNIX_EPOCH + Duration::from_secs(1_000),
             0o644,
         )));
-        plan.add_action(SyncAction::Overwrite(FileEntry::new(
-            PathBuf::from("update.txt"),
-            2,
-            UNIX_EPOCH + Duration::from_secs(2_000),
-            0o644,
+        plan.add_action(SyncAction::Overwrite(
+            FileEntry::new(
+                PathBuf::from("update.txt"),
+                2,
+                UNIX_EPOCH + Duration::from_secs(2_000),
+                0o644,
+            ),
+            UpdateReason::SourceNewer,
+        ));
+        plan.add_action(SyncAction::UpdateMetadata(FileEntry::new(
+            PathBuf::from("chmod.sh"),
+            3,
+            UNIX_EPOCH + Duration::from_secs(3_000),
+            0o755,
         )));
         plan.add_action(SyncAction::Delete(PathBuf::from("delete.txt")));
         plan.add_action(SyncAction::Skip);
 
         let preview = format_dry_run_actions(&plan);
+        assert!(preview.contains("METADATA  chmod.sh"));
+        assert!(format_plan_preview(&plan.stats).contains("Metadata: 1"));
         assert!(preview.contains("Dry-run actions:"));
         assert!(preview.contains("COPY      copy.txt"));
-        assert!(preview.contains("UPDATE    update.txt"));
+        assert!(preview.contains("UPDATE    update.txt (source is newer)"));
         assert!(preview.contains("DELETE    delete.txt"));
         assert!(preview.contains("unchanged file(s) omitted"));
     }
 
+    #[test]
+    fn test_format_dry_run_actions_lists_conflicts_without_actions() {
+        let mut plan = DiffPlan::new();
+        plan.add_conflict(PlanConflict {
+            path: PathBuf::from("edited.txt"),
+            source_mtime: UNIX_EPOCH + Duration::from_secs(1_000),
+            destination_mtime: UNIX_EPOCH + Duration::from_secs(2_000),
+            outcome: ConflictOutcome::KeepDestination,
+        });
+
+        assert!(format_plan_preview(&plan.stats).contains("Conflict: 1"));
+        assert!(format_dry_run_actions(&plan)
+            .contains("CONFLICT  edited.txt (destination is newer; destination kept)"));
+    }
+
+    #[test]
+    fn test_format_dry_run_actions_lists_protected_paths() {
+        let mut plan = DiffPlan::new();
+        plan.add_protected(PathBuf::from("uploads/a.jpg"));
+
+        assert!(format_plan_preview(&plan.stats).contains("Protected: 1"));
+        assert!(format_dry_run_actions(&plan).contains("PROTECT   uploads/a.jpg"));
+    }
+
     #[test]
     fn test_format_dry_run_actions_handles_empty_plan() {
         let plan = DiffPlan::new();
@@ -675,6 +1331,35 @@ mod tests {
         assert!(summary.contains("Try: Check file permissions or run with a user that has access."));
     }
 
+    #[test]
+    fn test_format_fan_out_results_marks_failed_destinations() {
+        let outcomes = vec![
+            ReplicaOutcome {
+                destination: PathBuf::from("/mnt/a"),
+                result: Ok(()),
+                errors: Vec::new(),
+            },
+            ReplicaOutcome {
+                destination: PathBuf::from("/mnt/b"),
+                result: Err(KopyError::Validation("failed".to_string())),
+                errors: vec![ErrorRecord::new(
+                    Some(std::path::Path::new("x.txt")),
+                    &KopyError::Validation("boom".to_string()),
+                )],
+            },
+        ];
+
+        let text = format_fan_out_results(&outcomes);
+        assert!(text.contains("/mnt/a: ok"));
+        assert!(text.contains("/mnt/b: FAILED (1 error(s))"));
+        match finish_fan_out(outcomes) {
+            Err(KopyError::Validation(msg)) => {
+                assert_eq!(msg, "1 of 2 destination(s) failed: /mnt/b")
+            }
+            other => panic!("Expected Validation error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_error_record_io_error_is_plain_english_with_suggestion() {
         let err = KopyError::Io(std::io::Error::new(
@@ -0,0 +1,83 @@
+//! `kopy trash`: inspect and restore `.kopy_trash` snapshots
+
+use crate::config::{TrashArgs, TrashCommand};
+use crate::executor::trash::{list_trash_snapshots, restore_trash_snapshot, TrashSnapshot};
+use crate::types::KopyError;
+use indicatif::HumanBytes;
+
+/// Dispatch a `kopy trash` subcommand.
+pub fn run(args: TrashArgs) -> Result<(), KopyError> {
+    match args.command {
+        TrashCommand::List { destination } => {
+            let snapshots = list_trash_snapshots(&destination)?;
+            println!("{}", format_snapshot_list(&snapshots));
+        }
+        TrashCommand::Restore {
+            destination,
+            snapshot,
+            overwrite,
+        } => {
+            let report = restore_trash_snapshot(&destination, &snapshot, overwrite)?;
+            println!(
+                "Restored {} file(s) from {}.",
+                report.restored.len(),
+                snapshot
+            );
+            for path in &report.skipped {
+                println!("  SKIPPED   {}", path.display());
+            }
+            if !report.skipped.is_empty() && !overwrite {
+                println!("Skipped files remain in the trash; re-run with --overwrite to replace existing paths.");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_snapshot_list(snapshots: &[TrashSnapshot]) -> String {
+    if snapshots.is_empty() {
+        return "Trash is empty.".to_string();
+    }
+
+    let mut lines = vec!["Trash snapshots:".to_string()];
+    for snapshot in snapshots {
+        lines.push(format!(
+            "  {}  {} file(s), {}",
+            snapshot.name,
+            snapshot.manifest.files.len(),
+            HumanBytes(snapshot.total_size())
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::trash::{DeletedFile, TrashManifest};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_snapshot_list_empty() {
+        assert_eq!(format_snapshot_list(&[]), "Trash is empty.");
+    }
+
+    #[test]
+    fn test_format_snapshot_list_shows_counts_and_sizes() {
+        let mut manifest = TrashManifest::new();
+        manifest.add_file(DeletedFile {
+            original_path: "a.txt".to_string(),
+            trash_path: "a.txt".to_string(),
+            deleted_at: "2026-01-01T12:00:00+00:00".to_string(),
+            size: 2048,
+        });
+        let snapshot = TrashSnapshot {
+            name: "2026-01-01_120000".to_string(),
+            path: PathBuf::from("/dest/.kopy_trash/2026-01-01_120000"),
+            manifest,
+        };
+
+        let text = format_snapshot_list(&[snapshot]);
+        assert!(text.contains("2026-01-01_120000  1 file(s), 2.00 KiB"));
+    }
+}
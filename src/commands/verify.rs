@@ -0,0 +1,140 @@
+//! `kopy verify`: check that destination content matches source
+
+use crate::commands::sync::build_plans;
+use crate::diff::DiffPlan;
+use crate::types::{DeleteMode, KopyError, SyncAction};
+use crate::Config;
+
+/// Compare every source file against the destination using Blake3 hashes.
+///
+/// Missing and differing files fail verification. Destination-only files are
+/// listed too, but only fail verification when a delete mode was requested
+/// (the caller expects an exact mirror). Every destination is verified.
+///
+/// # Errors
+/// Returns `KopyError::Validation` when any difference is found.
+pub fn run(mut config: Config) -> Result<(), KopyError> {
+    let strict_extras = config.delete_mode != DeleteMode::None;
+    config.checksum_mode = true;
+    config.dry_run = true;
+    if config.delete_mode == DeleteMode::None {
+        // Planning deletes is the cheapest way to surface destination-only paths.
+        config.delete_mode = DeleteMode::Trash;
+    }
+
+    let plans = build_plans(&config)?;
+    let several = plans.len() > 1;
+    let mut failures = 0;
+    for (plan, target) in &plans {
+        if several {
+            println!("Destination: {}", target.destination.display());
+        }
+        let report = VerifyReport::from_plan(plan);
+        println!("{}", report.format());
+
+        failures += report.missing.len()
+            + report.differ.len()
+            + if strict_extras { report.extra.len() } else { 0 };
+    }
+    if failures > 0 {
+        return Err(KopyError::Validation(format!(
+            "Verification failed: {} path(s) differ",
+            failures
+        )));
+    }
+
+    println!("Verification passed.");
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct VerifyReport {
+    matched: usize,
+    missing: Vec<String>,
+    differ: Vec<String>,
+    extra: Vec<String>,
+}
+
+impl VerifyReport {
+    fn from_plan(plan: &DiffPlan) -> Self {
+        let mut report = VerifyReport {
+            matched: plan.stats.skip_count,
+            ..VerifyReport::default()
+        };
+        for action in &plan.actions {
+            match action {
+                SyncAction::CopyNew(entry) => report.missing.push(entry.path.display().to_string()),
+                SyncAction::Overwrite(entry, _) => {
+                    report.differ.push(entry.path.display().to_string())
+                }
+                SyncAction::Delete(path) => report.extra.push(path.display().to_string()),
+                SyncAction::Move { to, .. } => report.missing.push(to.display().to_string()),
+                // Content matches; only permissions or mtime differ.
+                SyncAction::UpdateMetadata(_) => report.matched += 1,
+                SyncAction::Skip => {}
+            }
+        }
+        report
+    }
+
+    fn format(&self) -> String {
+        let mut lines = vec![format!(
+            "Verify: {} match, {} differ, {} missing, {} extra",
+            self.matched,
+            self.differ.len(),
+            self.missing.len(),
+            self.extra.len()
+        )];
+        for path in &self.missing {
+            lines.push(format!("  MISSING   {}", path));
+        }
+        for path in &self.differ {
+            lines.push(format!("  DIFFERS   {}", path));
+        }
+        for path in &self.extra {
+            lines.push(format!("  EXTRA     {}", path));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileEntry, UpdateReason};
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            1,
+            UNIX_EPOCH + Duration::from_secs(1_000),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_verify_report_classifies_actions() {
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::CopyNew(entry("missing.txt")));
+        plan.add_action(SyncAction::Overwrite(
+            entry("changed.txt"),
+            UpdateReason::SourceNewer,
+        ));
+        plan.add_action(SyncAction::Delete(PathBuf::from("extra.txt")));
+        plan.stats.skip_count = 4;
+
+        let report = VerifyReport::from_plan(&plan);
+        assert_eq!(report.matched, 4);
+        assert_eq!(report.missing, vec!["missing.txt"]);
+        assert_eq!(report.differ, vec!["changed.txt"]);
+        assert_eq!(report.extra, vec!["extra.txt"]);
+
+        let text = report.format();
+        assert!(text.contains("Verify: 4 match, 1 differ, 1 missing, 1 extra"));
+        assert!(text.contains("MISSING   missing.txt"));
+        assert!(text.contains("DIFFERS   changed.txt"));
+        assert!(text.contains("EXTRA     extra.txt"));
+    }
+}
@@ -1,20 +1,274 @@
 //! Configuration management
 
+mod presets;
+mod profile;
+
+pub use presets::Preset;
+pub use profile::{
+    locate_profile_file, profile_search_paths, JobProfile, ProfileFile, PROFILE_FILE_NAME,
+};
+
 use super::types::DeleteMode;
-use clap::{Parser, ValueEnum};
+use crate::bisync::ConflictPolicy;
+use crate::diff::DeleteLimit;
+use crate::filter::{ByteSize, TimeBound};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
 /// kopy - Modern file synchronization tool
 #[derive(Parser, Debug)]
 #[command(name = "kopy")]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
-    /// Source directory
-    pub source: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Bare `kopy SRC DST [flags]` form, equivalent to `kopy sync`.
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+/// kopy subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Sync source into destination (default when no subcommand is given)
+    Sync(SyncArgs),
+
+    /// Show the full plan for a sync without changing anything
+    Plan(PlanArgs),
+
+    /// Run a named job from kopy.toml
+    Run(RunArgs),
+
+    /// List or restore files moved to .kopy_trash
+    Trash(TrashArgs),
+
+    /// Check that destination content matches source using Blake3 checksums
+    Verify(SyncArgs),
+
+    /// List paths that differ between source and destination
+    Diff(SyncArgs),
+
+    /// Show which ignore file or filter rule includes or excludes a path
+    ExplainFilter(ExplainFilterArgs),
+
+    /// Show the built-in exclude presets
+    Presets(PresetsArgs),
+
+    /// Two-way sync: apply changes made on either side since the last run
+    Bisync(BisyncArgs),
+
+    /// Execute a plan saved with `kopy plan --out`
+    Apply(ApplyArgs),
+
+    /// Carry a sync to an offline destination as a single batch file
+    Batch(BatchArgs),
+}
+
+/// Source/destination pair plus sync flags.
+#[derive(Args, Debug, Clone, Default)]
+pub struct SyncArgs {
+    /// Source directory; give several to overlay them (later sources win on collisions)
+    #[arg(required = true, num_args = 1.., value_name = "SOURCE")]
+    pub sources: Vec<PathBuf>,
 
     /// Destination directory
-    pub destination: PathBuf,
+    #[arg(required = true)]
+    pub destination: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: SyncOptions,
+}
+
+/// Arguments for `kopy plan`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct PlanArgs {
+    /// Only explain why PATH (relative to the source) is or is not in the plan
+    #[arg(long, value_name = "PATH")]
+    pub explain: Option<PathBuf>,
+
+    /// Also save the plan to FILE for review and `kopy apply`
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+/// Arguments for `kopy apply`.
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// Plan file written by `kopy plan --out`
+    pub plan: PathBuf,
+
+    /// Execute the actions that are still valid and report the stale ones,
+    /// instead of refusing the whole plan
+    #[arg(long)]
+    pub skip_stale: bool,
+}
+
+/// Arguments for `kopy run`.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Job name as declared by `name = "..."` in a `[[job]]` table
+    pub job: String,
+
+    /// Read jobs from this file instead of searching for kopy.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: SyncOptions,
+}
+
+/// Arguments for `kopy explain-filter`.
+#[derive(Args, Debug)]
+pub struct ExplainFilterArgs {
+    /// Scan root (a sync source or destination directory)
+    pub root: PathBuf,
+
+    /// Path to explain, relative to ROOT or to the current directory
+    pub path: PathBuf,
+
+    #[command(flatten)]
+    pub filters: FilterOptions,
+}
+
+/// Arguments for `kopy bisync`.
+#[derive(Args, Debug)]
+pub struct BisyncArgs {
+    /// First directory
+    pub left: PathBuf,
 
+    /// Second directory (created on the first run if missing)
+    pub right: PathBuf,
+
+    /// How to settle paths changed on both sides
+    #[arg(long, value_enum, default_value_t = ConflictPolicy::KeepBoth)]
+    pub conflict: ConflictPolicy,
+
+    /// Show what would be done without changing anything or saving state
+    #[arg(long, short = 'n')]
+    pub dry_run: bool,
+
+    /// Keep state files in DIR [default: $XDG_STATE_HOME/kopy/bisync]
+    #[arg(long, value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Abort if either direction deletes more than N paths or P% of its side
+    #[arg(long, value_name = "N|P%")]
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Allow running when a side that held files at the last run is empty
+    #[arg(long)]
+    pub allow_empty_source: bool,
+
+    #[command(flatten)]
+    pub filters: FilterOptions,
+}
+
+/// Arguments for `kopy batch`.
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    #[command(subcommand)]
+    pub command: BatchCommand,
+}
+
+/// `kopy batch` operations.
+// Parsed once per run; boxing `SyncOptions` would only complicate clap.
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+pub enum BatchCommand {
+    /// Record the destination tree for planning a batch elsewhere
+    Index {
+        /// Destination directory
+        destination: PathBuf,
+
+        /// Index file to write
+        index: PathBuf,
+
+        #[command(flatten)]
+        filters: FilterOptions,
+    },
+
+    /// Plan SOURCE against an index and pack the actions with the file contents they need
+    Create {
+        /// Source directory
+        source: PathBuf,
+
+        /// Index written by `kopy batch index`
+        index: PathBuf,
+
+        /// Batch file to write
+        out: PathBuf,
+
+        #[command(flatten)]
+        options: SyncOptions,
+    },
+
+    /// Check the destination against the batch and replay it
+    Apply {
+        /// Batch file written by `kopy batch create`
+        batch: PathBuf,
+
+        /// Destination directory the index was taken from
+        destination: PathBuf,
+    },
+}
+
+/// Arguments for `kopy presets`.
+#[derive(Args, Debug)]
+pub struct PresetsArgs {
+    #[command(subcommand)]
+    pub command: PresetsCommand,
+}
+
+/// `kopy presets` operations.
+#[derive(Subcommand, Debug)]
+pub enum PresetsCommand {
+    /// List every preset with the patterns it excludes
+    List,
+}
+
+/// Arguments for `kopy trash`.
+#[derive(Args, Debug)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    pub command: TrashCommand,
+}
+
+/// `kopy trash` operations.
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+    /// List trash snapshots under DESTINATION/.kopy_trash
+    List {
+        /// Destination directory that owns the trash
+        destination: PathBuf,
+    },
+
+    /// Move the files of a trash snapshot back to their original paths
+    Restore {
+        /// Destination directory that owns the trash
+        destination: PathBuf,
+
+        /// Snapshot name as shown by `kopy trash list`
+        snapshot: String,
+
+        /// Replace files that exist again at their original path
+        #[arg(long)]
+        overwrite: bool,
+    },
+}
+
+/// Sync flags shared by every command that builds a [`Config`].
+///
+/// Flags that a job profile can also set are optional here so that an
+/// explicit flag can be told apart from its default.
+#[derive(Args, Debug, Clone, Default)]
+pub struct SyncOptions {
     /// Perform a dry run (show what would be done without executing)
     #[arg(long, short = 'n')]
     pub dry_run: bool,
@@ -23,6 +277,54 @@ pub struct Cli {
     #[arg(long, short = 'c')]
     pub checksum: bool,
 
+    /// Compare by size and mtime even if the job enables checksum mode
+    #[arg(long, conflicts_with = "checksum")]
+    pub no_checksum: bool,
+
+    /// Compare files by size only, ignoring modification times
+    #[arg(long)]
+    pub size_only: bool,
+
+    /// Turn off a job's size-only comparison
+    #[arg(long, conflicts_with = "size_only")]
+    pub no_size_only: bool,
+
+    /// Transfer every file, even when size and mtime match
+    #[arg(long, short = 'I')]
+    pub ignore_times: bool,
+
+    /// Turn off a job's ignore-times setting
+    #[arg(long, conflicts_with = "ignore_times")]
+    pub no_ignore_times: bool,
+
+    /// Treat mtimes at most SECS apart as equal (e.g. 2 for FAT)
+    #[arg(long, value_name = "SECS")]
+    pub modify_window: Option<u64>,
+
+    /// Only update files that already exist in the destination
+    #[arg(long)]
+    pub existing: bool,
+
+    /// Turn off a job's existing-only setting
+    #[arg(long, conflicts_with = "existing")]
+    pub no_existing: bool,
+
+    /// Only create new files; never overwrite destination files
+    #[arg(long)]
+    pub ignore_existing: bool,
+
+    /// Turn off a job's ignore-existing setting
+    #[arg(long, conflicts_with = "ignore_existing")]
+    pub no_ignore_existing: bool,
+
+    /// Never replace a destination file that is newer than its source
+    #[arg(long, short = 'u')]
+    pub update: bool,
+
+    /// Turn off a job's update setting
+    #[arg(long, conflicts_with = "update")]
+    pub no_update: bool,
+
     /// Delete files in destination that don't exist in source (moves to trash)
     #[arg(long, conflicts_with = "delete_permanent")]
     pub delete: bool,
@@ -31,6 +333,228 @@ pub struct Cli {
     #[arg(long, conflicts_with = "delete")]
     pub delete_permanent: bool,
 
+    /// Never delete destination files, even if the job enables deletes
+    #[arg(long, conflicts_with_all = ["delete", "delete_permanent"])]
+    pub no_delete: bool,
+
+    /// Abort before executing if the plan deletes more than N paths or P% of the destination
+    #[arg(long, value_name = "N|P%")]
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Allow deletes even when the source is empty and the destination is not
+    #[arg(long)]
+    pub allow_empty_source: bool,
+
+    /// Turn off a job's allow-empty-source setting
+    #[arg(long, conflicts_with = "allow_empty_source")]
+    pub no_allow_empty_source: bool,
+
+    /// Never delete or overwrite destination paths matching GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub protect: Vec<String>,
+
+    /// What to do when a destination file is newer than its source [default: skip]
+    #[arg(long, value_enum)]
+    pub conflict: Option<ConflictMode>,
+
+    /// Order in which planned actions run [default: path]
+    #[arg(long, value_enum)]
+    pub order: Option<PlanOrder>,
+
+    /// Also sync into DIR from the same source scan (repeatable)
+    #[arg(long = "dest", value_name = "DIR")]
+    pub extra_destinations: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub filters: FilterOptions,
+
+    /// Sync only the paths listed in FILE ('-' for stdin), newline- or NUL-separated
+    #[arg(long, value_name = "FILE")]
+    pub files_from: Option<PathBuf>,
+
+    /// Skip source files smaller than SIZE (e.g. 10K, 5M)
+    #[arg(long, value_name = "SIZE")]
+    pub min_size: Option<ByteSize>,
+
+    /// Skip source files larger than SIZE (e.g. 500M, 4G)
+    #[arg(long, value_name = "SIZE")]
+    pub max_size: Option<ByteSize>,
+
+    /// Only sync source files modified within AGE (e.g. 12h, 7d) or after a date (YYYY-MM-DD)
+    #[arg(long, value_name = "AGE|DATE")]
+    pub newer_than: Option<TimeBound>,
+
+    /// Only sync source files modified more than AGE ago or before a date (YYYY-MM-DD)
+    #[arg(long, value_name = "AGE|DATE")]
+    pub older_than: Option<TimeBound>,
+
+    /// Skip directories containing a valid CACHEDIR.TAG
+    #[arg(long)]
+    pub exclude_caches: bool,
+
+    /// Turn off a job's exclude-caches setting
+    #[arg(long, conflicts_with = "exclude_caches")]
+    pub no_exclude_caches: bool,
+
+    /// Skip directories containing an entry named NAME, e.g. .nobackup (repeatable)
+    #[arg(long, value_name = "NAME")]
+    pub exclude_if_present: Vec<String>,
+
+    /// Skip files and directories with the nodump attribute (chattr +d)
+    #[arg(long)]
+    pub exclude_nodump: bool,
+
+    /// Turn off a job's exclude-nodump setting
+    #[arg(long, conflicts_with = "exclude_nodump")]
+    pub no_exclude_nodump: bool,
+
+    /// Do not cross filesystem boundaries below source or destination
+    #[arg(long, short = 'x')]
+    pub one_file_system: bool,
+
+    /// Cross filesystem boundaries even if the job sets one-file-system
+    #[arg(long, conflicts_with = "one_file_system")]
+    pub no_one_file_system: bool,
+
+    /// Descend at most N directory levels (1 = only files directly in the root)
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Scan strategy: auto chooses based on sampled tree shape [default: auto]
+    #[arg(long, value_enum)]
+    pub scan_mode: Option<ScanMode>,
+
+    /// Plan from sorted scans in bounded memory, for very large trees (no rename detection)
+    #[arg(long)]
+    pub streaming: bool,
+
+    /// Plan from whole-tree scans even if the job enables streaming
+    #[arg(long, conflicts_with = "streaming")]
+    pub no_streaming: bool,
+
+    /// Number of worker threads [default: 4]
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+}
+
+impl SyncOptions {
+    /// Apply explicitly given flags on top of an existing configuration.
+    ///
+    /// Boolean flags switch behavior on and their `no_*` counterparts switch
+    /// it off; pattern lists replace the configured lists when at least one
+    /// pattern is given.
+    pub fn apply_to(&self, config: &mut Config) {
+        config.dry_run |= self.dry_run;
+        override_flag(&mut config.checksum_mode, self.checksum, self.no_checksum);
+        override_flag(&mut config.size_only, self.size_only, self.no_size_only);
+        override_flag(
+            &mut config.ignore_times,
+            self.ignore_times,
+            self.no_ignore_times,
+        );
+        if let Some(secs) = self.modify_window {
+            config.modify_window = Duration::from_secs(secs);
+            config.widen_modify_window = false;
+        }
+        override_flag(&mut config.existing, self.existing, self.no_existing);
+        override_flag(
+            &mut config.ignore_existing,
+            self.ignore_existing,
+            self.no_ignore_existing,
+        );
+        override_flag(&mut config.update, self.update, self.no_update);
+
+        if self.delete_permanent {
+            config.delete_mode = DeleteMode::Permanent;
+        } else if self.delete {
+            config.delete_mode = DeleteMode::Trash;
+        } else if self.no_delete {
+            config.delete_mode = DeleteMode::None;
+        }
+
+        if let Some(max_delete) = self.max_delete {
+            config.max_delete = Some(max_delete);
+        }
+        override_flag(
+            &mut config.allow_empty_source,
+            self.allow_empty_source,
+            self.no_allow_empty_source,
+        );
+        if !self.protect.is_empty() {
+            config.protect_patterns = self.protect.clone();
+        }
+
+        if let Some(conflict) = self.conflict {
+            config.conflict_mode = conflict;
+        }
+        if let Some(order) = self.order {
+            config.plan_order = order;
+        }
+
+        if !self.extra_destinations.is_empty() {
+            config.extra_destinations = self.extra_destinations.clone();
+        }
+
+        self.filters.apply_to(config);
+
+        if let Some(files_from) = &self.files_from {
+            config.files_from = Some(files_from.clone());
+        }
+        if let Some(min_size) = self.min_size {
+            config.min_size = Some(min_size.0);
+        }
+        if let Some(max_size) = self.max_size {
+            config.max_size = Some(max_size.0);
+        }
+        if let Some(newer_than) = self.newer_than {
+            config.newer_than = Some(newer_than);
+        }
+        if let Some(older_than) = self.older_than {
+            config.older_than = Some(older_than);
+        }
+        override_flag(
+            &mut config.exclude_caches,
+            self.exclude_caches,
+            self.no_exclude_caches,
+        );
+        if !self.exclude_if_present.is_empty() {
+            config.exclude_if_present = self.exclude_if_present.clone();
+        }
+        override_flag(
+            &mut config.exclude_nodump,
+            self.exclude_nodump,
+            self.no_exclude_nodump,
+        );
+        override_flag(
+            &mut config.one_file_system,
+            self.one_file_system,
+            self.no_one_file_system,
+        );
+        if let Some(max_depth) = self.max_depth {
+            config.max_depth = Some(max_depth);
+        }
+        if let Some(scan_mode) = self.scan_mode {
+            config.scan_mode = scan_mode;
+        }
+        override_flag(&mut config.streaming, self.streaming, self.no_streaming);
+        if let Some(threads) = self.threads {
+            config.threads = threads;
+        }
+    }
+}
+
+/// Set `value` when `on` is given and clear it when `off` is given.
+fn override_flag(value: &mut bool, on: bool, off: bool) {
+    if on {
+        *value = true;
+    } else if off {
+        *value = false;
+    }
+}
+
+/// Path selection flags, shared by sync commands and `kopy explain-filter`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct FilterOptions {
     /// Exclude patterns (can be specified multiple times)
     #[arg(long, short = 'e')]
     pub exclude: Vec<String>,
@@ -39,15 +563,112 @@ pub struct Cli {
     #[arg(long, short = 'i')]
     pub include: Vec<String>,
 
-    /// Scan strategy: auto chooses based on sampled tree shape.
-    #[arg(long, value_enum, default_value_t = ScanMode::Auto)]
-    pub scan_mode: ScanMode,
+    /// Exclude a built-in pattern set (repeatable; see `kopy presets list`)
+    #[arg(long, value_enum, value_name = "PRESET")]
+    pub preset: Vec<Preset>,
+
+    /// Ordered filter rule, e.g. '+ *.rs', '- target/', ': .kopy-filter' (repeatable; first match wins)
+    #[arg(
+        long = "filter",
+        short = 'f',
+        value_name = "RULE",
+        allow_hyphen_values = true
+    )]
+    pub filter: Vec<String>,
+
+    /// Read exclude patterns from FILE, one per line (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Read include patterns from FILE, one per line (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pub include_from: Vec<PathBuf>,
+
+    /// Do not read any ignore files (.gitignore, git excludes, .ignore, .kopyignore)
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Do not read .gitignore files
+    #[arg(long)]
+    pub no_gitignore: bool,
+
+    /// Do not read the global git excludes file (core.excludesFile)
+    #[arg(long)]
+    pub no_git_global: bool,
+
+    /// Do not read .git/info/exclude
+    #[arg(long)]
+    pub no_git_exclude: bool,
+
+    /// Do not read .ignore files
+    #[arg(long)]
+    pub no_dot_ignore: bool,
+
+    /// Do not read .kopyignore files
+    #[arg(long)]
+    pub no_kopyignore: bool,
+
+    /// Also read per-directory ignore files named NAME (repeatable, gitignore syntax)
+    #[arg(long, value_name = "NAME")]
+    pub ignore_file: Vec<String>,
+
+    /// Skip hidden files and directories
+    #[arg(long)]
+    pub skip_hidden: bool,
+
+    /// Scan the destination without ignore files (ignored destination files become extras)
+    #[arg(long)]
+    pub no_ignore_destination: bool,
+}
+
+impl FilterOptions {
+    /// Replace configured pattern and rule lists with any lists given here.
+    ///
+    /// Ignore-source flags can only switch sources off; `--skip-hidden` can
+    /// only switch hidden-file skipping on.
+    pub fn apply_to(&self, config: &mut Config) {
+        if !self.exclude.is_empty() {
+            config.exclude_patterns = self.exclude.clone();
+        }
+        if !self.include.is_empty() {
+            config.include_patterns = self.include.clone();
+        }
+        if !self.preset.is_empty() {
+            config.presets = self.preset.clone();
+        }
+        if !self.filter.is_empty() {
+            config.filter_rules = self.filter.clone();
+        }
+        if !self.exclude_from.is_empty() {
+            config.exclude_from = self.exclude_from.clone();
+        }
+        if !self.include_from.is_empty() {
+            config.include_from = self.include_from.clone();
+        }
+
+        let ignore = &mut config.ignore;
+        if self.no_ignore {
+            *ignore = ignore.without_ignore_files();
+        }
+        ignore.gitignore &= !self.no_gitignore;
+        ignore.git_global &= !self.no_git_global;
+        ignore.git_exclude &= !self.no_git_exclude;
+        ignore.dot_ignore &= !self.no_dot_ignore;
+        ignore.kopyignore &= !self.no_kopyignore;
+        if !self.ignore_file.is_empty() {
+            ignore.extra_files = self.ignore_file.clone();
+        }
+        ignore.skip_hidden |= self.skip_hidden;
+        ignore.apply_to_destination &= !self.no_ignore_destination;
+    }
 }
 
 /// Directory scan execution mode.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ScanMode {
     /// Choose sequential/parallel based on sampled tree shape.
+    #[default]
     Auto,
     /// Force sequential scanner.
     Sequential,
@@ -55,36 +676,241 @@ pub enum ScanMode {
     Parallel,
 }
 
+/// What to do with a destination file that looks newer than its source.
+///
+/// Applies when the destination mtime is newer, which size/mtime comparison
+/// would otherwise skip forever, or overwrite when the size changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictMode {
+    /// Leave the destination file as is and report the conflict.
+    #[default]
+    Skip,
+    /// Overwrite the destination file with the source version.
+    SourceWins,
+    /// Keep the version with the newer mtime (the destination one).
+    NewerWins,
+    /// Rename the destination file to NAME.conflict-destination-TIMESTAMP, then copy the source.
+    KeepBoth,
+    /// Refuse to sync while conflicts exist.
+    Fail,
+}
+
+/// Order in which a plan's actions are executed.
+///
+/// Every order keeps deletes that clear a file/directory clash ahead of the
+/// copies into that path (see [`crate::diff::order_plan`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlanOrder {
+    /// Sorted by path.
+    #[default]
+    Path,
+    /// Deletes before anything else, to free space on tight destinations.
+    DeletesFirst,
+    /// Deletes and renames, then transfers from the largest file down.
+    LargestFirst,
+    /// Deletes and renames, then transfers from the smallest file up.
+    SmallestFirst,
+    /// Deletes and renames, then transfers in source inode order (close to on-disk order).
+    Locality,
+}
+
+/// Which ignore files the scanners read, and whether hidden entries are skipped.
+///
+/// In `kopy.toml` this is the `[job.ignore]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IgnoreSettings {
+    /// Read `.gitignore` files (inside git repositories).
+    pub gitignore: bool,
+
+    /// Read the global git excludes file (`core.excludesFile`).
+    pub git_global: bool,
+
+    /// Read `.git/info/exclude`.
+    pub git_exclude: bool,
+
+    /// Read `.ignore` files.
+    pub dot_ignore: bool,
+
+    /// Read `.kopyignore` files.
+    pub kopyignore: bool,
+
+    /// Additional per-directory ignore file names (gitignore syntax).
+    pub extra_files: Vec<String>,
+
+    /// Skip hidden (dot) files and directories.
+    pub skip_hidden: bool,
+
+    /// Apply ignore files to the destination scan as well as the source scan.
+    pub apply_to_destination: bool,
+}
+
+impl Default for IgnoreSettings {
+    fn default() -> Self {
+        Self {
+            gitignore: true,
+            git_global: true,
+            git_exclude: true,
+            dot_ignore: true,
+            kopyignore: true,
+            extra_files: Vec::new(),
+            skip_hidden: false,
+            apply_to_destination: true,
+        }
+    }
+}
+
+impl IgnoreSettings {
+    /// Per-directory ignore file names handled as custom ignore files, in
+    /// increasing precedence.
+    pub fn custom_filenames(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        if self.kopyignore {
+            names.push(".kopyignore");
+        }
+        names.extend(self.extra_files.iter().map(String::as_str));
+        names
+    }
+
+    /// Settings with every ignore file disabled (hidden-file handling is kept).
+    pub fn without_ignore_files(&self) -> Self {
+        Self {
+            gitignore: false,
+            git_global: false,
+            git_exclude: false,
+            dot_ignore: false,
+            kopyignore: false,
+            extra_files: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
 /// Global configuration for kopy
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Source directory
     pub source: PathBuf,
 
+    /// Further source directories layered over `source`; later ones win on
+    /// path collisions
+    pub overlay_sources: Vec<PathBuf>,
+
     /// Destination directory
     pub destination: PathBuf,
 
+    /// Further destinations synced from the same source scan
+    pub extra_destinations: Vec<PathBuf>,
+
     /// Dry run (show plan, don't execute)
     pub dry_run: bool,
 
     /// Force checksum verification (slow but paranoid)
     pub checksum_mode: bool,
 
+    /// Compare by size only, ignoring mtimes
+    pub size_only: bool,
+
+    /// Transfer every regular file regardless of size and mtime
+    pub ignore_times: bool,
+
+    /// Mtimes at most this far apart count as equal
+    pub modify_window: Duration,
+
+    /// Widen `modify_window` to the destination's mtime resolution; off when
+    /// a window was given explicitly
+    pub widen_modify_window: bool,
+
+    /// Skip source files missing from the destination
+    pub existing: bool,
+
+    /// Skip source files already present in the destination
+    pub ignore_existing: bool,
+
+    /// Keep destination files that are newer than their source
+    pub update: bool,
+
     /// How to handle deletes
     pub delete_mode: DeleteMode,
 
+    /// How to handle destination files newer than their source
+    pub conflict_mode: ConflictMode,
+
+    /// Order in which planned actions run
+    pub plan_order: PlanOrder,
+
+    /// Refuse plans that delete more than this (`--max-delete`)
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Allow deletes when the source is empty but the destination is not
+    pub allow_empty_source: bool,
+
+    /// Destination paths never deleted or overwritten (`--protect`), in
+    /// filter-rule pattern syntax
+    pub protect_patterns: Vec<String>,
+
     /// Exclude patterns (globs)
     pub exclude_patterns: Vec<String>,
 
     /// Include patterns (overrides excludes)
     pub include_patterns: Vec<String>,
 
+    /// Built-in exclude sets, checked together with `exclude_patterns`
+    pub presets: Vec<Preset>,
+
+    /// Ordered rsync-style filter rules (`+ PAT`, `- PAT`, `: NAME`, `. FILE`)
+    pub filter_rules: Vec<String>,
+
+    /// Files listing exclude patterns, one per line
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Files listing include patterns, one per line
+    pub include_from: Vec<PathBuf>,
+
+    /// Ignore-file sources and hidden-file handling
+    pub ignore: IgnoreSettings,
+
+    /// Only sync paths listed in this file (`-` = stdin); see `--files-from`
+    pub files_from: Option<PathBuf>,
+
+    /// Skip source files smaller than this many bytes
+    pub min_size: Option<u64>,
+
+    /// Skip source files larger than this many bytes
+    pub max_size: Option<u64>,
+
+    /// Only sync source files modified after this point
+    pub newer_than: Option<TimeBound>,
+
+    /// Only sync source files modified before this point
+    pub older_than: Option<TimeBound>,
+
+    /// Skip directories tagged with a valid `CACHEDIR.TAG`
+    pub exclude_caches: bool,
+
+    /// Skip directories containing an entry with one of these names
+    pub exclude_if_present: Vec<String>,
+
+    /// Skip files and directories carrying the `nodump` inode flag
+    pub exclude_nodump: bool,
+
+    /// Stay on the filesystem of the scan root
+    pub one_file_system: bool,
+
+    /// Maximum directory depth below the scan root (`None` = unlimited)
+    pub max_depth: Option<usize>,
+
     /// Number of worker threads.
     pub threads: usize,
 
     /// Directory scan mode.
     pub scan_mode: ScanMode,
 
+    /// Plan by merging sorted scans instead of holding both trees in memory
+    pub streaming: bool,
+
     /// Bandwidth limit (bytes/sec, None = unlimited)
     pub bandwidth_limit: Option<u64>,
 
@@ -102,14 +928,44 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             source: PathBuf::new(),
+            overlay_sources: Vec::new(),
             destination: PathBuf::new(),
+            extra_destinations: Vec::new(),
             dry_run: false,
             checksum_mode: false,
+            size_only: false,
+            ignore_times: false,
+            modify_window: Duration::ZERO,
+            widen_modify_window: true,
+            existing: false,
+            ignore_existing: false,
+            update: false,
             delete_mode: DeleteMode::None,
+            conflict_mode: ConflictMode::Skip,
+            plan_order: PlanOrder::Path,
+            max_delete: None,
+            allow_empty_source: false,
+            protect_patterns: Vec::new(),
             exclude_patterns: Vec::new(),
             include_patterns: Vec::new(),
+            presets: Vec::new(),
+            filter_rules: Vec::new(),
+            exclude_from: Vec::new(),
+            include_from: Vec::new(),
+            ignore: IgnoreSettings::default(),
+            files_from: None,
+            min_size: None,
+            max_size: None,
+            newer_than: None,
+            older_than: None,
+            exclude_caches: false,
+            exclude_if_present: Vec::new(),
+            exclude_nodump: false,
+            one_file_system: false,
+            max_depth: None,
             threads: 4,
             scan_mode: ScanMode::Auto,
+            streaming: false,
             bandwidth_limit: None,
             backup_dir: None,
             watch: false,
@@ -119,12 +975,57 @@ impl Default for Config {
 }
 
 impl Config {
+    /// The primary source followed by overlay sources, in priority order
+    /// (later sources win).
+    pub fn sources(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.source).chain(&self.overlay_sources)
+    }
+
+    /// The primary destination followed by `extra_destinations`.
+    pub fn destinations(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.destination).chain(&self.extra_destinations)
+    }
+
+    /// One configuration per destination, each without further destinations.
+    pub fn destination_configs(&self) -> Vec<Config> {
+        self.destinations()
+            .map(|destination| Config {
+                destination: destination.clone(),
+                extra_destinations: Vec::new(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Exclude globs from `presets` followed by `exclude_patterns`.
+    pub fn effective_exclude_patterns(&self) -> Vec<String> {
+        self.presets
+            .iter()
+            .flat_map(|preset| preset.patterns().iter().map(|pattern| pattern.to_string()))
+            .chain(self.exclude_patterns.iter().cloned())
+            .collect()
+    }
+
+    /// Ignore settings for a scan of `root`.
+    ///
+    /// The destination scan reads no ignore files when
+    /// `ignore.apply_to_destination` is off.
+    pub fn ignore_settings_for(&self, root: &Path) -> IgnoreSettings {
+        if root == self.destination && !self.ignore.apply_to_destination {
+            self.ignore.without_ignore_files()
+        } else {
+            self.ignore.clone()
+        }
+    }
+
     /// Validate configuration
     ///
     /// Ensures:
     /// - Source path exists and is a file or directory
     /// - Source and destination are different paths
     /// - All exclude and include patterns are valid glob patterns
+    /// - Filter rules parse and rule files are readable
+    /// - At least one worker thread is configured
     ///
     /// # Example
     /// ```no_run
@@ -194,6 +1095,65 @@ impl Config {
             )));
         }
 
+        // 3.2. Overlay sources are directories merged over the primary source
+        for overlay in &self.overlay_sources {
+            if !source_is_dir || !overlay.is_dir() {
+                return Err(super::types::KopyError::Config(format!(
+                    "Every source must be an existing directory when several are given: {:?}",
+                    if source_is_dir { overlay } else { &self.source }
+                )));
+            }
+            let overlay_normalized = canonical_or_normalized(overlay)?;
+            if overlay_normalized == destination_normalized
+                || is_strict_descendant(&destination_normalized, &overlay_normalized)
+                || is_strict_descendant(&overlay_normalized, &destination_normalized)
+            {
+                return Err(super::types::KopyError::Config(format!(
+                    "Source and destination cannot be the same or nested. source='{}', destination='{}'",
+                    overlay.display(),
+                    self.destination.display()
+                )));
+            }
+        }
+
+        // 3.3. Extra destinations follow the same rules as the primary one and
+        // must not overlap each other
+        let mut destinations_normalized = vec![destination_normalized];
+        for extra in &self.extra_destinations {
+            if source_is_dir && extra.exists() && !extra.is_dir() {
+                return Err(super::types::KopyError::Config(format!(
+                    "Destination path must be a directory if it exists: {:?}",
+                    extra
+                )));
+            }
+            let extra_normalized = canonical_or_normalized(extra)?;
+            for source in self.sources() {
+                let source_normalized = canonical_or_normalized(source)?;
+                if source_normalized == extra_normalized
+                    || (source_is_dir
+                        && (is_strict_descendant(&extra_normalized, &source_normalized)
+                            || is_strict_descendant(&source_normalized, &extra_normalized)))
+                {
+                    return Err(super::types::KopyError::Config(format!(
+                        "Source and destination cannot be the same or nested. source='{}', destination='{}'",
+                        source.display(),
+                        extra.display()
+                    )));
+                }
+            }
+            if destinations_normalized.iter().any(|other| {
+                *other == extra_normalized
+                    || is_strict_descendant(other, &extra_normalized)
+                    || is_strict_descendant(&extra_normalized, other)
+            }) {
+                return Err(super::types::KopyError::Config(format!(
+                    "Destinations cannot be the same or nested: {:?}",
+                    extra
+                )));
+            }
+            destinations_normalized.push(extra_normalized);
+        }
+
         // 4. Validate exclude patterns are valid globs
         for pattern in &self.exclude_patterns {
             glob::Pattern::new(pattern).map_err(|e| {
@@ -214,6 +1174,120 @@ impl Config {
             })?;
         }
 
+        // 5.1. Validate ordered filter rules and rule files
+        crate::filter::FilterRules::from_config(self)?;
+
+        // 5.2. Extra ignore files and marker files are looked up by name in every directory
+        let is_plain_name =
+            |name: &str| !(name.is_empty() || name.contains('/') || name == "." || name == "..");
+        if let Some(name) = self.ignore.extra_files.iter().find(|n| !is_plain_name(n)) {
+            return Err(super::types::KopyError::Config(format!(
+                "Invalid ignore file name '{}': expected a plain file name",
+                name
+            )));
+        }
+        if let Some(name) = self.exclude_if_present.iter().find(|n| !is_plain_name(n)) {
+            return Err(super::types::KopyError::Config(format!(
+                "Invalid --exclude-if-present name '{}': expected a plain file name such as .nobackup",
+                name
+            )));
+        }
+
+        // 5.3. A file list selects paths below a source directory
+        if let Some(files_from) = &self.files_from {
+            if !source_is_dir {
+                return Err(super::types::KopyError::Config(
+                    "--files-from requires the source to be a directory".to_string(),
+                ));
+            }
+            if files_from != Path::new("-") && !files_from.is_file() {
+                return Err(super::types::KopyError::Config(format!(
+                    "File list not found: {}",
+                    files_from.display()
+                )));
+            }
+        }
+
+        // 5.4. Size and age limits must leave something to select
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min > max {
+                return Err(super::types::KopyError::Config(format!(
+                    "--min-size ({} bytes) is larger than --max-size ({} bytes)",
+                    min, max
+                )));
+            }
+        }
+        if let (Some(newer), Some(older)) = (self.newer_than, self.older_than) {
+            let now = std::time::SystemTime::now();
+            if newer.resolve(now) >= older.resolve(now) {
+                return Err(super::types::KopyError::Config(
+                    "--newer-than and --older-than leave no modification time to select"
+                        .to_string(),
+                ));
+            }
+        }
+
+        // 5.5. A depth of zero would scan nothing
+        if self.max_depth == Some(0) {
+            return Err(super::types::KopyError::Config(
+                "--max-depth must be at least 1".to_string(),
+            ));
+        }
+
+        // 5.6. Only one comparison policy can decide what changed
+        if self.size_only && (self.checksum_mode || self.ignore_times) {
+            return Err(super::types::KopyError::Config(
+                "--size-only cannot be combined with --checksum or --ignore-times".to_string(),
+            ));
+        }
+
+        // 5.7. A streamed plan merges exactly one source and one destination tree
+        if self.streaming
+            && (!source_is_dir
+                || !self.overlay_sources.is_empty()
+                || !self.extra_destinations.is_empty()
+                || self.files_from.is_some())
+        {
+            return Err(super::types::KopyError::Config(
+                "--streaming needs a single source directory and cannot be combined with overlays, --dest or --files-from"
+                    .to_string(),
+            ));
+        }
+
+        // 5.8. Reordering needs every action at once
+        if self.streaming && self.plan_order != PlanOrder::Path {
+            return Err(super::types::KopyError::Config(
+                "--order needs the whole plan in memory and cannot be combined with --streaming"
+                    .to_string(),
+            ));
+        }
+
+        // 5.9. Protect patterns are filter-rule globs matched against the destination
+        for (index, pattern) in self.protect_patterns.iter().enumerate() {
+            crate::filter::FilterRule::new(
+                crate::filter::FilterAction::Exclude,
+                pattern,
+                PathBuf::new(),
+                crate::filter::RuleSource::Argument {
+                    flag: "--protect",
+                    position: index + 1,
+                },
+            )?;
+        }
+        if self.streaming && !self.protect_patterns.is_empty() {
+            return Err(super::types::KopyError::Config(
+                "--protect needs the whole destination tree and cannot be combined with --streaming"
+                    .to_string(),
+            ));
+        }
+
+        // 6. Worker pools need at least one thread
+        if self.threads == 0 {
+            return Err(super::types::KopyError::Config(
+                "Thread count must be at least 1".to_string(),
+            ));
+        }
+
         Ok(())
     }
 }
@@ -285,45 +1359,95 @@ fn normalize_path(path: &Path) -> PathBuf {
     normalized
 }
 
+impl TryFrom<SyncArgs> for Config {
+    type Error = super::types::KopyError;
+
+    /// Convert CLI arguments to Config
+    ///
+    /// This performs the following mappings:
+    /// - the first source becomes `source`, further ones `overlay_sources`
+    /// - `destination` is copied directly
+    /// - `dry_run` and `checksum` flags are copied directly
+    /// - Delete mode is determined by flags:
+    ///   - `--delete-permanent` → `DeleteMode::Permanent`
+    ///   - `--delete` → `DeleteMode::Trash`
+    ///   - Neither → `DeleteMode::None`
+    /// - `exclude` → `exclude_patterns`
+    /// - `include` → `include_patterns`
+    /// - `scan_mode` and `threads` fall back to their defaults when omitted
+    ///
+    /// The resulting Config is validated before being returned.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for missing paths, invalid path relationships
+    /// or invalid glob patterns.
+    fn try_from(args: SyncArgs) -> Result<Self, Self::Error> {
+        let mut sources = args.sources.into_iter();
+        let (Some(source), Some(destination)) = (sources.next(), args.destination) else {
+            return Err(super::types::KopyError::Config(
+                "Both a source and a destination path are required".to_string(),
+            ));
+        };
+
+        let mut config = Config {
+            source,
+            overlay_sources: sources.collect(),
+            destination,
+            ..Default::default()
+        };
+        args.options.apply_to(&mut config);
+
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
 impl TryFrom<Cli> for Config {
     type Error = super::types::KopyError;
 
-    /// Convert CLI arguments to Config
-    ///
-    /// This performs the following mappings:
-    /// - `source` and `destination` are copied directly
-    /// - `dry_run` and `checksum` flags are copied directly
-    /// - Delete mode is determined by flags:
-    ///   - `--delete-permanent` → `DeleteMode::Permanent`
-    ///   - `--delete` → `DeleteMode::Trash`
-    ///   - Neither → `DeleteMode::None`
-    /// - `exclude` → `exclude_patterns`
-    /// - `include` → `include_patterns`
+    /// Convert a parsed command line describing a source/destination pair.
     ///
-    /// The resulting Config is validated before being returned.
+    /// Accepts the bare `kopy SRC DST` form as well as `sync`, `plan`,
+    /// `verify` and `diff`.
     ///
     /// # Errors
-    /// Returns `KopyError::Config` for invalid path relationships or invalid glob patterns.
+    /// Returns `KopyError::Config` for subcommands without a source/destination
+    /// pair, or when [`Config::try_from`] on the arguments fails.
     fn try_from(cli: Cli) -> Result<Self, Self::Error> {
-        let delete_mode = if cli.delete_permanent {
-            DeleteMode::Permanent
-        } else if cli.delete {
-            DeleteMode::Trash
-        } else {
-            DeleteMode::None
-        };
+        match cli.command {
+            None => Config::try_from(cli.sync),
+            Some(Command::Sync(args)) | Some(Command::Verify(args)) | Some(Command::Diff(args)) => {
+                Config::try_from(args)
+            }
+            Some(Command::Plan(args)) => Config::try_from(args.sync),
+            Some(Command::Run(_))
+            | Some(Command::Trash(_))
+            | Some(Command::ExplainFilter(_))
+            | Some(Command::Presets(_))
+            | Some(Command::Bisync(_))
+            | Some(Command::Apply(_))
+            | Some(Command::Batch(_)) => Err(super::types::KopyError::Config(
+                "This command does not take a source and destination".to_string(),
+            )),
+        }
+    }
+}
 
-        let config = Config {
-            source: cli.source,
-            destination: cli.destination,
-            dry_run: cli.dry_run,
-            checksum_mode: cli.checksum,
-            delete_mode,
-            exclude_patterns: cli.exclude,
-            include_patterns: cli.include,
-            scan_mode: cli.scan_mode,
-            ..Default::default()
-        };
+impl Config {
+    /// Build a configuration from a `kopy.toml` job, with CLI flags taking precedence.
+    ///
+    /// The merged configuration goes through [`Config::validate`] exactly like
+    /// a configuration built from [`Cli`].
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` when the merged configuration is invalid.
+    pub fn from_job(
+        job: &JobProfile,
+        overrides: &SyncOptions,
+    ) -> Result<Self, super::types::KopyError> {
+        let mut config = job.to_config();
+        overrides.apply_to(&mut config);
 
         config.validate()?;
 
@@ -498,6 +1622,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validation_invalid_filter_rule() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let config = Config {
+            source: src_dir.path().to_path_buf(),
+            destination: dest_dir.path().to_path_buf(),
+            filter_rules: vec!["- *.tmp".to_string(), "*.log".to_string()],
+            ..Default::default()
+        };
+
+        if let Err(super::super::types::KopyError::Config(msg)) = config.validate() {
+            assert!(msg.contains("--filter #2"));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+
+    #[test]
+    fn test_validation_rejects_ignore_file_paths() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let config = Config {
+            source: src_dir.path().to_path_buf(),
+            destination: dest_dir.path().to_path_buf(),
+            ignore: IgnoreSettings {
+                extra_files: vec!["conf/ignore".to_string()],
+                ..IgnoreSettings::default()
+            },
+            ..Default::default()
+        };
+
+        if let Err(super::super::types::KopyError::Config(msg)) = config.validate() {
+            assert!(msg.contains("Invalid ignore file name"));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+
     #[test]
     fn test_validation_success() {
         let src_dir = create_temp_dir();
@@ -586,19 +1751,21 @@ mod tests {
         let src_dir = create_temp_dir();
         let dest_dir = create_temp_dir();
 
-        let cli = Cli {
-            source: src_dir.path().to_path_buf(),
-            destination: dest_dir.path().to_path_buf(),
-            dry_run: false,
-            checksum: false,
-            delete: true,
-            delete_permanent: false,
-            exclude: vec!["*.tmp".to_string()],
-            include: vec!["*.rs".to_string()],
-            scan_mode: ScanMode::Auto,
+        let args = SyncArgs {
+            sources: vec![src_dir.path().to_path_buf()],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions {
+                delete: true,
+                filters: FilterOptions {
+                    exclude: vec!["*.tmp".to_string()],
+                    include: vec!["*.rs".to_string()],
+                    ..FilterOptions::default()
+                },
+                ..SyncOptions::default()
+            },
         };
 
-        let config = Config::try_from(cli).unwrap();
+        let config = Config::try_from(args).unwrap();
 
         assert_eq!(config.delete_mode, DeleteMode::Trash);
         assert_eq!(config.exclude_patterns, vec!["*.tmp"]);
@@ -613,19 +1780,16 @@ mod tests {
         let src_dir = create_temp_dir();
         let dest_dir = create_temp_dir();
 
-        let cli = Cli {
-            source: src_dir.path().to_path_buf(),
-            destination: dest_dir.path().to_path_buf(),
-            dry_run: false,
-            checksum: false,
-            delete: false,
-            delete_permanent: true,
-            exclude: vec![],
-            include: vec![],
-            scan_mode: ScanMode::Auto,
+        let args = SyncArgs {
+            sources: vec![src_dir.path().to_path_buf()],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions {
+                delete_permanent: true,
+                ..SyncOptions::default()
+            },
         };
 
-        let config = Config::try_from(cli).unwrap();
+        let config = Config::try_from(args).unwrap();
 
         assert_eq!(config.delete_mode, DeleteMode::Permanent);
     }
@@ -635,19 +1799,15 @@ mod tests {
         let src_dir = create_temp_dir();
         let dest_dir = create_temp_dir();
 
-        let cli = Cli {
-            source: src_dir.path().to_path_buf(),
-            destination: dest_dir.path().to_path_buf(),
-            dry_run: false,
-            checksum: false,
-            delete: false,
-            delete_permanent: false,
-            exclude: vec![],
-            include: vec![],
-            scan_mode: ScanMode::Auto,
+        let args = SyncArgs {
+            sources: vec![src_dir.path().to_path_buf()],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions {
+                ..SyncOptions::default()
+            },
         };
 
-        let config = Config::try_from(cli).unwrap();
+        let config = Config::try_from(args).unwrap();
 
         assert_eq!(config.delete_mode, DeleteMode::None);
     }
@@ -657,19 +1817,17 @@ mod tests {
         let src_dir = create_temp_dir();
         let dest_dir = create_temp_dir();
 
-        let cli = Cli {
-            source: src_dir.path().to_path_buf(),
-            destination: dest_dir.path().to_path_buf(),
-            dry_run: true,
-            checksum: true,
-            delete: false,
-            delete_permanent: false,
-            exclude: vec![],
-            include: vec![],
-            scan_mode: ScanMode::Auto,
+        let args = SyncArgs {
+            sources: vec![src_dir.path().to_path_buf()],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions {
+                dry_run: true,
+                checksum: true,
+                ..SyncOptions::default()
+            },
         };
 
-        let config = Config::try_from(cli).unwrap();
+        let config = Config::try_from(args).unwrap();
 
         assert!(config.checksum_mode);
         assert!(config.dry_run);
@@ -678,19 +1836,15 @@ mod tests {
     #[test]
     fn test_cli_conversion_validation_failure() {
         // Non-existent source path should fail validation
-        let cli = Cli {
-            source: PathBuf::from("/nonexistent/path/that/does/not/exist"),
-            destination: PathBuf::from("/some/other/path"),
-            dry_run: false,
-            checksum: false,
-            delete: false,
-            delete_permanent: false,
-            exclude: vec![],
-            include: vec![],
-            scan_mode: ScanMode::Auto,
+        let args = SyncArgs {
+            sources: vec![PathBuf::from("/nonexistent/path/that/does/not/exist")],
+            destination: Some(PathBuf::from("/some/other/path")),
+            options: SyncOptions {
+                ..SyncOptions::default()
+            },
         };
 
-        let result = Config::try_from(cli);
+        let result = Config::try_from(args);
         assert!(result.is_err());
 
         if let Err(super::super::types::KopyError::Config(msg)) = result {
@@ -703,14 +1857,649 @@ mod tests {
     #[test]
     fn test_cli_parse_scan_mode_default_auto() {
         let cli = Cli::try_parse_from(["kopy", "src", "dst"]).expect("parse cli");
-        assert_eq!(cli.scan_mode, ScanMode::Auto);
+        assert!(cli.command.is_none());
+        assert_eq!(
+            cli.sync.options.scan_mode.unwrap_or_default(),
+            ScanMode::Auto
+        );
     }
 
     #[test]
     fn test_cli_parse_scan_mode_parallel() {
         let cli = Cli::try_parse_from(["kopy", "src", "dst", "--scan-mode", "parallel"])
             .expect("parse cli");
-        assert_eq!(cli.scan_mode, ScanMode::Parallel);
+        assert_eq!(cli.sync.options.scan_mode, Some(ScanMode::Parallel));
+    }
+
+    #[test]
+    fn test_cli_parse_filter_rules_keep_order() {
+        let cli = Cli::try_parse_from([
+            "kopy",
+            "src",
+            "dst",
+            "--filter",
+            "+ *.rs",
+            "-f",
+            "- *",
+            "--exclude-from",
+            "excludes.txt",
+        ])
+        .expect("parse cli");
+        assert_eq!(cli.sync.options.filters.filter, vec!["+ *.rs", "- *"]);
+        assert_eq!(
+            cli.sync.options.filters.exclude_from,
+            vec![PathBuf::from("excludes.txt")]
+        );
+    }
+
+    #[test]
+    fn test_cli_ignore_flags_switch_sources_off() {
+        let cli = Cli::try_parse_from([
+            "kopy",
+            "src",
+            "dst",
+            "--no-gitignore",
+            "--ignore-file",
+            ".backupignore",
+            "--skip-hidden",
+            "--no-ignore-destination",
+        ])
+        .expect("parse cli");
+
+        let mut config = Config::default();
+        cli.sync.options.apply_to(&mut config);
+        assert!(!config.ignore.gitignore);
+        assert!(config.ignore.git_exclude);
+        assert!(config.ignore.kopyignore);
+        assert_eq!(config.ignore.extra_files, vec![".backupignore"]);
+        assert!(config.ignore.skip_hidden);
+        assert!(!config.ignore.apply_to_destination);
+
+        let cli = Cli::try_parse_from(["kopy", "src", "dst", "--no-ignore"]).expect("parse cli");
+        let mut config = Config::default();
+        cli.sync.options.apply_to(&mut config);
+        assert!(config.ignore.custom_filenames().is_empty());
+        assert!(!config.ignore.gitignore && !config.ignore.git_global);
+        assert!(!config.ignore.git_exclude && !config.ignore.dot_ignore);
+    }
+
+    #[test]
+    fn test_cli_parse_run_subcommand() {
+        let cli = Cli::try_parse_from(["kopy", "run", "nightly", "--threads", "8", "--delete"])
+            .expect("parse cli");
+        match cli.command {
+            Some(Command::Run(args)) => {
+                assert_eq!(args.job, "nightly");
+                assert_eq!(args.options.threads, Some(8));
+                assert!(args.options.delete);
+                assert!(args.config.is_none());
+            }
+            other => panic!("Expected run subcommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_requires_destination_without_subcommand() {
+        assert!(Cli::try_parse_from(["kopy", "src"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_bare_form_is_sync_alias() {
+        let cli = Cli::try_parse_from(["kopy", "src", "dst", "--delete"]).expect("parse cli");
+        assert!(cli.command.is_none());
+        assert_eq!(cli.sync.sources, vec![PathBuf::from("src")]);
+        assert_eq!(cli.sync.destination, Some(PathBuf::from("dst")));
+        assert!(cli.sync.options.delete);
+
+        let cli =
+            Cli::try_parse_from(["kopy", "sync", "src", "dst", "--delete"]).expect("parse cli");
+        match cli.command {
+            Some(Command::Sync(args)) => {
+                assert_eq!(args.sources, vec![PathBuf::from("src")]);
+                assert!(args.options.delete);
+            }
+            other => panic!("Expected sync subcommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_plan_verify_diff_take_paths() {
+        for name in ["plan", "verify", "diff"] {
+            let cli = Cli::try_parse_from(["kopy", name, "src", "dst"]).expect("parse cli");
+            let args = match cli.command {
+                Some(Command::Plan(PlanArgs { sync: args, .. }))
+                | Some(Command::Verify(args))
+                | Some(Command::Diff(args)) => args,
+                other => panic!("Expected {name} subcommand, got {:?}", other),
+            };
+            assert_eq!(args.destination, Some(PathBuf::from("dst")));
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_plan_explain() {
+        let cli = Cli::try_parse_from(["kopy", "plan", "src", "dst", "--explain", "a/b.txt"])
+            .expect("parse cli");
+        let Some(Command::Plan(args)) = cli.command else {
+            panic!("Expected plan subcommand");
+        };
+        assert_eq!(args.explain, Some(PathBuf::from("a/b.txt")));
+        assert_eq!(args.sync.destination, Some(PathBuf::from("dst")));
+    }
+
+    #[test]
+    fn test_cli_parse_multiple_sources() {
+        let cli = Cli::try_parse_from(["kopy", "sync", "base", "staging", "prod", "dst"])
+            .expect("parse cli");
+        let Some(Command::Sync(args)) = cli.command else {
+            panic!("Expected sync subcommand");
+        };
+        assert_eq!(
+            args.sources,
+            vec![
+                PathBuf::from("base"),
+                PathBuf::from("staging"),
+                PathBuf::from("prod")
+            ]
+        );
+        assert_eq!(args.destination, Some(PathBuf::from("dst")));
+    }
+
+    #[test]
+    fn test_cli_conversion_multiple_sources() {
+        let base = create_temp_dir();
+        let overlay = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let args = SyncArgs {
+            sources: vec![base.path().to_path_buf(), overlay.path().to_path_buf()],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions::default(),
+        };
+        let config = Config::try_from(args).expect("valid config");
+        assert_eq!(config.source, base.path());
+        assert_eq!(config.overlay_sources, vec![overlay.path().to_path_buf()]);
+        assert_eq!(config.sources().count(), 2);
+
+        let file = overlay.path().join("single.txt");
+        fs::write(&file, "x").expect("write file");
+        let args = SyncArgs {
+            sources: vec![base.path().to_path_buf(), file],
+            destination: Some(dest_dir.path().to_path_buf()),
+            options: SyncOptions::default(),
+        };
+        match Config::try_from(args) {
+            Err(super::super::types::KopyError::Config(msg)) => {
+                assert!(msg.contains("must be an existing directory"))
+            }
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_conversion_comparison_policies() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+        let src = src_dir.path().to_str().expect("utf-8 path");
+        let dest = dest_dir.path().to_str().expect("utf-8 path");
+
+        let cli = Cli::try_parse_from([
+            "kopy",
+            src,
+            dest,
+            "--size-only",
+            "--modify-window",
+            "2",
+            "--existing",
+            "-u",
+        ])
+        .expect("parse comparison flags");
+        let config = Config::try_from(cli).expect("valid config");
+        assert!(config.size_only);
+        assert_eq!(config.modify_window, Duration::from_secs(2));
+        assert!(config.existing && config.update && !config.ignore_existing);
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--size-only", "--checksum"])
+            .expect("parse conflicting flags");
+        match Config::try_from(cli) {
+            Err(super::super::types::KopyError::Config(msg)) => {
+                assert!(msg.contains("--size-only cannot be combined"))
+            }
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_conversion_extra_destinations() {
+        let src_dir = create_temp_dir();
+        let first = create_temp_dir();
+        let second = create_temp_dir();
+
+        let cli = Cli::try_parse_from([
+            "kopy",
+            src_dir.path().to_str().expect("utf-8 path"),
+            first.path().to_str().expect("utf-8 path"),
+            "--dest",
+            second.path().to_str().expect("utf-8 path"),
+        ])
+        .expect("parse --dest");
+        let config = Config::try_from(cli).expect("valid config");
+        assert_eq!(config.extra_destinations, vec![second.path().to_path_buf()]);
+        let targets = config.destination_configs();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].destination, second.path());
+        assert!(targets.iter().all(|t| t.extra_destinations.is_empty()));
+
+        let nested = Config {
+            source: src_dir.path().to_path_buf(),
+            destination: first.path().to_path_buf(),
+            extra_destinations: vec![first.path().join("copy")],
+            ..Config::default()
+        };
+        match nested.validate() {
+            Err(super::super::types::KopyError::Config(msg)) => {
+                assert!(msg.contains("Destinations cannot be the same or nested"))
+            }
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+
+        let inside_source = Config {
+            source: src_dir.path().to_path_buf(),
+            destination: first.path().to_path_buf(),
+            extra_destinations: vec![src_dir.path().join("backup")],
+            ..Config::default()
+        };
+        assert!(inside_source.validate().is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_order() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+        let src = src_dir.path().to_str().expect("utf8 path");
+        let dest = dest_dir.path().to_str().expect("utf8 path");
+
+        let cli = Cli::try_parse_from(["kopy", src, dest]).expect("parse cli");
+        assert_eq!(
+            Config::try_from(cli).expect("valid config").plan_order,
+            PlanOrder::Path
+        );
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--order", "deletes-first"])
+            .expect("parse cli");
+        assert_eq!(
+            Config::try_from(cli).expect("valid config").plan_order,
+            PlanOrder::DeletesFirst
+        );
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--order", "locality", "--streaming"])
+            .expect("parse cli");
+        assert!(Config::try_from(cli).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_delete_guard() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+        let src = src_dir.path().to_str().expect("utf8 path");
+        let dest = dest_dir.path().to_str().expect("utf8 path");
+
+        let cli = Cli::try_parse_from(["kopy", src, dest]).expect("parse cli");
+        let config = Config::try_from(cli).expect("valid config");
+        assert_eq!(config.max_delete, None);
+        assert!(!config.allow_empty_source);
+
+        let cli = Cli::try_parse_from([
+            "kopy",
+            src,
+            dest,
+            "--max-delete",
+            "5%",
+            "--allow-empty-source",
+        ])
+        .expect("parse cli");
+        let config = Config::try_from(cli).expect("valid config");
+        assert_eq!(config.max_delete, Some(DeleteLimit::Percent(5.0)));
+        assert!(config.allow_empty_source);
+
+        assert!(Cli::try_parse_from(["kopy", src, dest, "--max-delete", "lots"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_protect() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+        let src = src_dir.path().to_str().expect("utf8 path");
+        let dest = dest_dir.path().to_str().expect("utf8 path");
+
+        let cli = Cli::try_parse_from([
+            "kopy",
+            src,
+            dest,
+            "--protect",
+            ".well-known/",
+            "--protect",
+            "/config.local",
+        ])
+        .expect("parse cli");
+        assert_eq!(
+            Config::try_from(cli)
+                .expect("valid config")
+                .protect_patterns,
+            vec![".well-known/".to_string(), "/config.local".to_string()]
+        );
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--protect", "[bad"]).expect("parse cli");
+        assert!(Config::try_from(cli).is_err());
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--protect", "uploads/", "--streaming"])
+            .expect("parse cli");
+        assert!(Config::try_from(cli).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_streaming_needs_single_pair() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+        let overlay = create_temp_dir();
+        let src = src_dir.path().to_str().expect("utf8 path");
+        let dest = dest_dir.path().to_str().expect("utf8 path");
+
+        let cli = Cli::try_parse_from(["kopy", src, dest, "--streaming"]).expect("parse cli");
+        assert!(Config::try_from(cli).expect("valid config").streaming);
+
+        let overlay = overlay.path().to_str().expect("utf8 path");
+        let cli =
+            Cli::try_parse_from(["kopy", src, overlay, dest, "--streaming"]).expect("parse cli");
+        match Config::try_from(cli) {
+            Err(super::super::types::KopyError::Config(msg)) => {
+                assert!(msg.contains("--streaming needs a single source directory"))
+            }
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_trash_subcommands() {
+        let cli = Cli::try_parse_from(["kopy", "trash", "list", "dst"]).expect("parse cli");
+        assert!(matches!(
+            cli.command,
+            Some(Command::Trash(TrashArgs {
+                command: TrashCommand::List { .. }
+            }))
+        ));
+
+        let cli = Cli::try_parse_from([
+            "kopy",
+            "trash",
+            "restore",
+            "dst",
+            "2026-01-01_120000",
+            "--overwrite",
+        ])
+        .expect("parse cli");
+        match cli.command {
+            Some(Command::Trash(TrashArgs {
+                command:
+                    TrashCommand::Restore {
+                        snapshot,
+                        overwrite,
+                        ..
+                    },
+            })) => {
+                assert_eq!(snapshot, "2026-01-01_120000");
+                assert!(overwrite);
+            }
+            other => panic!("Expected trash restore, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_conversion_rejects_trash_command() {
+        let cli = Cli::try_parse_from(["kopy", "trash", "list", "dst"]).expect("parse cli");
+        assert!(Config::try_from(cli).is_err());
+    }
+
+    #[test]
+    fn test_from_job_cli_flags_override_profile_values() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let job = JobProfile {
+            name: "nightly".to_string(),
+            source: src_dir.path().to_path_buf(),
+            overlays: vec![],
+            extra_destinations: vec![],
+            destination: dest_dir.path().to_path_buf(),
+            delete: DeleteMode::Trash,
+            conflict: ConflictMode::Skip,
+            order: PlanOrder::Path,
+            max_delete: None,
+            allow_empty_source: false,
+            protect: vec![],
+            checksum: false,
+            size_only: false,
+            ignore_times: false,
+            modify_window: None,
+            existing: false,
+            ignore_existing: false,
+            update: false,
+            exclude: vec!["*.log".to_string()],
+            include: vec![],
+            presets: vec![],
+            filter: vec![],
+            exclude_from: vec![],
+            include_from: vec![],
+            threads: Some(2),
+            scan_mode: ScanMode::Sequential,
+            streaming: false,
+            ignore: IgnoreSettings::default(),
+            files_from: None,
+            min_size: None,
+            max_size: None,
+            newer_than: None,
+            older_than: None,
+            exclude_caches: false,
+            exclude_if_present: vec![],
+            exclude_nodump: false,
+            one_file_system: false,
+            max_depth: None,
+        };
+        let overrides = SyncOptions {
+            delete_permanent: true,
+            conflict: Some(ConflictMode::KeepBoth),
+            filters: FilterOptions {
+                exclude: vec!["*.tmp".to_string()],
+                ..FilterOptions::default()
+            },
+            threads: Some(8),
+            ..SyncOptions::default()
+        };
+
+        let config = Config::from_job(&job, &overrides).expect("merge job");
+
+        assert_eq!(config.delete_mode, DeleteMode::Permanent);
+        assert_eq!(config.conflict_mode, ConflictMode::KeepBoth);
+        assert_eq!(config.exclude_patterns, vec!["*.tmp"]);
+        assert_eq!(config.threads, 8);
+        assert_eq!(config.scan_mode, ScanMode::Sequential);
+        assert!(!config.checksum_mode);
+    }
+
+    #[test]
+    fn test_from_job_cli_flags_override_profile_booleans_both_ways() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let job = JobProfile {
+            name: "nightly".to_string(),
+            source: src_dir.path().to_path_buf(),
+            overlays: vec![],
+            extra_destinations: vec![],
+            destination: dest_dir.path().to_path_buf(),
+            delete: DeleteMode::Trash,
+            conflict: ConflictMode::Skip,
+            order: PlanOrder::Path,
+            max_delete: None,
+            allow_empty_source: true,
+            protect: vec![],
+            checksum: true,
+            size_only: false,
+            ignore_times: false,
+            modify_window: None,
+            existing: false,
+            ignore_existing: false,
+            update: true,
+            exclude: vec![],
+            include: vec![],
+            presets: vec![],
+            filter: vec![],
+            exclude_from: vec![],
+            include_from: vec![],
+            threads: None,
+            scan_mode: ScanMode::Auto,
+            streaming: true,
+            ignore: IgnoreSettings::default(),
+            files_from: None,
+            min_size: None,
+            max_size: None,
+            newer_than: None,
+            older_than: None,
+            exclude_caches: true,
+            exclude_if_present: vec![],
+            exclude_nodump: false,
+            one_file_system: true,
+            max_depth: None,
+        };
+        let run_options = |flags: &[&str]| {
+            let args = ["kopy", "run", "nightly"].iter().chain(flags);
+            match Cli::try_parse_from(args).expect("parse cli").command {
+                Some(Command::Run(args)) => args.options,
+                other => panic!("Expected run subcommand, got {:?}", other),
+            }
+        };
+
+        let off = run_options(&[
+            "--no-checksum",
+            "--no-delete",
+            "--no-update",
+            "--no-allow-empty-source",
+            "--no-exclude-caches",
+            "--no-one-file-system",
+            "--no-streaming",
+        ]);
+        let config = Config::from_job(&job, &off).expect("merge job");
+        assert!(!config.checksum_mode);
+        assert_eq!(config.delete_mode, DeleteMode::None);
+        assert!(!config.update);
+        assert!(!config.allow_empty_source);
+        assert!(!config.exclude_caches);
+        assert!(!config.one_file_system);
+        assert!(!config.streaming);
+
+        let job = JobProfile {
+            delete: DeleteMode::None,
+            allow_empty_source: false,
+            checksum: false,
+            update: false,
+            streaming: false,
+            exclude_caches: false,
+            one_file_system: false,
+            ..job
+        };
+        let on = run_options(&[
+            "--checksum",
+            "--delete",
+            "--update",
+            "--allow-empty-source",
+            "--exclude-caches",
+            "--one-file-system",
+            "--streaming",
+        ]);
+        let config = Config::from_job(&job, &on).expect("merge job");
+        assert!(config.checksum_mode);
+        assert_eq!(config.delete_mode, DeleteMode::Trash);
+        assert!(config.update);
+        assert!(config.allow_empty_source);
+        assert!(config.exclude_caches);
+        assert!(config.one_file_system);
+        assert!(config.streaming);
+
+        assert!(
+            Cli::try_parse_from(["kopy", "run", "nightly", "--delete", "--no-delete"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_from_job_runs_validation() {
+        let src_dir = create_temp_dir();
+
+        let job = JobProfile {
+            name: "broken".to_string(),
+            source: src_dir.path().to_path_buf(),
+            overlays: vec![],
+            extra_destinations: vec![],
+            destination: src_dir.path().join("nested"),
+            delete: DeleteMode::None,
+            conflict: ConflictMode::Skip,
+            order: PlanOrder::Path,
+            max_delete: None,
+            allow_empty_source: false,
+            protect: vec![],
+            checksum: false,
+            size_only: false,
+            ignore_times: false,
+            modify_window: None,
+            existing: false,
+            ignore_existing: false,
+            update: false,
+            exclude: vec![],
+            include: vec![],
+            presets: vec![],
+            filter: vec![],
+            exclude_from: vec![],
+            include_from: vec![],
+            threads: None,
+            scan_mode: ScanMode::Auto,
+            streaming: false,
+            ignore: IgnoreSettings::default(),
+            files_from: None,
+            min_size: None,
+            max_size: None,
+            newer_than: None,
+            older_than: None,
+            exclude_caches: false,
+            exclude_if_present: vec![],
+            exclude_nodump: false,
+            one_file_system: false,
+            max_depth: None,
+        };
+
+        let result = Config::from_job(&job, &SyncOptions::default());
+        if let Err(super::super::types::KopyError::Config(msg)) = result {
+            assert!(msg.contains("cannot be nested"));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+
+    #[test]
+    fn test_validation_rejects_zero_threads() {
+        let src_dir = create_temp_dir();
+        let dest_dir = create_temp_dir();
+
+        let config = Config {
+            source: src_dir.path().to_path_buf(),
+            destination: dest_dir.path().to_path_buf(),
+            threads: 0,
+            ..Default::default()
+        };
+
+        if let Err(super::super::types::KopyError::Config(msg)) = config.validate() {
+            assert!(msg.contains("at least 1"));
+        } else {
+            panic!("Expected Config error");
+        }
     }
 
     #[cfg(unix)]
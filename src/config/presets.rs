@@ -0,0 +1,169 @@
+//! Built-in exclude sets for common ecosystems (`--preset`)
+//!
+//! Preset patterns are plain exclude globs, checked together with
+//! `--exclude` patterns, so `--include` overrides them the same way.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// A curated exclude set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Cargo build output
+    Rust,
+    /// npm/yarn dependencies and bundler caches
+    Node,
+    /// Bytecode, virtualenvs and tool caches
+    Python,
+    /// Gradle/Maven build output and class files
+    Jvm,
+    /// Editor swap, backup and project files
+    Editor,
+    /// Operating system metadata files
+    OsJunk,
+}
+
+impl Preset {
+    /// Every preset, in listing order.
+    pub const ALL: [Preset; 6] = [
+        Preset::Rust,
+        Preset::Node,
+        Preset::Python,
+        Preset::Jvm,
+        Preset::Editor,
+        Preset::OsJunk,
+    ];
+
+    /// Name used on the command line and in `kopy.toml`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Rust => "rust",
+            Preset::Node => "node",
+            Preset::Python => "python",
+            Preset::Jvm => "jvm",
+            Preset::Editor => "editor",
+            Preset::OsJunk => "os-junk",
+        }
+    }
+
+    /// One-line summary for `kopy presets list`.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::Rust => "Cargo build output",
+            Preset::Node => "npm/yarn dependencies and bundler caches",
+            Preset::Python => "bytecode, virtualenvs and tool caches",
+            Preset::Jvm => "Gradle/Maven build output and class files",
+            Preset::Editor => "editor swap, backup and project files",
+            Preset::OsJunk => "macOS, Windows and desktop metadata files",
+        }
+    }
+
+    /// Exclude globs, matched against paths relative to the scan root.
+    pub fn patterns(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Rust => &["**/target/**", "**/*.rs.bk"],
+            Preset::Node => &[
+                "**/node_modules/**",
+                "**/.npm/**",
+                "**/.yarn/cache/**",
+                "**/.pnpm-store/**",
+                "**/.next/**",
+                "**/.parcel-cache/**",
+                "**/npm-debug.log*",
+                "**/yarn-error.log",
+            ],
+            Preset::Python => &[
+                "**/__pycache__/**",
+                "**/*.py[co]",
+                "**/.venv/**",
+                "**/.tox/**",
+                "**/.mypy_cache/**",
+                "**/.pytest_cache/**",
+                "**/.ruff_cache/**",
+                "**/*.egg-info/**",
+            ],
+            Preset::Jvm => &[
+                "**/*.class",
+                "**/.gradle/**",
+                "**/build/**",
+                "**/target/**",
+                "**/hs_err_pid*.log",
+            ],
+            Preset::Editor => &[
+                "**/*.swp",
+                "**/*.swo",
+                "**/*~",
+                "**/.#*",
+                "**/.idea/**",
+                "**/*.sublime-workspace",
+            ],
+            Preset::OsJunk => &[
+                "**/.DS_Store",
+                "**/._*",
+                "**/.Spotlight-V100/**",
+                "**/.Trashes/**",
+                "**/Thumbs.db",
+                "**/ehthumbs.db",
+                "**/desktop.ini",
+                "**/.directory",
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn excludes(preset: Preset, path: &str) -> bool {
+        preset.patterns().iter().any(|pattern| {
+            glob::Pattern::new(pattern)
+                .expect("valid preset pattern")
+                .matches_path(Path::new(path))
+        })
+    }
+
+    #[test]
+    fn test_all_preset_patterns_compile() {
+        for preset in Preset::ALL {
+            for pattern in preset.patterns() {
+                assert!(
+                    glob::Pattern::new(pattern).is_ok(),
+                    "{} pattern {} must compile",
+                    preset.name(),
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_preset_names_match_cli_values() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_str(preset.name(), false), Ok(preset));
+        }
+    }
+
+    #[test]
+    fn test_presets_match_at_any_depth() {
+        assert!(excludes(Preset::Rust, "target/debug/kopy"));
+        assert!(excludes(
+            Preset::Rust,
+            "crates/core/target/release/lib.rlib"
+        ));
+        assert!(!excludes(Preset::Rust, "src/target.rs"));
+        assert!(excludes(Preset::Node, "web/node_modules/react/index.js"));
+        assert!(excludes(
+            Preset::Python,
+            "pkg/__pycache__/mod.cpython-312.pyc"
+        ));
+        assert!(excludes(Preset::Python, "pkg/mod.pyc"));
+        assert!(!excludes(Preset::Python, "pkg/mod.py"));
+        assert!(excludes(Preset::Editor, "notes/.todo.md.swp"));
+        assert!(excludes(Preset::OsJunk, ".DS_Store"));
+        assert!(excludes(Preset::OsJunk, "photos/2024/.DS_Store"));
+        assert!(!excludes(Preset::OsJunk, "photos/2024/img.DS_Store.jpg"));
+    }
+}
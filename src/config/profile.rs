@@ -0,0 +1,553 @@
+//! Named sync jobs loaded from `kopy.toml`
+//!
+//! A profile file declares any number of `[[job]]` tables:
+//!
+//! ```toml
+//! [[job]]
+//! name = "photos"
+//! source = "~/Pictures"
+//! overlays = []          # directories merged over source, later ones win
+//! destination = "/mnt/backup/pictures"
+//! extra_destinations = ["/mnt/offsite/pictures"]   # synced from the same scan
+//! delete = "trash"        # none | trash | permanent
+//! max_delete = "5%"       # refuse plans deleting more (count or percentage)
+//! protect = ["/config.local", ".well-known/"]   # never deleted or overwritten
+//! order = "path"          # path | deletes-first | largest-first | smallest-first | locality
+//! checksum = false
+//! exclude = ["*.tmp", "**/.cache/**"]
+//! include = []
+//! presets = ["os-junk", "editor"]   # see `kopy presets list`
+//! filter = ["+ *.jpg", "- *"]
+//! files_from = "manifest.txt"
+//! max_size = "4G"          # also min_size; units K, M, G, T
+//! exclude_caches = true  # skip directories with a valid CACHEDIR.TAG
+//! exclude_if_present = [".nobackup"]
+//! exclude_nodump = false  # skip `chattr +d` files
+//! one_file_system = true  # stay on the source's filesystem
+//! max_depth = 4
+//! newer_than = "7d"        # also older_than; age (90m, 12h, 7d, 2w) or YYYY-MM-DD
+//! exclude_from = ["excludes.txt"]
+//! threads = 8
+//! scan_mode = "auto"      # auto | sequential | parallel
+//! streaming = false       # plan from sorted scans in bounded memory
+//!
+//! [job.ignore]            # optional, defaults shown
+//! gitignore = true
+//! git_global = true
+//! git_exclude = true
+//! dot_ignore = true
+//! kopyignore = true
+//! extra_files = []
+//! skip_hidden = false
+//! apply_to_destination = true
+//! ```
+//!
+//! Relative paths are resolved against the directory containing the file.
+
+use super::{Config, ConflictMode, IgnoreSettings, PlanOrder, Preset, ScanMode};
+use crate::diff::DeleteLimit;
+use crate::filter::{ByteSize, TimeBound};
+use crate::types::{DeleteMode, KopyError};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// File name searched for by [`locate_profile_file`].
+pub const PROFILE_FILE_NAME: &str = "kopy.toml";
+
+/// Parsed contents of a `kopy.toml` file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileFile {
+    /// Declared jobs, in file order.
+    #[serde(default)]
+    pub job: Vec<JobProfile>,
+}
+
+/// One `[[job]]` entry.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobProfile {
+    /// Name used with `kopy run <name>`.
+    pub name: String,
+
+    /// Source directory or file.
+    pub source: PathBuf,
+
+    /// Directories layered over `source`; later ones win on collisions.
+    #[serde(default)]
+    pub overlays: Vec<PathBuf>,
+
+    /// Destination directory or file.
+    pub destination: PathBuf,
+
+    /// Further destinations synced from the same source scan.
+    #[serde(default)]
+    pub extra_destinations: Vec<PathBuf>,
+
+    /// How to handle destination-only files.
+    #[serde(default)]
+    pub delete: DeleteMode,
+
+    /// Refuse plans deleting more than this many paths (`100`) or share of the destination (`5%`).
+    #[serde(default)]
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Allow deletes even when the source is empty and the destination is not.
+    #[serde(default)]
+    pub allow_empty_source: bool,
+
+    /// Destination paths never deleted or overwritten.
+    #[serde(default)]
+    pub protect: Vec<String>,
+
+    /// What to do with destination files newer than their source.
+    #[serde(default)]
+    pub conflict: ConflictMode,
+
+    /// Order in which planned actions run.
+    #[serde(default)]
+    pub order: PlanOrder,
+
+    /// Compare file contents with Blake3 instead of size/mtime only.
+    #[serde(default)]
+    pub checksum: bool,
+
+    /// Compare by size only, ignoring mtimes.
+    #[serde(default)]
+    pub size_only: bool,
+
+    /// Transfer every file, even when size and mtime match.
+    #[serde(default)]
+    pub ignore_times: bool,
+
+    /// Treat mtimes at most this many seconds apart as equal; when unset,
+    /// the destination's mtime resolution is used.
+    #[serde(default)]
+    pub modify_window: Option<u64>,
+
+    /// Only update files that already exist in the destination.
+    #[serde(default)]
+    pub existing: bool,
+
+    /// Only create new files; never overwrite destination files.
+    #[serde(default)]
+    pub ignore_existing: bool,
+
+    /// Never replace a destination file that is newer than its source.
+    #[serde(default)]
+    pub update: bool,
+
+    /// Exclude patterns (globs).
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Include patterns (override excludes).
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Built-in exclude sets (`rust`, `node`, `python`, `jvm`, `editor`, `os-junk`).
+    #[serde(default)]
+    pub presets: Vec<Preset>,
+
+    /// Ordered filter rules (`+ PAT`, `- PAT`, `: NAME`, `. FILE`).
+    #[serde(default)]
+    pub filter: Vec<String>,
+
+    /// Files listing exclude patterns.
+    #[serde(default)]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Files listing include patterns.
+    #[serde(default)]
+    pub include_from: Vec<PathBuf>,
+
+    /// Worker thread count (defaults to the CLI default).
+    #[serde(default)]
+    pub threads: Option<usize>,
+
+    /// Directory scan mode.
+    #[serde(default)]
+    pub scan_mode: ScanMode,
+
+    /// Plan from sorted scans in bounded memory.
+    #[serde(default)]
+    pub streaming: bool,
+
+    /// Ignore-file sources and hidden-file handling.
+    #[serde(default)]
+    pub ignore: IgnoreSettings,
+
+    /// Only sync the paths listed in this file.
+    #[serde(default)]
+    pub files_from: Option<PathBuf>,
+
+    /// Skip source files smaller than this (e.g. `"10K"`).
+    #[serde(default)]
+    pub min_size: Option<ByteSize>,
+
+    /// Skip source files larger than this (e.g. `"4G"`).
+    #[serde(default)]
+    pub max_size: Option<ByteSize>,
+
+    /// Only sync source files modified within this age or after this date.
+    #[serde(default)]
+    pub newer_than: Option<TimeBound>,
+
+    /// Only sync source files modified before this age or date.
+    #[serde(default)]
+    pub older_than: Option<TimeBound>,
+
+    /// Skip directories tagged with a valid `CACHEDIR.TAG`.
+    #[serde(default)]
+    pub exclude_caches: bool,
+
+    /// Skip directories containing an entry with one of these names.
+    #[serde(default)]
+    pub exclude_if_present: Vec<String>,
+
+    /// Skip files and directories with the `nodump` attribute.
+    #[serde(default)]
+    pub exclude_nodump: bool,
+
+    /// Do not cross filesystem boundaries.
+    #[serde(default)]
+    pub one_file_system: bool,
+
+    /// Maximum directory depth below the source and destination roots.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+impl JobProfile {
+    /// Convert this job into an unvalidated [`Config`].
+    ///
+    /// Use [`Config::from_job`] to apply CLI overrides and validate.
+    pub fn to_config(&self) -> Config {
+        let defaults = Config::default();
+        Config {
+            source: self.source.clone(),
+            overlay_sources: self.overlays.clone(),
+            destination: self.destination.clone(),
+            extra_destinations: self.extra_destinations.clone(),
+            checksum_mode: self.checksum,
+            size_only: self.size_only,
+            ignore_times: self.ignore_times,
+            modify_window: std::time::Duration::from_secs(self.modify_window.unwrap_or(0)),
+            widen_modify_window: self.modify_window.is_none(),
+            existing: self.existing,
+            ignore_existing: self.ignore_existing,
+            update: self.update,
+            delete_mode: self.delete,
+            conflict_mode: self.conflict,
+            max_delete: self.max_delete,
+            allow_empty_source: self.allow_empty_source,
+            protect_patterns: self.protect.clone(),
+            plan_order: self.order,
+            exclude_patterns: self.exclude.clone(),
+            include_patterns: self.include.clone(),
+            presets: self.presets.clone(),
+            filter_rules: self.filter.clone(),
+            exclude_from: self.exclude_from.clone(),
+            include_from: self.include_from.clone(),
+            threads: self.threads.unwrap_or(defaults.threads),
+            scan_mode: self.scan_mode,
+            streaming: self.streaming,
+            ignore: self.ignore.clone(),
+            files_from: self.files_from.clone(),
+            min_size: self.min_size.map(|size| size.0),
+            max_size: self.max_size.map(|size| size.0),
+            newer_than: self.newer_than,
+            older_than: self.older_than,
+            exclude_caches: self.exclude_caches,
+            exclude_if_present: self.exclude_if_present.clone(),
+            exclude_nodump: self.exclude_nodump,
+            one_file_system: self.one_file_system,
+            max_depth: self.max_depth,
+            ..defaults
+        }
+    }
+
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        self.source = resolve_path(&self.source, base_dir);
+        for overlay in &mut self.overlays {
+            *overlay = resolve_path(overlay, base_dir);
+        }
+        self.destination = resolve_path(&self.destination, base_dir);
+        for destination in &mut self.extra_destinations {
+            *destination = resolve_path(destination, base_dir);
+        }
+        for path in self
+            .exclude_from
+            .iter_mut()
+            .chain(self.include_from.iter_mut())
+        {
+            *path = resolve_path(path, base_dir);
+        }
+        if let Some(path) = self
+            .files_from
+            .as_mut()
+            .filter(|path| *path != Path::new("-"))
+        {
+            *path = resolve_path(path, base_dir);
+        }
+    }
+}
+
+impl ProfileFile {
+    /// Load and parse a profile file from disk.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Io` if the file cannot be read and `KopyError::Config`
+    /// if it is not valid TOML or violates the job schema.
+    pub fn load(path: &Path) -> Result<Self, KopyError> {
+        let contents = std::fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        Self::parse(&contents, base_dir)
+            .map_err(|e| KopyError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parse profile contents, resolving relative paths against `base_dir`.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for malformed TOML, unknown keys or
+    /// duplicate job names.
+    pub fn parse(contents: &str, base_dir: &Path) -> Result<Self, KopyError> {
+        let mut file: ProfileFile =
+            toml::from_str(contents).map_err(|e| KopyError::Config(e.to_string()))?;
+
+        let mut seen = HashSet::new();
+        for job in &mut file.job {
+            if !seen.insert(job.name.clone()) {
+                return Err(KopyError::Config(format!(
+                    "Duplicate job name '{}'",
+                    job.name
+                )));
+            }
+            job.resolve_paths(base_dir);
+        }
+
+        Ok(file)
+    }
+
+    /// Look up a job by name.
+    pub fn job(&self, name: &str) -> Option<&JobProfile> {
+        self.job.iter().find(|job| job.name == name)
+    }
+
+    /// Names of all declared jobs, in file order.
+    pub fn job_names(&self) -> Vec<&str> {
+        self.job.iter().map(|job| job.name.as_str()).collect()
+    }
+}
+
+/// Candidate profile locations, in lookup order.
+///
+/// The current directory wins over `$XDG_CONFIG_HOME/kopy/` (falling back to
+/// `~/.config/kopy/` when `XDG_CONFIG_HOME` is unset).
+pub fn profile_search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Ok(cwd) = std::env::current_dir() {
+        paths.push(cwd.join(PROFILE_FILE_NAME));
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("kopy").join(PROFILE_FILE_NAME));
+    }
+
+    paths
+}
+
+/// Return the first existing profile file from [`profile_search_paths`].
+pub fn locate_profile_file() -> Option<PathBuf> {
+    profile_search_paths()
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Expand a leading `~/` and anchor relative paths at `base_dir`.
+fn resolve_path(path: &Path, base_dir: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = home_dir() {
+            return home.join(rest);
+        }
+    }
+
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[[job]]
+name = "photos"
+source = "/data/photos"
+destination = "backup/photos"
+delete = "trash"
+max_delete = "5%"
+protect = ["uploads/"]
+conflict = "newer-wins"
+checksum = true
+modify_window = 2
+exclude = ["*.tmp"]
+presets = ["os-junk"]
+exclude_from = ["photos.exclude"]
+files_from = "photos.list"
+max_size = "4G"
+newer_than = "7d"
+threads = 8
+scan_mode = "parallel"
+
+
+[job.ignore]
+gitignore = false
+extra_files = [".backupignore"]
+
+[[job]]
+name = "docs"
+source = "docs"
+destination = "/mnt/docs"
+"#;
+
+    #[test]
+    fn test_parse_jobs_with_defaults() {
+        let file = ProfileFile::parse(SAMPLE, Path::new("/etc/kopy")).expect("parse profile");
+        assert_eq!(file.job_names(), vec!["photos", "docs"]);
+
+        let photos = file.job("photos").expect("photos job");
+        assert_eq!(photos.delete, DeleteMode::Trash);
+        assert_eq!(photos.conflict, ConflictMode::NewerWins);
+        assert!(photos.checksum);
+        assert_eq!(photos.modify_window, Some(2));
+        assert_eq!(photos.exclude, vec!["*.tmp"]);
+        assert_eq!(photos.presets, vec![Preset::OsJunk]);
+        assert_eq!(photos.threads, Some(8));
+        assert_eq!(photos.max_size, Some(ByteSize(4 << 30)));
+        assert_eq!(
+            photos.newer_than,
+            Some(TimeBound::Age(std::time::Duration::from_secs(7 * 86_400)))
+        );
+        assert_eq!(photos.scan_mode, ScanMode::Parallel);
+        assert!(!photos.ignore.gitignore);
+        assert!(photos.ignore.git_exclude);
+        assert_eq!(photos.ignore.extra_files, vec![".backupignore"]);
+
+        let docs = file.job("docs").expect("docs job");
+        assert_eq!(docs.delete, DeleteMode::None);
+        assert_eq!(docs.conflict, ConflictMode::Skip);
+        assert!(!docs.checksum);
+        assert_eq!(docs.threads, None);
+        assert_eq!(docs.scan_mode, ScanMode::Auto);
+        assert_eq!(docs.ignore, IgnoreSettings::default());
+    }
+
+    #[test]
+    fn test_parse_resolves_relative_paths_against_file_dir() {
+        let file = ProfileFile::parse(SAMPLE, Path::new("/etc/kopy")).expect("parse profile");
+
+        let photos = file.job("photos").expect("photos job");
+        assert_eq!(photos.source, PathBuf::from("/data/photos"));
+        assert_eq!(photos.destination, PathBuf::from("/etc/kopy/backup/photos"));
+
+        assert_eq!(
+            photos.exclude_from,
+            vec![PathBuf::from("/etc/kopy/photos.exclude")]
+        );
+        assert_eq!(
+            photos.files_from,
+            Some(PathBuf::from("/etc/kopy/photos.list"))
+        );
+
+        let docs = file.job("docs").expect("docs job");
+        assert_eq!(docs.source, PathBuf::from("/etc/kopy/docs"));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        let contents = r#"
+[[job]]
+name = "typo"
+source = "a"
+destination = "b"
+chekcsum = true
+"#;
+        let result = ProfileFile::parse(contents, Path::new("."));
+        if let Err(KopyError::Config(msg)) = result {
+            assert!(msg.contains("chekcsum"));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_duplicate_job_names() {
+        let contents = r#"
+[[job]]
+name = "same"
+source = "a"
+destination = "b"
+
+[[job]]
+name = "same"
+source = "c"
+destination = "d"
+"#;
+        let result = ProfileFile::parse(contents, Path::new("."));
+        if let Err(KopyError::Config(msg)) = result {
+            assert!(msg.contains("Duplicate job name"));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+
+    #[test]
+    fn test_job_to_config_maps_fields() {
+        let file = ProfileFile::parse(SAMPLE, Path::new("/etc/kopy")).expect("parse profile");
+        let config = file.job("photos").expect("photos job").to_config();
+
+        assert_eq!(config.source, PathBuf::from("/data/photos"));
+        assert_eq!(config.delete_mode, DeleteMode::Trash);
+        assert_eq!(config.max_delete, Some(DeleteLimit::Percent(5.0)));
+        assert_eq!(config.protect_patterns, vec!["uploads/".to_string()]);
+        assert!(config.checksum_mode);
+        assert_eq!(config.threads, 8);
+        assert_eq!(config.scan_mode, ScanMode::Parallel);
+        assert_eq!(config.modify_window, std::time::Duration::from_secs(2));
+        assert!(!config.widen_modify_window);
+
+        let docs = file.job("docs").expect("docs job").to_config();
+        assert_eq!(docs.threads, Config::default().threads);
+        assert!(docs.widen_modify_window);
+    }
+
+    #[test]
+    fn test_load_reports_file_path_on_error() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join(PROFILE_FILE_NAME);
+        std::fs::write(&path, "[[job]]\nname = 3\n").expect("write profile");
+
+        let result = ProfileFile::load(&path);
+        if let Err(KopyError::Config(msg)) = result {
+            assert!(msg.contains(PROFILE_FILE_NAME));
+        } else {
+            panic!("Expected Config error");
+        }
+    }
+}
@@ -1,16 +1,22 @@
 //! File comparison logic
 
 use crate::hash::compute_hash;
-use crate::types::{FileEntry, SyncAction};
+use crate::types::{FileEntry, SyncAction, UpdateReason};
 use crate::Config;
+use std::time::{Duration, SystemTime};
 
 /// Compare two files and determine what action is needed
 ///
 /// The decision order is:
 /// 1. File kind differences (symlink vs regular, or symlink target mismatch)
-/// 2. Size differences
-/// 3. Optional content hash comparison (`--checksum`)
-/// 4. Metadata fallback (mtime)
+/// 2. `--ignore-times`: every regular file is transferred
+/// 3. Size differences (`--size-only` stops here)
+/// 4. Optional content hash comparison (`--checksum`)
+/// 5. Metadata fallback (mtime, within `config.modify_window`)
+///
+/// When content is known equal (same hash, or same size and mtime) but the
+/// permission bits or, in checksum mode, the mtime differ, the result is
+/// `UpdateMetadata` instead of a re-copy or a silent skip.
 ///
 /// # Arguments
 /// * `src` - Source file entry
@@ -46,58 +52,112 @@ use crate::Config;
 /// ```
 pub fn compare_files(src: &FileEntry, dest: &FileEntry, config: &Config) -> SyncAction {
     if src.is_symlink != dest.is_symlink {
-        return SyncAction::Overwrite(src.clone());
+        return SyncAction::Overwrite(src.clone(), UpdateReason::TypeChanged);
     }
 
     if src.is_symlink && dest.is_symlink {
         return if src.symlink_target == dest.symlink_target {
             SyncAction::Skip
         } else {
-            SyncAction::Overwrite(src.clone())
+            SyncAction::Overwrite(src.clone(), UpdateReason::SymlinkTargetChanged)
         };
     }
 
-    if src.size != dest.size {
-        return SyncAction::Overwrite(src.clone());
+    if config.ignore_times {
+        return SyncAction::Overwrite(src.clone(), UpdateReason::IgnoreTimes);
     }
 
-    if config.checksum_mode {
-        let (src_path, dest_path) = resolve_compare_paths(src, dest, config);
-
-        let src_hash = match src.hash {
-            Some(hash) => hash,
-            None => match compute_hash(&src_path) {
-                Ok(hash) => hash,
-                Err(_) => {
-                    return SyncAction::Overwrite(src.clone());
-                }
-            },
+    if src.size != dest.size {
+        let reason = UpdateReason::SizeChanged {
+            source: src.size,
+            destination: dest.size,
         };
+        return SyncAction::Overwrite(src.clone(), reason);
+    }
+
+    if config.size_only {
+        return SyncAction::Skip;
+    }
 
-        let dest_hash = match dest.hash {
-            Some(hash) => hash,
-            None => match compute_hash(&dest_path) {
-                Ok(hash) => hash,
-                Err(_) => {
-                    return SyncAction::Overwrite(src.clone());
-                }
-            },
+    if config.checksum_mode {
+        let Some((src_hash, dest_hash)) = content_hashes(src, dest, config) else {
+            return SyncAction::Overwrite(src.clone(), UpdateReason::HashFailed);
         };
 
         if src_hash != dest_hash {
-            return SyncAction::Overwrite(src.clone());
-        } else {
-            return SyncAction::Skip;
+            return SyncAction::Overwrite(src.clone(), UpdateReason::HashMismatch);
         }
+        let metadata_differs = permissions_differ(src, dest)
+            || !mtimes_match(src.mtime, dest.mtime, config.modify_window);
+        return if metadata_differs {
+            SyncAction::UpdateMetadata(src.clone())
+        } else {
+            SyncAction::Skip
+        };
     }
 
+    if mtimes_match(src.mtime, dest.mtime, config.modify_window) {
+        return if permissions_differ(src, dest) {
+            SyncAction::UpdateMetadata(src.clone())
+        } else {
+            SyncAction::Skip
+        };
+    }
     match src.mtime.cmp(&dest.mtime) {
-        std::cmp::Ordering::Greater => SyncAction::Overwrite(src.clone()),
+        std::cmp::Ordering::Greater => {
+            SyncAction::Overwrite(src.clone(), UpdateReason::SourceNewer)
+        }
         std::cmp::Ordering::Less => SyncAction::Skip,
         std::cmp::Ordering::Equal => SyncAction::Skip,
     }
 }
 
+/// Whether the permission bits (not the file type) of two entries differ.
+fn permissions_differ(src: &FileEntry, dest: &FileEntry) -> bool {
+    src.permissions & 0o7777 != dest.permissions & 0o7777
+}
+
+/// Whether two mtimes are at most `window` apart.
+pub(super) fn mtimes_match(a: SystemTime, b: SystemTime, window: Duration) -> bool {
+    let difference = a.duration_since(b).or_else(|_| b.duration_since(a));
+    difference.is_ok_and(|difference| difference <= window)
+}
+
+/// Whether `dest` looks edited after `src`: both regular files, and the
+/// destination mtime is newer by more than `config.modify_window`.
+///
+/// The size does not matter: an edit usually changes it, and
+/// [`compare_files`] would then overwrite the edit as a size change. In
+/// checksum mode the content must differ as well (a file whose hash cannot
+/// be computed counts as different). Size-only and ignore-times modes do not
+/// look at mtimes, so they never report one.
+pub(super) fn is_destination_newer(src: &FileEntry, dest: &FileEntry, config: &Config) -> bool {
+    let newer = !config.size_only
+        && !config.ignore_times
+        && !src.is_symlink
+        && !dest.is_symlink
+        && dest.mtime > src.mtime
+        && !mtimes_match(src.mtime, dest.mtime, config.modify_window);
+    if !newer || !config.checksum_mode || src.size != dest.size {
+        return newer;
+    }
+    content_hashes(src, dest, config).is_none_or(|(src_hash, dest_hash)| src_hash != dest_hash)
+}
+
+/// Blake3 hashes of `src` and `dest`, reusing hashes already in the entries.
+///
+/// `None` when either file cannot be read.
+fn content_hashes(
+    src: &FileEntry,
+    dest: &FileEntry,
+    config: &Config,
+) -> Option<([u8; 32], [u8; 32])> {
+    let (src_path, dest_path) = resolve_compare_paths(src, dest, config);
+    let src_hash = src.hash.or_else(|| compute_hash(&src_path).ok())?;
+    let dest_hash = dest.hash.or_else(|| compute_hash(&dest_path).ok())?;
+    Some((src_hash, dest_hash))
+}
+
 fn resolve_compare_paths(
     src: &FileEntry,
     dest: &FileEntry,
@@ -108,7 +168,7 @@ fn resolve_compare_paths(
     }
 
     (
-        config.source.join(&src.path),
+        src.source_path(&config.source),
         config.destination.join(&dest.path),
     )
 }
@@ -0,0 +1,180 @@
+//! Destination-newer conflicts
+//!
+//! A destination file with a newer mtime than its source was most likely
+//! edited in place. Comparing size and mtime alone would skip it, or
+//! overwrite the edit when the size changed, so the plan records it as a
+//! [`PlanConflict`] and applies the configured [`ConflictMode`].
+
+use crate::config::ConflictMode;
+use crate::types::{FileEntry, SyncAction, UpdateReason};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A destination file that looks newer than its source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanConflict {
+    /// Relative path of the file
+    pub path: PathBuf,
+
+    /// Modification time of the source version
+    pub source_mtime: SystemTime,
+
+    /// Modification time of the (newer) destination version
+    pub destination_mtime: SystemTime,
+
+    /// What the plan does about it
+    pub outcome: ConflictOutcome,
+}
+
+/// How a conflict is settled by the plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictOutcome {
+    /// The destination version is left as is
+    KeepDestination,
+
+    /// The source version overwrites the destination
+    SourceWins,
+
+    /// The destination version is renamed to this path, then the source is copied
+    KeepBoth(PathBuf),
+
+    /// The sync is refused while this conflict exists
+    Refuse,
+}
+
+impl ConflictOutcome {
+    /// Short description for plan output.
+    pub fn describe(&self) -> String {
+        match self {
+            ConflictOutcome::KeepDestination => "destination kept".to_string(),
+            ConflictOutcome::SourceWins => "source wins".to_string(),
+            ConflictOutcome::KeepBoth(renamed) => {
+                format!("destination kept as {}", renamed.display())
+            }
+            ConflictOutcome::Refuse => "sync refused".to_string(),
+        }
+    }
+}
+
+/// Settle a conflict between `src` and the newer `dest` under `mode`.
+///
+/// Returns the conflict record plus the actions that carry it out, in the
+/// order they must run.
+pub(super) fn settle_conflict(
+    src: &FileEntry,
+    dest: &FileEntry,
+    mode: ConflictMode,
+    timestamp: &str,
+) -> (PlanConflict, Vec<SyncAction>) {
+    let (outcome, actions) = match mode {
+        ConflictMode::Skip | ConflictMode::NewerWins => {
+            (ConflictOutcome::KeepDestination, Vec::new())
+        }
+        ConflictMode::Fail => (ConflictOutcome::Refuse, Vec::new()),
+        ConflictMode::SourceWins => (
+            ConflictOutcome::SourceWins,
+            vec![SyncAction::Overwrite(
+                src.clone(),
+                UpdateReason::ConflictSourceWins,
+            )],
+        ),
+        ConflictMode::KeepBoth => {
+            let renamed = conflict_copy_path(&dest.path, "destination", timestamp);
+            let actions = vec![
+                SyncAction::Move {
+                    from: dest.path.clone(),
+                    to: renamed.clone(),
+                },
+                SyncAction::CopyNew(src.clone()),
+            ];
+            (ConflictOutcome::KeepBoth(renamed), actions)
+        }
+    };
+
+    let conflict = PlanConflict {
+        path: src.path.clone(),
+        source_mtime: src.mtime,
+        destination_mtime: dest.mtime,
+        outcome,
+    };
+    (conflict, actions)
+}
+
+/// Name for a second copy kept beside `path` after a conflict, e.g.
+/// `notes.conflict-destination-20260217-101500.md`.
+pub fn conflict_copy_path(path: &Path, label: &str, timestamp: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{}.conflict-{}-{}", stem, label, timestamp);
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(secs: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from("docs/notes.md"),
+            4,
+            UNIX_EPOCH + Duration::from_secs(secs),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_settle_conflict_per_mode() {
+        let (src, dest) = (entry(100), entry(200));
+
+        let (conflict, actions) = settle_conflict(&src, &dest, ConflictMode::Skip, "t");
+        assert_eq!(conflict.outcome, ConflictOutcome::KeepDestination);
+        assert!(actions.is_empty());
+
+        let (_, actions) = settle_conflict(&src, &dest, ConflictMode::SourceWins, "t");
+        assert_eq!(
+            actions,
+            vec![SyncAction::Overwrite(
+                src.clone(),
+                UpdateReason::ConflictSourceWins
+            )]
+        );
+
+        let (conflict, actions) = settle_conflict(&src, &dest, ConflictMode::KeepBoth, "t");
+        let renamed = PathBuf::from("docs/notes.conflict-destination-t.md");
+        assert_eq!(conflict.outcome, ConflictOutcome::KeepBoth(renamed.clone()));
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Move {
+                    from: PathBuf::from("docs/notes.md"),
+                    to: renamed
+                },
+                SyncAction::CopyNew(src.clone()),
+            ]
+        );
+
+        let (conflict, _) = settle_conflict(&src, &dest, ConflictMode::Fail, "t");
+        assert_eq!(conflict.outcome, ConflictOutcome::Refuse);
+    }
+
+    #[test]
+    fn test_conflict_copy_path_keeps_extension() {
+        assert_eq!(
+            conflict_copy_path(Path::new("docs/notes.md"), "right", "20260217-101500"),
+            PathBuf::from("docs/notes.conflict-right-20260217-101500.md")
+        );
+        assert_eq!(
+            conflict_copy_path(Path::new(".bashrc"), "right", "t"),
+            PathBuf::from(".bashrc.conflict-right-t")
+        );
+    }
+}
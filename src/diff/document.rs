@@ -0,0 +1,529 @@
+//! Saved plans for review-then-apply
+//!
+//! `kopy plan --out plan.json` writes a [`PlanDocument`]: the [`DiffPlan`]
+//! together with the settings it was built with, the totals of both scanned
+//! trees and the destination entries each action expects to find. `kopy
+//! apply plan.json` reloads it and, before executing anything, checks every
+//! planned source and destination entry against the filesystem with
+//! [`PlanDocument::stale_entries`].
+
+use super::conflict::ConflictOutcome;
+use super::engine::DiffPlan;
+use super::guard::{DeleteLimit, TreeTotals};
+use crate::config::ConflictMode;
+use crate::types::{DeleteMode, FileEntry, FileTree, KopyError, SyncAction};
+use crate::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Current plan document format.
+const PLAN_FORMAT: u32 = 1;
+
+/// A sync plan saved for later review and execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanDocument {
+    /// Plan document format version
+    pub format: u32,
+
+    /// kopy version that wrote the plan
+    pub kopy_version: String,
+
+    /// Settings the plan was built with; `kopy apply` executes with these
+    pub settings: PlanSettings,
+
+    /// File counts of the scanned trees, for the delete guard
+    #[serde(default)]
+    pub totals: TreeTotals,
+
+    /// Destination entries the actions expect, by relative path; a touched
+    /// path missing here is expected to be absent
+    pub destination_entries: BTreeMap<PathBuf, FileEntry>,
+
+    /// The planned actions, statistics and conflicts
+    pub plan: DiffPlan,
+}
+
+/// The part of [`Config`] that decides what a saved plan does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanSettings {
+    /// Source directory
+    pub source: PathBuf,
+
+    /// Overlay source directories, in priority order
+    pub overlay_sources: Vec<PathBuf>,
+
+    /// Destination directory
+    pub destination: PathBuf,
+
+    /// How deletes are carried out
+    pub delete_mode: DeleteMode,
+
+    /// Conflict handling the plan was built with
+    pub conflict_mode: ConflictMode,
+
+    /// Whether content was compared by hash
+    pub checksum_mode: bool,
+
+    /// Modify window the plan was built with
+    pub modify_window: Duration,
+
+    /// Worker threads for execution
+    pub threads: usize,
+
+    /// Bandwidth limit (bytes/sec)
+    pub bandwidth_limit: Option<u64>,
+
+    /// Backup directory for snapshots
+    pub backup_dir: Option<PathBuf>,
+
+    /// Most the plan may delete before `kopy apply` refuses it
+    #[serde(default)]
+    pub max_delete: Option<DeleteLimit>,
+
+    /// Whether deleting everything from an empty source is intended
+    #[serde(default)]
+    pub allow_empty_source: bool,
+}
+
+impl PlanSettings {
+    /// Capture the settings of `config`, with roots made absolute so the
+    /// plan can be applied from any working directory.
+    pub fn from_config(config: &Config) -> Self {
+        let absolute = |path: &PathBuf| std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        Self {
+            source: absolute(&config.source),
+            overlay_sources: config.overlay_sources.iter().map(absolute).collect(),
+            destination: absolute(&config.destination),
+            delete_mode: config.delete_mode,
+            conflict_mode: config.conflict_mode,
+            checksum_mode: config.checksum_mode,
+            modify_window: config.modify_window,
+            threads: config.threads,
+            bandwidth_limit: config.bandwidth_limit,
+            backup_dir: config.backup_dir.clone(),
+            max_delete: config.max_delete,
+            allow_empty_source: config.allow_empty_source,
+        }
+    }
+
+    /// A configuration that executes the plan the way it was built.
+    pub fn to_config(&self) -> Config {
+        Config {
+            source: self.source.clone(),
+            overlay_sources: self.overlay_sources.clone(),
+            destination: self.destination.clone(),
+            delete_mode: self.delete_mode,
+            conflict_mode: self.conflict_mode,
+            checksum_mode: self.checksum_mode,
+            modify_window: self.modify_window,
+            threads: self.threads,
+            bandwidth_limit: self.bandwidth_limit,
+            backup_dir: self.backup_dir.clone(),
+            max_delete: self.max_delete,
+            allow_empty_source: self.allow_empty_source,
+            ..Config::default()
+        }
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.source).chain(&self.overlay_sources)
+    }
+}
+
+/// A planned entry that no longer matches the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleEntry {
+    /// Absolute path of the entry
+    pub path: PathBuf,
+
+    /// What changed
+    pub reason: String,
+}
+
+/// What is found at a path when a plan is applied.
+enum Observed {
+    Missing,
+    Directory,
+    Entry(FileEntry),
+}
+
+impl PlanDocument {
+    /// Bundle `plan` with the trees and configuration it was built from.
+    pub fn new(plan: DiffPlan, config: &Config, source: &FileTree, destination: &FileTree) -> Self {
+        let destination_entries = destination_paths(&plan)
+            .into_iter()
+            .filter_map(|path| {
+                destination
+                    .get(&path)
+                    .map(|entry| (path.clone(), entry.clone()))
+            })
+            .collect();
+        Self {
+            format: PLAN_FORMAT,
+            kopy_version: crate::VERSION.to_string(),
+            settings: PlanSettings::from_config(config),
+            totals: TreeTotals::of(source, destination),
+            destination_entries,
+            plan,
+        }
+    }
+
+    /// Load a plan document.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for unreadable, malformed or newer-format
+    /// documents.
+    pub fn load(path: &Path) -> Result<Self, KopyError> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            KopyError::Config(format!("Failed to read plan {}: {}", path.display(), err))
+        })?;
+        let document: Self = serde_json::from_str(&text).map_err(|err| {
+            KopyError::Config(format!("Invalid plan {}: {}", path.display(), err))
+        })?;
+        if document.format != PLAN_FORMAT {
+            return Err(KopyError::Config(format!(
+                "Unsupported plan format {} in {}",
+                document.format,
+                path.display()
+            )));
+        }
+        Ok(document)
+    }
+
+    /// Write the document as pretty-printed JSON, atomically.
+    pub fn save(&self, path: &Path) -> Result<(), KopyError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| KopyError::Config(format!("Failed to encode plan: {}", err)))?;
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        fs::write(&part, json).map_err(KopyError::Io)?;
+        fs::rename(&part, path).map_err(KopyError::Io)
+    }
+
+    /// Check every planned entry against the filesystem.
+    ///
+    /// Source entries of copies and updates must still have the planned
+    /// size, mtime, permissions and symlink target. Destination paths must
+    /// still hold the entry recorded at plan time, or still be absent when
+    /// none was recorded; the origin of a directory move must still be a
+    /// directory. A keep-both conflict rename only moves a destination file,
+    /// so only its destination paths are checked.
+    pub fn stale_entries(&self) -> Vec<StaleEntry> {
+        let settings = &self.settings;
+        let conflict_renames = self.conflict_renames();
+        let mut stale = Vec::new();
+
+        for action in &self.plan.actions {
+            let checked = match action {
+                SyncAction::CopyNew(entry)
+                | SyncAction::Overwrite(entry, _)
+                | SyncAction::UpdateMetadata(entry) => {
+                    let path = self.source_path(entry);
+                    check_entry(&path, Some(entry))
+                }
+                SyncAction::Move { to, .. } if conflict_renames.contains(to.as_path()) => None,
+                SyncAction::Move { to, .. } => {
+                    let found = settings
+                        .sources()
+                        .map(|root| root.join(to))
+                        .find(|path| path.symlink_metadata().is_ok());
+                    match found {
+                        Some(_) => None,
+                        None => Some(StaleEntry {
+                            path: settings.source.join(to),
+                            reason: "no longer in source".to_string(),
+                        }),
+                    }
+                }
+                SyncAction::Delete(_) | SyncAction::Skip => None,
+            };
+            stale.extend(checked);
+        }
+
+        stale.extend(stale_destination_entries(
+            &self.plan,
+            &settings.destination,
+            &self.destination_entries,
+        ));
+        stale
+    }
+
+    /// The plan without actions that touch any of `stale`.
+    ///
+    /// A rename and the copy into its origin stand or fall together: when
+    /// either is dropped, so is the other, so a keep-both conflict never
+    /// overwrites the destination file without keeping it first.
+    pub fn without_stale(&self, stale: &[StaleEntry]) -> DiffPlan {
+        let stale_paths: BTreeSet<&Path> = stale.iter().map(|entry| entry.path.as_path()).collect();
+        let touches_stale = |action: &SyncAction| {
+            self.touched_paths(action)
+                .iter()
+                .any(|path| stale_paths.contains(path.as_path()))
+        };
+
+        let rename_origins: BTreeSet<&Path> = self
+            .plan
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                SyncAction::Move { from, .. } => Some(from.as_path()),
+                _ => None,
+            })
+            .collect();
+        let dropped_origins: BTreeSet<&Path> = self
+            .plan
+            .actions
+            .iter()
+            .filter(|action| touches_stale(action))
+            .filter_map(|action| match action {
+                SyncAction::Move { from, .. } => Some(from.as_path()),
+                SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry, _)
+                    if rename_origins.contains(entry.path.as_path()) =>
+                {
+                    Some(entry.path.as_path())
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut plan = DiffPlan::new();
+        for action in &self.plan.actions {
+            let paired_drop = match action {
+                SyncAction::Move { from, .. } => dropped_origins.contains(from.as_path()),
+                SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry, _) => {
+                    dropped_origins.contains(entry.path.as_path())
+                }
+                _ => false,
+            };
+            if !paired_drop && !touches_stale(action) {
+                plan.add_action(action.clone());
+            }
+        }
+        for conflict in &self.plan.conflicts {
+            plan.add_conflict(conflict.clone());
+        }
+        // Bytes below a dropped directory delete are not recorded; the
+        // planned total is an upper bound for the delete guard.
+        plan.stats.delete_bytes = self.plan.stats.delete_bytes;
+        plan
+    }
+
+    /// Absolute source and destination paths `action` depends on.
+    fn touched_paths(&self, action: &SyncAction) -> Vec<PathBuf> {
+        match action {
+            SyncAction::CopyNew(entry)
+            | SyncAction::Overwrite(entry, _)
+            | SyncAction::UpdateMetadata(entry) => {
+                vec![self.source_path(entry), self.destination_path(&entry.path)]
+            }
+            SyncAction::Delete(path) => vec![self.destination_path(path)],
+            SyncAction::Move { from, to } => vec![
+                self.settings.source.join(to),
+                self.destination_path(from),
+                self.destination_path(to),
+            ],
+            SyncAction::Skip => Vec::new(),
+        }
+    }
+
+    /// Targets of the keep-both renames the plan settles conflicts with.
+    fn conflict_renames(&self) -> BTreeSet<&Path> {
+        self.plan
+            .conflicts
+            .iter()
+            .filter_map(|conflict| match &conflict.outcome {
+                ConflictOutcome::KeepBoth(renamed) => Some(renamed.as_path()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn source_path(&self, entry: &FileEntry) -> PathBuf {
+        entry.source_path(&self.settings.source)
+    }
+
+    fn destination_path(&self, relative: &Path) -> PathBuf {
+        self.settings.destination.join(relative)
+    }
+}
+
+/// Check the destination paths `plan` touches under `destination` against
+/// `expected`, the entries recorded when the plan was built.
+///
+/// A recorded path must still hold the same entry, an unrecorded path must
+/// still be absent, and the origin of a directory move must still be a
+/// directory.
+pub(crate) fn stale_destination_entries(
+    plan: &DiffPlan,
+    destination: &Path,
+    expected: &BTreeMap<PathBuf, FileEntry>,
+) -> Vec<StaleEntry> {
+    let move_origins: BTreeSet<&PathBuf> = plan
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            SyncAction::Move { from, .. } => Some(from),
+            _ => None,
+        })
+        .collect();
+
+    let mut stale = Vec::new();
+    for relative in destination_paths(plan) {
+        let path = destination.join(&relative);
+        let recorded = expected.get(&relative);
+        let checked = if recorded.is_none() && move_origins.contains(&relative) {
+            match observe(&path) {
+                Ok(Observed::Directory) => None,
+                Ok(_) => Some(stale_entry(&path, "no longer a directory")),
+                Err(err) => Some(stale_entry(&path, &err.to_string())),
+            }
+        } else {
+            check_entry(&path, recorded)
+        };
+        stale.extend(checked);
+    }
+    stale
+}
+
+/// Relative destination paths an action reads or writes.
+pub(crate) fn destination_paths(plan: &DiffPlan) -> BTreeSet<PathBuf> {
+    let mut paths = BTreeSet::new();
+    for action in &plan.actions {
+        match action {
+            SyncAction::CopyNew(entry)
+            | SyncAction::Overwrite(entry, _)
+            | SyncAction::UpdateMetadata(entry) => {
+                paths.insert(entry.path.clone());
+            }
+            SyncAction::Delete(path) => {
+                paths.insert(path.clone());
+            }
+            SyncAction::Move { from, to } => {
+                paths.insert(from.clone());
+                paths.insert(to.clone());
+            }
+            SyncAction::Skip => {}
+        }
+    }
+    paths
+}
+
+/// Compare what is at `path` with `expected` (`None` = must be absent).
+fn check_entry(path: &Path, expected: Option<&FileEntry>) -> Option<StaleEntry> {
+    let observed = match observe(path) {
+        Ok(observed) => observed,
+        Err(err) => return Some(stale_entry(path, &err.to_string())),
+    };
+    match (expected, observed) {
+        (None, Observed::Missing) => None,
+        (None, _) => Some(stale_entry(path, "appeared since the plan was made")),
+        (Some(_), Observed::Missing) => Some(stale_entry(path, "no longer exists")),
+        (Some(_), Observed::Directory) => Some(stale_entry(path, "is now a directory")),
+        (Some(expected), Observed::Entry(actual)) => {
+            let reason = if actual.is_symlink != expected.is_symlink
+                || actual.symlink_target != expected.symlink_target
+            {
+                Some("file type or symlink target changed")
+            } else if actual.size != expected.size {
+                Some("size changed")
+            } else if actual.mtime != expected.mtime {
+                Some("modification time changed")
+            } else if actual.permissions != expected.permissions {
+                Some("permissions changed")
+            } else {
+                None
+            };
+            reason.map(|reason| stale_entry(path, reason))
+        }
+    }
+}
+
+fn stale_entry(path: &Path, reason: &str) -> StaleEntry {
+    StaleEntry {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+fn observe(path: &Path) -> std::io::Result<Observed> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Observed::Missing),
+        Err(err) => return Err(err),
+    };
+    if metadata.is_dir() {
+        return Ok(Observed::Directory);
+    }
+
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode()
+    };
+    #[cfg(not(unix))]
+    let permissions = 0o644;
+
+    let mtime = metadata.modified()?;
+    let entry = if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        FileEntry::new_symlink(PathBuf::new(), metadata.len(), mtime, permissions, target)
+    } else {
+        FileEntry::new(PathBuf::new(), metadata.len(), mtime, permissions)
+    };
+    Ok(Observed::Entry(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(path: &str, size: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(path),
+            size,
+            UNIX_EPOCH + Duration::from_secs(1_000),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_load_accepts_plans_saved_with_tree_fingerprints() {
+        let mut tree = FileTree::new(PathBuf::from("src"));
+        tree.insert(PathBuf::from("x"), entry("x", 1));
+        let document = PlanDocument::new(DiffPlan::new(), &Config::default(), &tree, &tree);
+
+        let mut json = serde_json::to_value(&document).expect("encode plan");
+        json["source_fingerprint"] = serde_json::json!("0f".repeat(32));
+        json["destination_fingerprint"] = serde_json::json!("0f".repeat(32));
+        let dir = tempfile::TempDir::new().expect("create temp dir");
+        let path = dir.path().join("plan.json");
+        fs::write(&path, json.to_string()).expect("write plan");
+
+        assert_eq!(PlanDocument::load(&path).expect("load plan"), document);
+    }
+
+    #[test]
+    fn test_destination_paths_cover_every_action_kind() {
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::CopyNew(entry("new", 1)));
+        plan.add_action(SyncAction::Delete(PathBuf::from("gone")));
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("old"),
+            to: PathBuf::from("renamed"),
+        });
+        plan.add_action(SyncAction::Skip);
+
+        let paths: Vec<PathBuf> = destination_paths(&plan).into_iter().collect();
+        assert_eq!(
+            paths,
+            ["gone", "new", "old", "renamed"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+    }
+}
@@ -1,15 +1,27 @@
 //! Diff engine types and plan generation
 
+use super::conflict::PlanConflict;
 use crate::types::SyncAction;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 /// Diff plan containing actions and statistics
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DiffPlan {
     /// List of sync actions to execute
     pub actions: Vec<SyncAction>,
 
     /// Aggregate statistics about the plan
     pub stats: PlanStats,
+
+    /// Destination files newer than their source, with what the plan does
+    /// about each
+    pub conflicts: Vec<PlanConflict>,
+
+    /// Destination paths left alone because `--protect` or a `.kopykeep`
+    /// file covers them
+    #[serde(default)]
+    pub protected: Vec<PathBuf>,
 }
 
 impl DiffPlan {
@@ -18,37 +30,29 @@ impl DiffPlan {
         Self {
             actions: Vec::new(),
             stats: PlanStats::default(),
+            conflicts: Vec::new(),
+            protected: Vec::new(),
         }
     }
 
     /// Add an action to the plan and update statistics
     pub fn add_action(&mut self, action: SyncAction) {
-        // Update statistics based on action type
-        match &action {
-            SyncAction::CopyNew(entry) => {
-                self.stats.copy_count += 1;
-                self.stats.total_files += 1;
-                self.stats.total_bytes += entry.size;
-            }
-            SyncAction::Overwrite(entry) => {
-                self.stats.overwrite_count += 1;
-                self.stats.total_files += 1;
-                self.stats.total_bytes += entry.size;
-            }
-            SyncAction::Delete(_) => {
-                self.stats.delete_count += 1;
-            }
-            SyncAction::Skip => {
-                self.stats.skip_count += 1;
-            }
-            SyncAction::Move { .. } => {
-                // Move actions are not counted as transfers here.
-            }
-        }
-
+        self.stats.record(&action);
         self.actions.push(action);
     }
 
+    /// Record a conflict and update statistics
+    pub fn add_conflict(&mut self, conflict: PlanConflict) {
+        self.stats.conflict_count += 1;
+        self.conflicts.push(conflict);
+    }
+
+    /// Record a protected destination path and update statistics
+    pub fn add_protected(&mut self, path: PathBuf) {
+        self.stats.protected_count += 1;
+        self.protected.push(path);
+    }
+
     /// Sort actions by path for deterministic output
     pub fn sort_by_path(&mut self) {
         self.actions.sort_by(|a, b| {
@@ -72,7 +76,7 @@ impl Default for DiffPlan {
 }
 
 /// Statistics about a diff plan
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct PlanStats {
     /// Total number of files to transfer (CopyNew + Overwrite)
     pub total_files: usize,
@@ -86,14 +90,63 @@ pub struct PlanStats {
     /// Number of Overwrite actions
     pub overwrite_count: usize,
 
+    /// Number of UpdateMetadata actions (no bytes transferred)
+    #[serde(default)]
+    pub metadata_count: usize,
+
     /// Number of Delete actions
     pub delete_count: usize,
 
+    /// Bytes held by the destination files the Delete actions remove
+    #[serde(default)]
+    pub delete_bytes: u64,
+
+    /// Number of Move actions (renames inside the destination)
+    #[serde(default)]
+    pub move_count: usize,
+
     /// Number of Skip actions
     pub skip_count: usize,
+
+    /// Number of destination-newer conflicts
+    #[serde(default)]
+    pub conflict_count: usize,
+
+    /// Number of protected destination paths the plan leaves alone
+    #[serde(default)]
+    pub protected_count: usize,
 }
 
 impl PlanStats {
+    /// Count one planned action.
+    pub fn record(&mut self, action: &SyncAction) {
+        match action {
+            SyncAction::CopyNew(entry) => {
+                self.copy_count += 1;
+                self.total_files += 1;
+                self.total_bytes += entry.size;
+            }
+            SyncAction::Overwrite(entry, _) => {
+                self.overwrite_count += 1;
+                self.total_files += 1;
+                self.total_bytes += entry.size;
+            }
+            SyncAction::UpdateMetadata(_) => {
+                self.metadata_count += 1;
+            }
+            SyncAction::Delete(_) => {
+                self.delete_count += 1;
+            }
+            SyncAction::Skip => {
+                self.skip_count += 1;
+            }
+            SyncAction::Move { .. } => {
+                // Renames happen inside the destination and transfer no bytes.
+                self.move_count += 1;
+            }
+        }
+    }
+
     /// Estimate time remaining for the sync operation
     ///
     /// This provides a rough estimate based on the total bytes to transfer
@@ -194,7 +247,7 @@ impl PlanStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::FileEntry;
+    use crate::types::{FileEntry, UpdateReason};
     use std::path::PathBuf;
     use std::time::{Duration, UNIX_EPOCH};
 
@@ -233,7 +286,7 @@ mod tests {
         let mut plan = DiffPlan::new();
         let entry = create_test_entry("file.txt", 2048);
 
-        plan.add_action(SyncAction::Overwrite(entry));
+        plan.add_action(SyncAction::Overwrite(entry, UpdateReason::SourceNewer));
 
         assert_eq!(plan.actions.len(), 1);
         assert_eq!(plan.stats.overwrite_count, 1);
@@ -265,6 +318,31 @@ mod tests {
         assert_eq!(plan.stats.total_bytes, 0);
     }
 
+    #[test]
+    fn test_add_move_action() {
+        let mut plan = DiffPlan::new();
+
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("old"),
+            to: PathBuf::from("new"),
+        });
+
+        assert_eq!(plan.stats.move_count, 1);
+        assert_eq!(plan.stats.total_files, 0);
+        assert_eq!(plan.stats.total_bytes, 0);
+    }
+
+    #[test]
+    fn test_add_update_metadata_action() {
+        let mut plan = DiffPlan::new();
+
+        plan.add_action(SyncAction::UpdateMetadata(create_test_entry("a.txt", 100)));
+
+        assert_eq!(plan.stats.metadata_count, 1);
+        assert_eq!(plan.stats.total_files, 0);
+        assert_eq!(plan.stats.total_bytes, 0);
+    }
+
     #[test]
     fn test_sort_by_path() {
         let mut plan = DiffPlan::new();
@@ -285,7 +363,10 @@ mod tests {
         let mut plan = DiffPlan::new();
 
         plan.add_action(SyncAction::CopyNew(create_test_entry("new.txt", 1000)));
-        plan.add_action(SyncAction::Overwrite(create_test_entry("update.txt", 2000)));
+        plan.add_action(SyncAction::Overwrite(
+            create_test_entry("update.txt", 2000),
+            UpdateReason::SourceNewer,
+        ));
         plan.add_action(SyncAction::Delete(PathBuf::from("old.txt")));
         plan.add_action(SyncAction::Skip);
 
@@ -0,0 +1,248 @@
+//! Mass-delete safety guard
+//!
+//! A source that is an unmounted mount point or a mistyped path looks
+//! empty, and with `--delete` the plan then removes the whole destination.
+//! [`check_deletes`] refuses such plans before anything is executed: by
+//! default when the source is empty but the destination is not, and with
+//! `--max-delete` when the plan deletes more than the given limit.
+
+use super::PlanStats;
+use crate::types::{FileTree, KopyError};
+use crate::Config;
+use indicatif::HumanBytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Upper bound on what a plan may delete, written as `N` or `P%`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DeleteLimit {
+    /// At most this many delete actions
+    Count(usize),
+
+    /// At most this percentage of the destination's files and of its bytes
+    Percent(f64),
+}
+
+impl FromStr for DeleteLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        match trimmed.strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                    Ok(DeleteLimit::Percent(percent))
+                }
+                _ => Err(format!(
+                    "invalid delete limit '{}': expected a percentage from 0% to 100%",
+                    value
+                )),
+            },
+            None => trimmed.parse().map(DeleteLimit::Count).map_err(|_| {
+                format!(
+                    "invalid delete limit '{}': expected a count such as 100 or a percentage such as 5%",
+                    value
+                )
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for DeleteLimit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DeleteLimit> for String {
+    fn from(limit: DeleteLimit) -> Self {
+        limit.to_string()
+    }
+}
+
+impl fmt::Display for DeleteLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeleteLimit::Count(count) => write!(f, "{}", count),
+            DeleteLimit::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// File counts of the trees a plan was built from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeTotals {
+    /// Files in the source tree
+    pub source_files: usize,
+
+    /// Files in the destination tree
+    pub destination_files: usize,
+
+    /// Bytes in the destination tree
+    pub destination_bytes: u64,
+}
+
+impl TreeTotals {
+    /// Totals of the scanned `source` and `destination` trees.
+    pub fn of(source: &FileTree, destination: &FileTree) -> Self {
+        Self {
+            source_files: source.total_files,
+            destination_files: destination.total_files,
+            destination_bytes: destination.total_size,
+        }
+    }
+}
+
+/// Refuse a plan whose deletes look like a mistake.
+///
+/// Plans without deletes always pass.
+///
+/// # Errors
+/// Returns `KopyError::DeleteRefused` when the source is empty but the
+/// destination is not (unless `config.allow_empty_source`), or when the
+/// deletes exceed `config.max_delete`.
+///
+/// # Example
+/// ```
+/// use kopy::diff::{check_deletes, PlanStats, TreeTotals};
+/// use kopy::types::KopyError;
+/// use kopy::Config;
+///
+/// let stats = PlanStats {
+///     delete_count: 3,
+///     ..Default::default()
+/// };
+/// let totals = TreeTotals {
+///     source_files: 0,
+///     destination_files: 3,
+///     destination_bytes: 30,
+/// };
+///
+/// let refused = check_deletes(&stats, &totals, &Config::default());
+/// assert!(matches!(refused, Err(KopyError::DeleteRefused { planned: 3, .. })));
+/// ```
+pub fn check_deletes(
+    stats: &PlanStats,
+    totals: &TreeTotals,
+    config: &Config,
+) -> Result<(), KopyError> {
+    let planned = stats.delete_count;
+    if planned == 0 {
+        return Ok(());
+    }
+    let refuse = |reason: String| Err(KopyError::DeleteRefused { planned, reason });
+
+    if totals.source_files == 0 && totals.destination_files > 0 && !config.allow_empty_source {
+        return refuse(format!(
+            "the source is empty but the destination holds {} file(s); \
+             check that the source is mounted and spelled correctly, or pass --allow-empty-source",
+            totals.destination_files
+        ));
+    }
+
+    match config.max_delete {
+        Some(DeleteLimit::Count(limit)) if planned > limit => {
+            refuse(format!("more than the --max-delete limit of {}", limit))
+        }
+        Some(DeleteLimit::Percent(limit)) => {
+            let file_share = share(planned as f64, totals.destination_files as f64);
+            let byte_share = share(stats.delete_bytes as f64, totals.destination_bytes as f64);
+            if file_share > limit {
+                refuse(format!(
+                    "{:.1}% of the destination's files, over the --max-delete limit of {}%",
+                    file_share, limit
+                ))
+            } else if byte_share > limit {
+                refuse(format!(
+                    "{} is {:.1}% of the destination's bytes, over the --max-delete limit of {}%",
+                    HumanBytes(stats.delete_bytes),
+                    byte_share,
+                    limit
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `part` as a percentage of `whole` (0 when `whole` is 0).
+fn share(part: f64, whole: f64) -> f64 {
+    if whole == 0.0 {
+        0.0
+    } else {
+        part * 100.0 / whole
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(delete_count: usize, delete_bytes: u64) -> PlanStats {
+        PlanStats {
+            delete_count,
+            delete_bytes,
+            ..Default::default()
+        }
+    }
+
+    fn totals(source_files: usize, destination_files: usize, bytes: u64) -> TreeTotals {
+        TreeTotals {
+            source_files,
+            destination_files,
+            destination_bytes: bytes,
+        }
+    }
+
+    #[test]
+    fn test_parse_delete_limit() {
+        assert_eq!("100".parse(), Ok(DeleteLimit::Count(100)));
+        assert_eq!(" 2.5% ".parse(), Ok(DeleteLimit::Percent(2.5)));
+        assert!("150%".parse::<DeleteLimit>().is_err());
+        assert!("-1".parse::<DeleteLimit>().is_err());
+        assert!("many".parse::<DeleteLimit>().is_err());
+        assert_eq!(String::from(DeleteLimit::Percent(2.5)), "2.5%");
+    }
+
+    #[test]
+    fn test_empty_source_refused_unless_allowed() {
+        let config = Config::default();
+        let err = check_deletes(&stats(5, 50), &totals(0, 5, 50), &config).unwrap_err();
+        assert!(
+            matches!(err, KopyError::DeleteRefused { planned: 5, ref reason } if reason.contains("--allow-empty-source"))
+        );
+
+        let allowed = Config {
+            allow_empty_source: true,
+            ..Config::default()
+        };
+        assert!(check_deletes(&stats(5, 50), &totals(0, 5, 50), &allowed).is_ok());
+        assert!(check_deletes(&stats(0, 0), &totals(0, 5, 50), &config).is_ok());
+    }
+
+    #[test]
+    fn test_max_delete_count_and_percent() {
+        let count = Config {
+            max_delete: Some(DeleteLimit::Count(2)),
+            ..Config::default()
+        };
+        assert!(check_deletes(&stats(2, 20), &totals(8, 10, 100), &count).is_ok());
+        assert!(check_deletes(&stats(3, 30), &totals(8, 10, 100), &count).is_err());
+
+        let percent = Config {
+            max_delete: Some(DeleteLimit::Percent(25.0)),
+            ..Config::default()
+        };
+        assert!(check_deletes(&stats(2, 20), &totals(8, 10, 100), &percent).is_ok());
+        assert!(check_deletes(&stats(3, 30), &totals(8, 10, 100), &percent).is_err());
+        // Few files, but most of the bytes.
+        let err = check_deletes(&stats(1, 90), &totals(8, 10, 100), &percent).unwrap_err();
+        assert!(err.to_string().contains("bytes"));
+    }
+}
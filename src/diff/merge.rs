@@ -0,0 +1,316 @@
+//! Streaming merge-join plan generation
+//!
+//! [`merge_sync_plan`] plans the same sync as [`super::generate_sync_plan`],
+//! but from two scans in `Path` order (see [`crate::scanner::scan_sorted`])
+//! instead of two complete trees. Only the head of each scan is held, plus
+//! any conflicts, so memory no longer grows with the number of files.
+//!
+//! In `Path` order everything below a path comes right after it, so the
+//! file/directory clashes that [`super::generate_sync_plan`] finds through
+//! ancestor sets are visible by looking at the other side's head.
+//!
+//! Rename detection needs every new file and every orphan at once, so a
+//! streamed plan never contains moves: a renamed file is a copy plus a
+//! delete. Keep files are read as the destination scan enters their
+//! directory, see [`ScopedProtection`].
+
+use super::compare::is_destination_newer;
+use super::conflict::settle_conflict;
+use super::plan::keeps_destination;
+use super::protect::ScopedProtection;
+use super::{compare_files, StreamedPlan};
+use crate::scanner::ScanItem;
+use crate::types::{DeleteMode, FileEntry, KopyError, SyncAction};
+use crate::Config;
+use std::cmp::Ordering;
+use std::path::Path;
+
+/// Plan a sync by merging two sorted scans into `plan`.
+///
+/// Both iterators must yield relative paths in ascending `Path` order, as
+/// [`crate::scanner::scan_sorted`] does. Actions are emitted in path order;
+/// conflicts are settled last, as in [`super::generate_sync_plan`].
+///
+/// # Errors
+/// Returns the first error yielded by either scan, or a
+/// `KopyError::Io` if the plan cannot be spilled to disk.
+///
+/// # Example
+/// ```
+/// use kopy::diff::{merge_sync_plan, StreamedPlan};
+/// use kopy::scanner::ScanItem;
+/// use kopy::types::FileEntry;
+/// use kopy::Config;
+/// use std::path::PathBuf;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let entry = FileEntry::new(
+///     PathBuf::from("new.txt"),
+///     4,
+///     UNIX_EPOCH + Duration::from_secs(1_000),
+///     0o644,
+/// );
+/// let source = vec![Ok(ScanItem::Entry(entry))];
+///
+/// let mut plan = StreamedPlan::new();
+/// merge_sync_plan(source, Vec::new(), &Config::default(), &mut plan)?;
+/// assert_eq!(plan.stats.copy_count, 1);
+/// # Ok::<(), kopy::types::KopyError>(())
+/// ```
+pub fn merge_sync_plan<S, D>(
+    source: S,
+    destination: D,
+    config: &Config,
+    plan: &mut StreamedPlan,
+) -> Result<(), KopyError>
+where
+    S: IntoIterator<Item = Result<ScanItem, KopyError>>,
+    D: IntoIterator<Item = Result<ScanItem, KopyError>>,
+{
+    let mut source = Head::new(source.into_iter())?;
+    let mut destination = Head::new(destination.into_iter())?;
+    let allow_deletes = config.delete_mode != DeleteMode::None;
+    let mut conflicts: Vec<(FileEntry, FileEntry)> = Vec::new();
+    let mut protection = ScopedProtection::new(config);
+
+    loop {
+        let order = match (&source.item, &destination.item) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(src), Some(dest)) => src.path().cmp(dest.path()),
+        };
+
+        match order {
+            Ordering::Less => {
+                let Some(item) = source.advance()? else {
+                    unreachable!("source head checked above");
+                };
+                let ScanItem::Entry(src_entry) = item else {
+                    continue;
+                };
+                // Destination entries below a source file: delete the directory,
+                // unless that removes a protected file; the copy is dropped then.
+                if allow_deletes && destination.is_below(&src_entry.path) {
+                    let (mut bytes, mut shielded) = (0, false);
+                    while destination.is_below(&src_entry.path) {
+                        if let Some(item) = destination.advance()? {
+                            shielded |= protection.protects(item.path());
+                            bytes += item.size();
+                        }
+                    }
+                    if shielded {
+                        plan.add_protected(src_entry.path);
+                        continue;
+                    }
+                    plan.add_action(SyncAction::Delete(src_entry.path.clone()))?;
+                    plan.stats.delete_bytes += bytes;
+                }
+                if !config.existing {
+                    plan.add_action(SyncAction::CopyNew(src_entry))?;
+                }
+            }
+            Ordering::Greater => {
+                let Some(item) = destination.advance()? else {
+                    unreachable!("destination head checked above");
+                };
+                // An orphan, or a file where the source has a directory.
+                if allow_deletes {
+                    if protection.protects(item.path()) {
+                        // Nothing can be copied below a file that stays.
+                        source.skip_below(item.path())?;
+                        plan.add_protected(item.path().to_path_buf());
+                    } else {
+                        plan.add_action(SyncAction::Delete(item.path().to_path_buf()))?;
+                        plan.stats.delete_bytes += item.size();
+                    }
+                }
+            }
+            Ordering::Equal => {
+                let (Some(src_item), Some(dest_item)) = (source.advance()?, destination.advance()?)
+                else {
+                    unreachable!("both heads checked above");
+                };
+                // A deselected source leaves its destination alone.
+                let (ScanItem::Entry(src_entry), ScanItem::Entry(dest_entry)) =
+                    (src_item, dest_item)
+                else {
+                    continue;
+                };
+                if keeps_destination(&src_entry, &dest_entry, config) {
+                    plan.stats.skip_count += 1;
+                    continue;
+                }
+                let protected = protection.protects(&dest_entry.path);
+                if is_destination_newer(&src_entry, &dest_entry, config) {
+                    if protected {
+                        plan.add_protected(dest_entry.path);
+                    } else {
+                        conflicts.push((src_entry, dest_entry));
+                    }
+                    continue;
+                }
+                let action = compare_files(&src_entry, &dest_entry, config);
+                if protected
+                    && matches!(
+                        action,
+                        SyncAction::Overwrite(..) | SyncAction::UpdateMetadata(_)
+                    )
+                {
+                    plan.add_protected(dest_entry.path);
+                } else if !action.is_skip() {
+                    plan.add_action(action)?;
+                } else {
+                    plan.stats.skip_count += 1;
+                }
+            }
+        }
+    }
+
+    // Scans arrive in path order, so the conflicts already are.
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    for (src_entry, dest_entry) in conflicts {
+        let (conflict, actions) =
+            settle_conflict(&src_entry, &dest_entry, config.conflict_mode, &timestamp);
+        if actions.is_empty() {
+            plan.stats.skip_count += 1;
+        }
+        for action in actions {
+            plan.add_action(action)?;
+        }
+        plan.add_conflict(conflict);
+    }
+
+    Ok(())
+}
+
+/// A scan with its next item pulled ahead.
+struct Head<I> {
+    item: Option<ScanItem>,
+    rest: I,
+}
+
+impl<I: Iterator<Item = Result<ScanItem, KopyError>>> Head<I> {
+    fn new(mut rest: I) -> Result<Self, KopyError> {
+        let item = rest.next().transpose()?;
+        Ok(Self { item, rest })
+    }
+
+    /// Take the head and pull the next item.
+    fn advance(&mut self) -> Result<Option<ScanItem>, KopyError> {
+        let next = self.rest.next().transpose()?;
+        Ok(std::mem::replace(&mut self.item, next))
+    }
+
+    /// Whether the head lies strictly below `path`.
+    fn is_below(&self, path: &Path) -> bool {
+        self.item
+            .as_ref()
+            .is_some_and(|item| item.path() != path && item.path().starts_with(path))
+    }
+
+    /// Drop every item below `path`, returning their total size.
+    fn skip_below(&mut self, path: &Path) -> Result<u64, KopyError> {
+        let mut bytes = 0;
+        while self.is_below(path) {
+            bytes += self.advance()?.map_or(0, |item| item.size());
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::generate_sync_plan;
+    use crate::types::FileTree;
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(name: &str, size: u64, secs: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from(name),
+            size,
+            UNIX_EPOCH + Duration::from_secs(secs),
+            0o644,
+        )
+    }
+
+    fn tree(entries: &[FileEntry]) -> FileTree {
+        let mut tree = FileTree::new(PathBuf::from("root"));
+        for entry in entries {
+            tree.insert(entry.path.clone(), entry.clone());
+        }
+        tree
+    }
+
+    fn items(entries: &[FileEntry]) -> Vec<Result<ScanItem, KopyError>> {
+        let mut sorted = entries.to_vec();
+        sorted.sort_by(|a, b| a.path.cmp(&b.path));
+        sorted.into_iter().map(|e| Ok(ScanItem::Entry(e))).collect()
+    }
+
+    fn merged(src: &[FileEntry], dest: &[FileEntry], config: &Config) -> StreamedPlan {
+        let mut plan = StreamedPlan::with_threshold(2);
+        merge_sync_plan(items(src), items(dest), config, &mut plan).unwrap();
+        plan
+    }
+
+    #[test]
+    fn test_merge_matches_tree_plan_without_moves() {
+        let src = [
+            entry("a", 1, 100),
+            entry("b/new", 2, 100),
+            entry("c.txt", 3, 200),
+            entry("same", 4, 100),
+            entry("x/y", 5, 100),
+        ];
+        let dest = [
+            entry("a/old", 1, 100),
+            entry("a/old2", 1, 100),
+            entry("b.txt", 9, 100),
+            entry("c.txt", 3, 100),
+            entry("same", 4, 100),
+            entry("x", 7, 100),
+        ];
+        let config = Config {
+            delete_mode: DeleteMode::Permanent,
+            ..Config::default()
+        };
+
+        let expected = generate_sync_plan(&tree(&src), &tree(&dest), &config);
+        let plan = merged(&src, &dest, &config);
+        assert_eq!(plan.stats, expected.stats);
+        assert_eq!(plan.into_plan().unwrap().actions, expected.actions);
+    }
+
+    #[test]
+    fn test_merge_keeps_destination_of_deselected_source() {
+        let dest = [entry("big", 100, 100)];
+        let config = Config {
+            delete_mode: DeleteMode::Permanent,
+            ..Config::default()
+        };
+        let source = vec![Ok(ScanItem::Deselected(PathBuf::from("big")))];
+
+        let mut plan = StreamedPlan::new();
+        merge_sync_plan(source, items(&dest), &config, &mut plan).unwrap();
+        assert_eq!(plan.stats.delete_count, 0);
+    }
+
+    #[test]
+    fn test_merge_settles_conflicts_last() {
+        let src = [entry("a", 1, 100), entry("b", 1, 100)];
+        let dest = [entry("a", 1, 500)];
+        let config = Config {
+            conflict_mode: crate::config::ConflictMode::SourceWins,
+            ..Config::default()
+        };
+
+        let plan = merged(&src, &dest, &config);
+        assert_eq!(plan.stats.conflict_count, 1);
+        let actions = plan.into_plan().unwrap().actions;
+        assert_eq!(actions[0], SyncAction::CopyNew(entry("b", 1, 100)));
+        assert!(matches!(&actions[1], SyncAction::Overwrite(e, _) if e.path == Path::new("a")));
+    }
+}
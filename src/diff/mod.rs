@@ -1,9 +1,25 @@
 //! Diff engine - Comparison logic and plan generation
 
 mod compare;
+mod conflict;
+mod document;
 mod engine;
+mod guard;
+mod merge;
+mod moves;
+mod order;
 mod plan;
+mod protect;
+mod spill;
 
 pub use compare::compare_files;
+pub use conflict::{conflict_copy_path, ConflictOutcome, PlanConflict};
+pub(crate) use document::{destination_paths, stale_destination_entries};
+pub use document::{PlanDocument, PlanSettings, StaleEntry};
 pub use engine::{DiffPlan, PlanStats};
+pub use guard::{check_deletes, DeleteLimit, TreeTotals};
+pub use merge::merge_sync_plan;
+pub use order::order_plan;
 pub use plan::generate_sync_plan;
+pub use protect::KEEP_FILE_NAME;
+pub use spill::{PlanChunks, StreamedPlan, SPILL_THRESHOLD};
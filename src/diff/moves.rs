@@ -0,0 +1,366 @@
+//! Rename detection
+//!
+//! Destination files that are about to be deleted are paired with new source
+//! files of the same size and Blake3 hash. Outside checksum mode only files
+//! that also share their mtime are hashed, which keeps detection cheap; a
+//! size and mtime match alone is never trusted, since renaming the wrong file
+//! would leave different content at the new path for good. Each pair becomes a `SyncAction::Move` inside the destination instead of a copy
+//! plus a delete. When every file below a destination directory moved to the
+//! same relative place below a new source directory, those pairs collapse
+//! into a single directory move.
+
+use super::compare::mtimes_match;
+use crate::hash::compute_hash;
+use crate::types::{FileEntry, FileTree};
+use crate::Config;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Renames found between new source files and orphaned destination files.
+#[derive(Debug, Default)]
+pub(super) struct DetectedMoves {
+    /// `(from, to)` pairs, relative to the destination root
+    pub pairs: Vec<(PathBuf, PathBuf)>,
+    /// New source files that no longer need copying
+    pub moved_to: HashSet<PathBuf>,
+    /// Orphaned destination files that no longer need deleting
+    pub moved_from: HashSet<PathBuf>,
+}
+
+/// Pair `new_files` (missing from the destination) with `orphans` (missing
+/// from the source).
+///
+/// Empty files and symlinks are never paired; copying them is as cheap as
+/// renaming. When several orphans match, one with the same file name wins.
+pub(super) fn detect_moves(
+    new_files: &[&FileEntry],
+    orphans: &[PathBuf],
+    src_tree: &FileTree,
+    dest_tree: &FileTree,
+    config: &Config,
+) -> DetectedMoves {
+    let file_pairs = pair_files(new_files, orphans, dest_tree, config);
+    if file_pairs.is_empty() {
+        return DetectedMoves::default();
+    }
+
+    let dir_pairs = collapse_directories(&file_pairs, src_tree, dest_tree);
+    let mut detected = DetectedMoves::default();
+    for (from, to) in file_pairs {
+        detected.moved_from.insert(from.clone());
+        detected.moved_to.insert(to.clone());
+        if !dir_pairs
+            .iter()
+            .any(|(dir_from, _)| from.starts_with(dir_from))
+        {
+            detected.pairs.push((from, to));
+        }
+    }
+    detected.pairs.extend(dir_pairs);
+    detected
+}
+
+fn pair_files(
+    new_files: &[&FileEntry],
+    orphans: &[PathBuf],
+    dest_tree: &FileTree,
+    config: &Config,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut by_size: HashMap<u64, Vec<(&PathBuf, &FileEntry)>> = HashMap::new();
+    let mut sorted_orphans: Vec<&PathBuf> = orphans.iter().collect();
+    sorted_orphans.sort();
+    for path in sorted_orphans {
+        if let Some(entry) = dest_tree.get(path) {
+            if !entry.is_symlink && entry.size > 0 {
+                by_size.entry(entry.size).or_default().push((path, entry));
+            }
+        }
+    }
+    if by_size.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted_new: Vec<&FileEntry> = new_files.to_vec();
+    sorted_new.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut used: HashSet<&PathBuf> = HashSet::new();
+    let mut dest_hashes: HashMap<&PathBuf, Option<[u8; 32]>> = HashMap::new();
+    let mut pairs = Vec::new();
+    for src in sorted_new {
+        if src.is_symlink || src.size == 0 {
+            continue;
+        }
+        let Some(candidates) = by_size.get(&src.size) else {
+            continue;
+        };
+
+        let same_name = |path: &&PathBuf| path.file_name() == src.path.file_name();
+        let ordered = candidates
+            .iter()
+            .filter(|(path, _)| same_name(path))
+            .chain(candidates.iter().filter(|(path, _)| !same_name(path)));
+
+        let mut src_hash: Option<Option<[u8; 32]>> = None;
+        for (path, dest) in ordered {
+            if used.contains(path) {
+                continue;
+            }
+            if !config.checksum_mode && !mtimes_match(src.mtime, dest.mtime, config.modify_window) {
+                continue;
+            }
+            let src_hash = *src_hash.get_or_insert_with(|| {
+                src.hash
+                    .or_else(|| compute_hash(&src.source_path(&config.source)).ok())
+            });
+            let dest_hash = *dest_hashes.entry(path).or_insert_with(|| {
+                dest.hash
+                    .or_else(|| compute_hash(&config.destination.join(path)).ok())
+            });
+            let matches = src_hash.is_some() && src_hash == dest_hash;
+            if matches {
+                used.insert(path);
+                pairs.push(((*path).clone(), src.path.clone()));
+                break;
+            }
+        }
+    }
+    pairs
+}
+
+/// Find the topmost directory pairs whose whole content moved together.
+///
+/// A pair `(from_dir, to_dir)` qualifies when every destination file below
+/// `from_dir` and every source file below `to_dir` belongs to a file pair with
+/// the same path relative to the two directories, nothing in the source is
+/// left below `from_dir`, and the destination has nothing below `to_dir`.
+fn collapse_directories(
+    file_pairs: &[(PathBuf, PathBuf)],
+    src_tree: &FileTree,
+    dest_tree: &FileTree,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut support: HashMap<(&Path, &Path), usize> = HashMap::new();
+    for (from, to) in file_pairs {
+        let (mut from_dir, mut to_dir) = (from.as_path(), to.as_path());
+        while from_dir.file_name().is_some() && from_dir.file_name() == to_dir.file_name() {
+            let (Some(from_parent), Some(to_parent)) = (from_dir.parent(), to_dir.parent()) else {
+                break;
+            };
+            if from_parent.as_os_str().is_empty()
+                || to_parent.as_os_str().is_empty()
+                || from_parent.starts_with(to_parent)
+                || to_parent.starts_with(from_parent)
+            {
+                break;
+            }
+            *support.entry((from_parent, to_parent)).or_default() += 1;
+            from_dir = from_parent;
+            to_dir = to_parent;
+        }
+    }
+    if support.is_empty() {
+        return Vec::new();
+    }
+
+    let candidate_dirs: HashSet<&Path> = support
+        .keys()
+        .flat_map(|(from_dir, to_dir)| [*from_dir, *to_dir])
+        .collect();
+    let src_counts = count_files_below(src_tree, &candidate_dirs);
+    let dest_counts = count_files_below(dest_tree, &candidate_dirs);
+    let count = |counts: &HashMap<&Path, usize>, dir: &Path| counts.get(dir).copied().unwrap_or(0);
+
+    let mut valid: Vec<(&Path, &Path)> = support
+        .iter()
+        .filter(|((from_dir, to_dir), supported)| {
+            count(&dest_counts, from_dir) == **supported
+                && count(&src_counts, to_dir) == **supported
+                && count(&src_counts, from_dir) == 0
+                && count(&dest_counts, to_dir) == 0
+                && !dest_tree.contains(&to_dir.to_path_buf())
+        })
+        .map(|(dirs, _)| *dirs)
+        .collect();
+    valid.sort_by_key(|(from_dir, to_dir)| (from_dir.components().count(), *from_dir, *to_dir));
+
+    let mut chosen: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (from_dir, to_dir) in valid {
+        if !chosen.iter().any(|(outer, _)| from_dir.starts_with(outer)) {
+            chosen.push((from_dir.to_path_buf(), to_dir.to_path_buf()));
+        }
+    }
+    chosen
+}
+
+/// Count the files of `tree` strictly below each directory in `dirs`.
+fn count_files_below<'a>(tree: &FileTree, dirs: &HashSet<&'a Path>) -> HashMap<&'a Path, usize> {
+    let mut counts = HashMap::new();
+    for path in tree.paths() {
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(dir) = dirs.get(ancestor) {
+                *counts.entry(*dir).or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// Files written below a temp source and destination, with their trees.
+    struct Fixture {
+        src: tempfile::TempDir,
+        dest: tempfile::TempDir,
+        src_tree: FileTree,
+        dest_tree: FileTree,
+    }
+
+    /// `(path, content, mtime secs)` per side.
+    fn fixture(src: &[(&str, &str, u64)], dest: &[(&str, &str, u64)]) -> Fixture {
+        fn side(files: &[(&str, &str, u64)]) -> (tempfile::TempDir, FileTree) {
+            let root = tempfile::tempdir().unwrap();
+            let mut tree = FileTree::new(root.path().to_path_buf());
+            for (path, content, secs) in files {
+                let full = root.path().join(path);
+                std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+                std::fs::write(&full, content).unwrap();
+                let entry = FileEntry::new(
+                    PathBuf::from(path),
+                    content.len() as u64,
+                    UNIX_EPOCH + Duration::from_secs(*secs),
+                    0o644,
+                );
+                tree.insert(entry.path.clone(), entry);
+            }
+            (root, tree)
+        }
+        let (src, src_tree) = side(src);
+        let (dest, dest_tree) = side(dest);
+        Fixture {
+            src,
+            dest,
+            src_tree,
+            dest_tree,
+        }
+    }
+
+    fn detect(fixture: &Fixture) -> DetectedMoves {
+        let (src, dest) = (&fixture.src_tree, &fixture.dest_tree);
+        let new_files: Vec<&FileEntry> = src
+            .iter()
+            .filter(|(path, _)| !dest.contains(path))
+            .map(|(_, entry)| entry)
+            .collect();
+        let orphans: Vec<PathBuf> = dest
+            .paths()
+            .filter(|path| !src.contains(path))
+            .cloned()
+            .collect();
+        let config = Config {
+            source: fixture.src.path().to_path_buf(),
+            destination: fixture.dest.path().to_path_buf(),
+            ..Config::default()
+        };
+        detect_moves(&new_files, &orphans, src, dest, &config)
+    }
+
+    #[test]
+    fn test_file_rename_pairs_on_content() {
+        let fixture = fixture(
+            &[
+                ("new.txt", "0123456789", 100),
+                ("other.txt", "abcdefghij", 100),
+            ],
+            &[("old.txt", "0123456789", 100)],
+        );
+
+        let detected = detect(&fixture);
+        assert_eq!(
+            detected.pairs,
+            vec![(PathBuf::from("old.txt"), PathBuf::from("new.txt"))]
+        );
+        assert!(detected.moved_to.contains(&PathBuf::from("new.txt")));
+        assert!(!detected.moved_to.contains(&PathBuf::from("other.txt")));
+    }
+
+    #[test]
+    fn test_same_size_and_mtime_with_different_content_is_not_paired() {
+        let fixture = fixture(&[("b.txt", "hello", 100)], &[("a.txt", "world", 100)]);
+        assert!(detect(&fixture).pairs.is_empty());
+    }
+
+    #[test]
+    fn test_different_mtime_is_not_hashed_outside_checksum_mode() {
+        let fixture = fixture(&[("b.txt", "hello", 100)], &[("a.txt", "hello", 200)]);
+        assert!(detect(&fixture).pairs.is_empty());
+    }
+
+    #[test]
+    fn test_same_file_name_wins_among_candidates() {
+        let fixture = fixture(
+            &[("b/report.pdf", "0123456789", 100)],
+            &[
+                ("a/copy.pdf", "0123456789", 100),
+                ("a/report.pdf", "0123456789", 100),
+            ],
+        );
+
+        let detected = detect(&fixture);
+        assert_eq!(
+            detected.pairs,
+            vec![(PathBuf::from("a/report.pdf"), PathBuf::from("b/report.pdf"))]
+        );
+    }
+
+    #[test]
+    fn test_empty_files_are_not_paired() {
+        let fixture = fixture(&[("new.txt", "", 100)], &[("old.txt", "", 100)]);
+        assert!(detect(&fixture).pairs.is_empty());
+    }
+
+    #[test]
+    fn test_directory_rename_collapses_to_one_move() {
+        let fixture = fixture(
+            &[
+                ("photos-2024/a.jpg", "aaaaaaaaaa", 100),
+                ("photos-2024/trip/b.jpg", "bbbbbbbbbbbbbbbbbbbb", 200),
+                ("keep.txt", "keep!", 300),
+            ],
+            &[
+                ("photos/a.jpg", "aaaaaaaaaa", 100),
+                ("photos/trip/b.jpg", "bbbbbbbbbbbbbbbbbbbb", 200),
+                ("keep.txt", "keep!", 300),
+            ],
+        );
+
+        let detected = detect(&fixture);
+        assert_eq!(
+            detected.pairs,
+            vec![(PathBuf::from("photos"), PathBuf::from("photos-2024"))]
+        );
+        assert_eq!(detected.moved_from.len(), 2);
+        assert_eq!(detected.moved_to.len(), 2);
+    }
+
+    #[test]
+    fn test_partial_directory_rename_stays_file_level() {
+        let fixture = fixture(
+            &[
+                ("new/a.jpg", "aaaaaaaaaa", 100),
+                ("new/extra.jpg", "extra", 300),
+            ],
+            &[
+                ("old/a.jpg", "aaaaaaaaaa", 100),
+                ("old/gone.jpg", "gone!", 200),
+            ],
+        );
+
+        let detected = detect(&fixture);
+        assert_eq!(
+            detected.pairs,
+            vec![(PathBuf::from("old/a.jpg"), PathBuf::from("new/a.jpg"))]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Plan execution order
+//!
+//! Plans are built in path order, which already runs a delete that clears a
+//! file/directory clash before the copy into that path, and a keep-both
+//! rename before the copy that replaces the renamed file. [`order_plan`]
+//! reorders a plan for a [`PlanOrder`] without breaking either: the other
+//! orders run every delete first, then every rename, and only reorder the
+//! transfers behind them.
+
+use super::DiffPlan;
+use crate::config::PlanOrder;
+use crate::types::{FileEntry, SyncAction};
+use std::cmp::Reverse;
+use std::path::Path;
+
+/// Reorder `plan` (built in path order) for `order`.
+///
+/// `source_root` is used by [`PlanOrder::Locality`] to look up source
+/// inodes; files that cannot be read sort last. Actions that compare equal
+/// keep their path order.
+///
+/// # Example
+/// ```
+/// use kopy::diff::{order_plan, DiffPlan};
+/// use kopy::types::{FileEntry, SyncAction};
+/// use kopy::PlanOrder;
+/// use std::path::{Path, PathBuf};
+/// use std::time::UNIX_EPOCH;
+///
+/// let mut plan = DiffPlan::new();
+/// plan.add_action(SyncAction::CopyNew(FileEntry::new(
+///     PathBuf::from("a.bin"),
+///     10,
+///     UNIX_EPOCH,
+///     0o644,
+/// )));
+/// plan.add_action(SyncAction::Delete(PathBuf::from("b.bin")));
+///
+/// order_plan(&mut plan, PlanOrder::DeletesFirst, Path::new("src"));
+/// assert_eq!(plan.actions[0], SyncAction::Delete(PathBuf::from("b.bin")));
+/// ```
+pub fn order_plan(plan: &mut DiffPlan, order: PlanOrder, source_root: &Path) {
+    match order {
+        PlanOrder::Path => {}
+        PlanOrder::DeletesFirst => plan
+            .actions
+            .sort_by_key(|action| !matches!(action, SyncAction::Delete(_))),
+        PlanOrder::LargestFirst => order_transfers(plan, |entry| Reverse(entry.size)),
+        PlanOrder::SmallestFirst => order_transfers(plan, |entry| entry.size),
+        PlanOrder::Locality => order_transfers(plan, |entry| source_inode(entry, source_root)),
+    }
+}
+
+/// Deletes, then renames, then metadata updates, then transfers by `key`.
+fn order_transfers<K: Ord>(plan: &mut DiffPlan, key: impl Fn(&FileEntry) -> K) {
+    plan.actions.sort_by_cached_key(|action| {
+        let phase = match action {
+            SyncAction::Delete(_) => 0,
+            SyncAction::Move { .. } => 1,
+            SyncAction::UpdateMetadata(_) | SyncAction::Skip => 2,
+            SyncAction::CopyNew(_) | SyncAction::Overwrite(..) => 3,
+        };
+        let transfer_key = match action {
+            SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry, _) => Some(key(entry)),
+            _ => None,
+        };
+        (phase, transfer_key)
+    });
+}
+
+#[cfg(unix)]
+fn source_inode(entry: &FileEntry, source_root: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(entry.source_path(source_root))
+        .map(|metadata| metadata.ino())
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(not(unix))]
+fn source_inode(_entry: &FileEntry, _source_root: &Path) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UpdateReason;
+    use std::path::PathBuf;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(name: &str, size: u64) -> FileEntry {
+        FileEntry::new(PathBuf::from(name), size, UNIX_EPOCH, 0o644)
+    }
+
+    fn sample_plan() -> DiffPlan {
+        let mut plan = DiffPlan::new();
+        // Path order: the clash delete of `a` precedes the copy into `a/`.
+        plan.add_action(SyncAction::Delete(PathBuf::from("a")));
+        plan.add_action(SyncAction::CopyNew(entry("a/big", 300)));
+        plan.add_action(SyncAction::CopyNew(entry("b", 100)));
+        plan.add_action(SyncAction::UpdateMetadata(entry("c", 5)));
+        plan.add_action(SyncAction::Delete(PathBuf::from("d")));
+        plan.add_action(SyncAction::Overwrite(
+            entry("e", 200),
+            UpdateReason::SourceNewer,
+        ));
+        // Keep-both conflict: rename before the copy that replaces it.
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("f"),
+            to: PathBuf::from("f.conflict"),
+        });
+        plan.add_action(SyncAction::Overwrite(
+            entry("f", 50),
+            UpdateReason::ConflictSourceWins,
+        ));
+        plan
+    }
+
+    fn paths(plan: &DiffPlan) -> Vec<String> {
+        plan.actions
+            .iter()
+            .map(|action| action.path().unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_path_order_leaves_plan_unchanged() {
+        let mut plan = sample_plan();
+        order_plan(&mut plan, PlanOrder::Path, Path::new("src"));
+        assert_eq!(plan, sample_plan());
+    }
+
+    #[test]
+    fn test_deletes_first_keeps_other_actions_in_path_order() {
+        let mut plan = sample_plan();
+        order_plan(&mut plan, PlanOrder::DeletesFirst, Path::new("src"));
+        assert_eq!(
+            paths(&plan),
+            ["a", "d", "a/big", "b", "c", "e", "f.conflict", "f"]
+        );
+    }
+
+    #[test]
+    fn test_size_orders_sort_transfers_behind_deletes_and_renames() {
+        let mut plan = sample_plan();
+        order_plan(&mut plan, PlanOrder::LargestFirst, Path::new("src"));
+        assert_eq!(
+            paths(&plan),
+            ["a", "d", "f.conflict", "c", "a/big", "e", "b", "f"]
+        );
+
+        let mut plan = sample_plan();
+        order_plan(&mut plan, PlanOrder::SmallestFirst, Path::new("src"));
+        assert_eq!(
+            paths(&plan),
+            ["a", "d", "f.conflict", "c", "f", "b", "e", "a/big"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_locality_orders_transfers_by_source_inode() {
+        let src = tempfile::tempdir().unwrap();
+        for name in ["x", "y", "z"] {
+            std::fs::write(src.path().join(name), name).unwrap();
+        }
+        let mut expected = vec!["x", "y", "z"];
+        expected.sort_by_key(|name| source_inode(&entry(name, 1), src.path()));
+
+        let mut plan = DiffPlan::new();
+        for name in ["x", "y", "z"] {
+            plan.add_action(SyncAction::CopyNew(entry(name, 1)));
+        }
+        order_plan(&mut plan, PlanOrder::Locality, src.path());
+        assert_eq!(paths(&plan), expected);
+    }
+}
@@ -1,7 +1,12 @@
 //! SyncAction plan generation
 
+use super::compare::{is_destination_newer, mtimes_match};
+use super::conflict::settle_conflict;
+use super::moves::detect_moves;
+use super::order::order_plan;
+use super::protect::Protection;
 use crate::diff::{compare_files, DiffPlan};
-use crate::types::{DeleteMode, FileTree, SyncAction};
+use crate::types::{DeleteMode, FileEntry, FileTree, SyncAction};
 use crate::Config;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
@@ -9,7 +14,24 @@ use std::path::{Path, PathBuf};
 /// Generate a sync plan by comparing source and destination trees
 ///
 /// The plan includes copy/update actions for source entries and optional delete
-/// actions for destination orphans when deletes are enabled.
+/// actions for destination orphans when deletes are enabled. Destination files
+/// whose source was deselected by size/age limits are not orphans. With deletes
+/// enabled, orphans that match a new source file become moves (see
+/// [`super::moves`]).
+///
+/// `config.existing` drops source files missing from the destination (and so
+/// rename detection), `config.ignore_existing` skips every file present on
+/// both sides, and `config.update` skips those whose destination is newer.
+///
+/// Protected destination paths (`config.protect_patterns` and `.kopykeep`
+/// files, see [`super::protect`]) are never deleted, moved, overwritten or
+/// given new metadata; they are recorded in `plan.protected`, together with
+/// source files that could only be copied by deleting one.
+///
+/// Destination files newer than their source are recorded in
+/// `plan.conflicts` and settled by `config.conflict_mode` (see
+/// [`super::conflict`]); their actions come after every other action.
+/// Finally, `config.plan_order` reorders the plan (see [`super::order`]).
 ///
 /// # Arguments
 /// * `src_tree` - Source directory tree
@@ -47,23 +69,56 @@ pub fn generate_sync_plan(src_tree: &FileTree, dest_tree: &FileTree, config: &Co
     let mut planned_deletes: HashSet<PathBuf> = HashSet::new();
     let dest_parent_prefixes = build_dest_parent_prefixes(dest_tree);
     let allow_deletes = config.delete_mode != DeleteMode::None;
+    let mut new_files: Vec<&FileEntry> = Vec::new();
+    let mut conflicts: Vec<(&FileEntry, &FileEntry)> = Vec::new();
+    let protection = Protection::new(dest_tree, config);
+    let mut shielded: HashSet<PathBuf> = HashSet::new();
 
     for (path, src_entry) in src_tree.iter() {
         if allow_deletes {
+            let mut blocked = false;
             for conflict_path in conflict_delete_roots(path, dest_tree, &dest_parent_prefixes) {
-                if planned_deletes.insert(conflict_path.clone()) {
+                if protection.shields(&conflict_path) {
+                    // The copy needs this path cleared, so it is dropped too.
+                    blocked = true;
+                    if shielded.insert(conflict_path.clone()) {
+                        plan.add_protected(conflict_path);
+                    }
+                } else if planned_deletes.insert(conflict_path.clone()) {
                     plan.add_action(SyncAction::Delete(conflict_path));
                 }
             }
+            if blocked {
+                continue;
+            }
         }
 
         match dest_tree.get(path) {
-            None => {
-                plan.add_action(SyncAction::CopyNew(src_entry.clone()));
+            None if config.existing => {}
+            None => new_files.push(src_entry),
+            Some(dest_entry) if keeps_destination(src_entry, dest_entry, config) => {
+                plan.stats.skip_count += 1;
             }
             Some(dest_entry) => {
+                let protected = protection.protects(path);
+                if is_destination_newer(src_entry, dest_entry, config) {
+                    if protected {
+                        // Conflict handling could overwrite or rename it.
+                        plan.add_protected(path.clone());
+                    } else {
+                        conflicts.push((src_entry, dest_entry));
+                    }
+                    continue;
+                }
                 let action = compare_files(src_entry, dest_entry, config);
-                if !action.is_skip() {
+                if protected
+                    && matches!(
+                        action,
+                        SyncAction::Overwrite(..) | SyncAction::UpdateMetadata(_)
+                    )
+                {
+                    plan.add_protected(path.clone());
+                } else if !action.is_skip() {
                     plan.add_action(action);
                 } else {
                     plan.stats.skip_count += 1;
@@ -72,22 +127,96 @@ pub fn generate_sync_plan(src_tree: &FileTree, dest_tree: &FileTree, config: &Co
         }
     }
 
+    let mut orphans: Vec<PathBuf> = Vec::new();
     if allow_deletes {
         for (path, _dest_entry) in dest_tree.iter() {
             if !src_tree.contains(path)
+                && !src_tree.is_deselected(path)
                 && !planned_deletes.contains(path)
                 && !is_covered_by_planned_delete(path, &planned_deletes)
             {
-                plan.add_action(SyncAction::Delete(path.clone()));
+                if protection.protects(path) {
+                    plan.add_protected(path.clone());
+                } else {
+                    orphans.push(path.clone());
+                }
             }
         }
     }
 
+    // Renames only replace a copy plus a delete, so they need deletes enabled.
+    let moves = if allow_deletes {
+        detect_moves(&new_files, &orphans, src_tree, dest_tree, config)
+    } else {
+        Default::default()
+    };
+    for entry in new_files {
+        if !moves.moved_to.contains(&entry.path) {
+            plan.add_action(SyncAction::CopyNew(entry.clone()));
+        }
+    }
+    for path in orphans {
+        if !moves.moved_from.contains(&path) {
+            plan.add_action(SyncAction::Delete(path));
+        }
+    }
+    for (from, to) in moves.pairs {
+        plan.add_action(SyncAction::Move { from, to });
+    }
+    plan.stats.delete_bytes = deleted_bytes(&plan, dest_tree);
+
     plan.sort_by_path();
+    plan.protected.sort();
+
+    // Appended after sorting: a keep-both rename must run before its copy.
+    conflicts.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    for (src_entry, dest_entry) in conflicts {
+        let (conflict, actions) =
+            settle_conflict(src_entry, dest_entry, config.conflict_mode, &timestamp);
+        if actions.is_empty() {
+            plan.stats.skip_count += 1;
+        }
+        for action in actions {
+            plan.add_action(action);
+        }
+        plan.add_conflict(conflict);
+    }
 
+    order_plan(&mut plan, config.plan_order, &config.source);
     plan
 }
 
+/// Whether `--ignore-existing` or `--update` leaves `dest` alone, whatever
+/// the comparison would decide.
+pub(super) fn keeps_destination(src: &FileEntry, dest: &FileEntry, config: &Config) -> bool {
+    config.ignore_existing
+        || (config.update
+            && dest.mtime > src.mtime
+            && !mtimes_match(src.mtime, dest.mtime, config.modify_window))
+}
+
+/// Bytes of the destination files removed by the plan's deletes, including
+/// everything below a deleted directory.
+fn deleted_bytes(plan: &DiffPlan, dest_tree: &FileTree) -> u64 {
+    let deletes: HashSet<&Path> = plan
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            SyncAction::Delete(path) => Some(path.as_path()),
+            _ => None,
+        })
+        .collect();
+    if deletes.is_empty() {
+        return 0;
+    }
+    dest_tree
+        .iter()
+        .filter(|(path, _)| path.ancestors().any(|ancestor| deletes.contains(ancestor)))
+        .map(|(_, entry)| entry.size)
+        .sum()
+}
+
 fn build_dest_parent_prefixes(dest_tree: &FileTree) -> HashSet<PathBuf> {
     let mut prefixes = HashSet::new();
     for dest_path in dest_tree.paths() {
@@ -0,0 +1,297 @@
+//! Protected destination paths
+//!
+//! Some destination files must outlive every sync: server-local config,
+//! upload directories, `.well-known/`. A destination path is protected when
+//! it, or a directory above it, matches a `--protect` pattern, or when a
+//! [`KEEP_FILE_NAME`] file covers it. [`super::generate_sync_plan`] never
+//! deletes, renames away, overwrites or updates the metadata of a protected
+//! path; it records the path in `DiffPlan::protected` instead.
+//!
+//! A keep file protects the directory it sits in. Pattern lines in it (blank
+//! lines and `#` comments aside) narrow that to the matching paths below the
+//! directory, in the same syntax as `--protect`. The keep file itself is
+//! always protected.
+//!
+//! [`super::merge_sync_plan`] applies the same rules through
+//! [`ScopedProtection`], which holds only the keep files above the current
+//! path.
+
+use crate::filter::{FilterAction, FilterRule, RuleSource};
+use crate::types::FileTree;
+use crate::Config;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Name of the destination-side keep file.
+pub const KEEP_FILE_NAME: &str = ".kopykeep";
+
+/// Protected files of one destination tree, and the directories above them.
+#[derive(Debug, Default)]
+pub(super) struct Protection {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Protection {
+    /// Collect the files of `dest_tree` covered by `config.protect_patterns`
+    /// or by keep files below `dest_tree.root_path`.
+    pub(super) fn new(dest_tree: &FileTree, config: &Config) -> Self {
+        let mut rules = protect_rules(config);
+        rules.extend(keep_file_rules(dest_tree));
+        if rules.is_empty() {
+            return Self::default();
+        }
+
+        let mut protection = Self::default();
+        for path in dest_tree.paths() {
+            if path.file_name() == Some(OsStr::new(KEEP_FILE_NAME)) || is_covered(path, &rules) {
+                protection.files.insert(path.clone());
+                protection.dirs.extend(
+                    path.ancestors()
+                        .skip(1)
+                        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                        .map(Path::to_path_buf),
+                );
+            }
+        }
+        protection
+    }
+
+    /// Whether the destination file at `path` is protected.
+    pub(super) fn protects(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+
+    /// Whether deleting `path` would remove a protected file.
+    pub(super) fn shields(&self, path: &Path) -> bool {
+        self.files.contains(path) || self.dirs.contains(path)
+    }
+}
+
+/// Protection for a destination read in `Path` order, as
+/// [`super::merge_sync_plan`] does.
+///
+/// Only the keep files of the directories above the current path are held:
+/// a directory's keep file is read when the scan enters it and dropped when
+/// the scan leaves it. The rules are the same as [`Protection::new`]'s.
+#[derive(Debug)]
+pub(super) struct ScopedProtection {
+    root: PathBuf,
+    rules: Vec<FilterRule>,
+    scopes: Vec<(PathBuf, Vec<FilterRule>)>,
+}
+
+impl ScopedProtection {
+    /// Protection for the destination of `config`.
+    pub(super) fn new(config: &Config) -> Self {
+        Self {
+            root: config.destination.clone(),
+            rules: protect_rules(config),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Whether the destination file at `path` is protected.
+    ///
+    /// Cheapest when called in ascending path order.
+    pub(super) fn protects(&mut self, path: &Path) -> bool {
+        self.enter(path.parent().unwrap_or(Path::new("")));
+        path.file_name() == Some(OsStr::new(KEEP_FILE_NAME))
+            || is_covered(path, &self.rules)
+            || self.scopes.iter().any(|(_, rules)| is_covered(path, rules))
+    }
+
+    /// Hold the keep files of `dir` and every directory above it.
+    fn enter(&mut self, dir: &Path) {
+        while self
+            .scopes
+            .last()
+            .is_some_and(|(scope, _)| !dir.starts_with(scope))
+        {
+            self.scopes.pop();
+        }
+        let innermost = self.scopes.last().map(|(scope, _)| scope.as_path());
+        let mut missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| Some(*ancestor) != innermost)
+            .map(Path::to_path_buf)
+            .collect();
+        while let Some(scope) = missing.pop() {
+            let rules = read_keep_file(&self.root, scope.clone());
+            self.scopes.push((scope, rules));
+        }
+    }
+}
+
+/// A file is covered when a rule matches it or a directory above it.
+fn is_covered(path: &Path, rules: &[FilterRule]) -> bool {
+    rules.iter().any(|rule| {
+        rule.matches(path, false)
+            || path
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| rule.matches(ancestor, true))
+    })
+}
+
+/// `--protect` rules; invalid patterns are rejected by `Config::validate`.
+fn protect_rules(config: &Config) -> Vec<FilterRule> {
+    config
+        .protect_patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(index, pattern)| {
+            let source = RuleSource::Argument {
+                flag: "--protect",
+                position: index + 1,
+            };
+            FilterRule::new(FilterAction::Exclude, pattern, PathBuf::new(), source).ok()
+        })
+        .collect()
+}
+
+/// Rules from every keep file in a directory of `dest_tree`.
+fn keep_file_rules(dest_tree: &FileTree) -> Vec<FilterRule> {
+    let mut dirs: HashSet<PathBuf> = HashSet::from([PathBuf::new()]);
+    for path in dest_tree.paths() {
+        dirs.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+    }
+    dirs.into_iter()
+        .flat_map(|dir| read_keep_file(&dest_tree.root_path, dir))
+        .collect()
+}
+
+/// Rules of the keep file in `dir` below `root`, if there is one.
+///
+/// A keep file that cannot be read or holds an invalid pattern protects its
+/// whole directory.
+fn read_keep_file(root: &Path, dir: PathBuf) -> Vec<FilterRule> {
+    let keep_file = root.join(&dir).join(KEEP_FILE_NAME);
+    let contents = match std::fs::read_to_string(&keep_file) {
+        Ok(contents) => contents,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+            return Vec::new();
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to read {}: {}. The whole directory will be protected.",
+                keep_file.display(),
+                e
+            );
+            String::new()
+        }
+    };
+
+    let mut rules = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            continue;
+        }
+        let source = RuleSource::File {
+            path: keep_file.clone(),
+            line: index + 1,
+        };
+        match FilterRule::new(FilterAction::Exclude, pattern, dir.clone(), source) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => {
+                eprintln!("Warning: {}. The whole directory will be protected.", e);
+                rules.clear();
+                break;
+            }
+        }
+    }
+    if rules.is_empty() {
+        let source = RuleSource::File {
+            path: keep_file,
+            line: 0,
+        };
+        rules.extend(FilterRule::new(FilterAction::Exclude, "*", dir, source).ok());
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileEntry;
+    use std::fs;
+    use std::time::UNIX_EPOCH;
+
+    fn tree(root: &Path, paths: &[&str]) -> FileTree {
+        let mut tree = FileTree::new(root.to_path_buf());
+        for path in paths {
+            let path = PathBuf::from(path);
+            tree.insert(path.clone(), FileEntry::new(path, 1, UNIX_EPOCH, 0o644));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_protect_patterns_cover_files_and_directories() {
+        let dest = tree(
+            Path::new("/nonexistent-kopy-root"),
+            &[
+                "config.local",
+                "app/config.local",
+                ".well-known/acme/token",
+                "index.html",
+            ],
+        );
+        let config = Config {
+            protect_patterns: vec!["/config.local".to_string(), ".well-known/".to_string()],
+            ..Config::default()
+        };
+
+        let protection = Protection::new(&dest, &config);
+        assert!(protection.protects(Path::new("config.local")));
+        assert!(!protection.protects(Path::new("app/config.local")));
+        assert!(protection.protects(Path::new(".well-known/acme/token")));
+        assert!(protection.shields(Path::new(".well-known")));
+        assert!(!protection.shields(Path::new("index.html")));
+    }
+
+    #[test]
+    fn test_keep_files_protect_their_directory_or_matching_paths() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("uploads/2024")).unwrap();
+        fs::create_dir_all(root.path().join("site")).unwrap();
+        fs::write(root.path().join("uploads").join(KEEP_FILE_NAME), "").unwrap();
+        fs::write(
+            root.path().join("site").join(KEEP_FILE_NAME),
+            "# local settings\n*.env\n",
+        )
+        .unwrap();
+        let dest = tree(
+            root.path(),
+            &[
+                "uploads/.kopykeep",
+                "uploads/2024/a.jpg",
+                "site/.kopykeep",
+                "site/prod.env",
+                "site/index.html",
+                "prod.env",
+            ],
+        );
+
+        let protection = Protection::new(&dest, &Config::default());
+        assert!(protection.protects(Path::new("uploads/2024/a.jpg")));
+        assert!(protection.protects(Path::new("uploads/.kopykeep")));
+        assert!(protection.protects(Path::new("site/prod.env")));
+        assert!(protection.protects(Path::new("site/.kopykeep")));
+        assert!(!protection.protects(Path::new("site/index.html")));
+        assert!(!protection.protects(Path::new("prod.env")));
+
+        let mut scoped = ScopedProtection::new(&Config {
+            destination: root.path().to_path_buf(),
+            ..Config::default()
+        });
+        let mut paths: Vec<PathBuf> = dest.paths().cloned().collect();
+        paths.sort();
+        let streamed: Vec<bool> = paths.iter().map(|path| scoped.protects(path)).collect();
+        let whole: Vec<bool> = paths.iter().map(|path| protection.protects(path)).collect();
+        assert_eq!(streamed, whole);
+    }
+}
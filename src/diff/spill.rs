@@ -0,0 +1,282 @@
+//! Action lists that spill to disk
+//!
+//! A streamed plan over tens of millions of files can hold as many actions
+//! as there are files. [`StreamedPlan`] keeps up to a fixed number of actions
+//! in memory and moves them to a JSON-lines temp file beyond that, then hands
+//! them back in bounded chunks for execution.
+
+use super::{DiffPlan, PlanConflict, PlanStats};
+use crate::types::{KopyError, SyncAction};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Lines, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Actions kept in memory before a [`StreamedPlan`] starts spilling.
+pub const SPILL_THRESHOLD: usize = 100_000;
+
+/// Names tried before giving up on creating a spill file.
+const SPILL_NAME_ATTEMPTS: usize = 1_000;
+
+/// Plan built by [`super::merge_sync_plan`], with actions possibly on disk.
+#[derive(Debug)]
+pub struct StreamedPlan {
+    /// Aggregate statistics over every action, spilled or not
+    pub stats: PlanStats,
+
+    /// Destination files newer than their source
+    pub conflicts: Vec<PlanConflict>,
+
+    /// Protected destination paths the plan leaves alone
+    pub protected: Vec<PathBuf>,
+
+    actions: Vec<SyncAction>,
+    spill: Option<Spill>,
+    threshold: usize,
+}
+
+#[derive(Debug)]
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl StreamedPlan {
+    /// Create an empty plan that spills beyond [`SPILL_THRESHOLD`] actions.
+    pub fn new() -> Self {
+        Self::with_threshold(SPILL_THRESHOLD)
+    }
+
+    /// Create an empty plan that spills beyond `threshold` actions.
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            stats: PlanStats::default(),
+            conflicts: Vec::new(),
+            protected: Vec::new(),
+            actions: Vec::new(),
+            spill: None,
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// Append an action, spilling the in-memory batch once it is full.
+    pub fn add_action(&mut self, action: SyncAction) -> Result<(), KopyError> {
+        self.stats.record(&action);
+        self.actions.push(action);
+        if self.actions.len() >= self.threshold {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Record a conflict and update statistics.
+    pub fn add_conflict(&mut self, conflict: PlanConflict) {
+        self.stats.conflict_count += 1;
+        self.conflicts.push(conflict);
+    }
+
+    /// Record a protected destination path and update statistics.
+    pub fn add_protected(&mut self, path: PathBuf) {
+        self.stats.protected_count += 1;
+        self.protected.push(path);
+    }
+
+    /// Whether any action went to disk.
+    pub fn is_spilled(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    /// Hand the actions back in order, at most `chunk_size` at a time.
+    ///
+    /// Each chunk is a [`DiffPlan`] with its own statistics; conflicts and
+    /// protected paths are not repeated in the chunks.
+    pub fn chunks(&mut self, chunk_size: usize) -> Result<PlanChunks<'_>, KopyError> {
+        let spilled = match &mut self.spill {
+            Some(spill) => {
+                spill.writer.flush().map_err(KopyError::Io)?;
+                let file = File::open(&spill.path).map_err(KopyError::Io)?;
+                Some(BufReader::new(file).lines())
+            }
+            None => None,
+        };
+        Ok(PlanChunks {
+            spilled,
+            memory: self.actions.iter(),
+            chunk_size: chunk_size.max(1),
+        })
+    }
+
+    /// Load every action into an in-memory [`DiffPlan`].
+    pub fn into_plan(mut self) -> Result<DiffPlan, KopyError> {
+        let mut plan = DiffPlan::new();
+        for chunk in self.chunks(self.threshold)? {
+            plan.actions.extend(chunk?.actions);
+        }
+        plan.stats = self.stats.clone();
+        plan.conflicts = std::mem::take(&mut self.conflicts);
+        plan.protected = std::mem::take(&mut self.protected);
+        Ok(plan)
+    }
+
+    fn flush(&mut self) -> Result<(), KopyError> {
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => {
+                let (path, file) = create_spill_file().map_err(KopyError::Io)?;
+                self.spill.insert(Spill {
+                    path,
+                    writer: BufWriter::new(file),
+                })
+            }
+        };
+        for action in self.actions.drain(..) {
+            serde_json::to_writer(&mut spill.writer, &action).map_err(|err| {
+                KopyError::Io(std::io::Error::other(format!(
+                    "Failed to spill plan to {}: {}",
+                    spill.path.display(),
+                    err
+                )))
+            })?;
+            spill.writer.write_all(b"\n").map_err(KopyError::Io)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for StreamedPlan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StreamedPlan {
+    fn drop(&mut self) {
+        if let Some(spill) = &self.spill {
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+}
+
+/// Iterator returned by [`StreamedPlan::chunks`].
+pub struct PlanChunks<'a> {
+    spilled: Option<Lines<BufReader<File>>>,
+    memory: std::slice::Iter<'a, SyncAction>,
+    chunk_size: usize,
+}
+
+impl PlanChunks<'_> {
+    fn next_action(&mut self) -> Option<Result<SyncAction, KopyError>> {
+        if let Some(lines) = &mut self.spilled {
+            match lines.next() {
+                Some(Ok(line)) => {
+                    return Some(serde_json::from_str(&line).map_err(|err| {
+                        KopyError::Io(std::io::Error::other(format!(
+                            "Failed to read spilled plan: {}",
+                            err
+                        )))
+                    }));
+                }
+                Some(Err(err)) => return Some(Err(KopyError::Io(err))),
+                None => self.spilled = None,
+            }
+        }
+        self.memory.next().cloned().map(Ok)
+    }
+}
+
+impl Iterator for PlanChunks<'_> {
+    type Item = Result<DiffPlan, KopyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = DiffPlan::new();
+        while chunk.actions.len() < self.chunk_size {
+            match self.next_action() {
+                Some(Ok(action)) => chunk.add_action(action),
+                Some(Err(err)) => return Some(Err(err)),
+                None => break,
+            }
+        }
+        (!chunk.actions.is_empty()).then_some(Ok(chunk))
+    }
+}
+
+/// Create a new spill file in the temp directory, readable by the owner
+/// only.
+///
+/// The temp directory is shared, so an existing file or symlink at a
+/// candidate name is never opened; the next name is tried instead.
+fn create_spill_file() -> std::io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    for _ in 0..SPILL_NAME_ATTEMPTS {
+        let path = std::env::temp_dir().join(format!(
+            "kopy-plan-{}-{}.jsonl",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Err(std::io::Error::new(
+        ErrorKind::AlreadyExists,
+        "no free name for a plan spill file in the temp directory",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delete(name: &str) -> SyncAction {
+        SyncAction::Delete(PathBuf::from(name))
+    }
+
+    #[test]
+    fn test_streamed_plan_spills_and_keeps_order() {
+        let mut plan = StreamedPlan::with_threshold(2);
+        for name in ["a", "b", "c", "d", "e"] {
+            plan.add_action(delete(name)).unwrap();
+        }
+        assert!(plan.is_spilled());
+        assert_eq!(plan.stats.delete_count, 5);
+
+        let chunks: Vec<DiffPlan> = plan.chunks(3).unwrap().map(Result::unwrap).collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0].actions,
+            vec![delete("a"), delete("b"), delete("c")]
+        );
+        assert_eq!(chunks[1].stats.delete_count, 2);
+
+        let spill_path = plan.spill.as_ref().unwrap().path.clone();
+        let all = plan.into_plan().unwrap();
+        assert_eq!(all.actions.len(), 5);
+        assert_eq!(all.actions[4], delete("e"));
+        assert!(!spill_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spill_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (path, file) = create_spill_file().unwrap();
+        let mode = file.metadata().unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_streamed_plan_in_memory_without_spill() {
+        let mut plan = StreamedPlan::new();
+        plan.add_action(delete("a")).unwrap();
+        assert!(!plan.is_spilled());
+        assert_eq!(plan.into_plan().unwrap().actions, vec![delete("a")]);
+    }
+}
@@ -5,7 +5,7 @@ pub mod pool;
 pub mod trash;
 
 use crate::diff::DiffPlan;
-use crate::types::{DeleteMode, KopyError, SyncAction};
+use crate::types::{DeleteMode, FileEntry, KopyError, SyncAction};
 use crate::Config;
 use std::fs;
 use std::io::{Error, ErrorKind};
@@ -166,6 +166,11 @@ pub fn execute_plan(
 /// - Small transfer actions (CopyNew/Overwrite <= threshold) run concurrently.
 /// - Large transfer actions and non-transfer actions run sequentially.
 /// - Sequential actions form ordering barriers: queued small transfers are drained first.
+///
+/// Deletes are always sequential, so a delete that clears a file/directory
+/// clash finishes before any transfer after it in the plan starts. Every
+/// [`crate::PlanOrder`] keeps such deletes ahead of the transfers into their
+/// paths (see [`crate::diff::order_plan`]).
 pub fn execute_plan_parallel(
     plan: &DiffPlan,
     config: &Config,
@@ -250,19 +255,21 @@ pub fn execute_plan_parallel(
 
 fn execute_action(action: &SyncAction, config: &Config) -> Result<u64, KopyError> {
     match action {
-        SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry) => {
-            let (src_path, dest_path) = resolve_transfer_paths(config, &entry.path)?;
+        SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry, _) => {
+            let (src_path, dest_path) = resolve_transfer_paths(config, entry)?;
             if entry.is_symlink {
                 copy_symlink(&src_path, &dest_path, entry)
             } else {
                 copy_file_atomic(&src_path, &dest_path, config)
             }
         }
+        SyncAction::UpdateMetadata(entry) => {
+            let (_, dest_path) = resolve_transfer_paths(config, entry)?;
+            apply_metadata(&dest_path, entry).map(|_| 0)
+        }
         SyncAction::Delete(path) => execute_delete(path, config).map(|_| 0),
         SyncAction::Skip => Ok(0),
-        SyncAction::Move { .. } => Err(KopyError::Validation(
-            "Move action is not supported by this executor".to_string(),
-        )),
+        SyncAction::Move { from, to } => execute_move(from, to, config).map(|_| 0),
     }
 }
 
@@ -389,7 +396,7 @@ fn is_small_parallel_transfer(action: &SyncAction) -> bool {
 
 fn resolve_transfer_paths(
     config: &Config,
-    relative_path: &std::path::Path,
+    entry: &FileEntry,
 ) -> Result<(PathBuf, PathBuf), KopyError> {
     if config.source.is_file() {
         let src_path = config.source.clone();
@@ -407,8 +414,8 @@ fn resolve_transfer_paths(
         Ok((src_path, dest_path))
     } else {
         Ok((
-            config.source.join(relative_path),
-            config.destination.join(relative_path),
+            entry.source_path(&config.source),
+            config.destination.join(&entry.path),
         ))
     }
 }
@@ -488,11 +495,85 @@ fn execute_delete(path: &PathBuf, config: &Config) -> Result<(), KopyError> {
         DeleteMode::Permanent => match fs::symlink_metadata(&dest_path) {
             Ok(_) => remove_with_mapped_delete_error(&dest_path, true),
             Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
-            Err(e) => Err(map_delete_error(&dest_path, e)),
+            Err(e) => Err(map_fs_error(&dest_path, e)),
         },
     }
 }
 
+/// Give `dest_path` the permissions and mtime of `entry` without touching
+/// its content.
+fn apply_metadata(dest_path: &std::path::Path, entry: &FileEntry) -> Result<(), KopyError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            dest_path,
+            fs::Permissions::from_mode(entry.permissions & 0o7777),
+        )
+        .map_err(|e| map_fs_error(dest_path, e))?;
+    }
+    filetime::set_file_mtime(dest_path, filetime::FileTime::from_system_time(entry.mtime))
+        .map_err(|e| map_fs_error(dest_path, e))
+}
+
+/// Rename `from` to `to` inside the destination, creating missing parents,
+/// then give the moved files the permissions and mtime of their source.
+///
+/// An existing `to` is never replaced.
+fn execute_move(
+    from: &std::path::Path,
+    to: &std::path::Path,
+    config: &Config,
+) -> Result<(), KopyError> {
+    let from_path = config.destination.join(from);
+    let to_path = config.destination.join(to);
+    if fs::symlink_metadata(&to_path).is_ok() {
+        return Err(KopyError::Io(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Move target already exists: {}", to_path.display()),
+        )));
+    }
+    if let Some(parent) = to_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| map_fs_error(parent, e))?;
+    }
+    fs::rename(&from_path, &to_path).map_err(|e| map_fs_error(&from_path, e))?;
+    copy_moved_metadata(to, config)
+}
+
+/// Copy source permissions and mtimes onto the files moved to `path`.
+///
+/// Renames are paired on content, so only metadata can be stale. Paths with
+/// no regular file in any source, such as keep-both conflict copies, are
+/// left alone.
+fn copy_moved_metadata(path: &std::path::Path, config: &Config) -> Result<(), KopyError> {
+    let dest_path = config.destination.join(path);
+    let source = config
+        .overlay_sources
+        .iter()
+        .rev()
+        .chain(std::iter::once(&config.source))
+        .find_map(|root| fs::symlink_metadata(root.join(path)).ok());
+    match source {
+        Some(metadata) if metadata.is_file() => {
+            fs::set_permissions(&dest_path, metadata.permissions())
+                .map_err(|e| map_fs_error(&dest_path, e))?;
+            filetime::set_file_mtime(
+                &dest_path,
+                filetime::FileTime::from_last_modification_time(&metadata),
+            )
+            .map_err(|e| map_fs_error(&dest_path, e))
+        }
+        Some(metadata) if metadata.is_dir() => {
+            for child in fs::read_dir(&dest_path).map_err(|e| map_fs_error(&dest_path, e))? {
+                let child = child.map_err(|e| map_fs_error(&dest_path, e))?;
+                copy_moved_metadata(&path.join(child.file_name()), config)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
 fn remove_with_mapped_delete_error(
     path: &std::path::Path,
     not_found_is_ok: bool,
@@ -500,11 +581,13 @@ fn remove_with_mapped_delete_error(
     match remove_path_any(path) {
         Ok(()) => Ok(()),
         Err(e) if not_found_is_ok && e.kind() == ErrorKind::NotFound => Ok(()),
-        Err(e) => Err(map_delete_error(path, e)),
+        Err(e) => Err(map_fs_error(path, e)),
     }
 }
 
-fn map_delete_error(path: &std::path::Path, error: Error) -> KopyError {
+/// Map a filesystem error on `path` (delete, rename, chmod, utime) to a
+/// `KopyError`.
+fn map_fs_error(path: &std::path::Path, error: Error) -> KopyError {
     if matches!(error.kind(), ErrorKind::PermissionDenied) {
         KopyError::PermissionDenied {
             path: path.to_path_buf(),
@@ -543,6 +626,10 @@ fn clone_error_for_event(error: &KopyError) -> KopyError {
             offset: *offset,
         },
         KopyError::SshError(msg) => KopyError::SshError(msg.clone()),
+        KopyError::DeleteRefused { planned, reason } => KopyError::DeleteRefused {
+            planned: *planned,
+            reason: reason.clone(),
+        },
         KopyError::DryRun => KopyError::DryRun,
     }
 }
@@ -572,7 +659,7 @@ fn build_error_summary(errors: &[(Option<PathBuf>, KopyError)]) -> String {
 mod tests {
     use super::*;
     use crate::config::ScanMode;
-    use crate::types::FileEntry;
+    use crate::types::{FileEntry, UpdateReason};
     use std::sync::{Arc, Mutex};
     use std::time::{Duration, UNIX_EPOCH};
     use tempfile::TempDir;
@@ -592,6 +679,7 @@ mod tests {
             backup_dir: None,
             watch: false,
             watch_settle: 2,
+            ..Config::default()
         }
     }
 
@@ -616,7 +704,10 @@ mod tests {
 
         let mut plan = DiffPlan::new();
         plan.add_action(SyncAction::CopyNew(entry("new.txt", 11)));
-        plan.add_action(SyncAction::Overwrite(entry("keep.txt", 7)));
+        plan.add_action(SyncAction::Overwrite(
+            entry("keep.txt", 7),
+            UpdateReason::SourceNewer,
+        ));
         plan.add_action(SyncAction::Skip);
 
         let stats = execute_plan(&plan, &config, None).expect("execute plan");
@@ -634,6 +725,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_plan_move_renames_inside_destination() {
+        let src = tempfile::tempdir().expect("create src tempdir");
+        let dst = tempfile::tempdir().expect("create dst tempdir");
+        let config = config_for(&src, &dst, DeleteMode::Trash);
+
+        fs::create_dir_all(dst.path().join("old/sub")).expect("create dst dir");
+        fs::write(dst.path().join("old/sub/a.txt"), b"alpha").expect("write dst file");
+        fs::write(dst.path().join("taken.txt"), b"taken").expect("write dst taken");
+        fs::write(dst.path().join("loose.txt"), b"loose").expect("write dst loose");
+
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("old"),
+            to: PathBuf::from("renamed/dir"),
+        });
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("loose.txt"),
+            to: PathBuf::from("taken.txt"),
+        });
+
+        let result = execute_plan(&plan, &config, None);
+        assert!(result.is_err(), "an existing move target is an error");
+        assert_eq!(
+            fs::read(dst.path().join("renamed/dir/sub/a.txt")).expect("read moved file"),
+            b"alpha"
+        );
+        assert!(!dst.path().join("old").exists());
+        assert_eq!(
+            fs::read(dst.path().join("taken.txt")).expect("read taken"),
+            b"taken"
+        );
+        assert!(dst.path().join("loose.txt").exists());
+    }
+
+    #[test]
+    fn test_execute_plan_move_takes_source_metadata() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let src = tempfile::tempdir().expect("create src tempdir");
+        let dst = tempfile::tempdir().expect("create dst tempdir");
+        let config = config_for(&src, &dst, DeleteMode::Trash);
+        fs::create_dir_all(src.path().join("new")).expect("create src dir");
+        fs::write(src.path().join("new/run.sh"), b"echo hi").expect("write src file");
+        fs::set_permissions(
+            src.path().join("new/run.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .expect("chmod src file");
+        let mtime = filetime::FileTime::from_unix_time(1_500_000_000, 0);
+        filetime::set_file_mtime(src.path().join("new/run.sh"), mtime).expect("set src mtime");
+        fs::create_dir_all(dst.path().join("old")).expect("create dst dir");
+        fs::write(dst.path().join("old/run.sh"), b"echo hi").expect("write dst file");
+        fs::set_permissions(
+            dst.path().join("old/run.sh"),
+            fs::Permissions::from_mode(0o600),
+        )
+        .expect("chmod dst file");
+
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::Move {
+            from: PathBuf::from("old"),
+            to: PathBuf::from("new"),
+        });
+        execute_plan(&plan, &config, None).expect("move directory");
+
+        let metadata = fs::metadata(dst.path().join("new/run.sh")).expect("stat moved file");
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o755);
+        assert_eq!(
+            filetime::FileTime::from_last_modification_time(&metadata),
+            mtime
+        );
+    }
+
+    #[test]
+    fn test_execute_plan_update_metadata_keeps_content() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let src = tempfile::tempdir().expect("create src tempdir");
+        let dst = tempfile::tempdir().expect("create dst tempdir");
+        let config = config_for(&src, &dst, DeleteMode::None);
+        fs::write(dst.path().join("a.sh"), b"echo hi").expect("write dst file");
+
+        let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000);
+        let entry = FileEntry::new(PathBuf::from("a.sh"), 7, mtime, 0o100755);
+        let mut plan = DiffPlan::new();
+        plan.add_action(SyncAction::UpdateMetadata(entry));
+
+        let stats = execute_plan(&plan, &config, None).expect("apply metadata");
+        assert_eq!(stats.bytes_copied, 0);
+        let metadata = fs::metadata(dst.path().join("a.sh")).expect("stat dst file");
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o755);
+        assert_eq!(metadata.modified().expect("mtime"), mtime);
+        assert_eq!(
+            fs::read(dst.path().join("a.sh")).expect("read dst file"),
+            b"echo hi"
+        );
+    }
+
     #[test]
     fn test_execute_plan_delete_trash() {
         let src = tempfile::tempdir().expect("create src tempdir");
@@ -844,6 +1034,52 @@ mod tests {
         assert!(!dst.path().join("old.txt").exists());
     }
 
+    #[test]
+    fn test_execute_plan_parallel_reordered_plans_clear_type_clashes_first() {
+        use crate::diff::order_plan;
+        use crate::PlanOrder;
+
+        for order in [
+            PlanOrder::DeletesFirst,
+            PlanOrder::LargestFirst,
+            PlanOrder::SmallestFirst,
+            PlanOrder::Locality,
+        ] {
+            let src = tempfile::tempdir().expect("create src tempdir");
+            let dst = tempfile::tempdir().expect("create dst tempdir");
+            let mut config = config_for(&src, &dst, DeleteMode::Permanent);
+            config.threads = 4;
+
+            fs::create_dir_all(src.path().join("was_file")).expect("create source dir");
+            fs::write(src.path().join("was_file/inner.txt"), b"inner").expect("write inner");
+            fs::write(src.path().join("was_dir"), b"file now").expect("write source file");
+            fs::write(src.path().join("z.txt"), b"z").expect("write other source");
+            fs::write(dst.path().join("was_file"), b"old").expect("write blocking file");
+            fs::create_dir_all(dst.path().join("was_dir")).expect("create blocking dir");
+            fs::write(dst.path().join("was_dir/old.txt"), b"old").expect("write old file");
+
+            let mut plan = DiffPlan::new();
+            plan.add_action(SyncAction::Delete(PathBuf::from("was_dir")));
+            plan.add_action(SyncAction::CopyNew(entry("was_dir", 8)));
+            plan.add_action(SyncAction::Delete(PathBuf::from("was_file")));
+            plan.add_action(SyncAction::CopyNew(entry("was_file/inner.txt", 5)));
+            plan.add_action(SyncAction::CopyNew(entry("z.txt", 1)));
+            order_plan(&mut plan, order, src.path());
+
+            let stats = execute_plan_parallel(&plan, &config, None)
+                .unwrap_or_else(|err| panic!("{:?} failed: {}", order, err));
+            assert_eq!(stats.completed_actions, 5, "{:?}", order);
+            assert_eq!(
+                fs::read(dst.path().join("was_dir")).expect("read replaced file"),
+                b"file now"
+            );
+            assert_eq!(
+                fs::read(dst.path().join("was_file/inner.txt")).expect("read nested file"),
+                b"inner"
+            );
+        }
+    }
+
     #[test]
     fn test_execute_plan_parallel_continues_on_error() {
         let src = tempfile::tempdir().expect("create src tempdir");
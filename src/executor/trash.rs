@@ -10,7 +10,7 @@ use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{Error, ErrorKind};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 /// Represents a single deleted file in the trash
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,10 +143,172 @@ pub fn move_to_trash(
     Ok(())
 }
 
-fn resolve_unique_trash_path(
-    trash_root: &Path,
-    relative_path: &Path,
-) -> (std::path::PathBuf, std::path::PathBuf) {
+/// A timestamped snapshot directory under `.kopy_trash/`.
+#[derive(Debug, Clone)]
+pub struct TrashSnapshot {
+    /// Snapshot directory name (the deletion timestamp)
+    pub name: String,
+    /// Absolute path of the snapshot directory
+    pub path: PathBuf,
+    /// Parsed MANIFEST.json (empty if the snapshot has none)
+    pub manifest: TrashManifest,
+}
+
+impl TrashSnapshot {
+    /// Total size in bytes of the files recorded in the manifest
+    pub fn total_size(&self) -> u64 {
+        self.manifest.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// Outcome of [`restore_trash_snapshot`].
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    /// Original paths moved back into place
+    pub restored: Vec<PathBuf>,
+    /// Original paths left in the trash (occupied, missing, or unsafe)
+    pub skipped: Vec<PathBuf>,
+}
+
+/// List trash snapshots of a destination, oldest first.
+///
+/// Returns an empty list when the destination has no `.kopy_trash` directory.
+///
+/// # Example
+/// ```no_run
+/// use kopy::executor::trash::list_trash_snapshots;
+/// use std::path::Path;
+///
+/// for snapshot in list_trash_snapshots(Path::new("/backup"))? {
+///     println!("{} ({} files)", snapshot.name, snapshot.manifest.files.len());
+/// }
+/// # Ok::<(), kopy::types::KopyError>(())
+/// ```
+pub fn list_trash_snapshots(dest_root: &Path) -> Result<Vec<TrashSnapshot>, KopyError> {
+    let trash_root = dest_root.join(".kopy_trash");
+    let entries = match fs::read_dir(&trash_root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(map_file_error(&trash_root, e)),
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| map_file_error(&trash_root, e))?;
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        snapshots.push(TrashSnapshot {
+            name: entry.file_name().to_string_lossy().to_string(),
+            manifest: read_manifest(&path.join("MANIFEST.json"))?,
+            path,
+        });
+    }
+
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(snapshots)
+}
+
+/// Move the files of one trash snapshot back to their original locations.
+///
+/// Files whose original path is occupied are left in the trash unless
+/// `overwrite` is set. The manifest is rewritten to list only the files that
+/// remain, and the snapshot directory is removed once it is fully restored.
+///
+/// # Errors
+/// Returns `KopyError::Config` if the snapshot does not exist.
+pub fn restore_trash_snapshot(
+    dest_root: &Path,
+    snapshot: &str,
+    overwrite: bool,
+) -> Result<RestoreReport, KopyError> {
+    let trash_root = dest_root.join(".kopy_trash");
+    let snapshot_dir = trash_root.join(snapshot);
+    if !is_plain_relative(Path::new(snapshot))
+        || Path::new(snapshot).components().count() != 1
+        || !snapshot_dir.is_dir()
+    {
+        return Err(KopyError::Config(format!(
+            "Trash snapshot not found: {}",
+            snapshot
+        )));
+    }
+
+    let manifest_path = snapshot_dir.join("MANIFEST.json");
+    let manifest = read_manifest(&manifest_path)?;
+    let mut remaining = TrashManifest::new();
+    let mut report = RestoreReport::default();
+
+    for file in manifest.files {
+        let original = PathBuf::from(&file.original_path);
+        let trashed = Path::new(&file.trash_path);
+        if !is_plain_relative(&original) || !is_plain_relative(trashed) {
+            report.skipped.push(original);
+            remaining.add_file(file);
+            continue;
+        }
+
+        let source = snapshot_dir.join(trashed);
+        let target = dest_root.join(&original);
+        if fs::symlink_metadata(&source).is_err() {
+            report.skipped.push(original);
+            remaining.add_file(file);
+            continue;
+        }
+        if let Ok(existing) = fs::symlink_metadata(&target) {
+            if !overwrite {
+                report.skipped.push(original);
+                remaining.add_file(file);
+                continue;
+            }
+            let removed = if existing.is_dir() {
+                fs::remove_dir_all(&target)
+            } else {
+                fs::remove_file(&target)
+            };
+            removed.map_err(|e| map_file_error(&target, e))?;
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| map_file_error(parent, e))?;
+        }
+        fs::rename(&source, &target).map_err(|e| map_file_error(&source, e))?;
+        report.restored.push(original);
+    }
+
+    if remaining.files.is_empty() {
+        fs::remove_dir_all(&snapshot_dir).map_err(|e| map_file_error(&snapshot_dir, e))?;
+        // Leave no empty trash root behind; a non-empty one is kept as-is.
+        let _ = fs::remove_dir(&trash_root);
+    } else {
+        let manifest_json = serde_json::to_string_pretty(&remaining).map_err(|e| {
+            KopyError::Validation(format!("Failed to serialize MANIFEST.json: {}", e))
+        })?;
+        fs::write(&manifest_path, manifest_json).map_err(|e| map_file_error(&manifest_path, e))?;
+    }
+
+    Ok(report)
+}
+
+fn read_manifest(manifest_path: &Path) -> Result<TrashManifest, KopyError> {
+    match fs::read_to_string(manifest_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| KopyError::Validation(format!("Failed to parse MANIFEST.json: {}", e))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(TrashManifest::new()),
+        Err(e) => Err(map_file_error(manifest_path, e)),
+    }
+}
+
+/// Manifest paths must stay inside their root: relative and free of `..`.
+fn is_plain_relative(path: &Path) -> bool {
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn resolve_unique_trash_path(trash_root: &Path, relative_path: &Path) -> (PathBuf, PathBuf) {
     let candidate_relative = relative_path.to_path_buf();
     let candidate_absolute = trash_root.join(&candidate_relative);
     if !candidate_absolute.exists() {
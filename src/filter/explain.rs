@@ -0,0 +1,761 @@
+//! Per-layer explanation of why a path is included or excluded
+//!
+//! Mirrors the filtering done by the scanners: ignore files read by
+//! `ignore::WalkBuilder` (in its precedence order), then ordered filter
+//! rules, then `--exclude`/`--include` patterns.
+
+use super::{FilterRules, RuleSource};
+use crate::config::IgnoreSettings;
+use crate::types::KopyError;
+use crate::Config;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::{Component, Path, PathBuf};
+
+/// One source of include/exclude decisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterLayer {
+    /// `.kopyignore` and other custom per-directory ignore files
+    CustomIgnore,
+    /// `.ignore` files
+    DotIgnore,
+    /// `.gitignore` files inside a git repository
+    GitIgnore,
+    /// `.git/info/exclude` of the enclosing repository
+    GitExclude,
+    /// `core.excludesFile` from the git configuration
+    GitGlobal,
+    /// Hidden-entry skipping (only applies when no ignore file matched)
+    Hidden,
+    /// Ordered `--filter` / `--exclude-from` / `--include-from` rules
+    FilterRules,
+    /// `--exclude` / `--include` patterns
+    Patterns,
+}
+
+impl FilterLayer {
+    /// Layers in evaluation order. Among the ignore-file layers, the first
+    /// one with a match wins, as in `ignore::WalkBuilder`.
+    pub const ALL: [FilterLayer; 8] = [
+        FilterLayer::CustomIgnore,
+        FilterLayer::DotIgnore,
+        FilterLayer::GitIgnore,
+        FilterLayer::GitExclude,
+        FilterLayer::GitGlobal,
+        FilterLayer::Hidden,
+        FilterLayer::FilterRules,
+        FilterLayer::Patterns,
+    ];
+
+    /// Short human-readable name.
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterLayer::CustomIgnore => ".kopyignore/custom",
+            FilterLayer::DotIgnore => ".ignore",
+            FilterLayer::GitIgnore => ".gitignore",
+            FilterLayer::GitExclude => ".git/info/exclude",
+            FilterLayer::GitGlobal => "global git excludes",
+            FilterLayer::Hidden => "hidden files",
+            FilterLayer::FilterRules => "filter rules",
+            FilterLayer::Patterns => "--exclude/--include",
+        }
+    }
+
+    fn is_ignore_file(&self) -> bool {
+        !matches!(self, FilterLayer::FilterRules | FilterLayer::Patterns)
+    }
+
+    fn is_git(&self) -> bool {
+        matches!(
+            self,
+            FilterLayer::GitIgnore | FilterLayer::GitExclude | FilterLayer::GitGlobal
+        )
+    }
+
+    fn is_enabled(&self, settings: &IgnoreSettings) -> bool {
+        match self {
+            FilterLayer::CustomIgnore => !settings.custom_filenames().is_empty(),
+            FilterLayer::DotIgnore => settings.dot_ignore,
+            FilterLayer::GitIgnore => settings.gitignore,
+            FilterLayer::GitExclude => settings.git_exclude,
+            FilterLayer::GitGlobal => settings.git_global,
+            FilterLayer::Hidden => settings.skip_hidden,
+            FilterLayer::FilterRules | FilterLayer::Patterns => true,
+        }
+    }
+}
+
+/// The rule that decided a layer's verdict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleHit {
+    /// Rule text as written.
+    pub rule: String,
+    /// File and line, or command-line flag, that declared the rule.
+    pub source: RuleSource,
+    /// Path the rule matched: the path itself or an excluded ancestor directory.
+    pub matched_path: PathBuf,
+}
+
+/// What one layer says about a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayerVerdict {
+    /// Layer does not apply, with the reason.
+    Inactive(&'static str),
+    /// No rule in this layer matched.
+    NoMatch,
+    /// A rule explicitly included the path.
+    Include(RuleHit),
+    /// A rule excluded the path or one of its ancestors.
+    Exclude(RuleHit),
+}
+
+/// Verdict of a single layer.
+#[derive(Debug, Clone)]
+pub struct LayerReport {
+    pub layer: FilterLayer,
+    pub verdict: LayerVerdict,
+}
+
+/// Full explanation for one path.
+#[derive(Debug, Clone)]
+pub struct FilterExplanation {
+    /// Path relative to the scan root.
+    pub relative_path: PathBuf,
+    /// Path is a directory.
+    pub is_dir: bool,
+    /// Path exists on disk (missing paths are evaluated as files).
+    pub exists: bool,
+    /// Every layer's own verdict, in evaluation order.
+    pub layers: Vec<LayerReport>,
+    /// Layer whose exclusion removes the path from the scan, if any.
+    pub excluded_by: Option<FilterLayer>,
+}
+
+impl FilterExplanation {
+    /// True when a scan of the root would include the path.
+    pub fn is_included(&self) -> bool {
+        self.excluded_by.is_none()
+    }
+
+    /// Verdict of one layer.
+    pub fn verdict(&self, layer: FilterLayer) -> Option<&LayerVerdict> {
+        self.layers
+            .iter()
+            .find(|report| report.layer == layer)
+            .map(|report| &report.verdict)
+    }
+}
+
+/// Explain how the scanners treat `path` under `root`.
+///
+/// `path` may be relative to `root`, relative to the current directory, or
+/// absolute. Ignore settings, filter rules and patterns are taken from `config`.
+///
+/// # Errors
+/// Returns `KopyError::Config` when `root` is not a directory, `path` lies
+/// outside it, or the configured rules are invalid.
+pub fn explain_path(
+    root: &Path,
+    path: &Path,
+    config: &Config,
+) -> Result<FilterExplanation, KopyError> {
+    if !root.is_dir() {
+        return Err(KopyError::Config(format!(
+            "Root must be an existing directory: {}",
+            root.display()
+        )));
+    }
+    let canonical_root = root.canonicalize()?;
+    let relative_path = resolve_relative(root, &canonical_root, path)?;
+
+    let full_path = canonical_root.join(&relative_path);
+    let metadata = std::fs::symlink_metadata(&full_path).ok();
+    let exists = metadata.is_some();
+    let is_dir = metadata.is_some_and(|m| m.is_dir());
+
+    let rules = FilterRules::from_config(config)?.bind(&canonical_root);
+    let exclude_patterns = compile_excludes(config)?;
+    let include_patterns = compile("--include", &config.include_patterns)?;
+
+    let settings = &config.ignore_settings_for(root);
+    let (global, _) = Gitignore::global();
+    let in_git_repo = full_path
+        .parent()
+        .is_some_and(|dir| in_git_repo(dir, settings));
+
+    // Ignore-file layers: walk the path one component at a time, as the
+    // walker does. An ignored ancestor directory is never descended into.
+    let mut ignore_verdicts: Vec<(FilterLayer, LayerVerdict)> = FilterLayer::ALL
+        .iter()
+        .filter(|layer| layer.is_ignore_file())
+        .map(|layer| {
+            let verdict = if !layer.is_enabled(settings) {
+                LayerVerdict::Inactive("disabled")
+            } else if layer.is_git() && !in_git_repo {
+                LayerVerdict::Inactive("not inside a git repository")
+            } else {
+                LayerVerdict::NoMatch
+            };
+            (*layer, verdict)
+        })
+        .collect();
+    let mut excluded_by = None;
+
+    let components: Vec<_> = relative_path.components().collect();
+    for depth in 1..=components.len() {
+        let prefix: PathBuf = components[..depth].iter().collect();
+        let is_last = depth == components.len();
+        let prefix_is_dir = !is_last || is_dir;
+        let mut matches =
+            ignore_file_matches(&canonical_root, &prefix, prefix_is_dir, &global, settings);
+
+        // Hidden entries are skipped only when no ignore file matched them.
+        let decided = matches
+            .iter()
+            .any(|(_, verdict)| !matches!(verdict, LayerVerdict::NoMatch));
+        let hidden = prefix
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if settings.skip_hidden && hidden && !decided {
+            matches.push((
+                FilterLayer::Hidden,
+                LayerVerdict::Exclude(RuleHit {
+                    rule: ".*".to_string(),
+                    source: RuleSource::Argument {
+                        flag: "--skip-hidden",
+                        position: 1,
+                    },
+                    matched_path: prefix.clone(),
+                }),
+            ));
+        } else {
+            matches.push((FilterLayer::Hidden, LayerVerdict::NoMatch));
+        }
+
+        // Combined walker decision: first layer with any match wins.
+        if excluded_by.is_none() {
+            if let Some((layer, LayerVerdict::Exclude(_))) = matches
+                .iter()
+                .find(|(_, verdict)| !matches!(verdict, LayerVerdict::NoMatch))
+            {
+                excluded_by = Some(*layer);
+            }
+        }
+
+        for ((_, current), (_, found)) in ignore_verdicts.iter_mut().zip(matches) {
+            if matches!(
+                current,
+                LayerVerdict::Exclude(_) | LayerVerdict::Inactive(_)
+            ) {
+                continue;
+            }
+            match found {
+                LayerVerdict::Exclude(hit) => *current = LayerVerdict::Exclude(hit),
+                LayerVerdict::Include(hit) if is_last => *current = LayerVerdict::Include(hit),
+                _ => {}
+            }
+        }
+    }
+
+    let rules_verdict = if !rules.is_active() {
+        LayerVerdict::Inactive("no filter rules configured")
+    } else {
+        match rules.decide(&relative_path, is_dir) {
+            None => LayerVerdict::NoMatch,
+            Some(decision) => {
+                let hit = RuleHit {
+                    rule: decision.rule.describe(),
+                    source: decision.rule.source.clone(),
+                    matched_path: decision.matched_path.clone(),
+                };
+                if decision.is_exclude() {
+                    LayerVerdict::Exclude(hit)
+                } else {
+                    LayerVerdict::Include(hit)
+                }
+            }
+        }
+    };
+
+    let patterns_verdict = if exclude_patterns.is_empty() && include_patterns.is_empty() {
+        LayerVerdict::Inactive("no --exclude/--include patterns given")
+    } else {
+        pattern_verdict(&relative_path, &exclude_patterns, &include_patterns)
+    };
+
+    if excluded_by.is_none() && matches!(rules_verdict, LayerVerdict::Exclude(_)) {
+        excluded_by = Some(FilterLayer::FilterRules);
+    }
+    if excluded_by.is_none() && matches!(patterns_verdict, LayerVerdict::Exclude(_)) {
+        excluded_by = Some(FilterLayer::Patterns);
+    }
+
+    let mut layers: Vec<LayerReport> = ignore_verdicts
+        .into_iter()
+        .map(|(layer, verdict)| LayerReport { layer, verdict })
+        .collect();
+    layers.push(LayerReport {
+        layer: FilterLayer::FilterRules,
+        verdict: rules_verdict,
+    });
+    layers.push(LayerReport {
+        layer: FilterLayer::Patterns,
+        verdict: patterns_verdict,
+    });
+
+    Ok(FilterExplanation {
+        relative_path,
+        is_dir,
+        exists,
+        layers,
+        excluded_by,
+    })
+}
+
+/// Match `prefix` against every ignore-file layer, in precedence order.
+///
+/// Follows `ignore`'s lookup: for each layer the deepest directory with a
+/// matching rule wins, and `.gitignore`/`.git/info/exclude` are only read up
+/// to the nearest enclosing repository root.
+fn ignore_file_matches(
+    root: &Path,
+    prefix: &Path,
+    is_dir: bool,
+    global: &Gitignore,
+    settings: &IgnoreSettings,
+) -> Vec<(FilterLayer, LayerVerdict)> {
+    let full_path = root.join(prefix);
+    let parent = full_path.parent().unwrap_or(root);
+    let in_git_repo = in_git_repo(parent, settings);
+    let custom_names = settings.custom_filenames();
+
+    let mut custom_ignore = LayerVerdict::NoMatch;
+    let mut dot_ignore = LayerVerdict::NoMatch;
+    let mut git_ignore = LayerVerdict::NoMatch;
+    let mut git_exclude = LayerVerdict::NoMatch;
+    let mut saw_git = false;
+
+    for dir in parent.ancestors() {
+        if custom_ignore == LayerVerdict::NoMatch {
+            let files: Vec<PathBuf> = custom_names.iter().map(|name| dir.join(name)).collect();
+            custom_ignore = match_files(dir, &files, root, &full_path, is_dir);
+        }
+        if settings.dot_ignore && dot_ignore == LayerVerdict::NoMatch {
+            dot_ignore = match_files(dir, &[dir.join(".ignore")], root, &full_path, is_dir);
+        }
+        if settings.gitignore && in_git_repo && !saw_git && git_ignore == LayerVerdict::NoMatch {
+            git_ignore = match_files(dir, &[dir.join(".gitignore")], root, &full_path, is_dir);
+        }
+        if settings.git_exclude && in_git_repo && !saw_git && git_exclude == LayerVerdict::NoMatch {
+            let exclude_file = dir.join(".git").join("info").join("exclude");
+            git_exclude = match_files(dir, &[exclude_file], root, &full_path, is_dir);
+        }
+        saw_git = saw_git || (in_git_repo && has_git_dir(dir));
+    }
+
+    let git_global = if settings.git_global && in_git_repo {
+        verdict_from_match(global.matched(&full_path, is_dir), root, &full_path)
+    } else {
+        LayerVerdict::NoMatch
+    };
+
+    vec![
+        (FilterLayer::CustomIgnore, custom_ignore),
+        (FilterLayer::DotIgnore, dot_ignore),
+        (FilterLayer::GitIgnore, git_ignore),
+        (FilterLayer::GitExclude, git_exclude),
+        (FilterLayer::GitGlobal, git_global),
+    ]
+}
+
+/// Git-based layers only apply inside a repository. As in `ignore`, the
+/// repository is only detected while `.gitignore` or `.git/info/exclude`
+/// reading is enabled.
+fn in_git_repo(dir: &Path, settings: &IgnoreSettings) -> bool {
+    (settings.gitignore || settings.git_exclude) && dir.ancestors().any(has_git_dir)
+}
+
+/// Match against the ignore files of one directory; later files take precedence.
+fn match_files(
+    dir: &Path,
+    files: &[PathBuf],
+    root: &Path,
+    full_path: &Path,
+    is_dir: bool,
+) -> LayerVerdict {
+    let existing: Vec<&PathBuf> = files.iter().filter(|file| file.is_file()).collect();
+    if existing.is_empty() {
+        return LayerVerdict::NoMatch;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    for file in existing {
+        // Unparsable lines are skipped, as the walker does.
+        let _ = builder.add(file);
+    }
+    let Ok(matcher) = builder.build() else {
+        return LayerVerdict::NoMatch;
+    };
+    verdict_from_match(matcher.matched(full_path, is_dir), root, full_path)
+}
+
+fn verdict_from_match(
+    matched: Match<&ignore::gitignore::Glob>,
+    root: &Path,
+    full_path: &Path,
+) -> LayerVerdict {
+    let hit = |glob: &ignore::gitignore::Glob| {
+        let file = glob.from().map(Path::to_path_buf).unwrap_or_default();
+        RuleHit {
+            rule: glob.original().to_string(),
+            source: RuleSource::File {
+                line: find_line(&file, glob.original()),
+                path: file,
+            },
+            matched_path: full_path
+                .strip_prefix(root)
+                .unwrap_or(full_path)
+                .to_path_buf(),
+        }
+    };
+    match matched {
+        Match::None => LayerVerdict::NoMatch,
+        Match::Ignore(glob) => LayerVerdict::Exclude(hit(glob)),
+        Match::Whitelist(glob) => LayerVerdict::Include(hit(glob)),
+    }
+}
+
+/// Line number (1-based) of the last line declaring `original`; 0 if not found.
+///
+/// Later lines win in ignore files, so the last identical line is the one that matched.
+fn find_line(file: &Path, original: &str) -> usize {
+    let Ok(contents) = std::fs::read_to_string(file) else {
+        return 0;
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = if line.ends_with("\\ ") {
+                line
+            } else {
+                line.trim_end()
+            };
+            line == original
+        })
+        .map(|(idx, _)| idx + 1)
+        .last()
+        .unwrap_or(0)
+}
+
+fn has_git_dir(dir: &Path) -> bool {
+    dir.join(".git").exists() || dir.join(".jj").exists()
+}
+
+fn pattern_verdict(
+    relative_path: &Path,
+    exclude_patterns: &[(RuleSource, glob::Pattern)],
+    include_patterns: &[(RuleSource, glob::Pattern)],
+) -> LayerVerdict {
+    let hit = |(source, pattern): &(RuleSource, glob::Pattern)| RuleHit {
+        rule: pattern.as_str().to_string(),
+        source: source.clone(),
+        matched_path: relative_path.to_path_buf(),
+    };
+
+    let Some(exclude) = exclude_patterns
+        .iter()
+        .find(|(_, pattern)| pattern.matches_path(relative_path))
+    else {
+        return LayerVerdict::NoMatch;
+    };
+    match include_patterns
+        .iter()
+        .find(|(_, pattern)| pattern.matches_path(relative_path))
+    {
+        Some(include) => LayerVerdict::Include(hit(include)),
+        None => LayerVerdict::Exclude(hit(exclude)),
+    }
+}
+
+/// Preset patterns first, then `--exclude` patterns, each tagged with its origin.
+fn compile_excludes(config: &Config) -> Result<Vec<(RuleSource, glob::Pattern)>, KopyError> {
+    let mut compiled = Vec::new();
+    for preset in &config.presets {
+        for pattern in preset.patterns() {
+            let source = RuleSource::Preset {
+                name: preset.name(),
+            };
+            compiled.push((source, compile_pattern(pattern)?));
+        }
+    }
+    compiled.extend(compile("--exclude", &config.exclude_patterns)?);
+    Ok(compiled)
+}
+
+fn compile(
+    flag: &'static str,
+    patterns: &[String],
+) -> Result<Vec<(RuleSource, glob::Pattern)>, KopyError> {
+    patterns
+        .iter()
+        .enumerate()
+        .map(|(idx, pattern)| {
+            let source = RuleSource::Argument {
+                flag,
+                position: idx + 1,
+            };
+            Ok((source, compile_pattern(pattern)?))
+        })
+        .collect()
+}
+
+fn compile_pattern(pattern: &str) -> Result<glob::Pattern, KopyError> {
+    glob::Pattern::new(pattern)
+        .map_err(|e| KopyError::Config(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+/// Turn a user-supplied path into a clean path relative to the scan root.
+fn resolve_relative(root: &Path, canonical_root: &Path, path: &Path) -> Result<PathBuf, KopyError> {
+    let relative = if let Ok(rest) = path.strip_prefix(root) {
+        rest.to_path_buf()
+    } else if path.is_relative() && canonical_root.join(path).symlink_metadata().is_ok() {
+        path.to_path_buf()
+    } else if path.is_absolute() || path.symlink_metadata().is_ok() {
+        let absolute = canonicalize_parent(path)?;
+        absolute
+            .strip_prefix(canonical_root)
+            .map(Path::to_path_buf)
+            .map_err(|_| {
+                KopyError::Config(format!(
+                    "Path {} is not inside root {}",
+                    path.display(),
+                    root.display()
+                ))
+            })?
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut clean = PathBuf::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(KopyError::Config(format!(
+                    "Path {} must not leave the root directory",
+                    path.display()
+                )))
+            }
+        }
+    }
+    if clean.as_os_str().is_empty() {
+        return Err(KopyError::Config(
+            "Path must name an entry below the root, not the root itself".to_string(),
+        ));
+    }
+    Ok(clean)
+}
+
+/// Canonicalize the parent directory and keep the final component as-is, so
+/// symlinks and missing files are explained rather than resolved.
+fn canonicalize_parent(path: &Path) -> Result<PathBuf, KopyError> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => Ok(parent.canonicalize()?.join(name)),
+        _ => Ok(absolute),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn explain(root: &Path, path: &str, config: &Config) -> FilterExplanation {
+        explain_path(root, Path::new(path), config).expect("explain path")
+    }
+
+    fn hit_of(verdict: Option<&LayerVerdict>) -> &RuleHit {
+        match verdict {
+            Some(LayerVerdict::Include(hit)) | Some(LayerVerdict::Exclude(hit)) => hit,
+            other => panic!("Expected a matching rule, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_gitignore_rule_reports_file_and_line() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git")).expect("create .git");
+        fs::create_dir_all(root.join("build")).expect("create build");
+        fs::write(root.join(".gitignore"), "# outputs\n*.log\nbuild/\n").expect("write gitignore");
+        fs::write(root.join("build/app"), "bin").expect("write app");
+
+        let result = explain(root, "build/app", &Config::default());
+        assert!(!result.is_included());
+        assert_eq!(result.excluded_by, Some(FilterLayer::GitIgnore));
+
+        let hit = hit_of(result.verdict(FilterLayer::GitIgnore));
+        assert_eq!(hit.rule, "build/");
+        assert_eq!(hit.matched_path, PathBuf::from("build"));
+        let canonical_root = root.canonicalize().expect("canonical root");
+        assert_eq!(
+            hit.source,
+            RuleSource::File {
+                path: canonical_root.join(".gitignore"),
+                line: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_git_layers_inactive_outside_repository() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::write(root.join(".gitignore"), "*.log\n").expect("write gitignore");
+        fs::write(root.join("app.log"), "log").expect("write log");
+
+        let result = explain(root, "app.log", &Config::default());
+        assert!(result.is_included());
+        assert!(matches!(
+            result.verdict(FilterLayer::GitIgnore),
+            Some(LayerVerdict::Inactive(_))
+        ));
+    }
+
+    #[test]
+    fn test_kopyignore_whitelist_overrides_gitignore() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git")).expect("create .git");
+        fs::write(root.join(".gitignore"), ".env\n").expect("write gitignore");
+        fs::write(root.join(".kopyignore"), "!.env\n").expect("write kopyignore");
+        fs::write(root.join(".env"), "SECRET=1").expect("write env");
+
+        let result = explain(root, ".env", &Config::default());
+        assert!(result.is_included());
+        assert!(matches!(
+            result.verdict(FilterLayer::CustomIgnore),
+            Some(LayerVerdict::Include(_))
+        ));
+        assert!(matches!(
+            result.verdict(FilterLayer::GitIgnore),
+            Some(LayerVerdict::Exclude(_))
+        ));
+    }
+
+    #[test]
+    fn test_git_info_exclude_layer() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git/info")).expect("create .git/info");
+        fs::write(root.join(".git/info/exclude"), "secret.txt\n").expect("write exclude");
+        fs::write(root.join("secret.txt"), "s").expect("write secret");
+
+        let result = explain(root, "secret.txt", &Config::default());
+        assert_eq!(result.excluded_by, Some(FilterLayer::GitExclude));
+        assert_eq!(
+            hit_of(result.verdict(FilterLayer::GitExclude)).source,
+            RuleSource::File {
+                path: root.canonicalize().unwrap().join(".git/info/exclude"),
+                line: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_cli_patterns_and_filter_rules() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::write(root.join("debug.log"), "log").expect("write log");
+
+        let config = Config {
+            exclude_patterns: vec!["*.tmp".to_string(), "*.log".to_string()],
+            filter_rules: vec!["+ *.log".to_string()],
+            ..Config::default()
+        };
+        let result = explain(root, "debug.log", &config);
+
+        assert_eq!(result.excluded_by, Some(FilterLayer::Patterns));
+        let hit = hit_of(result.verdict(FilterLayer::Patterns));
+        assert_eq!(hit.rule, "*.log");
+        assert_eq!(hit.source.to_string(), "--exclude #2");
+        assert!(matches!(
+            result.verdict(FilterLayer::FilterRules),
+            Some(LayerVerdict::Include(_))
+        ));
+    }
+
+    #[test]
+    fn test_preset_patterns_report_their_preset() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join("target/debug")).expect("create target");
+        fs::write(root.join("target/debug/app"), "bin").expect("write binary");
+        fs::write(root.join("target/debug/keep.txt"), "keep").expect("write keep");
+
+        let config = Config {
+            presets: vec![crate::config::Preset::Rust],
+            include_patterns: vec!["**/keep.txt".to_string()],
+            ..Config::default()
+        };
+        let result = explain(root, "target/debug/app", &config);
+        assert_eq!(result.excluded_by, Some(FilterLayer::Patterns));
+        let hit = hit_of(result.verdict(FilterLayer::Patterns));
+        assert_eq!(hit.rule, "**/target/**");
+        assert_eq!(hit.source.to_string(), "--preset rust");
+
+        let result = explain(root, "target/debug/keep.txt", &config);
+        assert!(result.is_included());
+    }
+
+    #[test]
+    fn test_disabled_layers_and_hidden_files() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git")).expect("create .git");
+        fs::write(root.join(".gitignore"), ".env\n").expect("write gitignore");
+        fs::write(root.join(".env"), "SECRET=1").expect("write env");
+
+        let config = Config {
+            ignore: IgnoreSettings {
+                gitignore: false,
+                skip_hidden: true,
+                ..IgnoreSettings::default()
+            },
+            ..Config::default()
+        };
+        let result = explain(root, ".env", &config);
+
+        assert_eq!(
+            result.verdict(FilterLayer::GitIgnore),
+            Some(&LayerVerdict::Inactive("disabled"))
+        );
+        assert_eq!(result.excluded_by, Some(FilterLayer::Hidden));
+    }
+
+    #[test]
+    fn test_path_relative_to_cwd_or_root() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path().join("src");
+        fs::create_dir_all(root.join("a")).expect("create dirs");
+        fs::write(root.join("a/file.txt"), "x").expect("write file");
+
+        let by_root = explain(&root, "a/file.txt", &Config::default());
+        let joined = explain_path(&root, &root.join("a/file.txt"), &Config::default())
+            .expect("explain joined path");
+        assert_eq!(by_root.relative_path, PathBuf::from("a/file.txt"));
+        assert_eq!(joined.relative_path, by_root.relative_path);
+        assert!(by_root.exists);
+        assert!(explain(&root, "a", &Config::default()).is_dir);
+
+        assert!(explain_path(&root, temp.path(), &Config::default()).is_err());
+        assert!(explain_path(&root, Path::new("../x"), &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Ordered include/exclude filter rules
+//!
+//! Rules follow rsync's model: they are checked in order and the first
+//! matching rule decides whether a path is included (`+`) or excluded (`-`).
+//! An excluded directory excludes everything below it. Rules can come from
+//! `--filter`, `--include-from`/`--exclude-from` files, and per-directory
+//! merge files (`: .kopy-filter`) read while scanning. Size and age limits
+//! live in [`select`] and only apply to source files.
+
+mod explain;
+mod rule;
+mod select;
+mod tree;
+
+pub use explain::{
+    explain_path, FilterExplanation, FilterLayer, LayerReport, LayerVerdict, RuleHit,
+};
+pub use rule::{FilterAction, FilterRule, RuleSource};
+pub use select::{ByteSize, SelectionFilter, TimeBound};
+pub use tree::{FilterRules, RuleMatch, TreeFilter};
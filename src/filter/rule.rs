@@ -0,0 +1,357 @@
+//! Single filter rules and rule-line parsing
+
+use crate::types::KopyError;
+use globset::{GlobBuilder, GlobMatcher};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What a matching rule does with a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Keep the path (`+`)
+    Include,
+    /// Drop the path, and everything below it for directories (`-`)
+    Exclude,
+}
+
+/// Where a rule was declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSource {
+    /// Value of a command-line flag; `position` counts occurrences of that flag from 1.
+    Argument { flag: &'static str, position: usize },
+    /// Line of a rules file (`--exclude-from`, `--include-from`, merge files).
+    File { path: PathBuf, line: usize },
+    /// Pattern of a built-in `--preset`.
+    Preset { name: &'static str },
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSource::Argument { flag, position } => write!(f, "{} #{}", flag, position),
+            RuleSource::File { path, line } => write!(f, "{}:{}", path.display(), line),
+            RuleSource::Preset { name } => write!(f, "--preset {}", name),
+        }
+    }
+}
+
+/// One ordered include/exclude rule.
+///
+/// Pattern syntax follows rsync/gitignore conventions:
+/// - a leading `/` anchors the pattern at the rule's base directory
+/// - a `/` anywhere else also anchors it; otherwise it matches the file name at any depth
+/// - a trailing `/` restricts the rule to directories
+/// - `*` does not cross `/`, `**` does
+#[derive(Debug, Clone)]
+pub struct FilterRule {
+    /// Include or exclude.
+    pub action: FilterAction,
+    /// Pattern as written, without the action prefix.
+    pub pattern: String,
+    /// Pattern is matched against the path below `base` instead of the file name.
+    pub anchored: bool,
+    /// Rule only matches directories.
+    pub dir_only: bool,
+    /// Directory (relative to the scan root) the rule is scoped to.
+    pub base: PathBuf,
+    /// Declaration site, for diagnostics.
+    pub source: RuleSource,
+    matcher: GlobMatcher,
+}
+
+impl FilterRule {
+    /// Compile a rule.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for empty or invalid glob patterns.
+    pub fn new(
+        action: FilterAction,
+        pattern: &str,
+        base: PathBuf,
+        source: RuleSource,
+    ) -> Result<Self, KopyError> {
+        let mut body = pattern;
+        let dir_only = body.len() > 1 && body.ends_with('/');
+        if dir_only {
+            body = &body[..body.len() - 1];
+        }
+        let mut anchored = false;
+        if let Some(rest) = body.strip_prefix('/') {
+            anchored = true;
+            body = rest;
+        }
+        if body.contains('/') {
+            anchored = true;
+        }
+        if body.is_empty() {
+            return Err(KopyError::Config(format!(
+                "Empty filter pattern ({})",
+                source
+            )));
+        }
+
+        let matcher = GlobBuilder::new(body)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()
+            .map_err(|e| {
+                KopyError::Config(format!(
+                    "Invalid filter pattern '{}' ({}): {}",
+                    pattern, source, e
+                ))
+            })?
+            .compile_matcher();
+
+        Ok(Self {
+            action,
+            pattern: pattern.to_string(),
+            anchored,
+            dir_only,
+            base,
+            source,
+            matcher,
+        })
+    }
+
+    /// Check whether the rule matches a path relative to the scan root.
+    pub fn matches(&self, relative_path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(below_base) = relative_path.strip_prefix(&self.base) else {
+            return false;
+        };
+        if below_base.as_os_str().is_empty() {
+            return false;
+        }
+
+        if self.anchored {
+            self.matcher.is_match(below_base)
+        } else {
+            below_base
+                .file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+
+    /// Rule in `+ pattern` / `- pattern` form.
+    pub fn describe(&self) -> String {
+        let prefix = match self.action {
+            FilterAction::Include => '+',
+            FilterAction::Exclude => '-',
+        };
+        format!("{} {}", prefix, self.pattern)
+    }
+}
+
+/// Result of parsing one rule line.
+#[derive(Debug)]
+pub(crate) enum ParsedLine {
+    /// Blank line or comment.
+    Empty,
+    /// Regular include/exclude rule.
+    Rule(FilterRule),
+    /// `: NAME` / `dir-merge NAME`: read NAME from every directory.
+    DirMerge(String),
+    /// `. FILE` / `merge FILE`: splice rules from FILE here.
+    Merge(PathBuf),
+}
+
+/// Parse one rule line.
+///
+/// Accepted forms: `+ PAT`, `- PAT`, `include PAT`, `exclude PAT`,
+/// `: NAME`, `dir-merge NAME`, `. FILE`, `merge FILE`. Lines starting with
+/// `#` or `;` are comments. Bare patterns are accepted only when
+/// `default_action` is set (as for `--exclude-from`).
+pub(crate) fn parse_rule_line(
+    line: &str,
+    default_action: Option<FilterAction>,
+    base: &Path,
+    source: RuleSource,
+) -> Result<ParsedLine, KopyError> {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    if trimmed.trim().is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+        return Ok(ParsedLine::Empty);
+    }
+
+    let (keyword, rest) = match trimmed.split_once(' ') {
+        Some((keyword, rest)) => (keyword, rest.trim_start()),
+        None => (trimmed, ""),
+    };
+
+    let action = match keyword {
+        "+" | "include" => Some(FilterAction::Include),
+        "-" | "exclude" => Some(FilterAction::Exclude),
+        ":" | "dir-merge" => {
+            return if rest.is_empty() || rest.contains('/') {
+                Err(KopyError::Config(format!(
+                    "dir-merge rule needs a plain file name ({})",
+                    source
+                )))
+            } else {
+                Ok(ParsedLine::DirMerge(rest.to_string()))
+            };
+        }
+        "." | "merge" => {
+            return if rest.is_empty() {
+                Err(KopyError::Config(format!(
+                    "merge rule needs a file path ({})",
+                    source
+                )))
+            } else {
+                Ok(ParsedLine::Merge(PathBuf::from(rest)))
+            };
+        }
+        _ => None,
+    };
+
+    match (action, default_action) {
+        (Some(action), _) => {
+            FilterRule::new(action, rest, base.to_path_buf(), source).map(ParsedLine::Rule)
+        }
+        (None, Some(action)) => FilterRule::new(action, trimmed.trim(), base.to_path_buf(), source)
+            .map(ParsedLine::Rule),
+        (None, None) => Err(KopyError::Config(format!(
+            "Unrecognized filter rule '{}' ({}); expected '+ PATTERN' or '- PATTERN'",
+            trimmed, source
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: FilterAction, pattern: &str) -> FilterRule {
+        FilterRule::new(
+            action,
+            pattern,
+            PathBuf::new(),
+            RuleSource::Argument {
+                flag: "--filter",
+                position: 1,
+            },
+        )
+        .expect("valid rule")
+    }
+
+    #[test]
+    fn test_floating_pattern_matches_name_at_any_depth() {
+        let r = rule(FilterAction::Exclude, "*.o");
+        assert!(!r.anchored);
+        assert!(r.matches(Path::new("main.o"), false));
+        assert!(r.matches(Path::new("a/b/main.o"), false));
+        assert!(!r.matches(Path::new("a/main.c"), false));
+    }
+
+    #[test]
+    fn test_anchored_pattern_matches_from_base_only() {
+        let r = rule(FilterAction::Exclude, "/build");
+        assert!(r.anchored);
+        assert!(r.matches(Path::new("build"), true));
+        assert!(!r.matches(Path::new("src/build"), true));
+
+        let nested = rule(FilterAction::Exclude, "src/*.rs");
+        assert!(nested.anchored);
+        assert!(nested.matches(Path::new("src/main.rs"), false));
+        assert!(!nested.matches(Path::new("src/bin/main.rs"), false));
+    }
+
+    #[test]
+    fn test_double_star_crosses_directories() {
+        let r = rule(FilterAction::Exclude, "/src/**/*.rs");
+        assert!(r.matches(Path::new("src/a/b/lib.rs"), false));
+        assert!(r.matches(Path::new("src/lib.rs"), false));
+    }
+
+    #[test]
+    fn test_dir_only_rule_ignores_files() {
+        let r = rule(FilterAction::Exclude, "cache/");
+        assert!(r.dir_only);
+        assert!(r.matches(Path::new("a/cache"), true));
+        assert!(!r.matches(Path::new("a/cache"), false));
+    }
+
+    #[test]
+    fn test_rule_scoped_to_base_directory() {
+        let r = FilterRule::new(
+            FilterAction::Exclude,
+            "/tmp",
+            PathBuf::from("project"),
+            RuleSource::File {
+                path: PathBuf::from("project/.kopy-filter"),
+                line: 3,
+            },
+        )
+        .expect("valid rule");
+        assert!(r.matches(Path::new("project/tmp"), true));
+        assert!(!r.matches(Path::new("tmp"), true));
+        assert!(!r.matches(Path::new("other/tmp"), true));
+    }
+
+    #[test]
+    fn test_parse_rule_line_forms() {
+        let source = || RuleSource::Argument {
+            flag: "--filter",
+            position: 1,
+        };
+        let base = Path::new("");
+
+        match parse_rule_line("+ *.rs", None, base, source()).unwrap() {
+            ParsedLine::Rule(r) => {
+                assert_eq!(r.action, FilterAction::Include);
+                assert_eq!(r.describe(), "+ *.rs");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_rule_line("exclude target/", None, base, source()).unwrap() {
+            ParsedLine::Rule(r) => assert_eq!(r.action, FilterAction::Exclude),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            parse_rule_line(": .kopy-filter", None, base, source()).unwrap(),
+            ParsedLine::DirMerge(name) if name == ".kopy-filter"
+        ));
+        assert!(matches!(
+            parse_rule_line("merge rules.txt", None, base, source()).unwrap(),
+            ParsedLine::Merge(path) if path == Path::new("rules.txt")
+        ));
+        assert!(matches!(
+            parse_rule_line("# comment", None, base, source()).unwrap(),
+            ParsedLine::Empty
+        ));
+    }
+
+    #[test]
+    fn test_parse_bare_pattern_needs_default_action() {
+        let source = RuleSource::File {
+            path: PathBuf::from("excludes.txt"),
+            line: 4,
+        };
+        let err = parse_rule_line("*.tmp", None, Path::new(""), source.clone()).unwrap_err();
+        assert!(err.to_string().contains("excludes.txt:4"));
+
+        match parse_rule_line("*.tmp", Some(FilterAction::Exclude), Path::new(""), source).unwrap()
+        {
+            ParsedLine::Rule(r) => assert_eq!(r.action, FilterAction::Exclude),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_glob_is_config_error() {
+        let result = FilterRule::new(
+            FilterAction::Exclude,
+            "[abc",
+            PathBuf::new(),
+            RuleSource::Argument {
+                flag: "--filter",
+                position: 2,
+            },
+        );
+        match result {
+            Err(KopyError::Config(msg)) => assert!(msg.contains("--filter #2")),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! Size and age based selection (`--min-size`, `--max-size`, `--newer-than`, `--older-than`)
+//!
+//! Unlike path rules, these look at a file's metadata, so they only apply to
+//! regular files on the source side. A deselected source file is recorded in
+//! the source tree so the planner never deletes its destination copy.
+
+use crate::config::Config;
+use crate::types::FileEntry;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A byte count written as `1048576`, `512K`, `100M`, `4G` or `1T` (binary units).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (digits, unit) = trimmed.split_at(split);
+        let number: u64 = digits
+            .parse()
+            .map_err(|_| format!("invalid size '{}': expected e.g. 500K, 100M or 4G", value))?;
+
+        let shift = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+            "" => 0,
+            "K" => 10,
+            "M" => 20,
+            "G" => 30,
+            "T" => 40,
+            _ => {
+                return Err(format!(
+                    "invalid size unit in '{}': use K, M, G or T",
+                    value
+                ))
+            }
+        };
+        number
+            .checked_mul(1u64 << shift)
+            .map(ByteSize)
+            .ok_or_else(|| format!("size '{}' is too large", value))
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A point in time given either as an age (`30m`, `12h`, `7d`, `2w`, `1d12h`)
+/// or as a UTC date (`2024-05-01` or `2024-05-01T08:30:00`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TimeBound {
+    /// This long before the scan starts.
+    Age(Duration),
+    /// A fixed instant.
+    At(SystemTime),
+}
+
+impl TimeBound {
+    /// The instant this bound refers to for a scan starting at `now`.
+    pub fn resolve(&self, now: SystemTime) -> SystemTime {
+        match self {
+            TimeBound::Age(age) => now.checked_sub(*age).unwrap_or(UNIX_EPOCH),
+            TimeBound::At(instant) => *instant,
+        }
+    }
+}
+
+impl FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        if trimmed.len() >= 10 && trimmed.as_bytes()[4] == b'-' {
+            return parse_utc_date(trimmed).map(TimeBound::At);
+        }
+        parse_age(trimmed).map(TimeBound::Age)
+    }
+}
+
+impl TryFrom<String> for TimeBound {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid age '{}': expected e.g. 90m, 12h, 7d, 2w or a date like 2024-05-01",
+            value
+        )
+    };
+    if value.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        if split == 0 {
+            return Err(invalid());
+        }
+        let number: u64 = rest[..split].parse().map_err(|_| invalid())?;
+        let unit = rest[split..].chars().next().ok_or_else(invalid)?;
+        let seconds = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return Err(invalid()),
+        };
+        total = number
+            .checked_mul(seconds)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        rest = &rest[split + unit.len_utf8()..];
+    }
+    Ok(Duration::from_secs(total))
+}
+
+fn parse_utc_date(value: &str) -> Result<SystemTime, String> {
+    let invalid = || {
+        format!(
+            "invalid date '{}': expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS (UTC)",
+            value
+        )
+    };
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date_parts = date.split('-').map(|part| part.parse::<u32>());
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day)), None) = (
+        date_parts.next(),
+        date_parts.next(),
+        date_parts.next(),
+        date_parts.next(),
+    ) else {
+        return Err(invalid());
+    };
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || day == 0 {
+        return Err(invalid());
+    }
+    if day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    let seconds_of_day = match time {
+        None => 0,
+        Some(time) => {
+            let time = time.trim_end_matches('Z');
+            let parts: Vec<_> = time.split(':').map(|part| part.parse::<u64>()).collect();
+            let values: Vec<u64> = parts
+                .into_iter()
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid())?;
+            let (hours, minutes, seconds) = match values.as_slice() {
+                [h, m] => (*h, *m, 0),
+                [h, m, s] => (*h, *m, *s),
+                _ => return Err(invalid()),
+            };
+            if hours > 23 || minutes > 59 || seconds > 59 {
+                return Err(invalid());
+            }
+            hours * 3_600 + minutes * 60 + seconds
+        }
+    };
+
+    let days = days_since_epoch(year, month, day);
+    Ok(UNIX_EPOCH + Duration::from_secs(days * 86_400 + seconds_of_day))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to the given civil date (proleptic Gregorian).
+fn days_since_epoch(year: u32, month: u32, day: u32) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year } as u64;
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let shifted_month = (month as u64 + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Size and age limits resolved against the start of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SelectionFilter {
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    newer_than: Option<SystemTime>,
+    older_than: Option<SystemTime>,
+}
+
+impl SelectionFilter {
+    /// Resolve the configured limits; relative ages count back from `now`.
+    pub fn from_config(config: &Config, now: SystemTime) -> Self {
+        Self {
+            min_size: config.min_size,
+            max_size: config.max_size,
+            newer_than: config.newer_than.map(|bound| bound.resolve(now)),
+            older_than: config.older_than.map(|bound| bound.resolve(now)),
+        }
+    }
+
+    /// Whether any limit is set.
+    pub fn is_active(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some()
+    }
+
+    /// Check a scanned entry against all limits. Symlinks are always selected.
+    pub fn selects(&self, entry: &FileEntry) -> bool {
+        if entry.is_symlink {
+            return true;
+        }
+        self.min_size.is_none_or(|min| entry.size >= min)
+            && self.max_size.is_none_or(|max| entry.size <= max)
+            && self.newer_than.is_none_or(|bound| entry.mtime > bound)
+            && self.older_than.is_none_or(|bound| entry.mtime < bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(size: u64, mtime_secs: u64) -> FileEntry {
+        FileEntry::new(
+            PathBuf::from("file.bin"),
+            size,
+            UNIX_EPOCH + Duration::from_secs(mtime_secs),
+            0o644,
+        )
+    }
+
+    #[test]
+    fn test_parse_byte_sizes() {
+        assert_eq!("1024".parse::<ByteSize>(), Ok(ByteSize(1024)));
+        assert_eq!("4k".parse::<ByteSize>(), Ok(ByteSize(4096)));
+        assert_eq!("100M".parse::<ByteSize>(), Ok(ByteSize(100 << 20)));
+        assert_eq!("2GiB".parse::<ByteSize>(), Ok(ByteSize(2 << 30)));
+        assert!("10X".parse::<ByteSize>().is_err());
+        assert!("M".parse::<ByteSize>().is_err());
+    }
+
+    #[test]
+    fn test_parse_ages_and_dates() {
+        assert_eq!(
+            "1d12h".parse::<TimeBound>(),
+            Ok(TimeBound::Age(Duration::from_secs(36 * 3_600)))
+        );
+        assert_eq!(
+            "2w".parse::<TimeBound>(),
+            Ok(TimeBound::Age(Duration::from_secs(14 * 86_400)))
+        );
+        assert_eq!(
+            "2024-03-01".parse::<TimeBound>(),
+            Ok(TimeBound::At(
+                UNIX_EPOCH + Duration::from_secs(1_709_251_200)
+            ))
+        );
+        assert_eq!(
+            "2024-03-01T08:30:00".parse::<TimeBound>(),
+            Ok(TimeBound::At(
+                UNIX_EPOCH + Duration::from_secs(1_709_251_200 + 8 * 3_600 + 30 * 60)
+            ))
+        );
+        assert!("7".parse::<TimeBound>().is_err());
+        assert!("7y".parse::<TimeBound>().is_err());
+        assert!("2023-02-29".parse::<TimeBound>().is_err());
+    }
+
+    #[test]
+    fn test_selection_applies_all_limits() {
+        let now = UNIX_EPOCH + Duration::from_secs(100 * 86_400);
+        let config = Config {
+            min_size: Some(10),
+            max_size: Some(100),
+            newer_than: Some(TimeBound::Age(Duration::from_secs(10 * 86_400))),
+            older_than: Some(TimeBound::Age(Duration::from_secs(86_400))),
+            ..Config::default()
+        };
+        let filter = SelectionFilter::from_config(&config, now);
+        assert!(filter.is_active());
+
+        let recent = 95 * 86_400;
+        assert!(filter.selects(&entry(50, recent)));
+        assert!(!filter.selects(&entry(5, recent)), "below min size");
+        assert!(!filter.selects(&entry(500, recent)), "above max size");
+        assert!(!filter.selects(&entry(50, 80 * 86_400)), "too old");
+        assert!(!filter.selects(&entry(50, 99 * 86_400 + 10)), "too new");
+
+        let mut link = entry(500, 0);
+        link.is_symlink = true;
+        assert!(filter.selects(&link), "symlinks bypass selection");
+    }
+
+    #[test]
+    fn test_default_selection_is_inactive() {
+        let filter = SelectionFilter::from_config(&Config::default(), SystemTime::now());
+        assert!(!filter.is_active());
+        assert!(filter.selects(&entry(0, 0)));
+    }
+}
@@ -0,0 +1,514 @@
+//! Ordered rule lists and their evaluation against a directory tree
+
+use super::rule::{parse_rule_line, FilterAction, FilterRule, ParsedLine, RuleSource};
+use crate::types::KopyError;
+use crate::Config;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Nesting limit for `merge` rules that pull in other rule files.
+const MAX_MERGE_DEPTH: usize = 8;
+
+/// Position of a matched rule: `(slot, merge-directory depth key, line)`.
+///
+/// Lower ranks were declared earlier and therefore win. Rules from per-directory
+/// merge files sit at their `dir-merge` slot, with deeper directories first.
+type Rank = (usize, usize, usize);
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Rule(Arc<FilterRule>),
+    DirMerge(String),
+}
+
+/// Parsed, ordered filter rules (`--filter`, `--include-from`, `--exclude-from`).
+///
+/// Rules are evaluated first-match-wins. Bind them to a scan root with
+/// [`FilterRules::bind`] to evaluate paths.
+#[derive(Debug, Clone, Default)]
+pub struct FilterRules {
+    slots: Vec<Slot>,
+}
+
+impl FilterRules {
+    /// Collect rules from configuration.
+    ///
+    /// Order: `--filter` rules as given, then `--include-from` files, then
+    /// `--exclude-from` files.
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for unreadable rule files or invalid rules.
+    pub fn from_config(config: &Config) -> Result<Self, KopyError> {
+        let mut rules = FilterRules::default();
+        let cwd = PathBuf::new();
+
+        for (idx, line) in config.filter_rules.iter().enumerate() {
+            let source = RuleSource::Argument {
+                flag: "--filter",
+                position: idx + 1,
+            };
+            rules.push_line(line, None, &cwd, source, 0)?;
+        }
+        for path in &config.include_from {
+            rules.push_file(path, Some(FilterAction::Include), 0)?;
+        }
+        for path in &config.exclude_from {
+            rules.push_file(path, Some(FilterAction::Exclude), 0)?;
+        }
+
+        Ok(rules)
+    }
+
+    /// Build rules from `--filter`-style lines (mainly for tests and embedders).
+    ///
+    /// # Errors
+    /// Returns `KopyError::Config` for invalid rules.
+    pub fn parse_lines<S: AsRef<str>>(lines: &[S]) -> Result<Self, KopyError> {
+        let mut rules = FilterRules::default();
+        for (idx, line) in lines.iter().enumerate() {
+            let source = RuleSource::Argument {
+                flag: "--filter",
+                position: idx + 1,
+            };
+            rules.push_line(line.as_ref(), None, Path::new(""), source, 0)?;
+        }
+        Ok(rules)
+    }
+
+    /// True when no rules were configured.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Bind the rules to a scan root.
+    pub fn bind(&self, root: &Path) -> TreeFilter {
+        let first_include_slot = self.slots.iter().position(|slot| match slot {
+            Slot::Rule(rule) => rule.action == FilterAction::Include,
+            // Merge files may contain include rules we have not read yet.
+            Slot::DirMerge(_) => true,
+        });
+
+        TreeFilter {
+            root: root.to_path_buf(),
+            slots: self.slots.clone(),
+            first_include_slot,
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn push_line(
+        &mut self,
+        line: &str,
+        default_action: Option<FilterAction>,
+        relative_to: &Path,
+        source: RuleSource,
+        depth: usize,
+    ) -> Result<(), KopyError> {
+        // CLI and file rules are global: they are scoped to the scan root.
+        match parse_rule_line(line, default_action, Path::new(""), source)? {
+            ParsedLine::Empty => {}
+            ParsedLine::Rule(rule) => self.slots.push(Slot::Rule(Arc::new(rule))),
+            ParsedLine::DirMerge(name) => self.slots.push(Slot::DirMerge(name)),
+            ParsedLine::Merge(path) => {
+                let path = if path.is_absolute() {
+                    path
+                } else {
+                    relative_to.join(path)
+                };
+                self.push_file(&path, default_action, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn push_file(
+        &mut self,
+        path: &Path,
+        default_action: Option<FilterAction>,
+        depth: usize,
+    ) -> Result<(), KopyError> {
+        if depth > MAX_MERGE_DEPTH {
+            return Err(KopyError::Config(format!(
+                "Filter merge files nested too deeply at {}",
+                path.display()
+            )));
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            KopyError::Config(format!(
+                "Failed to read filter file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let relative_to = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let source = RuleSource::File {
+                path: path.to_path_buf(),
+                line: idx + 1,
+            };
+            self.push_line(line, default_action, &relative_to, source, depth)?;
+        }
+        Ok(())
+    }
+}
+
+/// A rule decision for one path.
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    /// Rule that decided the path.
+    pub rule: Arc<FilterRule>,
+    /// Path the rule matched: the path itself, or an excluded ancestor directory.
+    pub matched_path: PathBuf,
+    rank: Rank,
+}
+
+impl RuleMatch {
+    /// True when the deciding rule excludes the path.
+    pub fn is_exclude(&self) -> bool {
+        self.rule.action == FilterAction::Exclude
+    }
+}
+
+#[derive(Debug, Default)]
+struct DirState {
+    verdict: Option<RuleMatch>,
+    /// Rules from per-directory merge files that apply below this directory, deepest first.
+    merged: Vec<Arc<MergedFile>>,
+}
+
+#[derive(Debug)]
+struct MergedFile {
+    slot: usize,
+    depth: usize,
+    rules: Vec<Arc<FilterRule>>,
+}
+
+/// Filter rules bound to one scan root.
+///
+/// Directory decisions are cached, so a `TreeFilter` should be created per scan.
+/// An excluded directory excludes everything below it unless a path matches an
+/// include rule declared before the rule that excluded the directory; this
+/// makes whitelists such as `+ *.rs`, `- *` work for nested files.
+#[derive(Debug)]
+pub struct TreeFilter {
+    root: PathBuf,
+    slots: Vec<Slot>,
+    first_include_slot: Option<usize>,
+    dirs: Mutex<HashMap<PathBuf, Arc<DirState>>>,
+}
+
+impl TreeFilter {
+    /// True when there is at least one rule to evaluate.
+    pub fn is_active(&self) -> bool {
+        !self.slots.is_empty()
+    }
+
+    /// Return the rule deciding `relative_path`, if any.
+    ///
+    /// `None` means no rule matched and the path is included.
+    pub fn decide(&self, relative_path: &Path, is_dir: bool) -> Option<RuleMatch> {
+        if !self.is_active() || relative_path.as_os_str().is_empty() {
+            return None;
+        }
+        if is_dir {
+            return self.dir_state(relative_path).verdict.clone();
+        }
+
+        let parent = self.dir_state(relative_path.parent().unwrap_or_else(|| Path::new("")));
+        self.resolve(relative_path, false, &parent)
+    }
+
+    /// True when the rules exclude `relative_path`.
+    pub fn is_excluded(&self, relative_path: &Path, is_dir: bool) -> bool {
+        self.decide(relative_path, is_dir)
+            .is_some_and(|decision| decision.is_exclude())
+    }
+
+    /// True when an excluded directory can be skipped without descending.
+    ///
+    /// Excluded directories are still walked when an earlier include rule could
+    /// re-include something below them.
+    pub fn should_prune(&self, relative_dir: &Path) -> bool {
+        match self.decide(relative_dir, true) {
+            Some(decision) if decision.is_exclude() => self
+                .first_include_slot
+                .is_none_or(|slot| slot > decision.rank.0),
+            _ => false,
+        }
+    }
+
+    fn dir_state(&self, relative_dir: &Path) -> Arc<DirState> {
+        if let Ok(dirs) = self.dirs.lock() {
+            if let Some(state) = dirs.get(relative_dir) {
+                return Arc::clone(state);
+            }
+        }
+
+        let state = if relative_dir.as_os_str().is_empty() {
+            DirState {
+                verdict: None,
+                merged: self.load_merge_files(relative_dir, &[]),
+            }
+        } else {
+            let parent = self.dir_state(relative_dir.parent().unwrap_or_else(|| Path::new("")));
+            let verdict = self.resolve(relative_dir, true, &parent);
+            DirState {
+                merged: self.load_merge_files(relative_dir, &parent.merged),
+                verdict,
+            }
+        };
+
+        let state = Arc::new(state);
+        if let Ok(mut dirs) = self.dirs.lock() {
+            dirs.entry(relative_dir.to_path_buf())
+                .or_insert_with(|| Arc::clone(&state));
+        }
+        state
+    }
+
+    fn resolve(&self, relative_path: &Path, is_dir: bool, parent: &DirState) -> Option<RuleMatch> {
+        let inherited = parent
+            .verdict
+            .as_ref()
+            .filter(|decision| decision.is_exclude());
+        let own = self.first_match(relative_path, is_dir, &parent.merged);
+
+        match (own, inherited) {
+            (Some(own), Some(inherited)) if own.rank < inherited.rank => Some(own),
+            (_, Some(inherited)) => Some(inherited.clone()),
+            (own, None) => own,
+        }
+    }
+
+    fn first_match(
+        &self,
+        relative_path: &Path,
+        is_dir: bool,
+        merged: &[Arc<MergedFile>],
+    ) -> Option<RuleMatch> {
+        for (slot_idx, slot) in self.slots.iter().enumerate() {
+            match slot {
+                Slot::Rule(rule) => {
+                    if rule.matches(relative_path, is_dir) {
+                        return Some(RuleMatch {
+                            rule: Arc::clone(rule),
+                            matched_path: relative_path.to_path_buf(),
+                            rank: (slot_idx, 0, 0),
+                        });
+                    }
+                }
+                Slot::DirMerge(_) => {
+                    for file in merged.iter().filter(|file| file.slot == slot_idx) {
+                        for (line_idx, rule) in file.rules.iter().enumerate() {
+                            if rule.matches(relative_path, is_dir) {
+                                return Some(RuleMatch {
+                                    rule: Arc::clone(rule),
+                                    matched_path: relative_path.to_path_buf(),
+                                    rank: (slot_idx, usize::MAX - file.depth, line_idx),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn load_merge_files(
+        &self,
+        relative_dir: &Path,
+        inherited: &[Arc<MergedFile>],
+    ) -> Vec<Arc<MergedFile>> {
+        let depth = relative_dir.components().count();
+        let mut merged = Vec::new();
+
+        for (slot_idx, slot) in self.slots.iter().enumerate() {
+            let Slot::DirMerge(name) = slot else {
+                continue;
+            };
+            let path = self.root.join(relative_dir).join(name);
+            let Ok(contents) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+
+            let mut rules = Vec::new();
+            for (idx, line) in contents.lines().enumerate() {
+                let source = RuleSource::File {
+                    path: path.clone(),
+                    line: idx + 1,
+                };
+                match parse_rule_line(line, None, relative_dir, source) {
+                    Ok(ParsedLine::Rule(rule)) => rules.push(Arc::new(rule)),
+                    Ok(ParsedLine::Empty) => {}
+                    Ok(ParsedLine::DirMerge(_)) | Ok(ParsedLine::Merge(_)) => {
+                        eprintln!(
+                            "Warning: Nested merge rules are not supported in {}:{}. Line will be ignored.",
+                            path.display(),
+                            idx + 1
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: {}. Line will be ignored.", e);
+                    }
+                }
+            }
+
+            merged.push(Arc::new(MergedFile {
+                slot: slot_idx,
+                depth,
+                rules,
+            }));
+        }
+
+        merged.extend(inherited.iter().cloned());
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn rules(lines: &[&str]) -> FilterRules {
+        FilterRules::parse_lines(lines).expect("valid rules")
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let filter = rules(&["+ keep.log", "- *.log"]).bind(Path::new("/unused"));
+        assert!(!filter.is_excluded(Path::new("keep.log"), false));
+        assert!(filter.is_excluded(Path::new("other.log"), false));
+
+        let reversed = rules(&["- *.log", "+ keep.log"]).bind(Path::new("/unused"));
+        assert!(reversed.is_excluded(Path::new("keep.log"), false));
+    }
+
+    #[test]
+    fn test_excluded_directory_excludes_contents() {
+        let filter = rules(&["- build/"]).bind(Path::new("/unused"));
+        assert!(filter.is_excluded(Path::new("build"), true));
+        assert!(filter.is_excluded(Path::new("build/out/app"), false));
+        assert!(filter.should_prune(Path::new("build")));
+        assert!(!filter.is_excluded(Path::new("src/build.rs"), false));
+    }
+
+    #[test]
+    fn test_whitelist_reaches_nested_files() {
+        let filter = rules(&["+ *.rs", "- *"]).bind(Path::new("/unused"));
+        assert!(!filter.is_excluded(Path::new("src/lib.rs"), false));
+        assert!(!filter.is_excluded(Path::new("src/nested/mod.rs"), false));
+        assert!(filter.is_excluded(Path::new("src/notes.txt"), false));
+        assert!(filter.is_excluded(Path::new("README.md"), false));
+        assert!(
+            !filter.should_prune(Path::new("src")),
+            "directories must stay walkable for earlier include rules"
+        );
+    }
+
+    #[test]
+    fn test_include_after_directory_exclude_does_not_resurrect_contents() {
+        let filter = rules(&["- cache/", "+ *.keep"]).bind(Path::new("/unused"));
+        assert!(filter.is_excluded(Path::new("cache/a.keep"), false));
+        assert!(filter.should_prune(Path::new("cache")));
+    }
+
+    #[test]
+    fn test_decide_reports_ancestor_for_inherited_exclusion() {
+        let filter = rules(&["- /vendor"]).bind(Path::new("/unused"));
+        let decision = filter
+            .decide(Path::new("vendor/lib/a.c"), false)
+            .expect("decision");
+        assert!(decision.is_exclude());
+        assert_eq!(decision.matched_path, PathBuf::from("vendor"));
+        assert_eq!(decision.rule.describe(), "- /vendor");
+    }
+
+    #[test]
+    fn test_dir_merge_rules_are_scoped_and_deeper_files_win() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join("project/sub")).expect("create dirs");
+        fs::write(root.join(".kopy-filter"), "- *.tmp\n").expect("write root rules");
+        fs::write(root.join("project/.kopy-filter"), "- /local\n").expect("write project rules");
+        fs::write(root.join("project/sub/.kopy-filter"), "+ keep.tmp\n").expect("write sub rules");
+
+        let filter = rules(&[": .kopy-filter"]).bind(root);
+        assert!(filter.is_excluded(Path::new("a.tmp"), false));
+        assert!(filter.is_excluded(Path::new("project/b.tmp"), false));
+        assert!(!filter.is_excluded(Path::new("project/sub/keep.tmp"), false));
+        assert!(filter.is_excluded(Path::new("project/sub/other.tmp"), false));
+        assert!(filter.is_excluded(Path::new("project/local"), true));
+        assert!(!filter.is_excluded(Path::new("local"), true));
+
+        let decision = filter
+            .decide(Path::new("project/local"), true)
+            .expect("decision");
+        assert_eq!(
+            decision.rule.source,
+            RuleSource::File {
+                path: root.join("project/.kopy-filter"),
+                line: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_config_reads_rule_files_in_order() {
+        let temp = TempDir::new().expect("create temp dir");
+        let include_from = temp.path().join("include.txt");
+        let exclude_from = temp.path().join("exclude.txt");
+        fs::write(&include_from, "important.log\n").expect("write include file");
+        fs::write(&exclude_from, "# junk\n*.log\n+ *.txt\n").expect("write exclude file");
+
+        let config = Config {
+            filter_rules: vec!["- secret.txt".to_string()],
+            include_from: vec![include_from],
+            exclude_from: vec![exclude_from.clone()],
+            ..Config::default()
+        };
+        let filter = FilterRules::from_config(&config)
+            .expect("load rules")
+            .bind(Path::new("/unused"));
+
+        assert!(filter.is_excluded(Path::new("secret.txt"), false));
+        assert!(!filter.is_excluded(Path::new("important.log"), false));
+        assert!(filter.is_excluded(Path::new("debug.log"), false));
+        assert!(!filter.is_excluded(Path::new("notes.txt"), false));
+
+        let decision = filter
+            .decide(Path::new("debug.log"), false)
+            .expect("decision");
+        assert_eq!(
+            decision.rule.source,
+            RuleSource::File {
+                path: exclude_from,
+                line: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_config_missing_rule_file_is_config_error() {
+        let config = Config {
+            exclude_from: vec![PathBuf::from("/nonexistent/kopy/excludes.txt")],
+            ..Config::default()
+        };
+        match FilterRules::from_config(&config) {
+            Err(KopyError::Config(msg)) => assert!(msg.contains("excludes.txt")),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_empty_rules_are_inactive() {
+        let filter = FilterRules::default().bind(Path::new("/unused"));
+        assert!(!filter.is_active());
+        assert!(filter.decide(Path::new("a"), false).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use clap::Parser;
-use kopy::config::Cli;
+use kopy::commands;
+use kopy::config::{Cli, Command};
 use kopy::Config;
 
 fn main() -> anyhow::Result<()> {
@@ -12,11 +13,34 @@ fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
-    // Convert CLI args to Config - this validates immediately
-    let config = Config::try_from(cli)?;
+    // Convert CLI args (or a kopy.toml job) to Config - this validates immediately
+    match cli.command {
+        None => sync(Config::try_from(cli.sync)?)?,
+        Some(Command::Sync(args)) => sync(Config::try_from(args)?)?,
+        Some(Command::Run(args)) => sync(commands::run::load_job_config(&args)?)?,
+        Some(Command::Plan(args)) => {
+            let config = Config::try_from(args.sync)?;
+            println!("kopy v{}", kopy::VERSION);
+            commands::plan::run(config, args.explain.as_deref(), args.out.as_deref())?;
+        }
+        Some(Command::Verify(args)) => commands::verify::run(Config::try_from(args)?)?,
+        Some(Command::Diff(args)) => commands::diff::run(Config::try_from(args)?)?,
+        Some(Command::Trash(args)) => commands::trash::run(args)?,
+        Some(Command::ExplainFilter(args)) => commands::explain_filter::run(args)?,
+        Some(Command::Presets(args)) => commands::presets::run(args)?,
+        Some(Command::Bisync(args)) => commands::bisync::run(args)?,
+        Some(Command::Batch(args)) => commands::batch::run(args)?,
+        Some(Command::Apply(args)) => {
+            println!("kopy v{}", kopy::VERSION);
+            commands::apply::run(args)?;
+        }
+    }
 
-    println!("kopy v{}", kopy::VERSION);
-    kopy::commands::sync::run(config)?;
+    Ok(())
+}
 
+fn sync(config: Config) -> anyhow::Result<()> {
+    println!("kopy v{}", kopy::VERSION);
+    commands::sync::run(config)?;
     Ok(())
 }
@@ -0,0 +1,256 @@
+//! Trees built from an explicit path list (`--files-from`)
+
+use crate::config::Config;
+use crate::scanner::walker::{
+    is_destination_internal_trash, read_file_entry, walk_into, ProgressCallback, ScanFilter,
+    ScanProgress,
+};
+use crate::types::{FileTree, KopyError};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Read a `--files-from` list. `-` reads standard input.
+///
+/// Entries are separated by NUL bytes if the list contains any, otherwise by
+/// newlines; in newline mode, blank lines and lines starting with `#` or `;`
+/// are skipped. Entries are returned relative to `source_root` (absolute
+/// entries must lie inside it), without duplicates, in list order.
+///
+/// # Errors
+/// Returns `KopyError::Config` for unreadable lists and entries that leave
+/// the source root.
+pub fn read_file_list(list: &Path, source_root: &Path) -> Result<Vec<PathBuf>, KopyError> {
+    let mut contents = Vec::new();
+    let read = if list == Path::new("-") {
+        std::io::stdin().read_to_end(&mut contents)
+    } else {
+        std::fs::File::open(list).and_then(|mut file| file.read_to_end(&mut contents))
+    };
+    read.map_err(|e| {
+        KopyError::Config(format!(
+            "Failed to read file list {}: {}",
+            list.display(),
+            e
+        ))
+    })?;
+
+    parse_file_list(&contents, source_root)
+}
+
+fn parse_file_list(contents: &[u8], source_root: &Path) -> Result<Vec<PathBuf>, KopyError> {
+    let nul_separated = contents.contains(&0);
+    let separator = if nul_separated { 0 } else { b'\n' };
+
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for raw in contents.split(|byte| *byte == separator) {
+        let raw = if nul_separated {
+            raw
+        } else {
+            raw.strip_suffix(b"\r").unwrap_or(raw)
+        };
+        if raw.is_empty() || (!nul_separated && (raw[0] == b'#' || raw[0] == b';')) {
+            continue;
+        }
+
+        let entry = bytes_to_path(raw);
+        let relative = normalize_entry(&entry, source_root)?;
+        if seen.insert(relative.clone()) {
+            entries.push(relative);
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(unix)]
+fn bytes_to_path(raw: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(raw))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(raw: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(raw).into_owned())
+}
+
+fn normalize_entry(entry: &Path, source_root: &Path) -> Result<PathBuf, KopyError> {
+    let relative = if entry.is_absolute() {
+        entry.strip_prefix(source_root).map_err(|_| {
+            KopyError::Config(format!(
+                "File list entry {} is outside the source {}",
+                entry.display(),
+                source_root.display()
+            ))
+        })?
+    } else {
+        entry
+    };
+
+    let mut clean = PathBuf::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(KopyError::Config(format!(
+                    "File list entry {} must stay inside the source directory",
+                    entry.display()
+                )))
+            }
+        }
+    }
+    if clean.as_os_str().is_empty() {
+        return Err(KopyError::Config(format!(
+            "File list entry {} names the source root; list paths below it instead",
+            entry.display()
+        )));
+    }
+    Ok(clean)
+}
+
+/// Build a `FileTree` from listed paths under `root_path`.
+///
+/// Listed files are statted directly; listed directories are walked with the
+/// usual ignore files and filters. Entries missing under `root_path` are
+/// skipped. Only the listed paths are statted, so the rest of the tree is
+/// never walked.
+///
+/// # Errors
+/// Returns `KopyError` for invalid filters or unreadable metadata.
+pub fn scan_file_list(
+    root_path: &Path,
+    entries: &[PathBuf],
+    config: &Config,
+    on_progress: Option<&ProgressCallback>,
+) -> Result<FileTree, KopyError> {
+    let start_time = Instant::now();
+    let mut tree = FileTree::new(root_path.to_path_buf());
+    let mut progress = ScanProgress::default();
+    let filter = Arc::new(ScanFilter::new(root_path, config)?);
+
+    for relative_path in entries {
+        let full_path = root_path.join(relative_path);
+        let Ok(metadata) = std::fs::symlink_metadata(&full_path) else {
+            continue;
+        };
+        let is_dir = metadata.is_dir();
+
+        if !filter.allows(relative_path, is_dir)
+            || is_destination_internal_trash(root_path, config, relative_path)
+            || ((is_dir || metadata.is_file()) && filter.excluded_by_marker(&full_path, is_dir))
+        {
+            continue;
+        }
+
+        if is_dir {
+            walk_into(
+                &mut tree,
+                root_path,
+                &full_path,
+                config,
+                &filter,
+                on_progress,
+                &mut progress,
+            )?;
+            continue;
+        }
+        if !metadata.is_file() && !metadata.is_symlink() {
+            continue;
+        }
+        if tree.contains(relative_path) {
+            continue;
+        }
+
+        if let Some(file_entry) = read_file_entry(&full_path, relative_path.clone(), &metadata)? {
+            if !filter.selects(&file_entry) {
+                tree.mark_deselected(file_entry.path);
+                continue;
+            }
+            let size = file_entry.size;
+            tree.insert(relative_path.clone(), file_entry);
+            progress.record(size, on_progress);
+        }
+    }
+
+    tree.set_scan_duration(start_time.elapsed());
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_newline_list_skips_comments_and_duplicates() {
+        let list = b"src/main.rs\n# generated\n\n./docs/\nsrc/main.rs\r\n/data/src/lib.rs\n";
+        let entries = parse_file_list(list, Path::new("/data/src")).expect("parse list");
+        assert_eq!(
+            entries,
+            vec![
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("docs"),
+                PathBuf::from("lib.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_nul_separated_list_keeps_newlines_in_names() {
+        let list = b"odd\nname.txt\0#not-a-comment\0";
+        let entries = parse_file_list(list, Path::new("/src")).expect("parse list");
+        assert_eq!(
+            entries,
+            vec![
+                PathBuf::from("odd\nname.txt"),
+                PathBuf::from("#not-a-comment")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_entries_outside_source() {
+        assert!(parse_file_list(b"../secret\n", Path::new("/src")).is_err());
+        assert!(parse_file_list(b"/etc/passwd\n", Path::new("/src")).is_err());
+        assert!(parse_file_list(b".\n", Path::new("/src")).is_err());
+    }
+
+    #[test]
+    fn test_scan_file_list_stats_files_and_walks_directories() {
+        let temp = TempDir::new().expect("create temp dir");
+        let root = temp.path();
+        fs::create_dir_all(root.join("docs/api")).expect("create docs");
+        fs::create_dir_all(root.join("other")).expect("create other");
+        fs::write(root.join("README.md"), "readme").expect("write readme");
+        fs::write(root.join("docs/index.md"), "index").expect("write index");
+        fs::write(root.join("docs/api/ref.md"), "ref").expect("write ref");
+        fs::write(root.join("docs/draft.tmp"), "draft").expect("write draft");
+        fs::write(root.join("other/skip.txt"), "skip").expect("write skip");
+
+        let config = Config {
+            exclude_patterns: vec!["*.tmp".to_string()],
+            ..Config::default()
+        };
+        let entries = vec![
+            PathBuf::from("README.md"),
+            PathBuf::from("docs"),
+            PathBuf::from("missing.txt"),
+        ];
+        let tree = scan_file_list(root, &entries, &config, None).expect("scan list");
+
+        let mut paths: Vec<_> = tree.paths().cloned().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("README.md"),
+                PathBuf::from("docs/api/ref.md"),
+                PathBuf::from("docs/index.md"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Directory marker files and inode flags that exclude whole subtrees
+//!
+//! - `CACHEDIR.TAG` (<https://bford.info/cachedir/>) with a valid signature
+//! - user-chosen marker names (`--exclude-if-present .nobackup`)
+//! - the Linux `nodump` attribute (`chattr +d`)
+
+use std::io::Read;
+use std::path::Path;
+
+/// File name of a cache directory tag.
+pub(super) const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+
+/// Every valid `CACHEDIR.TAG` starts with this exact header.
+const CACHEDIR_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Check whether `dir` contains a `CACHEDIR.TAG` with the standard signature.
+pub(super) fn is_cachedir_tagged(dir: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(dir.join(CACHEDIR_TAG)) else {
+        return false;
+    };
+    let mut header = Vec::with_capacity(CACHEDIR_SIGNATURE.len());
+    file.take(CACHEDIR_SIGNATURE.len() as u64)
+        .read_to_end(&mut header)
+        .is_ok_and(|_| header == CACHEDIR_SIGNATURE)
+}
+
+/// Check whether `dir` contains any entry named in `markers`.
+pub(super) fn has_marker_file(dir: &Path, markers: &[String]) -> bool {
+    markers
+        .iter()
+        .any(|name| std::fs::symlink_metadata(dir.join(name)).is_ok())
+}
+
+/// Check the `nodump` inode flag of a regular file or directory.
+///
+/// Filesystems without inode flags (and unreadable paths) report `false`.
+#[cfg(target_os = "linux")]
+pub(super) fn has_nodump_flag(path: &Path) -> bool {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    /// `FS_NODUMP_FL` from `linux/fs.h`.
+    const FS_NODUMP_FL: libc::c_int = 0x0000_0040;
+
+    let Ok(file) = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)
+    else {
+        return false;
+    };
+
+    let mut flags: libc::c_int = 0;
+    // SAFETY: FS_IOC_GETFLAGS writes a single int through the provided pointer,
+    // which points to a live local for the duration of the call.
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    result == 0 && flags & FS_NODUMP_FL != 0
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn has_nodump_flag(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cachedir_tag_requires_signature() {
+        let temp = TempDir::new().expect("create temp dir");
+        let dir = temp.path();
+        assert!(!is_cachedir_tagged(dir));
+
+        fs::write(dir.join(CACHEDIR_TAG), "not a cache tag\n").expect("write bad tag");
+        assert!(!is_cachedir_tagged(dir));
+
+        fs::write(
+            dir.join(CACHEDIR_TAG),
+            "Signature: 8a477f597d28d172789f06886806bc55\n# This file is a cache directory tag.\n",
+        )
+        .expect("write tag");
+        assert!(is_cachedir_tagged(dir));
+    }
+
+    #[test]
+    fn test_marker_files_match_any_entry_type() {
+        let temp = TempDir::new().expect("create temp dir");
+        let dir = temp.path();
+        let markers = vec![".nobackup".to_string()];
+        assert!(!has_marker_file(dir, &markers));
+
+        fs::create_dir(dir.join(".nobackup")).expect("create marker dir");
+        assert!(has_marker_file(dir, &markers));
+    }
+
+    #[test]
+    fn test_plain_file_has_no_nodump_flag() {
+        let temp = TempDir::new().expect("create temp dir");
+        let file = temp.path().join("plain.txt");
+        fs::write(&file, "plain").expect("write file");
+        assert!(!has_nodump_flag(&file));
+        assert!(!has_nodump_flag(&temp.path().join("missing")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_nodump_flag_detected_when_filesystem_supports_it() {
+        use std::os::unix::io::AsRawFd;
+
+        let temp = TempDir::new_in(env!("CARGO_MANIFEST_DIR")).expect("create temp dir");
+        let path = temp.path().join("scratch.bin");
+        let file = fs::File::create(&path).expect("create file");
+
+        let mut flags: libc::c_int = 0;
+        // SAFETY: both ioctls read/write one int through a pointer to a live local.
+        unsafe {
+            if libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) != 0 {
+                return; // no inode flags on this filesystem (e.g. tmpfs)
+            }
+            flags |= 0x40;
+            if libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) != 0 {
+                return;
+            }
+        }
+        assert!(has_nodump_flag(&path));
+    }
+}
@@ -1,9 +1,16 @@
 //! Directory scanning logic
 
+mod files_from;
+mod markers;
 mod mode;
 mod parallel;
+mod resolution;
+mod sorted;
 mod walker;
 
+pub use files_from::{read_file_list, scan_file_list};
 pub use mode::{resolve_scan_mode, ResolvedScanMode};
 pub use parallel::scan_directory_parallel;
+pub use resolution::{infer_mtime_resolution, probe_mtime_resolution};
+pub use sorted::{scan_sorted, ScanItem, SortedScan};
 pub use walker::{scan_directory, ProgressCallback};
@@ -1,9 +1,8 @@
 use crate::config::{Config, ScanMode};
-use crate::scanner::walker::{
-    compile_patterns, is_destination_internal_trash, should_include_path,
-};
+use crate::scanner::walker::{build_walk, is_destination_internal_trash, ScanFilter};
 use crate::types::KopyError;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 const PROBE_ENTRY_LIMIT: usize = 512;
@@ -62,17 +61,8 @@ fn select_mode_from_shape(shape: ScanShape) -> ResolvedScanMode {
 }
 
 fn sample_scan_shape(root_path: &Path, config: &Config) -> Result<ScanShape, KopyError> {
-    let exclude_patterns = compile_patterns(&config.exclude_patterns)?;
-    let include_patterns = compile_patterns(&config.include_patterns)?;
-
-    let walker = ignore::WalkBuilder::new(root_path)
-        .hidden(false)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .ignore(true)
-        .add_custom_ignore_filename(".kopyignore")
-        .build();
+    let filter = Arc::new(ScanFilter::new(root_path, config)?);
+    let walker = build_walk(root_path, &filter).build();
 
     let mut shape = ScanShape::default();
     let start = Instant::now();
@@ -108,7 +98,7 @@ fn sample_scan_shape(root_path: &Path, config: &Config) -> Result<ScanShape, Kop
             shape.max_depth = depth;
         }
 
-        if !should_include_path(relative_path, &exclude_patterns, &include_patterns) {
+        if !filter.allows(relative_path, file_type.is_dir()) {
             continue;
         }
 
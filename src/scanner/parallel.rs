@@ -2,11 +2,11 @@
 
 use crate::config::Config;
 use crate::scanner::walker::{
-    compile_patterns, is_destination_internal_trash, should_include_path, ProgressCallback,
+    build_walk, is_destination_internal_trash, ProgressCallback, ScanFilter,
 };
 use crate::types::{FileEntry, FileTree, KopyError};
 use ignore::WalkState;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -16,6 +16,7 @@ const DEFAULT_COLLECTOR_MEMORY_LIMIT_BYTES: u64 = 128 * 1024 * 1024;
 #[derive(Default)]
 struct CollectedScan {
     files: Vec<FileEntry>,
+    deselected: Vec<PathBuf>,
     collector_bytes: u64,
     total_dirs: usize,
     fallback_tree: Option<FileTree>,
@@ -56,8 +57,7 @@ fn scan_directory_parallel_with_limit(
 ) -> Result<(FileTree, bool), KopyError> {
     let start_time = Instant::now();
 
-    let exclude_patterns = compile_patterns(&config.exclude_patterns)?;
-    let include_patterns = compile_patterns(&config.include_patterns)?;
+    let filter = Arc::new(ScanFilter::new(root_path, config)?);
 
     let root = root_path.to_path_buf();
     let cfg = config.clone();
@@ -65,20 +65,13 @@ fn scan_directory_parallel_with_limit(
     let thread_count = config.threads.max(1);
     let progress = Arc::new(Mutex::new(ProgressState::default()));
 
-    let walker = ignore::WalkBuilder::new(root_path)
+    let walker = build_walk(root_path, &filter)
         .threads(thread_count)
-        .hidden(false)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .ignore(true)
-        .add_custom_ignore_filename(".kopyignore")
         .build_parallel();
 
     walker.run(|| {
         let collected = Arc::clone(&collected);
-        let exclude_patterns = exclude_patterns.clone();
-        let include_patterns = include_patterns.clone();
+        let filter = Arc::clone(&filter);
         let root = root.clone();
         let cfg = cfg.clone();
         let progress = Arc::clone(&progress);
@@ -123,7 +116,7 @@ fn scan_directory_parallel_with_limit(
                 }
             };
 
-            if !should_include_path(&relative_path, &exclude_patterns, &include_patterns) {
+            if !filter.allows(&relative_path, file_type.is_dir()) {
                 return WalkState::Continue;
             }
 
@@ -215,6 +208,15 @@ fn scan_directory_parallel_with_limit(
                 None => FileEntry::new(relative_path, metadata.len(), mtime, permissions),
             };
 
+            if !filter.selects(&file_entry) {
+                let mut scan = match collected.lock() {
+                    Ok(s) => s,
+                    Err(_) => return WalkState::Quit,
+                };
+                scan.deselected.push(file_entry.path);
+                return WalkState::Continue;
+            }
+
             if let Some(callback) = on_progress {
                 // Keep callback delivery serialized and monotonic across workers.
                 let mut state = match progress.lock() {
@@ -270,6 +272,9 @@ fn scan_directory_parallel_with_limit(
         let relative_path = entry.path.clone();
         tree.insert(relative_path, entry);
     }
+    for path in scan.deselected.drain(..) {
+        tree.mark_deselected(path);
+    }
 
     tree.set_scan_duration(start_time.elapsed());
     Ok((tree, scan.fallback_triggered))
@@ -311,6 +316,7 @@ fn engage_chunked_merge_fallback(scan: &mut CollectedScan, root: &Path) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::IgnoreSettings;
     use crate::scanner::scan_directory;
     use std::collections::HashSet;
     use std::fs;
@@ -453,6 +459,257 @@ mod tests {
         assert_eq!(par_paths, seq_paths);
     }
 
+    #[test]
+    fn test_parallel_filter_rules_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join("src/deep")).expect("create src");
+        fs::create_dir_all(temp.path().join("target/debug")).expect("create target");
+        fs::write(temp.path().join("src/.kopy-filter"), "- *.bak\n").expect("write rules");
+        fs::write(temp.path().join("src/main.rs"), b"main").expect("write main");
+        fs::write(temp.path().join("src/main.rs.bak"), b"bak").expect("write bak");
+        fs::write(temp.path().join("src/deep/mod.rs"), b"mod").expect("write mod");
+        fs::write(temp.path().join("src/deep/notes.md"), b"notes").expect("write notes");
+        fs::write(temp.path().join("target/debug/app"), b"app").expect("write app");
+
+        let config = Config {
+            source: temp.path().to_path_buf(),
+            destination: temp.path().join("dest"),
+            filter_rules: vec![
+                ": .kopy-filter".to_string(),
+                "- /target/".to_string(),
+                "+ *.rs*".to_string(),
+                "- *.md".to_string(),
+            ],
+            ..Config::default()
+        };
+
+        let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+        assert!(seq_paths.contains(&PathBuf::from("src/deep/mod.rs")));
+        assert!(!seq_paths.contains(&PathBuf::from("src/main.rs.bak")));
+        assert!(!seq_paths.contains(&PathBuf::from("src/deep/notes.md")));
+        assert!(!seq_paths.contains(&PathBuf::from("target/debug/app")));
+    }
+
+    #[test]
+    fn test_parallel_ignore_settings_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join(".git")).expect("create .git");
+        fs::create_dir_all(temp.path().join(".config")).expect("create .config");
+        fs::create_dir_all(temp.path().join("build")).expect("create build");
+        fs::write(temp.path().join(".gitignore"), "build/\n").expect("write gitignore");
+        fs::write(temp.path().join(".backupignore"), "*.iso\n").expect("write ignore");
+        fs::write(temp.path().join(".config/app.toml"), b"cfg").expect("write config");
+        fs::write(temp.path().join("build/out"), b"out").expect("write out");
+        fs::write(temp.path().join("disk.iso"), b"iso").expect("write iso");
+        fs::write(temp.path().join("notes.txt"), b"notes").expect("write notes");
+
+        let config = Config {
+            source: temp.path().to_path_buf(),
+            destination: temp.path().join("dest"),
+            ignore: IgnoreSettings {
+                gitignore: false,
+                extra_files: vec![".backupignore".to_string()],
+                skip_hidden: true,
+                ..IgnoreSettings::default()
+            },
+            ..Config::default()
+        };
+
+        let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+        assert!(seq_paths.contains(&PathBuf::from("build/out")));
+        assert!(!seq_paths.contains(&PathBuf::from("disk.iso")));
+        assert!(!seq_paths.contains(&PathBuf::from(".config/app.toml")));
+    }
+
+    #[test]
+    fn test_parallel_size_selection_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join("vm")).expect("create vm");
+        fs::write(temp.path().join("vm/disk.img"), vec![0u8; 4096]).expect("write image");
+        fs::write(temp.path().join("notes.txt"), b"notes").expect("write notes");
+        fs::write(temp.path().join("empty.log"), b"").expect("write empty");
+
+        let config = Config {
+            source: temp.path().to_path_buf(),
+            destination: temp.path().join("dest"),
+            min_size: Some(1),
+            max_size: Some(1024),
+            ..Config::default()
+        };
+
+        let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+        assert_eq!(seq_paths, HashSet::from([PathBuf::from("notes.txt")]));
+        assert_eq!(parallel.deselected, sequential.deselected);
+        assert!(sequential.is_deselected(&PathBuf::from("vm/disk.img")));
+        assert!(sequential.is_deselected(&PathBuf::from("empty.log")));
+    }
+
+    #[test]
+    fn test_parallel_marker_pruning_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join("build/.cache/deep")).expect("create cache");
+        fs::write(
+            temp.path().join("build/.cache/CACHEDIR.TAG"),
+            "Signature: 8a477f597d28d172789f06886806bc55",
+        )
+        .expect("write tag");
+        fs::write(temp.path().join("build/.cache/deep/obj"), b"obj").expect("write obj");
+        fs::write(temp.path().join("build/out.bin"), b"out").expect("write out");
+        fs::create_dir_all(temp.path().join("vm")).expect("create vm");
+        fs::write(temp.path().join("vm/.nobackup"), b"").expect("write marker");
+        fs::write(temp.path().join("vm/disk.img"), b"disk").expect("write disk");
+
+        let config = Config {
+            source: temp.path().to_path_buf(),
+            destination: temp.path().join("dest"),
+            exclude_caches: true,
+            exclude_if_present: vec![".nobackup".to_string()],
+            ..Config::default()
+        };
+
+        let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+        assert_eq!(seq_paths, HashSet::from([PathBuf::from("build/out.bin")]));
+        assert_eq!(parallel.total_dirs, sequential.total_dirs);
+    }
+
+    #[test]
+    fn test_parallel_max_depth_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join("a/b/c")).expect("create nested dirs");
+        fs::write(temp.path().join("top.txt"), b"top").expect("write top");
+        fs::write(temp.path().join("a/one.txt"), b"one").expect("write one");
+        fs::write(temp.path().join("a/b/two.txt"), b"two").expect("write two");
+        fs::write(temp.path().join("a/b/c/three.txt"), b"three").expect("write three");
+
+        for (max_depth, expected) in [
+            (1, vec!["top.txt"]),
+            (2, vec!["a/one.txt", "top.txt"]),
+            (3, vec!["a/b/two.txt", "a/one.txt", "top.txt"]),
+        ] {
+            let config = Config {
+                max_depth: Some(max_depth),
+                ..Config::default()
+            };
+
+            let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+            let parallel =
+                scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+            let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+            let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+            assert_eq!(par_paths, seq_paths, "max_depth {max_depth}");
+            assert_eq!(
+                seq_paths,
+                expected
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect::<HashSet<_>>(),
+                "max_depth {max_depth}"
+            );
+            assert_eq!(parallel.total_dirs, sequential.total_dirs);
+        }
+    }
+
+    #[test]
+    fn test_parallel_one_file_system_parity_with_sequential() {
+        let temp = TempDir::new().expect("create temp dir");
+        fs::create_dir_all(temp.path().join("nested/deeper")).expect("create nested dirs");
+        fs::write(temp.path().join("root.txt"), b"root").expect("write root");
+        fs::write(temp.path().join("nested/deeper/leaf.txt"), b"leaf").expect("write leaf");
+
+        let config = Config {
+            one_file_system: true,
+            ..Config::default()
+        };
+
+        let unbounded =
+            scan_directory(temp.path(), &Config::default(), None).expect("unbounded scan");
+        let sequential = scan_directory(temp.path(), &config, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(temp.path(), &config, None).expect("parallel scan");
+
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        let all_paths: HashSet<_> = unbounded.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+        assert_eq!(
+            seq_paths, all_paths,
+            "a single filesystem is scanned in full"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parallel_one_file_system_stops_at_mount_points() {
+        use std::os::unix::fs::MetadataExt;
+
+        // /dev/shm is a tmpfs mounted below devtmpfs /dev on most Linux systems.
+        let root = Path::new("/dev");
+        let shm = root.join("shm");
+        let (Ok(root_meta), Ok(shm_meta)) = (fs::metadata(root), fs::metadata(&shm)) else {
+            return;
+        };
+        if root_meta.dev() == shm_meta.dev() {
+            return;
+        }
+        let Ok(marker_dir) = TempDir::new_in(&shm) else {
+            return;
+        };
+        fs::write(marker_dir.path().join("marker.txt"), b"x").expect("write marker");
+        let marker = marker_dir
+            .path()
+            .join("marker.txt")
+            .strip_prefix(root)
+            .expect("marker below /dev")
+            .to_path_buf();
+
+        let crossing = Config {
+            max_depth: Some(3),
+            ..Config::default()
+        };
+        let bounded = Config {
+            one_file_system: true,
+            ..crossing.clone()
+        };
+
+        let sequential = scan_directory(root, &crossing, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(root, &crossing, None).expect("parallel scan");
+        assert!(sequential.contains(&marker) && parallel.contains(&marker));
+
+        let sequential = scan_directory(root, &bounded, None).expect("sequential scan");
+        let parallel = scan_directory_parallel(root, &bounded, None).expect("parallel scan");
+        assert!(
+            !sequential.contains(&marker),
+            "sequential scan crossed into /dev/shm"
+        );
+        assert!(
+            !parallel.contains(&marker),
+            "parallel scan crossed into /dev/shm"
+        );
+        let seq_paths: HashSet<_> = sequential.paths().cloned().collect();
+        let par_paths: HashSet<_> = parallel.paths().cloned().collect();
+        assert_eq!(par_paths, seq_paths);
+    }
+
     #[test]
     fn test_parallel_fallback_threshold_preserves_parity() {
         let temp = TempDir::new().expect("create temp dir");
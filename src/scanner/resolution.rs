@@ -0,0 +1,132 @@
+//! Destination timestamp resolution probing
+//!
+//! Filesystems store mtimes at different granularities: nanoseconds on ext4
+//! and btrfs, 100ns on NTFS, 10ms on exFAT, 2 seconds on FAT. A copied file
+//! whose source mtime has a finer fraction than the destination keeps only a
+//! truncated mtime, so a plain comparison would see it as changed on every
+//! run. The sync commands widen the modify window to the resolution inferred
+//! from the mtimes already stored in the destination, which needs no write
+//! access and gives a dry run the same answer as the real run;
+//! [`probe_mtime_resolution`] measures it directly with a probe file.
+
+use filetime::FileTime;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Measure the mtime resolution of the filesystem holding `dir`.
+///
+/// A short-lived probe file is written to `dir`, given an mtime on an odd
+/// second with a fraction in every decimal place, read back and removed.
+/// Returns `None` when `dir` is missing or not writable.
+pub fn probe_mtime_resolution(dir: &Path) -> Option<Duration> {
+    if !dir.is_dir() {
+        return None;
+    }
+
+    let probe_path = dir.join(format!(".kopy-mtime-probe-{}", std::process::id()));
+    let written = FileTime::from_unix_time(1_000_000_001, 123_456_789);
+    let stored = fs::File::create(&probe_path)
+        .and_then(|_| filetime::set_file_mtime(&probe_path, written))
+        .and_then(|_| fs::metadata(&probe_path))
+        .map(|metadata| FileTime::from_last_modification_time(&metadata));
+    let _ = fs::remove_file(&probe_path);
+
+    stored
+        .ok()
+        .map(|stored| resolution_from_probe(written, stored))
+}
+
+/// Estimate the mtime resolution of a filesystem from mtimes stored on it.
+///
+/// Read-only counterpart of [`probe_mtime_resolution`]. The finest
+/// granularity among `mtimes` wins, since a single mtime with a nanosecond
+/// fraction proves a fine-grained filesystem; mtimes that are all on even
+/// seconds suggest FAT. Returns `None` when there are no mtimes.
+pub fn infer_mtime_resolution(mtimes: impl IntoIterator<Item = SystemTime>) -> Option<Duration> {
+    mtimes
+        .into_iter()
+        .map(|mtime| stored_granularity(FileTime::from_system_time(mtime)))
+        .min()
+}
+
+/// Coarsest granularity `stored` is consistent with.
+fn stored_granularity(stored: FileTime) -> Duration {
+    if stored.nanoseconds() == 0 {
+        let seconds = if stored.unix_seconds() % 2 == 0 { 2 } else { 1 };
+        return Duration::from_secs(seconds);
+    }
+    decimal_step(stored.nanoseconds())
+}
+
+/// Largest power of ten, in nanoseconds, that divides `nanos`.
+fn decimal_step(nanos: u32) -> Duration {
+    let mut step: u32 = 1;
+    while step < 1_000_000_000 && nanos.is_multiple_of(step * 10) {
+        step *= 10;
+    }
+    Duration::from_nanos(u64::from(step))
+}
+
+/// Granularity implied by how `written` came back as `stored`.
+fn resolution_from_probe(written: FileTime, stored: FileTime) -> Duration {
+    // FAT rounds to even seconds.
+    if stored.unix_seconds() != written.unix_seconds() {
+        return Duration::from_secs(2);
+    }
+
+    decimal_step(stored.nanoseconds())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolution_from_probe_per_granularity() {
+        let written = FileTime::from_unix_time(1_000_000_001, 123_456_789);
+        let cases = [
+            (FileTime::from_unix_time(1_000_000_001, 123_456_789), 1),
+            (FileTime::from_unix_time(1_000_000_001, 123_456_700), 100),
+            (
+                FileTime::from_unix_time(1_000_000_001, 120_000_000),
+                10_000_000,
+            ),
+            (FileTime::from_unix_time(1_000_000_001, 0), 1_000_000_000),
+            (FileTime::from_unix_time(1_000_000_002, 0), 2_000_000_000),
+        ];
+        for (stored, nanos) in cases {
+            assert_eq!(
+                resolution_from_probe(written, stored),
+                Duration::from_nanos(nanos)
+            );
+        }
+    }
+
+    #[test]
+    fn test_infer_resolution_takes_finest_stored_mtime() {
+        let at = |secs, nanos| std::time::UNIX_EPOCH + Duration::new(secs, nanos);
+        assert_eq!(infer_mtime_resolution(Vec::new()), None);
+        assert_eq!(
+            infer_mtime_resolution([at(1_000, 0), at(1_002, 0)]),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            infer_mtime_resolution([at(1_000, 0), at(1_001, 0)]),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            infer_mtime_resolution([at(1_000, 120_000_000), at(1_001, 123_456_789)]),
+            Some(Duration::from_nanos(1))
+        );
+    }
+
+    #[test]
+    fn test_probe_cleans_up_and_skips_missing_directories() {
+        let dir = TempDir::new().expect("create temp dir");
+        assert!(probe_mtime_resolution(dir.path()).is_some());
+        assert_eq!(fs::read_dir(dir.path()).expect("read dir").count(), 0);
+        assert_eq!(probe_mtime_resolution(&dir.path().join("missing")), None);
+    }
+}
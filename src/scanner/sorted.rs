@@ -0,0 +1,199 @@
+//! Sorted streaming scan
+//!
+//! [`scan_sorted`] walks a tree like [`super::scan_directory`] but yields
+//! entries one at a time in `Path` order instead of collecting a
+//! [`crate::types::FileTree`]. Each directory is read and sorted by name
+//! before it is descended into, so memory grows with the depth of the tree
+//! times the width of its largest directory rather than with the file count.
+//!
+//! Names are compared byte-wise at every level, which is how `Path` orders
+//! its components: `a/z` sorts before `a.txt`, and two sorted scans can be
+//! merged path by path.
+
+use super::walker::{
+    build_walk, classify_entry, warn_traversal_error, ProgressCallback, ScanFilter, ScanProgress,
+    Walked,
+};
+use crate::types::{FileEntry, KopyError};
+use crate::Config;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An entry yielded by [`SortedScan`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanItem {
+    /// A file or symlink that passed every filter
+    Entry(FileEntry),
+
+    /// A file dropped by `--min-size`/`--max-size`/`--newer-than`/`--older-than`
+    Deselected(PathBuf),
+}
+
+impl ScanItem {
+    /// Size of the entry (0 for deselected paths).
+    pub fn size(&self) -> u64 {
+        match self {
+            ScanItem::Entry(entry) => entry.size,
+            ScanItem::Deselected(_) => 0,
+        }
+    }
+
+    /// Relative path of the entry.
+    pub fn path(&self) -> &Path {
+        match self {
+            ScanItem::Entry(entry) => &entry.path,
+            ScanItem::Deselected(path) => path,
+        }
+    }
+}
+
+/// Iterator over a tree in `Path` order, see [`scan_sorted`].
+pub struct SortedScan<'a> {
+    root_path: PathBuf,
+    config: &'a Config,
+    filter: Arc<ScanFilter>,
+    walker: ignore::Walk,
+    on_progress: Option<&'a ProgressCallback>,
+    progress: ScanProgress,
+}
+
+/// Walk `root_path` with the same filters as [`super::scan_directory`],
+/// yielding files and deselected paths in `Path` order.
+///
+/// Traversal errors are reported as warnings and skipped, as in a full
+/// scan; only errors that would abort a full scan are yielded as `Err`.
+///
+/// # Errors
+/// Returns `KopyError::Config` for invalid filter patterns.
+///
+/// # Example
+/// ```no_run
+/// use kopy::scanner::{scan_sorted, ScanItem};
+/// use kopy::Config;
+/// use std::path::Path;
+///
+/// let config = Config::default();
+/// for item in scan_sorted(Path::new("./src_dir"), &config, None)? {
+///     if let ScanItem::Entry(entry) = item? {
+///         println!("{}", entry.path.display());
+///     }
+/// }
+/// # Ok::<(), kopy::types::KopyError>(())
+/// ```
+pub fn scan_sorted<'a>(
+    root_path: &Path,
+    config: &'a Config,
+    on_progress: Option<&'a ProgressCallback>,
+) -> Result<SortedScan<'a>, KopyError> {
+    let filter = Arc::new(ScanFilter::new(root_path, config)?);
+    let walker = build_walk(root_path, &filter)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    Ok(SortedScan {
+        root_path: root_path.to_path_buf(),
+        config,
+        filter,
+        walker,
+        on_progress,
+        progress: ScanProgress::default(),
+    })
+}
+
+impl Iterator for SortedScan<'_> {
+    type Item = Result<ScanItem, KopyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for result in self.walker.by_ref() {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn_traversal_error(&e);
+                    continue;
+                }
+            };
+            match classify_entry(&entry, &self.root_path, self.config, &self.filter) {
+                Ok(Some(Walked::File(file_entry))) => {
+                    self.progress.record(file_entry.size, self.on_progress);
+                    return Some(Ok(ScanItem::Entry(file_entry)));
+                }
+                Ok(Some(Walked::Deselected(path))) => {
+                    return Some(Ok(ScanItem::Deselected(path)));
+                }
+                Ok(Some(Walked::Dir) | None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn paths(root: &Path, config: &Config) -> Vec<PathBuf> {
+        scan_sorted(root, config, None)
+            .unwrap()
+            .map(|item| item.unwrap().path().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn test_sorted_scan_yields_path_order() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("a/z")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        for name in ["a.txt", "a/z/1", "a/y", "a-b", "b/c", "B"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        let scanned = paths(root, &Config::default());
+        let mut expected = scanned.clone();
+        expected.sort();
+        assert_eq!(scanned, expected);
+        assert_eq!(scanned.len(), 6);
+        assert_eq!(scanned[1], PathBuf::from("a/y"));
+    }
+
+    #[test]
+    fn test_sorted_scan_matches_full_scan() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("keep/deep")).unwrap();
+        fs::create_dir_all(root.join("skip")).unwrap();
+        fs::write(root.join("keep/deep/file"), "x").unwrap();
+        fs::write(root.join("keep/other"), "x").unwrap();
+        fs::write(root.join("skip/file"), "x").unwrap();
+        let config = Config {
+            exclude_patterns: vec!["skip".to_string()],
+            ..Config::default()
+        };
+
+        let tree = super::super::scan_directory(root, &config, None).unwrap();
+        let mut expected: Vec<PathBuf> = tree.paths().cloned().collect();
+        expected.sort();
+        assert_eq!(paths(root, &config), expected);
+    }
+
+    #[test]
+    fn test_sorted_scan_reports_deselected_files() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::write(root.join("big"), vec![0u8; 64]).unwrap();
+        fs::write(root.join("small"), "x").unwrap();
+        let config = Config {
+            max_size: Some(10),
+            ..Config::default()
+        };
+
+        let items: Vec<ScanItem> = scan_sorted(root, &config, None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items[0], ScanItem::Deselected(PathBuf::from("big")));
+        assert!(matches!(&items[1], ScanItem::Entry(entry) if entry.path == Path::new("small")));
+    }
+}
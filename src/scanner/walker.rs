@@ -1,9 +1,12 @@
 //! Directory walker
 
-use crate::config::Config;
+use crate::config::{Config, IgnoreSettings};
+use crate::filter::{FilterRules, SelectionFilter, TreeFilter};
+use crate::scanner::markers::{has_marker_file, has_nodump_flag, is_cachedir_tagged};
 use crate::types::{FileEntry, FileTree, KopyError};
-use std::path::Path;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 /// Callback for reporting scan progress
 ///
@@ -33,8 +36,10 @@ pub type ProgressCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
 /// * Other IO errors are propagated as KopyError
 ///
 /// Filter precedence:
-/// 1. walker-level ignore files (`.gitignore`, `.ignore`, `.git/info/exclude`, `.kopyignore`)
-/// 2. CLI pattern check where `--include` overrides `--exclude`
+/// 1. walker-level ignore files (`.gitignore`, `.ignore`, `.git/info/exclude`, `.kopyignore`,
+///    extra names), as enabled by `config.ignore`
+/// 2. ordered filter rules (`--filter`, `--include-from`, `--exclude-from`), first match wins
+/// 3. CLI pattern check where `--include` overrides `--exclude`
 ///
 /// # Example
 /// ```no_run
@@ -54,145 +59,348 @@ pub fn scan_directory(
 ) -> Result<FileTree, KopyError> {
     let start_time = Instant::now();
     let mut tree = FileTree::new(root_path.to_path_buf());
+    let mut progress = ScanProgress::default();
+
+    let filter = Arc::new(ScanFilter::new(root_path, config)?);
+    walk_into(
+        &mut tree,
+        root_path,
+        root_path,
+        config,
+        &filter,
+        on_progress,
+        &mut progress,
+    )?;
 
-    let mut scanned_count: u64 = 0;
-    let mut scanned_bytes: u64 = 0;
+    let duration = start_time.elapsed();
+    tree.set_scan_duration(duration);
 
-    let exclude_patterns = compile_patterns(&config.exclude_patterns)?;
-    let include_patterns = compile_patterns(&config.include_patterns)?;
+    Ok(tree)
+}
 
-    let walker = ignore::WalkBuilder::new(root_path)
-        .hidden(false)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .ignore(true)
-        .add_custom_ignore_filename(".kopyignore")
-        .build();
+/// Running totals reported through [`ProgressCallback`].
+#[derive(Debug, Default)]
+pub(super) struct ScanProgress {
+    files: u64,
+    bytes: u64,
+}
+
+impl ScanProgress {
+    pub(super) fn record(&mut self, size: u64, on_progress: Option<&ProgressCallback>) {
+        self.files += 1;
+        self.bytes += size;
+        if let Some(callback) = on_progress {
+            callback(self.files, self.bytes);
+        }
+    }
+}
+
+/// Walk `start` (the scan root or a directory below it) into `tree`.
+///
+/// Paths are recorded relative to `root_path`, and `filter` must be bound to
+/// `root_path`, so walking a subdirectory selects exactly what a full scan
+/// would select below it.
+pub(super) fn walk_into(
+    tree: &mut FileTree,
+    root_path: &Path,
+    start: &Path,
+    config: &Config,
+    filter: &Arc<ScanFilter>,
+    on_progress: Option<&ProgressCallback>,
+    progress: &mut ScanProgress,
+) -> Result<(), KopyError> {
+    let walker = build_walk_from(root_path, start, filter).build();
 
     for result in walker {
         match result {
-            Ok(entry) => {
-                let file_type = match entry.file_type() {
-                    Some(ft) => ft,
-                    None => continue,
-                };
-
-                let relative_path = match entry.path().strip_prefix(root_path) {
-                    Ok(p) => p.to_path_buf(),
-                    Err(_) => {
-                        eprintln!(
-                            "Warning: Failed to calculate relative path for {}. \
-                             This may indicate a symlink pointing outside the scan directory. File will be skipped.",
-                            entry.path().display()
-                        );
-                        continue;
-                    }
-                };
-
-                // Apply CLI exclude/include filtering:
-                // include patterns explicitly override exclude patterns.
-                if !should_include_path(&relative_path, &exclude_patterns, &include_patterns) {
-                    continue;
+            Ok(entry) => match classify_entry(&entry, root_path, config, filter)? {
+                Some(Walked::Dir) => tree.increment_dirs(),
+                Some(Walked::File(file_entry)) => {
+                    let size = file_entry.size;
+                    tree.insert(file_entry.path.clone(), file_entry);
+                    progress.record(size, on_progress);
                 }
+                Some(Walked::Deselected(path)) => tree.mark_deselected(path),
+                None => {}
+            },
+            Err(e) => warn_traversal_error(&e),
+        }
+    }
 
-                if is_destination_internal_trash(root_path, config, &relative_path) {
-                    continue;
-                }
+    Ok(())
+}
 
-                if file_type.is_dir() {
-                    tree.increment_dirs();
-                    continue;
-                }
+/// What a walked entry contributes to a scan.
+pub(super) enum Walked {
+    /// A directory that passed the filters
+    Dir,
+    /// A file or symlink to record
+    File(FileEntry),
+    /// A file dropped by `--min-size`/`--max-size`/`--newer-than`/`--older-than`
+    Deselected(PathBuf),
+}
 
-                if !file_type.is_file() && !file_type.is_symlink() {
-                    continue;
-                }
+/// Apply the scan filters to one walked entry.
+///
+/// Returns `Ok(None)` for entries that are filtered out or skipped with a
+/// warning.
+pub(super) fn classify_entry(
+    entry: &ignore::DirEntry,
+    root_path: &Path,
+    config: &Config,
+    filter: &ScanFilter,
+) -> Result<Option<Walked>, KopyError> {
+    let Some(file_type) = entry.file_type() else {
+        return Ok(None);
+    };
+
+    let relative_path = match entry.path().strip_prefix(root_path) {
+        Ok(p) => p.to_path_buf(),
+        Err(_) => {
+            eprintln!(
+                "Warning: Failed to calculate relative path for {}. \
+                 This may indicate a symlink pointing outside the scan directory. File will be skipped.",
+                entry.path().display()
+            );
+            return Ok(None);
+        }
+    };
 
-                let metadata = match entry.metadata() {
-                    Ok(m) => m,
-                    Err(e) => {
-                        eprintln!(
-                            "Warning: Failed to read metadata for {}: {}. \
-                             Try checking file permissions or if the file was deleted during scan.",
-                            entry.path().display(),
-                            e
-                        );
-                        continue;
-                    }
-                };
-
-                let (_is_symlink, symlink_target) = if metadata.is_symlink() {
-                    match std::fs::read_link(entry.path()) {
-                        Ok(target) => (true, Some(target)),
-                        Err(e) => {
-                            eprintln!(
-                                "Warning: Failed to read symlink target for {}: {}. \
-                                 Broken symlink will be skipped.",
-                                entry.path().display(),
-                                e
-                            );
-                            continue;
-                        }
-                    }
-                } else {
-                    (false, None)
-                };
-
-                #[cfg(unix)]
-                let permissions = {
-                    use std::os::unix::fs::PermissionsExt;
-                    metadata.permissions().mode()
-                };
-
-                #[cfg(not(unix))]
-                let permissions = 0o644;
-
-                let mtime = metadata.modified().map_err(|e| {
-                    KopyError::Io(std::io::Error::other(format!(
-                        "Failed to get modification time for {}: {}. \
-                         This may indicate an unsupported filesystem or corrupted metadata.",
-                        entry.path().display(),
-                        e
-                    )))
-                })?;
-
-                let file_entry = if let Some(target) = symlink_target {
-                    FileEntry::new_symlink(
-                        relative_path.clone(),
-                        metadata.len(),
-                        mtime,
-                        permissions,
-                        target,
-                    )
-                } else {
-                    FileEntry::new(relative_path.clone(), metadata.len(), mtime, permissions)
-                };
-
-                tree.insert(relative_path, file_entry);
-
-                scanned_count += 1;
-                scanned_bytes += metadata.len();
-
-                if let Some(callback) = on_progress {
-                    callback(scanned_count, scanned_bytes);
-                }
-            }
+    if !filter.allows(&relative_path, file_type.is_dir()) {
+        return Ok(None);
+    }
+
+    if is_destination_internal_trash(root_path, config, &relative_path) {
+        return Ok(None);
+    }
+
+    if file_type.is_dir() {
+        return Ok(Some(Walked::Dir));
+    }
+
+    if !file_type.is_file() && !file_type.is_symlink() {
+        return Ok(None);
+    }
+
+    let metadata = match entry.metadata() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to read metadata for {}: {}. \
+                 Try checking file permissions or if the file was deleted during scan.",
+                entry.path().display(),
+                e
+            );
+            return Ok(None);
+        }
+    };
+
+    let Some(file_entry) = read_file_entry(entry.path(), relative_path, &metadata)? else {
+        return Ok(None);
+    };
+    if !filter.selects(&file_entry) {
+        return Ok(Some(Walked::Deselected(file_entry.path)));
+    }
+    Ok(Some(Walked::File(file_entry)))
+}
+
+pub(super) fn warn_traversal_error(error: &ignore::Error) {
+    eprintln!(
+        "Warning: Error during directory traversal: {}. \
+         Scan will continue with remaining files.",
+        error
+    );
+}
+
+/// Build a `FileEntry` from already-read (non-following) metadata.
+///
+/// Returns `Ok(None)` for broken symlinks, which are skipped with a warning.
+pub(super) fn read_file_entry(
+    path: &Path,
+    relative_path: PathBuf,
+    metadata: &std::fs::Metadata,
+) -> Result<Option<FileEntry>, KopyError> {
+    let symlink_target = if metadata.is_symlink() {
+        match std::fs::read_link(path) {
+            Ok(target) => Some(target),
             Err(e) => {
                 eprintln!(
-                    "Warning: Error during directory traversal: {}. \
-                     Scan will continue with remaining files.",
+                    "Warning: Failed to read symlink target for {}: {}. \
+                     Broken symlink will be skipped.",
+                    path.display(),
                     e
                 );
-                continue;
+                return Ok(None);
             }
         }
+    } else {
+        None
+    };
+
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode()
+    };
+
+    #[cfg(not(unix))]
+    let permissions = 0o644;
+
+    let mtime = metadata.modified().map_err(|e| {
+        KopyError::Io(std::io::Error::other(format!(
+            "Failed to get modification time for {}: {}. \
+             This may indicate an unsupported filesystem or corrupted metadata.",
+            path.display(),
+            e
+        )))
+    })?;
+
+    let file_entry = if let Some(target) = symlink_target {
+        FileEntry::new_symlink(relative_path, metadata.len(), mtime, permissions, target)
+    } else {
+        FileEntry::new(relative_path, metadata.len(), mtime, permissions)
+    };
+    Ok(Some(file_entry))
+}
+
+/// Path filtering shared by the sequential walker, the parallel walker and
+/// the scan-mode probe, so all three select the same entries.
+pub(super) struct ScanFilter {
+    ignore: IgnoreSettings,
+    rules: TreeFilter,
+    exclude_patterns: Vec<glob::Pattern>,
+    include_patterns: Vec<glob::Pattern>,
+    selection: SelectionFilter,
+    exclude_caches: bool,
+    exclude_if_present: Vec<String>,
+    exclude_nodump: bool,
+    one_file_system: bool,
+    max_depth: Option<usize>,
+}
+
+impl ScanFilter {
+    pub(super) fn new(root_path: &Path, config: &Config) -> Result<Self, KopyError> {
+        // Size/age limits select what to send, so the destination is scanned in full.
+        let selection = if root_path == config.destination {
+            SelectionFilter::default()
+        } else {
+            SelectionFilter::from_config(config, SystemTime::now())
+        };
+        Ok(Self {
+            ignore: config.ignore_settings_for(root_path),
+            rules: FilterRules::from_config(config)?.bind(root_path),
+            exclude_patterns: compile_patterns(&config.effective_exclude_patterns())?,
+            include_patterns: compile_patterns(&config.include_patterns)?,
+            selection,
+            exclude_caches: config.exclude_caches,
+            exclude_if_present: config.exclude_if_present.clone(),
+            exclude_nodump: config.exclude_nodump,
+            one_file_system: config.one_file_system,
+            max_depth: config.max_depth,
+        })
     }
 
-    let duration = start_time.elapsed();
-    tree.set_scan_duration(duration);
+    /// Whether any marker check (`--exclude-caches`, `--exclude-if-present`,
+    /// `--exclude-nodump`) is enabled.
+    fn checks_markers(&self) -> bool {
+        self.exclude_caches || !self.exclude_if_present.is_empty() || self.exclude_nodump
+    }
 
-    Ok(tree)
+    /// Check a directory or regular file below the scan root for exclusion
+    /// markers. A marked directory is excluded with everything below it.
+    pub(super) fn excluded_by_marker(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir
+            && ((self.exclude_caches && is_cachedir_tagged(path))
+                || has_marker_file(path, &self.exclude_if_present))
+        {
+            return true;
+        }
+        self.exclude_nodump && has_nodump_flag(path)
+    }
+
+    /// Check a scanned file against `--min-size`/`--max-size`/`--newer-than`/`--older-than`.
+    pub(super) fn selects(&self, entry: &FileEntry) -> bool {
+        self.selection.selects(entry)
+    }
+
+    /// Check ordered filter rules first, then CLI `--exclude`/`--include` patterns
+    /// (where include patterns explicitly override exclude patterns).
+    pub(super) fn allows(&self, relative_path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_excluded(relative_path, is_dir) {
+            return false;
+        }
+        should_include_path(
+            relative_path,
+            &self.exclude_patterns,
+            &self.include_patterns,
+        )
+    }
+
+    /// Excluded directories whose contents cannot be re-included are not descended into.
+    fn should_prune(&self, relative_dir: &Path) -> bool {
+        self.rules.is_active() && self.rules.should_prune(relative_dir)
+    }
+}
+
+/// Walker configuration shared by all scanners.
+pub(super) fn build_walk(root_path: &Path, filter: &Arc<ScanFilter>) -> ignore::WalkBuilder {
+    build_walk_from(root_path, root_path, filter)
+}
+
+/// Like [`build_walk`], but starting at `start` (a directory below `root_path`).
+///
+/// `--max-depth` still counts from `root_path`.
+pub(super) fn build_walk_from(
+    root_path: &Path,
+    start: &Path,
+    filter: &Arc<ScanFilter>,
+) -> ignore::WalkBuilder {
+    let start_depth = start
+        .strip_prefix(root_path)
+        .map(|relative| relative.components().count())
+        .unwrap_or(0);
+    let ignore = &filter.ignore;
+    let mut builder = ignore::WalkBuilder::new(start);
+    builder
+        .same_file_system(filter.one_file_system)
+        .max_depth(
+            filter
+                .max_depth
+                .map(|depth| depth.saturating_sub(start_depth)),
+        )
+        .hidden(ignore.skip_hidden)
+        .git_ignore(ignore.gitignore)
+        .git_global(ignore.git_global)
+        .git_exclude(ignore.git_exclude)
+        .ignore(ignore.dot_ignore);
+    for name in ignore.custom_filenames() {
+        builder.add_custom_ignore_filename(name);
+    }
+
+    if filter.rules.is_active() || filter.checks_markers() {
+        let filter = Arc::clone(filter);
+        let root = root_path.to_path_buf();
+        builder.filter_entry(move |entry| {
+            let Some(file_type) = entry.file_type() else {
+                return true;
+            };
+            if entry.depth() > 0
+                && (file_type.is_dir() || file_type.is_file())
+                && filter.excluded_by_marker(entry.path(), file_type.is_dir())
+            {
+                return false;
+            }
+            if !file_type.is_dir() {
+                return true;
+            }
+            match entry.path().strip_prefix(&root) {
+                Ok(relative_dir) => !filter.should_prune(relative_dir),
+                Err(_) => true,
+            }
+        });
+    }
+
+    builder
 }
 
 pub(super) fn compile_patterns(patterns: &[String]) -> Result<Vec<glob::Pattern>, KopyError> {
@@ -234,7 +442,7 @@ pub(super) fn is_destination_internal_trash(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
+    use crate::config::{Config, IgnoreSettings};
     use std::fs;
     use std::io::Write;
     use std::path::PathBuf;
@@ -652,4 +860,323 @@ mod tests {
         // Verify tree has correct file count
         assert_eq!(tree.total_files, 5, "Tree should contain 5 files");
     }
+
+    #[test]
+    fn test_filter_whitelist_keeps_nested_matches_only() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("src/nested")).expect("Failed to create dirs");
+        fs::write(root.join("README.md"), "readme").expect("Failed to create README.md");
+        fs::write(root.join("src/lib.rs"), "lib").expect("Failed to create lib.rs");
+        fs::write(root.join("src/notes.txt"), "notes").expect("Failed to create notes.txt");
+        fs::write(root.join("src/nested/mod.rs"), "mod").expect("Failed to create mod.rs");
+
+        let config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/tmp/dest"),
+            filter_rules: vec!["+ *.rs".to_string(), "- *".to_string()],
+            ..Default::default()
+        };
+
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert_eq!(tree.total_files, 2);
+        assert!(tree.contains(&PathBuf::from("src/lib.rs")));
+        assert!(tree.contains(&PathBuf::from("src/nested/mod.rs")));
+        assert!(!tree.contains(&PathBuf::from("src/notes.txt")));
+        assert!(!tree.contains(&PathBuf::from("README.md")));
+    }
+
+    #[test]
+    fn test_filter_anchored_and_dir_only_rules() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("build")).expect("Failed to create build");
+        fs::create_dir_all(root.join("docs/build")).expect("Failed to create docs/build");
+        fs::create_dir_all(root.join("a/cache")).expect("Failed to create a/cache");
+        fs::write(root.join("build/out.bin"), "out").expect("Failed to create out.bin");
+        fs::write(root.join("docs/build/page.html"), "page").expect("Failed to create page");
+        fs::write(root.join("a/cache/blob"), "blob").expect("Failed to create blob");
+        fs::write(root.join("cache"), "file named cache").expect("Failed to create cache");
+
+        let config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/tmp/dest"),
+            filter_rules: vec!["- /build".to_string(), "- cache/".to_string()],
+            ..Default::default()
+        };
+
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert!(!tree.contains(&PathBuf::from("build/out.bin")));
+        assert!(tree.contains(&PathBuf::from("docs/build/page.html")));
+        assert!(!tree.contains(&PathBuf::from("a/cache/blob")));
+        assert!(
+            tree.contains(&PathBuf::from("cache")),
+            "dir-only rule must not match a regular file"
+        );
+    }
+
+    #[test]
+    fn test_filter_per_directory_merge_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("project")).expect("Failed to create project");
+        fs::write(root.join("project/.kopy-filter"), "- *.tmp\n").expect("Failed to write rules");
+        fs::write(root.join("project/a.tmp"), "a").expect("Failed to create a.tmp");
+        fs::write(root.join("project/b.txt"), "b").expect("Failed to create b.txt");
+        fs::write(root.join("top.tmp"), "top").expect("Failed to create top.tmp");
+
+        let config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/tmp/dest"),
+            filter_rules: vec![": .kopy-filter".to_string()],
+            ..Default::default()
+        };
+
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert!(!tree.contains(&PathBuf::from("project/a.tmp")));
+        assert!(tree.contains(&PathBuf::from("project/b.txt")));
+        assert!(
+            tree.contains(&PathBuf::from("top.tmp")),
+            "merge file rules only apply below their directory"
+        );
+    }
+
+    #[test]
+    fn test_filter_exclude_from_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("src");
+        fs::create_dir_all(root.join("logs")).expect("Failed to create logs");
+        fs::write(root.join("keep.txt"), "keep").expect("Failed to create keep.txt");
+        fs::write(root.join("logs/app.log"), "log").expect("Failed to create app.log");
+
+        let rules = temp_dir.path().join("excludes.txt");
+        fs::write(&rules, "# generated\nlogs/\n").expect("Failed to write excludes");
+
+        let config = Config {
+            source: root.clone(),
+            destination: PathBuf::from("/tmp/dest"),
+            exclude_from: vec![rules],
+            ..Default::default()
+        };
+
+        let tree = scan_directory(&root, &config, None).expect("scan_directory should succeed");
+
+        assert_eq!(tree.total_files, 1);
+        assert!(tree.contains(&PathBuf::from("keep.txt")));
+    }
+
+    #[test]
+    fn test_disabled_gitignore_keeps_ignored_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir(root.join(".git")).expect("Failed to create .git dir");
+        fs::write(root.join(".gitignore"), ".env\ntarget/\n").expect("Failed to create .gitignore");
+        fs::write(root.join(".kopyignore"), "*.tmp\n").expect("Failed to create .kopyignore");
+        fs::create_dir(root.join("target")).expect("Failed to create target");
+        fs::write(root.join("target/app"), "bin").expect("Failed to create target/app");
+        fs::write(root.join(".env"), "SECRET=1").expect("Failed to create .env");
+        fs::write(root.join("scratch.tmp"), "tmp").expect("Failed to create scratch.tmp");
+
+        let config = Config {
+            ignore: IgnoreSettings {
+                gitignore: false,
+                ..IgnoreSettings::default()
+            },
+            ..Default::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert!(tree.contains(&PathBuf::from(".env")));
+        assert!(tree.contains(&PathBuf::from("target/app")));
+        assert!(
+            !tree.contains(&PathBuf::from("scratch.tmp")),
+            ".kopyignore stays active"
+        );
+    }
+
+    #[test]
+    fn test_extra_ignore_file_names() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir(root.join("sub")).expect("Failed to create sub");
+        fs::write(root.join("sub/.backupignore"), "*.iso\n").expect("Failed to write ignore");
+        fs::write(root.join("sub/disk.iso"), "iso").expect("Failed to create disk.iso");
+        fs::write(root.join("disk.iso"), "iso").expect("Failed to create disk.iso");
+
+        let config = Config {
+            ignore: IgnoreSettings {
+                extra_files: vec![".backupignore".to_string()],
+                ..IgnoreSettings::default()
+            },
+            ..Default::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert!(!tree.contains(&PathBuf::from("sub/disk.iso")));
+        assert!(tree.contains(&PathBuf::from("disk.iso")));
+    }
+
+    #[test]
+    fn test_skip_hidden_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir(root.join(".cache")).expect("Failed to create .cache");
+        fs::write(root.join(".cache/blob"), "blob").expect("Failed to create blob");
+        fs::write(root.join(".hidden"), "hidden").expect("Failed to create .hidden");
+        fs::write(root.join("visible.txt"), "visible").expect("Failed to create visible.txt");
+
+        let config = Config {
+            ignore: IgnoreSettings {
+                skip_hidden: true,
+                ..IgnoreSettings::default()
+            },
+            ..Default::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+
+        assert_eq!(tree.total_files, 1);
+        assert!(tree.contains(&PathBuf::from("visible.txt")));
+    }
+
+    #[test]
+    fn test_destination_scan_can_skip_ignore_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::write(root.join(".kopyignore"), "*.log\n").expect("Failed to create .kopyignore");
+        fs::write(root.join("stale.log"), "log").expect("Failed to create stale.log");
+
+        let mut config = Config {
+            source: PathBuf::from("/src"),
+            destination: root.to_path_buf(),
+            ..Config::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+        assert!(!tree.contains(&PathBuf::from("stale.log")));
+
+        config.ignore.apply_to_destination = false;
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+        assert!(tree.contains(&PathBuf::from("stale.log")));
+
+        config.source = root.to_path_buf();
+        config.destination = PathBuf::from("/dest");
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+        assert!(
+            !tree.contains(&PathBuf::from("stale.log")),
+            "source scan keeps its ignore files"
+        );
+    }
+
+    #[test]
+    fn test_age_selection_applies_to_source_scan_only() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::write(root.join("fresh.log"), "fresh").expect("Failed to create fresh.log");
+        let old = fs::File::create(root.join("old.log")).expect("Failed to create old.log");
+        old.set_modified(SystemTime::now() - Duration::from_secs(30 * 86_400))
+            .expect("Failed to set mtime");
+        drop(old);
+
+        let mut config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/dest"),
+            newer_than: Some(crate::filter::TimeBound::Age(Duration::from_secs(86_400))),
+            ..Config::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+        assert!(tree.contains(&PathBuf::from("fresh.log")));
+        assert!(!tree.contains(&PathBuf::from("old.log")));
+        assert!(tree.is_deselected(&PathBuf::from("old.log")));
+
+        config.source = PathBuf::from("/src");
+        config.destination = root.to_path_buf();
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+        assert!(
+            tree.contains(&PathBuf::from("old.log")),
+            "destination scan ignores size/age limits"
+        );
+        assert!(tree.deselected.is_empty());
+    }
+
+    #[test]
+    fn test_presets_exclude_with_include_override() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("app/node_modules/left-pad")).expect("Failed to create dirs");
+        fs::write(root.join("app/node_modules/left-pad/index.js"), "pad")
+            .expect("Failed to create index.js");
+        fs::write(root.join("app/node_modules/.keep"), "").expect("Failed to create .keep");
+        fs::write(root.join("app/main.js"), "main").expect("Failed to create main.js");
+        fs::write(root.join(".DS_Store"), "junk").expect("Failed to create .DS_Store");
+
+        let config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/tmp/dest"),
+            presets: vec![crate::config::Preset::Node, crate::config::Preset::OsJunk],
+            include_patterns: vec!["**/node_modules/.keep".to_string()],
+            ..Default::default()
+        };
+
+        let tree = scan_directory(root, &config, None).expect("scan_directory should succeed");
+        let mut paths: Vec<_> = tree.paths().cloned().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("app/main.js"),
+                PathBuf::from("app/node_modules/.keep"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_and_marker_directories_are_pruned() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("cache/objects")).expect("Failed to create cache");
+        fs::write(
+            root.join("cache/CACHEDIR.TAG"),
+            "Signature: 8a477f597d28d172789f06886806bc55\n",
+        )
+        .expect("Failed to create CACHEDIR.TAG");
+        fs::write(root.join("cache/objects/blob"), "blob").expect("Failed to create blob");
+        fs::create_dir_all(root.join("scratch")).expect("Failed to create scratch");
+        fs::write(root.join("scratch/.nobackup"), "").expect("Failed to create marker");
+        fs::write(root.join("scratch/tmp.dat"), "tmp").expect("Failed to create tmp.dat");
+        fs::create_dir_all(root.join("fake")).expect("Failed to create fake");
+        fs::write(root.join("fake/CACHEDIR.TAG"), "no signature").expect("Failed to create tag");
+        fs::write(root.join("keep.txt"), "keep").expect("Failed to create keep.txt");
+
+        let config = Config {
+            source: root.to_path_buf(),
+            destination: PathBuf::from("/tmp/dest"),
+            exclude_caches: true,
+            exclude_if_present: vec![".nobackup".to_string()],
+            ..Default::default()
+        };
+        let tree = scan_directory(root, &config, None).expect("scan directory");
+
+        let mut paths: Vec<_> = tree.paths().cloned().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("fake/CACHEDIR.TAG"),
+                PathBuf::from("keep.txt")
+            ]
+        );
+        assert_eq!(tree.total_dirs, 2, "only the root and fake/ are walked");
+    }
 }
@@ -1,16 +1,22 @@
 //! SyncAction - Actions determined by the diff engine
 
 use super::FileEntry;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 /// Sync action determined by diff engine
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SyncAction {
     /// Copy new file (exists in src, missing in dest)
     CopyNew(FileEntry),
 
-    /// Overwrite existing file (src and dest differ)
-    Overwrite(FileEntry),
+    /// Overwrite existing file (src and dest differ), with why it was chosen
+    Overwrite(FileEntry, UpdateReason),
+
+    /// Apply source permissions and mtime to a destination file whose content
+    /// already matches
+    UpdateMetadata(FileEntry),
 
     /// Delete file (exists in dest, missing in src)
     Delete(PathBuf),
@@ -30,7 +36,12 @@ impl SyncAction {
 
     /// Check if this action is Overwrite
     pub fn is_overwrite(&self) -> bool {
-        matches!(self, SyncAction::Overwrite(_))
+        matches!(self, SyncAction::Overwrite(..))
+    }
+
+    /// Check if this action is UpdateMetadata
+    pub fn is_update_metadata(&self) -> bool {
+        matches!(self, SyncAction::UpdateMetadata(_))
     }
 
     /// Check if this action is Delete
@@ -68,7 +79,7 @@ impl SyncAction {
     /// assert!(!SyncAction::Delete(PathBuf::from("a.txt")).requires_transfer());
     /// ```
     pub fn requires_transfer(&self) -> bool {
-        matches!(self, SyncAction::CopyNew(_) | SyncAction::Overwrite(_))
+        matches!(self, SyncAction::CopyNew(_) | SyncAction::Overwrite(..))
     }
 
     /// Get the path associated with this action
@@ -76,7 +87,9 @@ impl SyncAction {
     /// Returns None for Skip variant
     pub fn path(&self) -> Option<&PathBuf> {
         match self {
-            SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry) => Some(&entry.path),
+            SyncAction::CopyNew(entry)
+            | SyncAction::Overwrite(entry, _)
+            | SyncAction::UpdateMetadata(entry) => Some(&entry.path),
             SyncAction::Delete(path) => Some(path),
             SyncAction::Move { to, .. } => Some(to),
             SyncAction::Skip => None,
@@ -85,10 +98,12 @@ impl SyncAction {
 
     /// Get the FileEntry if this action contains one
     ///
-    /// Returns Some for CopyNew and Overwrite, None otherwise
+    /// Returns Some for CopyNew, Overwrite and UpdateMetadata, None otherwise
     pub fn file_entry(&self) -> Option<&FileEntry> {
         match self {
-            SyncAction::CopyNew(entry) | SyncAction::Overwrite(entry) => Some(entry),
+            SyncAction::CopyNew(entry)
+            | SyncAction::Overwrite(entry, _)
+            | SyncAction::UpdateMetadata(entry) => Some(entry),
             _ => None,
         }
     }
@@ -97,7 +112,8 @@ impl SyncAction {
     pub fn action_name(&self) -> &'static str {
         match self {
             SyncAction::CopyNew(_) => "Copy",
-            SyncAction::Overwrite(_) => "Update",
+            SyncAction::Overwrite(..) => "Update",
+            SyncAction::UpdateMetadata(_) => "Metadata",
             SyncAction::Delete(_) => "Delete",
             SyncAction::Move { .. } => "Move",
             SyncAction::Skip => "Skip",
@@ -105,8 +121,61 @@ impl SyncAction {
     }
 }
 
+/// Why the diff engine chose to overwrite a destination file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateReason {
+    /// One side is a symlink and the other a regular file
+    TypeChanged,
+
+    /// Both sides are symlinks pointing at different targets
+    SymlinkTargetChanged,
+
+    /// `--ignore-times` transfers every regular file
+    IgnoreTimes,
+
+    /// Sizes differ
+    SizeChanged { source: u64, destination: u64 },
+
+    /// Source mtime is newer than the destination's
+    SourceNewer,
+
+    /// Blake3 hashes differ (`--checksum`)
+    HashMismatch,
+
+    /// A hash could not be computed, so the file is copied to be safe
+    HashFailed,
+
+    /// The destination is newer, but `--conflict source-wins` applies
+    ConflictSourceWins,
+
+    /// The file changed on the other side since the last bisync
+    ChangedOnOtherSide,
+}
+
+impl UpdateReason {
+    /// Short description for plan output.
+    pub fn describe(&self) -> String {
+        match self {
+            UpdateReason::TypeChanged => "file type changed".to_string(),
+            UpdateReason::SymlinkTargetChanged => "symlink target changed".to_string(),
+            UpdateReason::IgnoreTimes => "--ignore-times".to_string(),
+            UpdateReason::SizeChanged {
+                source,
+                destination,
+            } => format!("size changed: {} -> {} bytes", destination, source),
+            UpdateReason::SourceNewer => "source is newer".to_string(),
+            UpdateReason::HashMismatch => "content hash differs".to_string(),
+            UpdateReason::HashFailed => "hash could not be computed".to_string(),
+            UpdateReason::ConflictSourceWins => "destination is newer; source wins".to_string(),
+            UpdateReason::ChangedOnOtherSide => "changed since last bisync".to_string(),
+        }
+    }
+}
+
 /// Delete behavior
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DeleteMode {
     /// Don't delete anything
     #[default]
@@ -185,7 +254,7 @@ mod tests {
     #[test]
     fn test_overwrite_variant() {
         let entry = create_test_entry("existing.txt", 2048);
-        let action = SyncAction::Overwrite(entry.clone());
+        let action = SyncAction::Overwrite(entry.clone(), UpdateReason::SourceNewer);
 
         assert!(!action.is_copy_new());
         assert!(action.is_overwrite());
@@ -254,7 +323,7 @@ mod tests {
         let entry = create_test_entry("file.txt", 100);
 
         assert!(SyncAction::CopyNew(entry.clone()).requires_transfer());
-        assert!(SyncAction::Overwrite(entry).requires_transfer());
+        assert!(SyncAction::Overwrite(entry, UpdateReason::SourceNewer).requires_transfer());
         assert!(!SyncAction::Delete(PathBuf::from("file.txt")).requires_transfer());
         assert!(!SyncAction::Skip.requires_transfer());
         assert!(!SyncAction::Move {
@@ -274,7 +343,7 @@ mod tests {
             Some(&PathBuf::from("test.txt"))
         );
         assert_eq!(
-            SyncAction::Overwrite(entry).path(),
+            SyncAction::Overwrite(entry, UpdateReason::SourceNewer).path(),
             Some(&PathBuf::from("test.txt"))
         );
         assert_eq!(SyncAction::Delete(path.clone()).path(), Some(&path));
@@ -298,7 +367,7 @@ mod tests {
             Some(&entry)
         );
         assert_eq!(
-            SyncAction::Overwrite(entry.clone()).file_entry(),
+            SyncAction::Overwrite(entry.clone(), UpdateReason::SourceNewer).file_entry(),
             Some(&entry)
         );
         assert_eq!(
@@ -321,7 +390,10 @@ mod tests {
         let entry = create_test_entry("file.txt", 100);
 
         assert_eq!(SyncAction::CopyNew(entry.clone()).action_name(), "Copy");
-        assert_eq!(SyncAction::Overwrite(entry).action_name(), "Update");
+        assert_eq!(
+            SyncAction::Overwrite(entry, UpdateReason::SourceNewer).action_name(),
+            "Update"
+        );
         assert_eq!(
             SyncAction::Delete(PathBuf::from("file.txt")).action_name(),
             "Delete"
@@ -342,7 +414,8 @@ mod tests {
         let entry = create_test_entry("file.txt", 100);
         let actions = vec![
             SyncAction::CopyNew(entry.clone()),
-            SyncAction::Overwrite(entry),
+            SyncAction::Overwrite(entry.clone(), UpdateReason::SourceNewer),
+            SyncAction::UpdateMetadata(entry),
             SyncAction::Delete(PathBuf::from("old.txt")),
             SyncAction::Skip,
             SyncAction::Move {
@@ -353,6 +426,7 @@ mod tests {
 
         let mut copy_count = 0;
         let mut overwrite_count = 0;
+        let mut metadata_count = 0;
         let mut delete_count = 0;
         let mut skip_count = 0;
         let mut move_count = 0;
@@ -360,7 +434,8 @@ mod tests {
         for action in actions {
             match action {
                 SyncAction::CopyNew(_) => copy_count += 1,
-                SyncAction::Overwrite(_) => overwrite_count += 1,
+                SyncAction::Overwrite(..) => overwrite_count += 1,
+                SyncAction::UpdateMetadata(_) => metadata_count += 1,
                 SyncAction::Delete(_) => delete_count += 1,
                 SyncAction::Skip => skip_count += 1,
                 SyncAction::Move { .. } => move_count += 1,
@@ -369,6 +444,7 @@ mod tests {
 
         assert_eq!(copy_count, 1);
         assert_eq!(overwrite_count, 1);
+        assert_eq!(metadata_count, 1);
         assert_eq!(delete_count, 1);
         assert_eq!(skip_count, 1);
         assert_eq!(move_count, 1);
@@ -1,7 +1,7 @@
 //! FileEntry - Represents a single file in the sync tree
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 /// Represents a file in the sync tree
@@ -25,6 +25,11 @@ pub struct FileEntry {
     /// Symlink metadata
     pub is_symlink: bool,
     pub symlink_target: Option<PathBuf>,
+
+    /// Source root this entry was scanned from when several sources are
+    /// merged (`None` = `Config::source`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_root: Option<PathBuf>,
 }
 
 impl FileEntry {
@@ -53,6 +58,7 @@ impl FileEntry {
             hash: None,
             is_symlink: false,
             symlink_target: None,
+            source_root: None,
         }
     }
 
@@ -88,6 +94,7 @@ impl FileEntry {
             hash: None,
             is_symlink: true,
             symlink_target: Some(target),
+            source_root: None,
         }
     }
 
@@ -99,6 +106,14 @@ impl FileEntry {
         self
     }
 
+    /// Absolute source path of this entry, given the configured primary source.
+    pub fn source_path(&self, default_root: &Path) -> PathBuf {
+        self.source_root
+            .as_deref()
+            .unwrap_or(default_root)
+            .join(&self.path)
+    }
+
     /// Check if this entry has a computed hash
     pub fn has_hash(&self) -> bool {
         self.hash.is_some()
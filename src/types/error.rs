@@ -38,6 +38,10 @@ pub enum KopyError {
     #[error("SSH connection failed: {0}")]
     SshError(String),
 
+    /// A plan's deletes were refused by the mass-delete guard
+    #[error("Refusing to delete {planned} destination path(s): {reason}")]
+    DeleteRefused { planned: usize, reason: String },
+
     /// Dry run mode - safely abort execution
     #[error("Dry run mode: no changes were made")]
     DryRun,
@@ -167,6 +171,18 @@ mod tests {
         assert!(error.to_string().contains("Connection timeout"));
     }
 
+    #[test]
+    fn test_delete_refused() {
+        let error = KopyError::DeleteRefused {
+            planned: 120,
+            reason: "limit is 100 (--max-delete)".to_string(),
+        };
+        assert!(error.to_string().contains("Refusing to delete 120"));
+        assert!(error.to_string().contains("--max-delete"));
+        assert!(!error.is_recoverable());
+        assert!(!error.is_validation_error());
+    }
+
     #[test]
     fn test_dry_run() {
         let error = KopyError::DryRun;
@@ -5,7 +5,7 @@ mod entry;
 mod error;
 mod tree;
 
-pub use action::{DeleteMode, SyncAction};
+pub use action::{DeleteMode, SyncAction, UpdateReason};
 pub use entry::FileEntry;
 pub use error::KopyError;
 pub use tree::FileTree;
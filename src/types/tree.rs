@@ -1,8 +1,8 @@
 //! FileTree - Directory structure representation
 
 use super::FileEntry;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// File tree (directory structure)
@@ -19,6 +19,9 @@ pub struct FileTree {
     /// Scan metadata
     pub scan_duration: Duration,
     pub root_path: PathBuf,
+
+    /// Files left out by size/age selection; their destination copies are kept
+    pub deselected: HashSet<PathBuf>,
 }
 
 impl FileTree {
@@ -40,6 +43,7 @@ impl FileTree {
             total_dirs: 0,
             scan_duration: Duration::from_secs(0),
             root_path,
+            deselected: HashSet::new(),
         }
     }
 
@@ -89,6 +93,69 @@ impl FileTree {
         self.entries.keys()
     }
 
+    /// Record a file that exists but was left out by size/age selection.
+    pub fn mark_deselected(&mut self, path: PathBuf) {
+        self.deselected.insert(path);
+    }
+
+    /// Check whether a path was left out by size/age selection.
+    pub fn is_deselected(&self, path: &PathBuf) -> bool {
+        self.deselected.contains(path)
+    }
+
+    /// Remove a file entry, adjusting statistics.
+    pub fn remove(&mut self, path: &Path) -> Option<FileEntry> {
+        let entry = self.entries.remove(path)?;
+        self.total_size = self.total_size.saturating_sub(entry.size);
+        self.total_files = self.total_files.saturating_sub(1);
+        Some(entry)
+    }
+
+    /// Layer `overlay`, scanned from `source_root`, over this tree.
+    ///
+    /// Overlay entries replace entries at the same path and remember their
+    /// root in [`FileEntry::source_root`]. An overlay file also hides
+    /// everything this tree holds below its path, and an overlay directory
+    /// hides a file of this tree at its path.
+    pub fn merge_overlay(&mut self, overlay: FileTree, source_root: &Path) {
+        let overlay_files: HashSet<&Path> = overlay
+            .entries
+            .keys()
+            .chain(&overlay.deselected)
+            .map(PathBuf::as_path)
+            .collect();
+        let overlay_dirs: HashSet<&Path> = overlay_files
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect();
+        let shadowed: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .chain(&self.deselected)
+            .filter(|path| {
+                overlay_dirs.contains(path.as_path())
+                    || path
+                        .ancestors()
+                        .skip(1)
+                        .any(|ancestor| overlay_files.contains(ancestor))
+            })
+            .cloned()
+            .collect();
+        for path in shadowed {
+            self.remove(&path);
+            self.deselected.remove(&path);
+        }
+
+        for (path, mut entry) in overlay.entries {
+            entry.source_root = Some(source_root.to_path_buf());
+            self.insert(path, entry);
+        }
+        self.deselected.extend(overlay.deselected);
+        self.total_dirs = self.total_dirs.max(overlay.total_dirs);
+        self.scan_duration += overlay.scan_duration;
+    }
+
     /// Set the scan duration after scanning completes
     pub fn set_scan_duration(&mut self, duration: Duration) {
         self.scan_duration = duration;
@@ -348,4 +415,65 @@ mod tests {
         assert_eq!(cloned.scan_duration, Duration::from_secs(5));
         assert_eq!(cloned.total_dirs, 1);
     }
+
+    #[test]
+    fn test_merge_overlay_later_entries_win() {
+        let mut base = FileTree::new(PathBuf::from("/base"));
+        base.insert(PathBuf::from("app.conf"), create_test_entry("app.conf", 10));
+        base.insert(PathBuf::from("bin/app"), create_test_entry("bin/app", 1000));
+
+        let mut overlay = FileTree::new(PathBuf::from("/prod"));
+        overlay.insert(PathBuf::from("app.conf"), create_test_entry("app.conf", 25));
+        overlay.insert(
+            PathBuf::from("secrets.env"),
+            create_test_entry("secrets.env", 5),
+        );
+        overlay.mark_deselected(PathBuf::from("huge.log"));
+
+        base.merge_overlay(overlay, Path::new("/prod"));
+
+        assert_eq!(base.len(), 3);
+        assert_eq!(base.total_files, 3);
+        assert_eq!(base.total_size, 1030);
+        let conf = base.get(&PathBuf::from("app.conf")).expect("merged entry");
+        assert_eq!(conf.size, 25);
+        assert_eq!(conf.source_root, Some(PathBuf::from("/prod")));
+        assert_eq!(
+            base.get(&PathBuf::from("bin/app"))
+                .expect("base entry")
+                .source_root,
+            None
+        );
+        assert!(base.is_deselected(&PathBuf::from("huge.log")));
+    }
+
+    #[test]
+    fn test_merge_overlay_shadows_files_and_directories() {
+        let mut base = FileTree::new(PathBuf::from("/base"));
+        base.insert(PathBuf::from("a/b"), create_test_entry("a/b", 10));
+        base.insert(PathBuf::from("a/c/d"), create_test_entry("a/c/d", 20));
+        base.mark_deselected(PathBuf::from("a/big"));
+        base.insert(PathBuf::from("x"), create_test_entry("x", 30));
+        base.insert(PathBuf::from("keep"), create_test_entry("keep", 40));
+
+        let mut overlay = FileTree::new(PathBuf::from("/prod"));
+        overlay.insert(PathBuf::from("a"), create_test_entry("a", 1));
+        overlay.insert(PathBuf::from("x/y/z"), create_test_entry("x/y/z", 2));
+
+        base.merge_overlay(overlay, Path::new("/prod"));
+
+        let mut paths: Vec<&PathBuf> = base.paths().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                &PathBuf::from("a"),
+                &PathBuf::from("keep"),
+                &PathBuf::from("x/y/z")
+            ]
+        );
+        assert_eq!(base.total_files, 3);
+        assert_eq!(base.total_size, 43);
+        assert!(base.deselected.is_empty());
+    }
 }
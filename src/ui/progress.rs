@@ -1,6 +1,6 @@
 //! Progress reporting
 
-use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use std::path::Path;
 use std::time::Instant;
 
@@ -10,6 +10,7 @@ pub struct ProgressReporter {
     transfer_bar: ProgressBar,
     transfer_started_at: Option<Instant>,
     transferred_bytes: u64,
+    label: Option<String>,
 }
 
 impl ProgressReporter {
@@ -41,9 +42,27 @@ impl ProgressReporter {
             transfer_bar,
             transfer_started_at: None,
             transferred_bytes: 0,
+            label: None,
         }
     }
 
+    /// Create a reporter for one of several concurrent transfers.
+    ///
+    /// The transfer bar is drawn inside `multi` and its messages are
+    /// prefixed with `label` (usually the destination path).
+    pub fn labelled(multi: &MultiProgress, label: &str) -> Self {
+        let mut reporter = Self::new();
+        if let Ok(style) =
+            ProgressStyle::with_template("{prefix} {bar:30.cyan/blue} {pos}/{len} files | {msg}")
+        {
+            reporter.transfer_bar.set_style(style.progress_chars("=>-"));
+        }
+        reporter.transfer_bar.set_prefix(label.to_string());
+        reporter.transfer_bar = multi.add(reporter.transfer_bar);
+        reporter.label = Some(label.to_string());
+        reporter
+    }
+
     /// Mark start of a scanning phase.
     pub fn start_scan(&self, label: &str) {
         self.scan_bar
@@ -108,8 +127,11 @@ impl ProgressReporter {
         let path_display = path
             .map(|p| p.display().to_string())
             .unwrap_or_else(|| "<none>".to_string());
-        self.transfer_bar
-            .println(format!("ERROR {} {}: {}", action, path_display, err));
+        let line = format!("ERROR {} {}: {}", action, path_display, err);
+        self.transfer_bar.println(match &self.label {
+            Some(label) => format!("[{}] {}", label, line),
+            None => line,
+        });
     }
 
     /// Finalize transfer phase.
@@ -137,7 +159,10 @@ impl ProgressReporter {
             HumanBytes(throughput)
         );
         // Keep a plain summary line in scrollback for very fast runs where the bar is easy to miss.
-        self.transfer_bar.println(summary.clone());
+        self.transfer_bar.println(match &self.label {
+            Some(label) => format!("[{}] {}", label, summary),
+            None => summary.clone(),
+        });
         self.transfer_bar.finish_with_message(summary);
     }
 
@@ -222,6 +247,17 @@ mod tests {
         reporter.finish_scan("source", 3, 2048);
     }
 
+    #[test]
+    fn test_labelled_reporter_prefixes_transfer_bar() {
+        let multi = MultiProgress::new();
+        let mut reporter = ProgressReporter::labelled(&multi, "/mnt/backup");
+        reporter.start_transfer(1);
+        reporter.complete_transfer_file(64);
+
+        assert_eq!(reporter.transfer_bar.prefix(), "/mnt/backup");
+        assert_eq!(reporter.transfer_bar.position(), 1);
+    }
+
     #[test]
     fn test_finish_transfer_forces_final_position() {
         let mut reporter = ProgressReporter::new();
@@ -0,0 +1,180 @@
+//! End-to-end `kopy plan --out` / `kopy apply` tests.
+
+use kopy::commands::{apply, plan};
+use kopy::config::ApplyArgs;
+use kopy::diff::PlanDocument;
+use kopy::types::DeleteMode;
+use kopy::{Config, ConflictMode, KopyError};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn config_for(source: &Path, destination: &Path) -> Config {
+    Config {
+        source: source.to_path_buf(),
+        destination: destination.to_path_buf(),
+        ..Config::default()
+    }
+}
+
+fn save_plan(source: &Path, destination: &Path, out: &Path) {
+    save_config_plan(config_for(source, destination), out);
+}
+
+fn save_config_plan(config: Config, out: &Path) {
+    plan::run(config, None, Some(out)).expect("plan --out should succeed");
+}
+
+fn apply_args(plan: &Path, skip_stale: bool) -> ApplyArgs {
+    ApplyArgs {
+        plan: plan.to_path_buf(),
+        skip_stale,
+    }
+}
+
+#[test]
+fn test_saved_plan_round_trips_and_applies() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let out = work.path().join("plan.json");
+
+    fs::write(src.path().join("new.txt"), b"new").expect("write new");
+    fs::write(src.path().join("changed.txt"), b"changed!").expect("write changed");
+    fs::write(dst.path().join("changed.txt"), b"old").expect("write old");
+    filetime::set_file_mtime(
+        dst.path().join("changed.txt"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age destination file");
+
+    save_plan(src.path(), dst.path(), &out);
+    let document = PlanDocument::load(&out).expect("load saved plan");
+    assert_eq!(document.plan.stats.copy_count, 1);
+    assert_eq!(document.plan.stats.overwrite_count, 1);
+    assert!(document
+        .destination_entries
+        .contains_key(Path::new("changed.txt")));
+    assert!(
+        !dst.path().join("new.txt").exists(),
+        "plan must not execute"
+    );
+
+    apply::run(apply_args(&out, false)).expect("apply should succeed");
+    assert_eq!(fs::read(dst.path().join("new.txt")).unwrap(), b"new");
+    assert_eq!(
+        fs::read(dst.path().join("changed.txt")).unwrap(),
+        b"changed!"
+    );
+}
+
+#[test]
+fn test_apply_refuses_or_skips_stale_entries() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let out = work.path().join("plan.json");
+
+    fs::write(src.path().join("a.txt"), b"a").expect("write a");
+    fs::write(src.path().join("b.txt"), b"b").expect("write b");
+    save_plan(src.path(), dst.path(), &out);
+
+    fs::write(src.path().join("a.txt"), b"a edited after review").expect("edit a");
+
+    let err = apply::run(apply_args(&out, false)).expect_err("stale plan should be refused");
+    assert!(err.to_string().contains("1 planned entry changed"));
+    assert!(!dst.path().join("b.txt").exists());
+
+    apply::run(apply_args(&out, true)).expect("apply --skip-stale should succeed");
+    assert!(!dst.path().join("a.txt").exists());
+    assert_eq!(fs::read(dst.path().join("b.txt")).unwrap(), b"b");
+}
+
+#[test]
+fn test_apply_keep_both_conflict_renames_then_copies() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let out = work.path().join("plan.json");
+
+    fs::write(src.path().join("a.txt"), b"source").expect("write source");
+    fs::write(dst.path().join("a.txt"), b"edited in place").expect("write destination");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    filetime::set_file_mtime(src.path().join("a.txt"), older).expect("set source mtime");
+    filetime::set_file_mtime(dst.path().join("a.txt"), newer).expect("set dest mtime");
+
+    let config = Config {
+        conflict_mode: ConflictMode::KeepBoth,
+        ..config_for(src.path(), dst.path())
+    };
+    save_config_plan(config.clone(), &out);
+    apply::run(apply_args(&out, false)).expect("keep-both plan should apply");
+
+    assert_eq!(fs::read(dst.path().join("a.txt")).unwrap(), b"source");
+    let kept: Vec<Vec<u8>> = fs::read_dir(dst.path())
+        .expect("read destination")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| path.to_string_lossy().contains(".conflict-destination-"))
+        .map(|path| fs::read(path).expect("read conflict copy"))
+        .collect();
+    assert_eq!(kept, vec![b"edited in place".to_vec()]);
+}
+
+#[test]
+fn test_apply_skip_stale_drops_copy_with_its_conflict_rename() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let out = work.path().join("plan.json");
+
+    fs::write(src.path().join("a.txt"), b"source").expect("write source");
+    fs::write(dst.path().join("a.txt"), b"edited").expect("write destination");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    filetime::set_file_mtime(src.path().join("a.txt"), older).expect("set source mtime");
+    filetime::set_file_mtime(dst.path().join("a.txt"), newer).expect("set dest mtime");
+
+    let config = Config {
+        conflict_mode: ConflictMode::KeepBoth,
+        ..config_for(src.path(), dst.path())
+    };
+    save_config_plan(config, &out);
+    fs::write(dst.path().join("a.txt"), b"edited again").expect("edit destination again");
+
+    let err = apply::run(apply_args(&out, false)).expect_err("stale rename should be refused");
+    assert!(err.to_string().contains("1 planned entry changed"));
+
+    apply::run(apply_args(&out, true)).expect("apply --skip-stale should succeed");
+    assert_eq!(fs::read(dst.path().join("a.txt")).unwrap(), b"edited again");
+    assert_eq!(
+        fs::read_dir(dst.path()).expect("read destination").count(),
+        1
+    );
+}
+
+#[test]
+fn test_apply_refuses_deletes_from_empty_source() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let out = work.path().join("plan.json");
+    fs::write(dst.path().join("keep.txt"), b"keep").expect("write destination");
+
+    let config = Config {
+        delete_mode: DeleteMode::Permanent,
+        ..config_for(src.path(), dst.path())
+    };
+    save_config_plan(config.clone(), &out);
+    let err = apply::run(apply_args(&out, false)).expect_err("empty source must be refused");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 1, .. }));
+    assert_eq!(fs::read(dst.path().join("keep.txt")).unwrap(), b"keep");
+
+    let allowed = Config {
+        allow_empty_source: true,
+        ..config
+    };
+    save_config_plan(allowed, &out);
+    apply::run(apply_args(&out, false)).expect("allowed empty source should apply");
+    assert!(!dst.path().join("keep.txt").exists());
+}
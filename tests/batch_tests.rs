@@ -0,0 +1,249 @@
+//! End-to-end `kopy batch` tests.
+
+use kopy::batch::{write_batch, DestinationIndex};
+use kopy::commands::batch::run;
+use kopy::config::{BatchArgs, BatchCommand, FilterOptions, SyncOptions};
+use kopy::diff::{ConflictOutcome, DiffPlan, PlanConflict, TreeTotals};
+use kopy::types::{DeleteMode, SyncAction};
+use kopy::{Config, ConflictMode, KopyError};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tempfile::TempDir;
+
+fn index(destination: &Path, index: &Path) {
+    run(BatchArgs {
+        command: BatchCommand::Index {
+            destination: destination.to_path_buf(),
+            index: index.to_path_buf(),
+            filters: FilterOptions::default(),
+        },
+    })
+    .expect("batch index should succeed");
+}
+
+fn create(source: &Path, index: &Path, out: &Path) {
+    try_create(source, index, out).expect("batch create should succeed");
+}
+
+fn try_create(source: &Path, index: &Path, out: &Path) -> Result<(), KopyError> {
+    try_create_with(
+        source,
+        index,
+        out,
+        SyncOptions {
+            delete: true,
+            ..SyncOptions::default()
+        },
+    )
+}
+
+fn try_create_with(
+    source: &Path,
+    index: &Path,
+    out: &Path,
+    options: SyncOptions,
+) -> Result<(), KopyError> {
+    run(BatchArgs {
+        command: BatchCommand::Create {
+            source: source.to_path_buf(),
+            index: index.to_path_buf(),
+            out: out.to_path_buf(),
+            options,
+        },
+    })
+}
+
+fn apply(batch: &Path, destination: &Path) -> Result<(), KopyError> {
+    run(BatchArgs {
+        command: BatchCommand::Apply {
+            batch: batch.to_path_buf(),
+            destination: destination.to_path_buf(),
+        },
+    })
+}
+
+#[test]
+fn test_batch_replays_sync_on_indexed_destination() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let (index_path, batch) = (
+        work.path().join("dst.index"),
+        work.path().join("out.kbatch"),
+    );
+
+    fs::create_dir_all(src.path().join("nested")).expect("create nested");
+    fs::write(src.path().join("nested/new.txt"), b"new").expect("write new");
+    fs::write(src.path().join("changed.txt"), b"changed!").expect("write changed");
+    fs::write(dst.path().join("changed.txt"), b"old").expect("write old");
+    filetime::set_file_mtime(
+        dst.path().join("changed.txt"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age destination file");
+    fs::write(dst.path().join("orphan.txt"), b"orphan").expect("write orphan");
+
+    index(dst.path(), &index_path);
+    create(src.path(), &index_path, &batch);
+    assert!(!dst.path().join("nested/new.txt").exists());
+
+    apply(&batch, dst.path()).expect("batch apply should succeed");
+    assert_eq!(fs::read(dst.path().join("nested/new.txt")).unwrap(), b"new");
+    assert_eq!(
+        fs::read(dst.path().join("changed.txt")).unwrap(),
+        b"changed!"
+    );
+    assert!(!dst.path().join("orphan.txt").exists());
+    let leftovers: Vec<_> = fs::read_dir(dst.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with(".kopy_batch"))
+        .collect();
+    assert!(
+        leftovers.is_empty(),
+        "staging dir left behind: {leftovers:?}"
+    );
+}
+
+#[test]
+fn test_batch_apply_refuses_destination_changed_since_index() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let (index_path, batch) = (
+        work.path().join("dst.index"),
+        work.path().join("out.kbatch"),
+    );
+
+    fs::write(src.path().join("a.txt"), b"source").expect("write source");
+    fs::write(dst.path().join("a.txt"), b"old").expect("write old");
+    filetime::set_file_mtime(
+        dst.path().join("a.txt"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age destination file");
+    index(dst.path(), &index_path);
+    create(src.path(), &index_path, &batch);
+
+    fs::write(dst.path().join("a.txt"), b"edited offline").expect("edit destination");
+    let err = apply(&batch, dst.path()).expect_err("changed destination must be refused");
+    assert!(err.to_string().contains("no longer match the index"));
+    assert_eq!(
+        fs::read(dst.path().join("a.txt")).unwrap(),
+        b"edited offline"
+    );
+}
+
+#[test]
+fn test_batch_create_refuses_empty_source_deleting_everything() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let (index_path, batch) = (
+        work.path().join("dst.index"),
+        work.path().join("out.kbatch"),
+    );
+
+    fs::write(dst.path().join("keep.txt"), b"keep").expect("write destination");
+    index(dst.path(), &index_path);
+
+    let err = try_create(src.path(), &index_path, &batch).expect_err("empty source");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 1, .. }));
+    assert!(!batch.exists());
+}
+
+#[test]
+fn test_batch_apply_refuses_deletes_from_recorded_empty_source() {
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let batch = work.path().join("out.kbatch");
+    fs::write(dst.path().join("keep.txt"), b"keep").expect("write destination");
+    let index_path = work.path().join("dst.index");
+    index(dst.path(), &index_path);
+
+    let mut plan = DiffPlan::new();
+    plan.add_action(SyncAction::Delete(PathBuf::from("keep.txt")));
+    let config = Config {
+        source: work.path().to_path_buf(),
+        delete_mode: DeleteMode::Permanent,
+        ..Config::default()
+    };
+    let totals = TreeTotals {
+        source_files: 0,
+        destination_files: 1,
+        destination_bytes: 4,
+    };
+    let destination_entries = DestinationIndex::load(&index_path)
+        .expect("load index")
+        .entries;
+    write_batch(&batch, &plan, &config, totals, destination_entries).expect("write batch");
+
+    let err = apply(&batch, dst.path()).expect_err("empty source must be refused");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 1, .. }));
+    assert_eq!(fs::read(dst.path().join("keep.txt")).unwrap(), b"keep");
+}
+
+#[test]
+fn test_batch_create_refuses_conflicts_with_conflict_fail() {
+    let src = TempDir::new().expect("create src");
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let (index_path, batch) = (
+        work.path().join("dst.index"),
+        work.path().join("out.kbatch"),
+    );
+
+    fs::write(src.path().join("notes.md"), b"source").expect("write source");
+    fs::write(dst.path().join("notes.md"), b"edited").expect("write destination");
+    filetime::set_file_mtime(
+        src.path().join("notes.md"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age source file");
+    index(dst.path(), &index_path);
+
+    let options = SyncOptions {
+        conflict: Some(ConflictMode::Fail),
+        ..SyncOptions::default()
+    };
+    let err = try_create_with(src.path(), &index_path, &batch, options)
+        .expect_err("conflict should refuse the batch");
+    assert!(err.to_string().contains("newer than their source"));
+    assert!(!batch.exists());
+}
+
+#[test]
+fn test_batch_apply_refuses_recorded_conflicts_with_conflict_fail() {
+    let dst = TempDir::new().expect("create dst");
+    let work = TempDir::new().expect("create work dir");
+    let batch = work.path().join("out.kbatch");
+    fs::write(dst.path().join("notes.md"), b"edited").expect("write destination");
+    let index_path = work.path().join("dst.index");
+    index(dst.path(), &index_path);
+
+    let mut plan = DiffPlan::new();
+    plan.add_action(SyncAction::Delete(PathBuf::from("gone.txt")));
+    plan.add_conflict(PlanConflict {
+        path: PathBuf::from("notes.md"),
+        source_mtime: UNIX_EPOCH + Duration::from_secs(1_000_000),
+        destination_mtime: UNIX_EPOCH + Duration::from_secs(2_000_000),
+        outcome: ConflictOutcome::Refuse,
+    });
+    let config = Config {
+        source: work.path().to_path_buf(),
+        delete_mode: DeleteMode::Permanent,
+        conflict_mode: ConflictMode::Fail,
+        ..Config::default()
+    };
+    let totals = TreeTotals {
+        source_files: 1,
+        destination_files: 1,
+        destination_bytes: 6,
+    };
+    write_batch(&batch, &plan, &config, totals, Default::default()).expect("write batch");
+
+    let err = apply(&batch, dst.path()).expect_err("conflicts must be refused");
+    assert!(err.to_string().contains("--conflict fail"));
+    assert_eq!(fs::read(dst.path().join("notes.md")).unwrap(), b"edited");
+}
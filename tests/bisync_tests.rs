@@ -0,0 +1,202 @@
+//! End-to-end `kopy bisync` tests.
+
+use kopy::bisync::ConflictPolicy;
+use kopy::commands::bisync::run;
+use kopy::config::{BisyncArgs, FilterOptions};
+use kopy::diff::DeleteLimit;
+use kopy::KopyError;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn args_for(left: &Path, right: &Path, state_dir: &Path, conflict: ConflictPolicy) -> BisyncArgs {
+    BisyncArgs {
+        left: left.to_path_buf(),
+        right: right.to_path_buf(),
+        conflict,
+        dry_run: false,
+        state_dir: Some(state_dir.to_path_buf()),
+        max_delete: None,
+        allow_empty_source: false,
+        filters: FilterOptions::default(),
+    }
+}
+
+fn conflict_copies(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root)
+        .expect("read root")
+        .map(|entry| entry.expect("dir entry").file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("b.conflict-right-"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_bisync_propagates_both_ways_and_keeps_both_on_conflict() {
+    let left = TempDir::new().expect("create left");
+    let right = TempDir::new().expect("create right");
+    let state = TempDir::new().expect("create state dir");
+
+    fs::write(left.path().join("a.txt"), b"a").expect("write a");
+    fs::write(left.path().join("b.txt"), b"b").expect("write b");
+    fs::write(right.path().join("c.txt"), b"c").expect("write c");
+
+    let args = || {
+        args_for(
+            left.path(),
+            right.path(),
+            state.path(),
+            ConflictPolicy::KeepBoth,
+        )
+    };
+    run(args()).expect("first bisync should succeed");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(left.path().join(name).exists(), "left is missing {name}");
+        assert!(right.path().join(name).exists(), "right is missing {name}");
+    }
+
+    fs::write(left.path().join("a.txt"), b"a edited on left").expect("edit a");
+    fs::remove_file(right.path().join("c.txt")).expect("delete c");
+    fs::write(left.path().join("b.txt"), b"left version").expect("edit b on left");
+    fs::write(right.path().join("b.txt"), b"right version!").expect("edit b on right");
+
+    run(args()).expect("second bisync should succeed");
+
+    assert_eq!(
+        fs::read(right.path().join("a.txt")).expect("read right a"),
+        b"a edited on left"
+    );
+    assert!(!left.path().join("c.txt").exists());
+    assert!(left.path().join(".kopy_trash").exists());
+
+    assert_eq!(
+        fs::read(right.path().join("b.txt")).expect("read right b"),
+        b"left version"
+    );
+    let left_copies = conflict_copies(left.path());
+    assert_eq!(left_copies.len(), 1);
+    assert_eq!(left_copies, conflict_copies(right.path()));
+    assert_eq!(
+        fs::read(left.path().join(&left_copies[0])).expect("read conflict copy"),
+        b"right version!"
+    );
+
+    run(args()).expect("third bisync should find nothing to do");
+    assert_eq!(conflict_copies(left.path()).len(), 1);
+}
+
+#[test]
+fn test_bisync_skip_policy_leaves_conflicts_untouched() {
+    let left = TempDir::new().expect("create left");
+    let right = TempDir::new().expect("create right");
+    let state = TempDir::new().expect("create state dir");
+
+    fs::write(left.path().join("b.txt"), b"left").expect("write left b");
+    fs::write(right.path().join("b.txt"), b"right side").expect("write right b");
+
+    let args = || {
+        args_for(
+            left.path(),
+            right.path(),
+            state.path(),
+            ConflictPolicy::Skip,
+        )
+    };
+    for _ in 0..2 {
+        let err = run(args()).expect_err("unresolved conflict should fail");
+        assert!(err.to_string().contains("1 conflict(s) left unresolved"));
+        assert_eq!(fs::read(left.path().join("b.txt")).unwrap(), b"left");
+        assert_eq!(fs::read(right.path().join("b.txt")).unwrap(), b"right side");
+    }
+}
+
+#[test]
+fn test_bisync_refuses_side_that_vanished_since_last_run() {
+    let left = TempDir::new().expect("create left");
+    let right = TempDir::new().expect("create right");
+    let state = TempDir::new().expect("create state dir");
+
+    fs::write(left.path().join("a.txt"), b"a").expect("write a");
+    fs::write(left.path().join("b.txt"), b"b").expect("write b");
+    let args = || {
+        args_for(
+            left.path(),
+            right.path(),
+            state.path(),
+            ConflictPolicy::KeepBoth,
+        )
+    };
+    run(args()).expect("first bisync should succeed");
+
+    fs::remove_dir_all(right.path()).expect("unmount right");
+    let err = run(args()).expect_err("vanished right side must be refused");
+    assert!(err.to_string().contains("missing or empty"), "{err}");
+    assert!(left.path().join("a.txt").exists());
+    assert!(left.path().join("b.txt").exists());
+
+    fs::create_dir_all(right.path()).expect("remount empty right");
+    let err = run(args()).expect_err("empty right side must be refused");
+    assert!(err.to_string().contains("missing or empty"), "{err}");
+    assert!(left.path().join("a.txt").exists());
+
+    let allowed = BisyncArgs {
+        allow_empty_source: true,
+        ..args()
+    };
+    run(allowed).expect("emptying on purpose is allowed");
+    assert!(!left.path().join("a.txt").exists());
+}
+
+#[test]
+fn test_bisync_max_delete_refuses_before_changing_anything() {
+    let left = TempDir::new().expect("create left");
+    let right = TempDir::new().expect("create right");
+    let state = TempDir::new().expect("create state dir");
+
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(left.path().join(name), name).expect("write file");
+    }
+    let args = || BisyncArgs {
+        max_delete: Some(DeleteLimit::Count(1)),
+        ..args_for(
+            left.path(),
+            right.path(),
+            state.path(),
+            ConflictPolicy::KeepBoth,
+        )
+    };
+    run(args()).expect("first bisync should succeed");
+
+    fs::remove_file(right.path().join("a.txt")).expect("delete a on right");
+    fs::remove_file(right.path().join("b.txt")).expect("delete b on right");
+    fs::write(right.path().join("new.txt"), b"new").expect("write new on right");
+    let err = run(args()).expect_err("two deletes exceed the limit");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 2, .. }));
+    assert!(left.path().join("a.txt").exists());
+    assert!(!left.path().join("new.txt").exists());
+}
+
+#[test]
+fn test_bisync_newer_policy_trashes_the_losing_version() {
+    let left = TempDir::new().expect("create left");
+    let right = TempDir::new().expect("create right");
+    let state = TempDir::new().expect("create state dir");
+
+    fs::write(left.path().join("b.txt"), b"left").expect("write left b");
+    fs::write(right.path().join("b.txt"), b"right side").expect("write right b");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    filetime::set_file_mtime(left.path().join("b.txt"), older).expect("set left mtime");
+    filetime::set_file_mtime(right.path().join("b.txt"), newer).expect("set right mtime");
+
+    run(args_for(
+        left.path(),
+        right.path(),
+        state.path(),
+        ConflictPolicy::Newer,
+    ))
+    .expect("newer wins");
+    assert_eq!(fs::read(left.path().join("b.txt")).unwrap(), b"right side");
+    assert!(left.path().join(".kopy_trash").exists());
+}
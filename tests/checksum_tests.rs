@@ -5,7 +5,7 @@
 use kopy::config::ScanMode;
 use kopy::diff::generate_sync_plan;
 use kopy::hash::compute_hash;
-use kopy::types::{DeleteMode, FileEntry, FileTree};
+use kopy::types::{DeleteMode, FileEntry, FileTree, SyncAction, UpdateReason};
 use kopy::Config;
 use std::fs;
 use std::path::PathBuf;
@@ -27,6 +27,7 @@ fn create_test_config(checksum_mode: bool) -> Config {
         backup_dir: None,
         watch: false,
         watch_settle: 2,
+        ..Config::default()
     }
 }
 
@@ -143,7 +144,10 @@ fn test_checksum_mismatch() {
         plan.stats.overwrite_count, 1,
         "Checksum mismatch should trigger Overwrite"
     );
-    assert!(plan.actions[0].is_overwrite());
+    assert!(matches!(
+        plan.actions[0],
+        SyncAction::Overwrite(_, UpdateReason::HashMismatch)
+    ));
 }
 
 #[test]
@@ -186,10 +190,11 @@ fn test_checksum_match() {
     let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
 
     assert_eq!(
-        plan.stats.skip_count, 1,
-        "Checksum match should trigger Skip even with different mtimes"
+        plan.stats.metadata_count, 1,
+        "Checksum match with different mtimes should only update metadata"
     );
     assert_eq!(plan.stats.overwrite_count, 0);
+    assert_eq!(plan.stats.total_bytes, 0);
 }
 
 #[test]
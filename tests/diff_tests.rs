@@ -2,9 +2,9 @@
 //!
 //! Tests for the diff engine's ability to compare file trees and generate sync plans.
 
-use kopy::config::ScanMode;
-use kopy::diff::{compare_files, generate_sync_plan};
-use kopy::types::{DeleteMode, FileEntry, FileTree, SyncAction};
+use kopy::config::{ConflictMode, ScanMode};
+use kopy::diff::{compare_files, generate_sync_plan, ConflictOutcome};
+use kopy::types::{DeleteMode, FileEntry, FileTree, SyncAction, UpdateReason};
 use kopy::Config;
 use std::fs;
 use std::path::PathBuf;
@@ -45,6 +45,7 @@ fn create_test_config(delete_mode: DeleteMode) -> Config {
         backup_dir: None,
         watch: false,
         watch_settle: 2,
+        ..Config::default()
     }
 }
 
@@ -60,8 +61,15 @@ fn test_compare_size_mismatch() {
         action.is_overwrite(),
         "Size mismatch should trigger Overwrite"
     );
-    if let SyncAction::Overwrite(entry) = action {
+    if let SyncAction::Overwrite(entry, reason) = action {
         assert_eq!(entry.size, 1024);
+        assert_eq!(
+            reason,
+            UpdateReason::SizeChanged {
+                source: 1024,
+                destination: 2048
+            }
+        );
     }
 }
 
@@ -77,6 +85,10 @@ fn test_compare_src_newer() {
         action.is_overwrite(),
         "Source newer should trigger Overwrite"
     );
+    assert!(matches!(
+        action,
+        SyncAction::Overwrite(_, UpdateReason::SourceNewer)
+    ));
 }
 
 #[test]
@@ -101,6 +113,96 @@ fn test_compare_identical() {
     assert!(action.is_skip(), "Identical files should Skip");
 }
 
+#[test]
+fn test_compare_permission_drift_updates_metadata_only() {
+    let src = FileEntry::new(
+        PathBuf::from("run.sh"),
+        1024,
+        UNIX_EPOCH + Duration::from_secs(1000),
+        0o100755,
+    );
+    let dest = create_test_entry("run.sh", 1024, 1000);
+    let config = create_test_config(DeleteMode::None);
+
+    let action = compare_files(&src, &dest, &config);
+    assert_eq!(action, SyncAction::UpdateMetadata(src.clone()));
+
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(PathBuf::from("run.sh"), src);
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    dest_tree.insert(PathBuf::from("run.sh"), dest);
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(plan.stats.metadata_count, 1);
+    assert_eq!(plan.stats.total_bytes, 0);
+}
+
+#[test]
+fn test_compare_checksum_equal_content_with_mtime_drift_updates_metadata() {
+    let temp = TempDir::new().expect("create temp dir");
+    let src_root = temp.path().join("src");
+    let dest_root = temp.path().join("dest");
+    fs::create_dir_all(&src_root).expect("create src");
+    fs::create_dir_all(&dest_root).expect("create dest");
+    fs::write(src_root.join("same.txt"), b"same").expect("write src");
+    fs::write(dest_root.join("same.txt"), b"same").expect("write dest");
+
+    let src = create_test_entry("same.txt", 4, 2000);
+    let dest = create_test_entry("same.txt", 4, 1000);
+    let config = Config {
+        source: src_root,
+        destination: dest_root,
+        checksum_mode: true,
+        ..Config::default()
+    };
+
+    assert_eq!(
+        compare_files(&src, &dest, &config),
+        SyncAction::UpdateMetadata(src.clone())
+    );
+    assert!(compare_files(&src, &src.clone(), &config).is_skip());
+}
+
+#[test]
+fn test_compare_size_only_and_ignore_times() {
+    let src = create_test_entry("file.txt", 1024, 2000);
+    let dest = create_test_entry("file.txt", 1024, 1000);
+
+    let mut config = create_test_config(DeleteMode::None);
+    config.size_only = true;
+    assert!(compare_files(&src, &dest, &config).is_skip());
+    let smaller = create_test_entry("file.txt", 10, 1000);
+    assert!(compare_files(&src, &smaller, &config).is_overwrite());
+
+    let mut config = create_test_config(DeleteMode::None);
+    config.ignore_times = true;
+    assert!(compare_files(&src, &src.clone(), &config).is_overwrite());
+}
+
+#[test]
+fn test_compare_modify_window_tolerates_drift() {
+    let src = create_test_entry("file.txt", 1024, 1002);
+    let dest = create_test_entry("file.txt", 1024, 1000);
+
+    let mut config = create_test_config(DeleteMode::None);
+    assert!(compare_files(&src, &dest, &config).is_overwrite());
+
+    config.modify_window = Duration::from_secs(2);
+    assert!(compare_files(&src, &dest, &config).is_skip());
+    let plan_src = create_test_entry("file.txt", 1024, 1000);
+    let plan_dest = create_test_entry("file.txt", 1024, 1002);
+    assert!(compare_files(&plan_src, &plan_dest, &config).is_skip());
+
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(PathBuf::from("file.txt"), plan_src);
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    dest_tree.insert(PathBuf::from("file.txt"), plan_dest);
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert!(
+        plan.conflicts.is_empty(),
+        "drift within the window is no conflict"
+    );
+}
+
 #[test]
 fn test_compare_checksum_single_file_source_uses_direct_file_paths() {
     let src_dir = TempDir::new().expect("create src tempdir");
@@ -156,6 +258,45 @@ fn test_compare_file_kind_mismatch_symlink_vs_regular() {
     assert!(action.is_overwrite());
 }
 
+#[test]
+fn test_compare_records_overwrite_reason() {
+    let reason =
+        |src: &FileEntry, dest: &FileEntry, config: &Config| match compare_files(src, dest, config)
+        {
+            SyncAction::Overwrite(_, reason) => reason,
+            other => panic!("expected Overwrite, got {:?}", other),
+        };
+    let config = create_test_config(DeleteMode::None);
+    let file = create_test_entry("entry", 4, 1000);
+    let link = create_test_symlink_entry("entry", "a.txt", 1000);
+    let relinked = create_test_symlink_entry("entry", "b.txt", 1000);
+
+    assert_eq!(reason(&link, &file, &config), UpdateReason::TypeChanged);
+    assert_eq!(
+        reason(&link, &relinked, &config),
+        UpdateReason::SymlinkTargetChanged
+    );
+
+    let ignore_times = Config {
+        ignore_times: true,
+        ..create_test_config(DeleteMode::None)
+    };
+    assert_eq!(
+        reason(&file, &file.clone(), &ignore_times),
+        UpdateReason::IgnoreTimes
+    );
+
+    // Neither /src/entry nor /dest/entry exists, so hashing fails.
+    let checksum = Config {
+        checksum_mode: true,
+        ..create_test_config(DeleteMode::None)
+    };
+    assert_eq!(
+        reason(&file, &file.clone(), &checksum),
+        UpdateReason::HashFailed
+    );
+}
+
 #[test]
 fn test_diff_copy_new() {
     let mut src_tree = FileTree::new(PathBuf::from("/src"));
@@ -240,6 +381,178 @@ fn test_diff_skip_older() {
     assert_eq!(plan.stats.skip_count, 1);
     assert_eq!(plan.stats.overwrite_count, 0);
     assert_eq!(plan.stats.total_files, 0); // Skips don't count as transfers
+    assert_eq!(plan.stats.conflict_count, 1);
+    assert_eq!(plan.conflicts[0].path, PathBuf::from("file.txt"));
+    assert_eq!(plan.conflicts[0].outcome, ConflictOutcome::KeepDestination);
+}
+
+#[test]
+fn test_diff_destination_newer_conflict_modes() {
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(
+        PathBuf::from("file.txt"),
+        create_test_entry("file.txt", 1024, 1000),
+    );
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    dest_tree.insert(
+        PathBuf::from("file.txt"),
+        create_test_entry("file.txt", 1024, 2000),
+    );
+
+    let mut config = create_test_config(DeleteMode::None);
+    config.conflict_mode = ConflictMode::SourceWins;
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(plan.stats.overwrite_count, 1);
+    assert_eq!(plan.stats.skip_count, 0);
+    assert_eq!(plan.conflicts[0].outcome, ConflictOutcome::SourceWins);
+
+    config.conflict_mode = ConflictMode::KeepBoth;
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    let ConflictOutcome::KeepBoth(renamed) = &plan.conflicts[0].outcome else {
+        panic!("Expected keep-both outcome");
+    };
+    assert!(renamed
+        .to_string_lossy()
+        .starts_with("file.conflict-destination-"));
+    assert_eq!(
+        plan.actions,
+        vec![
+            SyncAction::Move {
+                from: PathBuf::from("file.txt"),
+                to: renamed.clone()
+            },
+            SyncAction::CopyNew(create_test_entry("file.txt", 1024, 1000)),
+        ]
+    );
+
+    // Checksum mode reports a conflict only when the content differs too.
+    config.checksum_mode = true;
+    config.conflict_mode = ConflictMode::Skip;
+    let hashed = |tree: &mut FileTree, hash: [u8; 32]| {
+        let entry = tree.get(&PathBuf::from("file.txt")).unwrap().clone();
+        tree.insert(PathBuf::from("file.txt"), entry.with_hash(hash));
+    };
+    hashed(&mut src_tree, [1; 32]);
+    hashed(&mut dest_tree, [2; 32]);
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(plan.conflicts.len(), 1);
+
+    hashed(&mut dest_tree, [1; 32]);
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert!(plan.conflicts.is_empty());
+    assert_eq!(plan.stats.metadata_count, 1);
+}
+
+#[test]
+fn test_diff_destination_newer_with_different_size_is_a_conflict() {
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(
+        PathBuf::from("notes.md"),
+        create_test_entry("notes.md", 100, 1000),
+    );
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    dest_tree.insert(
+        PathBuf::from("notes.md"),
+        create_test_entry("notes.md", 180, 2000),
+    );
+
+    let mut config = create_test_config(DeleteMode::None);
+    for mode in [ConflictMode::Skip, ConflictMode::NewerWins] {
+        config.conflict_mode = mode;
+        let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.conflicts[0].outcome, ConflictOutcome::KeepDestination);
+    }
+
+    config.conflict_mode = ConflictMode::Fail;
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert!(plan.actions.is_empty());
+    assert_eq!(plan.conflicts[0].outcome, ConflictOutcome::Refuse);
+
+    config.conflict_mode = ConflictMode::SourceWins;
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(
+        plan.actions,
+        vec![SyncAction::Overwrite(
+            create_test_entry("notes.md", 100, 1000),
+            UpdateReason::ConflictSourceWins
+        )]
+    );
+}
+
+/// Source and destination trees for the update-mode selectors: `new.txt` is
+/// source-only, `changed.txt` is newer in the source and `edited.txt` is
+/// newer in the destination (both differ in size).
+fn selector_trees() -> (FileTree, FileTree) {
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    for (path, src, dest) in [
+        ("new.txt", Some((10, 1000)), None),
+        ("changed.txt", Some((20, 2000)), Some((21, 1000))),
+        ("edited.txt", Some((30, 1000)), Some((31, 2000))),
+    ] {
+        if let Some((size, mtime)) = src {
+            src_tree.insert(PathBuf::from(path), create_test_entry(path, size, mtime));
+        }
+        if let Some((size, mtime)) = dest {
+            dest_tree.insert(PathBuf::from(path), create_test_entry(path, size, mtime));
+        }
+    }
+    (src_tree, dest_tree)
+}
+
+fn planned_paths(plan: &kopy::diff::DiffPlan) -> Vec<String> {
+    plan.actions
+        .iter()
+        .filter_map(|action| action.path())
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn test_diff_existing_only_updates_destination_files() {
+    let (src_tree, dest_tree) = selector_trees();
+    let mut config = create_test_config(DeleteMode::None);
+    config.existing = true;
+    config.conflict_mode = ConflictMode::SourceWins;
+
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(planned_paths(&plan), vec!["changed.txt", "edited.txt"]);
+    assert_eq!(plan.stats.copy_count, 0);
+    assert_eq!(plan.stats.overwrite_count, 2);
+}
+
+#[test]
+fn test_diff_ignore_existing_only_creates_new_files() {
+    let (src_tree, dest_tree) = selector_trees();
+    let mut config = create_test_config(DeleteMode::None);
+    config.ignore_existing = true;
+
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(planned_paths(&plan), vec!["new.txt"]);
+    assert_eq!(plan.stats.overwrite_count, 0);
+    assert_eq!(plan.stats.skip_count, 2);
+}
+
+#[test]
+fn test_diff_update_keeps_newer_destination_files() {
+    let (src_tree, dest_tree) = selector_trees();
+    let mut config = create_test_config(DeleteMode::None);
+    config.update = true;
+    config.conflict_mode = ConflictMode::SourceWins;
+
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(planned_paths(&plan), vec!["changed.txt", "new.txt"]);
+    assert_eq!(plan.stats.skip_count, 1);
+
+    // Without --update the newer destination is a conflict, settled last.
+    config.update = false;
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(
+        planned_paths(&plan),
+        vec!["changed.txt", "new.txt", "edited.txt"]
+    );
+    assert_eq!(plan.stats.conflict_count, 1);
 }
 
 #[test]
@@ -465,3 +778,126 @@ fn test_diff_conflict_in_none_mode_is_non_destructive() {
         |a| matches!(a, SyncAction::CopyNew(entry) if entry.path == std::path::Path::new("a"))
     ));
 }
+
+#[test]
+fn test_diff_protected_paths_are_not_deleted_or_overwritten() {
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(
+        PathBuf::from("config.local"),
+        create_test_entry("config.local", 10, 2000),
+    );
+    src_tree.insert(
+        PathBuf::from("index.html"),
+        create_test_entry("index.html", 10, 2000),
+    );
+    src_tree.insert(
+        PathBuf::from("uploads"),
+        create_test_entry("uploads", 3, 2000),
+    );
+
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    for (path, size) in [
+        ("config.local", 5),
+        ("index.html", 5),
+        ("uploads/a.jpg", 7),
+        ("stale.txt", 1),
+    ] {
+        dest_tree.insert(PathBuf::from(path), create_test_entry(path, size, 1000));
+    }
+
+    let config = Config {
+        protect_patterns: vec!["config.local".to_string(), "/uploads/".to_string()],
+        ..create_test_config(DeleteMode::Permanent)
+    };
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+
+    // The file `uploads` could only be copied by deleting the protected
+    // directory, whose orphaned contents are kept as well.
+    assert_eq!(
+        plan.protected,
+        vec![
+            PathBuf::from("config.local"),
+            PathBuf::from("uploads"),
+            PathBuf::from("uploads/a.jpg"),
+        ]
+    );
+    assert_eq!(plan.stats.protected_count, 3);
+    assert_eq!(
+        plan.actions,
+        vec![
+            SyncAction::Overwrite(
+                create_test_entry("index.html", 10, 2000),
+                UpdateReason::SizeChanged {
+                    source: 10,
+                    destination: 5
+                }
+            ),
+            SyncAction::Delete(PathBuf::from("stale.txt")),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_protected_paths_keep_their_metadata() {
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    let mut script = create_test_entry("deploy.sh", 10, 1000);
+    script.permissions = 0o755;
+    src_tree.insert(PathBuf::from("deploy.sh"), script);
+    let mut dest_tree = FileTree::new(PathBuf::from("/dest"));
+    dest_tree.insert(
+        PathBuf::from("deploy.sh"),
+        create_test_entry("deploy.sh", 10, 1000),
+    );
+
+    let mut config = create_test_config(DeleteMode::None);
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert_eq!(plan.stats.metadata_count, 1);
+
+    config.protect_patterns = vec!["deploy.sh".to_string()];
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+    assert!(plan.actions.is_empty());
+    assert_eq!(plan.protected, vec![PathBuf::from("deploy.sh")]);
+}
+
+#[test]
+fn test_diff_keep_file_protects_orphans_and_newer_destinations() {
+    let dest_dir = TempDir::new().expect("create dest tempdir");
+    fs::create_dir_all(dest_dir.path().join("site")).expect("create site dir");
+    fs::write(dest_dir.path().join("site/.kopykeep"), "*.env\n").expect("write keep file");
+
+    let mut src_tree = FileTree::new(PathBuf::from("/src"));
+    src_tree.insert(
+        PathBuf::from("site/prod.env"),
+        create_test_entry("site/prod.env", 4, 1000),
+    );
+
+    let mut dest_tree = FileTree::new(dest_dir.path().to_path_buf());
+    for (path, mtime) in [
+        ("site/.kopykeep", 1000),
+        ("site/prod.env", 5000),
+        ("site/local.env", 1000),
+        ("site/old.html", 1000),
+    ] {
+        dest_tree.insert(PathBuf::from(path), create_test_entry(path, 4, mtime));
+    }
+
+    let config = Config {
+        conflict_mode: ConflictMode::SourceWins,
+        ..create_test_config(DeleteMode::Permanent)
+    };
+    let plan = generate_sync_plan(&src_tree, &dest_tree, &config);
+
+    assert_eq!(
+        plan.protected,
+        vec![
+            PathBuf::from("site/.kopykeep"),
+            PathBuf::from("site/local.env"),
+            PathBuf::from("site/prod.env"),
+        ]
+    );
+    assert!(plan.conflicts.is_empty());
+    assert_eq!(
+        plan.actions,
+        vec![SyncAction::Delete(PathBuf::from("site/old.html"))]
+    );
+}
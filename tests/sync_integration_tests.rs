@@ -4,7 +4,9 @@
 //! basic sync, overwrite/update behavior, dry-run safety, and excludes.
 
 use kopy::commands::sync::run;
-use kopy::{Config, DeleteMode};
+use kopy::diff::DeleteLimit;
+
+use kopy::{Config, ConflictMode, DeleteMode, KopyError};
 use std::fs;
 use std::path::Path;
 use tempfile::TempDir;
@@ -46,6 +48,11 @@ fn test_sync_updates_existing_files() {
 
     fs::write(src.path().join("same.txt"), b"new-data").expect("write source version");
     fs::write(dst.path().join("same.txt"), b"old").expect("write destination version");
+    filetime::set_file_mtime(
+        dst.path().join("same.txt"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age destination file");
 
     run(config_for(src.path(), dst.path())).expect("sync run should succeed");
 
@@ -162,3 +169,513 @@ fn test_sync_single_file_checksum_is_idempotent_with_destination_directory() {
         "idempotent checksum sync should not rewrite destination file"
     );
 }
+
+#[test]
+fn test_sync_files_from_limits_copies_and_deletes_to_listed_paths() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+    let list_dir = TempDir::new().expect("create list tempdir");
+
+    fs::create_dir_all(src.path().join("docs")).expect("create source docs dir");
+    fs::write(src.path().join("docs/keep.md"), b"keep").expect("write listed dir file");
+    fs::write(src.path().join("notes.txt"), b"notes").expect("write listed file");
+    fs::write(src.path().join("unlisted.txt"), b"unlisted").expect("write unlisted file");
+
+    fs::create_dir_all(dst.path().join("docs")).expect("create destination docs dir");
+    fs::write(dst.path().join("docs/stale.md"), b"stale").expect("write stale listed file");
+    fs::write(dst.path().join("other.txt"), b"other").expect("write unlisted dest file");
+
+    let list = list_dir.path().join("files.txt");
+    fs::write(&list, "docs\nnotes.txt\nmissing.txt\n").expect("write file list");
+
+    let mut config = config_for(src.path(), dst.path());
+    config.files_from = Some(list);
+    config.delete_mode = DeleteMode::Permanent;
+
+    run(config).expect("sync with file list should succeed");
+
+    assert!(dst.path().join("docs/keep.md").exists());
+    assert!(dst.path().join("notes.txt").exists());
+    assert!(
+        !dst.path().join("unlisted.txt").exists(),
+        "unlisted source files must not be copied"
+    );
+    assert!(
+        !dst.path().join("docs/stale.md").exists(),
+        "extraneous files under listed directories are deleted"
+    );
+    assert!(
+        dst.path().join("other.txt").exists(),
+        "destination files outside listed paths must be kept"
+    );
+}
+
+#[test]
+fn test_sync_size_limit_keeps_deselected_destination_files() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::write(src.path().join("vm.img"), vec![0u8; 8192]).expect("write large source file");
+    fs::write(src.path().join("small.txt"), b"small").expect("write small source file");
+    fs::write(dst.path().join("vm.img"), b"older image").expect("write destination image");
+    fs::write(dst.path().join("orphan.txt"), b"orphan").expect("write destination orphan");
+
+    let mut config = config_for(src.path(), dst.path());
+    config.max_size = Some(1024);
+    config.delete_mode = DeleteMode::Permanent;
+
+    run(config).expect("sync with size limit should succeed");
+
+    assert_eq!(
+        fs::read(dst.path().join("vm.img")).expect("read destination image"),
+        b"older image",
+        "deselected source files must neither be copied nor deleted"
+    );
+    assert!(dst.path().join("small.txt").exists());
+    assert!(!dst.path().join("orphan.txt").exists());
+}
+
+#[test]
+fn test_sync_overlays_multiple_sources() {
+    let base = TempDir::new().expect("create base tempdir");
+    let prod = TempDir::new().expect("create overlay tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::create_dir_all(base.path().join("etc")).expect("create base etc");
+    fs::write(base.path().join("etc/app.conf"), b"base-conf").expect("write base conf");
+    fs::write(base.path().join("bin.sh"), b"base-bin").expect("write base bin");
+    fs::create_dir_all(prod.path().join("etc")).expect("create overlay etc");
+    fs::write(prod.path().join("etc/app.conf"), b"prod-conf").expect("write overlay conf");
+    fs::write(prod.path().join("prod.env"), b"prod-env").expect("write overlay env");
+    fs::write(dst.path().join("bin.sh"), b"stale-bin").expect("write stale dest bin");
+    filetime::set_file_mtime(
+        dst.path().join("bin.sh"),
+        filetime::FileTime::from_unix_time(1_000_000, 0),
+    )
+    .expect("age destination file");
+    fs::write(dst.path().join("orphan.txt"), b"orphan").expect("write dest orphan");
+
+    let mut config = config_for(base.path(), dst.path());
+    config.overlay_sources = vec![prod.path().to_path_buf()];
+    config.delete_mode = DeleteMode::Permanent;
+
+    run(config).expect("multi-source sync should succeed");
+
+    assert_eq!(
+        fs::read(dst.path().join("etc/app.conf")).expect("read conf"),
+        b"prod-conf",
+        "later sources win on collisions"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("bin.sh")).expect("read bin"),
+        b"base-bin"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("prod.env")).expect("read env"),
+        b"prod-env"
+    );
+    assert!(
+        !dst.path().join("orphan.txt").exists(),
+        "files that no source has are deleted"
+    );
+}
+
+#[test]
+fn test_sync_overlay_file_and_directory_shadow_each_other() {
+    let base = TempDir::new().expect("create base tempdir");
+    let prod = TempDir::new().expect("create overlay tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::create_dir_all(base.path().join("a")).expect("create base dir");
+    fs::write(base.path().join("a/b"), b"base-b").expect("write base nested file");
+    fs::write(base.path().join("x"), b"base-x").expect("write base file");
+    fs::write(prod.path().join("a"), b"prod-a").expect("write overlay file");
+    fs::create_dir_all(prod.path().join("x")).expect("create overlay dir");
+    fs::write(prod.path().join("x/y"), b"prod-y").expect("write overlay nested file");
+
+    let mut config = config_for(base.path(), dst.path());
+    config.overlay_sources = vec![prod.path().to_path_buf()];
+    run(config).expect("overlay sync should succeed");
+
+    assert_eq!(fs::read(dst.path().join("a")).unwrap(), b"prod-a");
+    assert_eq!(fs::read(dst.path().join("x/y")).unwrap(), b"prod-y");
+}
+
+#[test]
+fn test_sync_fans_out_to_every_destination() {
+    let src = TempDir::new().expect("create src tempdir");
+    let first = TempDir::new().expect("create first dst tempdir");
+    let second = TempDir::new().expect("create second dst tempdir");
+
+    fs::create_dir_all(src.path().join("docs")).expect("create src docs");
+    fs::write(src.path().join("docs/a.txt"), b"alpha").expect("write a");
+    fs::write(src.path().join("b.txt"), b"beta").expect("write b");
+
+    let mut config = config_for(src.path(), first.path());
+    config.extra_destinations = vec![second.path().join("replica")];
+
+    run(config).expect("fan-out sync should succeed");
+
+    for root in [first.path(), &second.path().join("replica")] {
+        assert_eq!(fs::read(root.join("docs/a.txt")).expect("read a"), b"alpha");
+        assert_eq!(fs::read(root.join("b.txt")).expect("read b"), b"beta");
+    }
+}
+
+#[test]
+fn test_sync_fan_out_failure_does_not_abort_other_destinations() {
+    let src = TempDir::new().expect("create src tempdir");
+    let healthy = TempDir::new().expect("create healthy dst tempdir");
+    let broken = TempDir::new().expect("create broken dst tempdir");
+
+    fs::write(src.path().join("a.txt"), b"alpha").expect("write a");
+    fs::write(src.path().join("b.txt"), b"beta").expect("write b");
+    // A directory where the source has a file makes the copy of a.txt fail.
+    fs::create_dir_all(broken.path().join("a.txt")).expect("create blocking dir");
+    fs::write(broken.path().join("a.txt/inner"), b"inner").expect("write blocking file");
+
+    let mut config = config_for(src.path(), broken.path());
+    config.extra_destinations = vec![healthy.path().to_path_buf()];
+
+    assert!(run(config).is_err(), "the broken destination is reported");
+    assert_eq!(
+        fs::read(healthy.path().join("a.txt")).expect("read healthy a"),
+        b"alpha"
+    );
+    assert_eq!(
+        fs::read(healthy.path().join("b.txt")).expect("read healthy b"),
+        b"beta"
+    );
+    assert_eq!(
+        fs::read(broken.path().join("b.txt")).expect("read broken b"),
+        b"beta",
+        "other actions on the failing destination still run"
+    );
+}
+
+#[test]
+fn test_sync_delete_turns_directory_rename_into_move() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::create_dir_all(src.path().join("photos/trip")).expect("create src dirs");
+    fs::write(src.path().join("photos/a.jpg"), b"aaaa").expect("write a");
+    fs::write(src.path().join("photos/trip/b.jpg"), b"bbbbbb").expect("write b");
+    fs::write(src.path().join("notes.txt"), b"notes").expect("write notes");
+
+    let mut config = config_for(src.path(), dst.path());
+    config.delete_mode = DeleteMode::Trash;
+    run(config.clone()).expect("initial sync");
+
+    fs::rename(src.path().join("photos"), src.path().join("photos-2024")).expect("rename dir");
+    fs::rename(src.path().join("notes.txt"), src.path().join("notes.md")).expect("rename file");
+
+    run(config).expect("rename sync");
+    assert_eq!(
+        fs::read(dst.path().join("photos-2024/trip/b.jpg")).expect("read moved b"),
+        b"bbbbbb"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("notes.md")).expect("read moved notes"),
+        b"notes"
+    );
+    assert!(!dst.path().join("photos").exists());
+    assert!(!dst.path().join("notes.txt").exists());
+    assert!(
+        !dst.path().join(".kopy_trash").exists(),
+        "renamed files are moved, not copied and trashed"
+    );
+}
+
+#[test]
+fn test_sync_delete_never_renames_different_content() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::write(src.path().join("b.txt"), b"hello").expect("write source");
+    fs::write(dst.path().join("a.txt"), b"world").expect("write destination");
+    let mtime = filetime::FileTime::from_unix_time(1_000_000, 0);
+    filetime::set_file_mtime(src.path().join("b.txt"), mtime).expect("set source mtime");
+    filetime::set_file_mtime(dst.path().join("a.txt"), mtime).expect("set dest mtime");
+
+    let mut config = config_for(src.path(), dst.path());
+    config.delete_mode = DeleteMode::Permanent;
+    run(config).expect("sync should succeed");
+
+    assert_eq!(fs::read(dst.path().join("b.txt")).unwrap(), b"hello");
+    assert!(!dst.path().join("a.txt").exists());
+}
+
+#[test]
+fn test_sync_destination_newer_conflict_fail_and_keep_both() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::write(src.path().join("notes.md"), b"source").expect("write source");
+    fs::write(dst.path().join("notes.md"), b"edited").expect("write destination");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    filetime::set_file_mtime(src.path().join("notes.md"), older).expect("set source mtime");
+    filetime::set_file_mtime(dst.path().join("notes.md"), newer).expect("set dest mtime");
+
+    let mut config = config_for(src.path(), dst.path());
+    config.conflict_mode = ConflictMode::Fail;
+    let err = run(config.clone()).expect_err("conflict should refuse the sync");
+    assert!(err.to_string().contains("newer than their source"));
+    assert_eq!(
+        fs::read(dst.path().join("notes.md")).expect("read untouched destination"),
+        b"edited"
+    );
+
+    config.conflict_mode = ConflictMode::KeepBoth;
+    run(config).expect("keep-both sync");
+    assert_eq!(
+        fs::read(dst.path().join("notes.md")).expect("read synced destination"),
+        b"source"
+    );
+    let kept: Vec<_> = fs::read_dir(dst.path())
+        .expect("read destination")
+        .map(|entry| entry.expect("dir entry").file_name())
+        .filter(|name| {
+            name.to_string_lossy()
+                .starts_with("notes.conflict-destination-")
+        })
+        .collect();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(
+        fs::read(dst.path().join(&kept[0])).expect("read kept copy"),
+        b"edited"
+    );
+}
+
+#[test]
+fn test_checksum_sync_still_refuses_destination_newer_conflict() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::write(src.path().join("a.txt"), b"source").expect("write source");
+    fs::write(dst.path().join("a.txt"), b"edited").expect("write destination");
+    fs::write(src.path().join("same.txt"), b"same").expect("write source twin");
+    fs::write(dst.path().join("same.txt"), b"same").expect("write destination twin");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    for name in ["a.txt", "same.txt"] {
+        filetime::set_file_mtime(src.path().join(name), older).expect("set source mtime");
+        filetime::set_file_mtime(dst.path().join(name), newer).expect("set dest mtime");
+    }
+
+    let mut config = config_for(src.path(), dst.path());
+    config.checksum_mode = true;
+    config.conflict_mode = ConflictMode::Fail;
+    let err = run(config.clone()).expect_err("checksum mode keeps the conflict check");
+    assert!(err.to_string().contains("newer than their source"));
+    assert_eq!(fs::read(dst.path().join("a.txt")).unwrap(), b"edited");
+
+    // Equal content with a newer mtime is not a conflict.
+    fs::write(dst.path().join("a.txt"), b"source").expect("restore destination");
+    filetime::set_file_mtime(dst.path().join("a.txt"), newer).expect("set dest mtime");
+    run(config).expect("same content should sync");
+}
+
+#[test]
+fn test_streaming_sync_resolves_type_clashes_and_orphans() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::create_dir_all(src.path().join("was_file")).expect("create source dir");
+    fs::write(src.path().join("was_file/inner.txt"), b"inner").expect("write nested file");
+    fs::write(src.path().join("was_dir"), b"now a file").expect("write source file");
+    fs::write(src.path().join("keep.txt"), b"keep").expect("write kept file");
+    fs::write(dst.path().join("was_file"), b"old file").expect("write blocking file");
+    fs::create_dir_all(dst.path().join("was_dir/deep")).expect("create blocking dir");
+    fs::write(dst.path().join("was_dir/deep/old.txt"), b"old").expect("write old file");
+    fs::write(dst.path().join("orphan.txt"), b"orphan").expect("write orphan");
+
+    let config = Config {
+        streaming: true,
+        delete_mode: DeleteMode::Permanent,
+        ..config_for(src.path(), dst.path())
+    };
+    run(config).expect("streaming sync should succeed");
+
+    assert_eq!(
+        fs::read(dst.path().join("was_file/inner.txt")).expect("read nested file"),
+        b"inner"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("was_dir")).expect("read replaced file"),
+        b"now a file"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("keep.txt")).expect("read kept"),
+        b"keep"
+    );
+    assert!(!dst.path().join("orphan.txt").exists());
+}
+
+#[test]
+fn test_streaming_sync_honours_keep_files() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::create_dir_all(src.path().join("site")).expect("create source site");
+    fs::write(src.path().join("site/prod.env"), b"from source").expect("write source env");
+    fs::write(src.path().join("site/index.html"), b"new index").expect("write source index");
+    fs::write(src.path().join("uploads"), b"now a file").expect("write clashing file");
+    fs::create_dir_all(dst.path().join("site")).expect("create destination site");
+    fs::write(dst.path().join("site/.kopykeep"), b"*.env\n").expect("write site keep file");
+    fs::write(dst.path().join("site/prod.env"), b"local").expect("write destination env");
+    fs::write(dst.path().join("site/old.html"), b"old").expect("write orphan");
+    fs::create_dir_all(dst.path().join("uploads")).expect("create uploads");
+    fs::write(dst.path().join("uploads/.kopykeep"), b"").expect("write uploads keep file");
+    fs::write(dst.path().join("uploads/a.jpg"), b"jpg").expect("write upload");
+    let aged = filetime::FileTime::from_unix_time(1_000_000, 0);
+    filetime::set_file_mtime(dst.path().join("site/prod.env"), aged).expect("age env");
+
+    let config = Config {
+        streaming: true,
+        delete_mode: DeleteMode::Permanent,
+        ..config_for(src.path(), dst.path())
+    };
+    run(config).expect("streaming sync should succeed");
+
+    assert_eq!(
+        fs::read(dst.path().join("site/prod.env")).unwrap(),
+        b"local"
+    );
+    assert_eq!(
+        fs::read(dst.path().join("site/index.html")).unwrap(),
+        b"new index"
+    );
+    assert!(dst.path().join("site/.kopykeep").exists());
+    assert!(!dst.path().join("site/old.html").exists());
+    assert_eq!(fs::read(dst.path().join("uploads/a.jpg")).unwrap(), b"jpg");
+}
+
+#[test]
+fn test_delete_guard_refuses_empty_source_and_max_delete() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(dst.path().join(name), name).expect("write destination file");
+    }
+    let config = Config {
+        delete_mode: DeleteMode::Permanent,
+        ..config_for(src.path(), dst.path())
+    };
+
+    let err = run(config.clone()).expect_err("empty source should be refused");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 3, .. }));
+    assert!(dst.path().join("a.txt").exists());
+
+    fs::write(src.path().join("a.txt"), "a.txt").expect("write source file");
+    let limited = Config {
+        max_delete: Some(DeleteLimit::Count(1)),
+        ..config.clone()
+    };
+    let err = run(limited).expect_err("two deletes exceed the limit");
+    assert!(matches!(err, KopyError::DeleteRefused { planned: 2, .. }));
+    assert!(dst.path().join("b.txt").exists());
+
+    fs::remove_file(src.path().join("a.txt")).expect("empty the source again");
+    let allowed = Config {
+        allow_empty_source: true,
+        ..config
+    };
+    run(allowed).expect("allowed empty source should sync");
+    assert!(!dst.path().join("a.txt").exists());
+}
+
+#[test]
+fn test_dry_run_fails_when_the_real_run_would_be_refused() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+    for name in ["a.txt", "b.txt"] {
+        fs::write(dst.path().join(name), name).expect("write destination file");
+    }
+    let config = Config {
+        dry_run: true,
+        delete_mode: DeleteMode::Permanent,
+        ..config_for(src.path(), dst.path())
+    };
+
+    for streaming in [false, true] {
+        let err = run(Config {
+            streaming,
+            ..config.clone()
+        })
+        .expect_err("dry run should report the empty-source refusal");
+        assert!(matches!(err, KopyError::DeleteRefused { planned: 2, .. }));
+    }
+
+    fs::write(src.path().join("a.txt"), b"source").expect("write source file");
+    let older = filetime::FileTime::from_unix_time(1_000_000, 0);
+    let newer = filetime::FileTime::from_unix_time(2_000_000, 0);
+    filetime::set_file_mtime(src.path().join("a.txt"), older).expect("set source mtime");
+    filetime::set_file_mtime(dst.path().join("a.txt"), newer).expect("set dest mtime");
+    let err = run(Config {
+        delete_mode: DeleteMode::None,
+        conflict_mode: ConflictMode::Fail,
+        ..config
+    })
+    .expect_err("dry run should report the conflict refusal");
+    assert!(err.to_string().contains("newer than their source"));
+    assert!(dst.path().join("b.txt").exists());
+}
+
+#[test]
+fn test_protected_destination_paths_survive_delete_and_overwrite() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+
+    fs::write(src.path().join("config.local"), b"from source").expect("write source config");
+    fs::write(src.path().join("index.html"), b"new index").expect("write source index");
+    fs::create_dir_all(dst.path().join("uploads")).expect("create uploads dir");
+    fs::write(dst.path().join("uploads/.kopykeep"), b"").expect("write keep file");
+    fs::write(dst.path().join("uploads/a.jpg"), b"upload").expect("write upload");
+    fs::write(dst.path().join("config.local"), b"server-local").expect("write local config");
+    fs::write(dst.path().join("stale.txt"), b"stale").expect("write stale file");
+
+    let config = Config {
+        delete_mode: DeleteMode::Permanent,
+        protect_patterns: vec!["config.local".to_string()],
+        ..config_for(src.path(), dst.path())
+    };
+    run(config).expect("sync with protected paths should succeed");
+
+    assert_eq!(
+        fs::read(dst.path().join("config.local")).expect("read local config"),
+        b"server-local"
+    );
+    assert!(dst.path().join("uploads/.kopykeep").exists());
+    assert!(dst.path().join("uploads/a.jpg").exists());
+    assert!(!dst.path().join("stale.txt").exists());
+    assert_eq!(
+        fs::read(dst.path().join("index.html")).expect("read index"),
+        b"new index"
+    );
+}
+
+#[test]
+fn test_dry_run_and_plan_do_not_write_to_destination() {
+    let src = TempDir::new().expect("create src tempdir");
+    let dst = TempDir::new().expect("create dst tempdir");
+    fs::write(src.path().join("file.txt"), b"content").expect("write source file");
+    fs::write(dst.path().join("old.txt"), b"old").expect("write destination file");
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(dst.path(), old).expect("age destination dir");
+
+    let config = Config {
+        dry_run: true,
+        ..config_for(src.path(), dst.path())
+    };
+    run(config.clone()).expect("dry run should succeed");
+    kopy::commands::plan::run(config, None, None).expect("plan should succeed");
+
+    let metadata = fs::metadata(dst.path()).expect("stat destination");
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&metadata),
+        old
+    );
+    assert!(!dst.path().join("file.txt").exists());
+}
@@ -1,6 +1,6 @@
 //! Tests for trash-based deletion functionality
 
-use kopy::executor::trash::move_to_trash;
+use kopy::executor::trash::{list_trash_snapshots, move_to_trash, restore_trash_snapshot};
 use kopy::Config;
 use std::fs;
 use std::path::Path;
@@ -166,3 +166,76 @@ fn test_trash_multiple_files() {
         "Should have 2 total files across all manifests"
     );
 }
+
+#[test]
+fn test_list_trash_snapshots_empty_without_trash_dir() {
+    let dest_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let snapshots = list_trash_snapshots(dest_dir.path()).expect("list snapshots");
+    assert!(snapshots.is_empty());
+}
+
+#[test]
+fn test_restore_trash_snapshot_moves_files_back() {
+    let dest_dir = TempDir::new().expect("Failed to create temp dir");
+    let dest_path = dest_dir.path();
+    let config = Config::default();
+
+    let file1 = create_test_file(dest_path, "a.txt", "alpha");
+    let file2 = create_test_file(dest_path, "nested/b.txt", "beta");
+    move_to_trash(&file1, dest_path, Path::new("a.txt"), &config).expect("trash a");
+    move_to_trash(&file2, dest_path, Path::new("nested/b.txt"), &config).expect("trash b");
+
+    let snapshots = list_trash_snapshots(dest_path).expect("list snapshots");
+    let listed: usize = snapshots.iter().map(|s| s.manifest.files.len()).sum();
+    assert_eq!(listed, 2);
+    assert_eq!(
+        snapshots.iter().map(|s| s.total_size()).sum::<u64>(),
+        "alpha".len() as u64 + "beta".len() as u64
+    );
+
+    for snapshot in &snapshots {
+        let report =
+            restore_trash_snapshot(dest_path, &snapshot.name, false).expect("restore snapshot");
+        assert!(report.skipped.is_empty());
+    }
+
+    assert_eq!(fs::read_to_string(&file1).unwrap(), "alpha");
+    assert_eq!(fs::read_to_string(&file2).unwrap(), "beta");
+    assert!(
+        !dest_path.join(".kopy_trash").exists(),
+        "fully restored trash should be cleaned up"
+    );
+}
+
+#[test]
+fn test_restore_trash_snapshot_skips_occupied_paths_without_overwrite() {
+    let dest_dir = TempDir::new().expect("Failed to create temp dir");
+    let dest_path = dest_dir.path();
+    let config = Config::default();
+
+    let file = create_test_file(dest_path, "a.txt", "old");
+    move_to_trash(&file, dest_path, Path::new("a.txt"), &config).expect("trash a");
+    create_test_file(dest_path, "a.txt", "new");
+
+    let snapshot = list_trash_snapshots(dest_path).expect("list snapshots")[0]
+        .name
+        .clone();
+
+    let report = restore_trash_snapshot(dest_path, &snapshot, false).expect("restore snapshot");
+    assert!(report.restored.is_empty());
+    assert_eq!(report.skipped, vec![Path::new("a.txt").to_path_buf()]);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+
+    let report = restore_trash_snapshot(dest_path, &snapshot, true).expect("restore snapshot");
+    assert_eq!(report.restored.len(), 1);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+}
+
+#[test]
+fn test_restore_trash_snapshot_rejects_unknown_or_unsafe_names() {
+    let dest_dir = TempDir::new().expect("Failed to create temp dir");
+
+    assert!(restore_trash_snapshot(dest_dir.path(), "missing", false).is_err());
+    assert!(restore_trash_snapshot(dest_dir.path(), "../outside", false).is_err());
+}